@@ -18,8 +18,7 @@ set -euo pipefail
 
 DOMAIN="${1:-sfu.example.com}"
 INSTALL_DIR="/opt/liverelay"
-TURN_USER="liverelay"
-TURN_PASS=$(openssl rand -hex 16)
+TURN_SECRET=$(openssl rand -hex 32)
 JWT_SECRET=$(openssl rand -hex 32)
 
 echo "================================================"
@@ -57,8 +56,7 @@ LIVERELAY_TLS_ENABLED=false
 # TURN — embedded.
 LIVERELAY_TURN_EMBEDDED=true
 LIVERELAY_TURN_PORT=3478
-LIVERELAY_TURN_USERNAME=$TURN_USER
-LIVERELAY_TURN_PASSWORD=$TURN_PASS
+LIVERELAY_TURN_SECRET=$TURN_SECRET
 LIVERELAY_TURN_REALM=$DOMAIN
 
 LIVERELAY_STUN_URLS=stun:stun.l.google.com:19302,stun:stun1.l.google.com:19302
@@ -128,8 +126,7 @@ echo "================================================"
 echo "  DEPLOYMENT SUMMARY"
 echo "================================================"
 echo "  Install dir:   $INSTALL_DIR"
-echo "  TURN user:     $TURN_USER"
-echo "  TURN password: $TURN_PASS"
+echo "  TURN secret:   $TURN_SECRET"
 echo "  JWT secret:    $JWT_SECRET"
 echo ""
 echo "  IMPORTANT: Save these credentials!"
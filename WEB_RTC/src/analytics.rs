@@ -104,7 +104,7 @@ pub fn estimate_mos(delay_ms: f64, packet_loss_pct: f64) -> f64 {
 // ─── Degradation thresholds ─────────────────────────────────────────────────
 
 /// Configurable thresholds that trigger `quality.degraded` events.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct QualityThresholds {
     /// Emit event when RTT exceeds this value (ms).
     pub max_rtt_ms: f64,
@@ -159,7 +159,7 @@ impl AnalyticsStore {
     pub async fn list(&self, room_id: Option<&str>) -> Vec<QualityMetrics> {
         let map = self.inner.read().await;
         map.values()
-            .filter(|m| room_id.map_or(true, |r| m.room_id == r))
+            .filter(|m| room_id.is_none_or(|r| m.room_id == r))
             .cloned()
             .collect()
     }
@@ -307,6 +307,9 @@ pub fn check_thresholds(
 
 // ─── Background stats collector ─────────────────────────────────────────────
 
+/// A `(peer_id, peer_connection)` pair snapshotted from a room.
+type PeerHandle = (String, Arc<webrtc::peer_connection::RTCPeerConnection>);
+
 /// Spawn the periodic stats collection task.
 ///
 /// Every `interval`, this task iterates over all rooms and publishers, calls
@@ -321,7 +324,6 @@ pub fn check_thresholds(
 pub fn spawn_stats_collector(
     state: Arc<crate::AppState>,
     interval: Duration,
-    thresholds: QualityThresholds,
 ) -> tokio::task::JoinHandle<()> {
     let bus = state.event_bus.clone();
     let store = state.analytics.clone();
@@ -342,7 +344,7 @@ pub fn spawn_stats_collector(
             ticker.tick().await;
 
             // Snapshot current rooms.
-            let rooms: Vec<(String, Vec<(String, Arc<webrtc::peer_connection::RTCPeerConnection>)>)> = {
+            let rooms: Vec<(String, Vec<PeerHandle>)> = {
                 let rooms_map = state.rooms.read().unwrap();
                 rooms_map
                     .iter()
@@ -384,7 +386,8 @@ pub fn spawn_stats_collector(
                     // Store.
                     store.upsert(metrics.clone()).await;
 
-                    // Check thresholds.
+                    // Check thresholds (reloadable, so read every time).
+                    let thresholds = state.settings.read().unwrap().quality_thresholds.clone();
                     check_thresholds(&metrics, &thresholds, &bus);
 
                     // Remember for next delta.
@@ -413,12 +416,12 @@ fn extract_raw_stats(
         ..Default::default()
     };
 
-    for (_key, stat) in &report.reports {
+    for stat in report.reports.values() {
         match stat {
             webrtc::stats::StatsReportType::CandidatePair(cp) => {
                 raw.current_rtt_secs = cp.current_round_trip_time;
-                raw.bytes_sent += cp.bytes_sent as u64;
-                raw.bytes_received += cp.bytes_received as u64;
+                raw.bytes_sent += cp.bytes_sent;
+                raw.bytes_received += cp.bytes_received;
             }
             webrtc::stats::StatsReportType::InboundRTP(inbound) => {
                 raw.packets_received += inbound.packets_received;
@@ -427,8 +430,8 @@ fn extract_raw_stats(
                 // We rely on candidate-pair RTT for quality estimation.
             }
             webrtc::stats::StatsReportType::OutboundRTP(outbound) => {
-                raw.bytes_sent += outbound.bytes_sent as u64;
-                raw.packets_received += outbound.packets_sent as u64;
+                raw.bytes_sent += outbound.bytes_sent;
+                raw.packets_received += outbound.packets_sent;
             }
             _ => {}
         }
@@ -14,6 +14,26 @@ use tracing::info;
 #[derive(Deserialize)]
 pub struct CreateRoomRequest {
     pub room_type: crate::room::RoomType,
+    /// Conference only: hold new peers in a lobby until a host admits them.
+    #[serde(default)]
+    pub lobby: bool,
+    /// Allowed codecs, in preference order (defaults to every supported one).
+    #[serde(default)]
+    pub codecs: crate::codec::CodecPolicy,
+    /// Call / conference only: allow one active screen share at a time.
+    #[serde(default)]
+    pub single_screen_share: bool,
+    /// Capture a keyframe snapshot of every publisher this often, for
+    /// moderation.  Off unless set.
+    #[serde(default)]
+    pub snapshot_interval_secs: Option<u64>,
+    /// Arbitrary JSON attached to the room, at most `MAX_METADATA_BYTES`
+    /// once serialised.
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Make the tokens returned here single-use: each admits one peer.
+    #[serde(default)]
+    pub single_use_tokens: bool,
 }
 
 #[derive(Serialize)]
@@ -29,13 +49,29 @@ pub struct CreateRoomResponse {
 pub enum RoomTokens {
     Broadcast { publish: String, subscribe: String },
     Call { caller: String, callee: String },
-    /// Conference tokens: a list of N tokens (one per participant slot).
-    Conference { tokens: Vec<String> },
+    /// Conference tokens: a list of N tokens (one per participant slot),
+    /// plus a `host` token when the room has a lobby.
+    Conference {
+        tokens: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        host: Option<String>,
+    },
 }
 
 #[derive(Deserialize)]
 pub struct CreateTokenRequest {
     pub role: String,
+    /// Participant metadata embedded in the token, at most
+    /// `MAX_METADATA_BYTES` once serialised.
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Admit a single peer; afterwards the token only serves that peer's
+    /// follow-up requests.
+    #[serde(default)]
+    pub single_use: bool,
+    /// Unix timestamp before which the token is rejected.
+    #[serde(default)]
+    pub not_before: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -46,12 +82,33 @@ pub struct CreateTokenResponse {
 #[derive(Deserialize)]
 pub struct CreateKeyRequest {
     pub name: String,
+    #[serde(default)]
+    pub quotas: crate::usage::KeyQuotas,
 }
 
 #[derive(Serialize)]
 pub struct CreateKeyResponse {
     pub key: String,
     pub name: String,
+    pub quotas: crate::usage::KeyQuotas,
+}
+
+// ---------------------------------------------------------------------------
+// Metadata
+// ---------------------------------------------------------------------------
+
+/// Upper bound on room / participant metadata, serialised.  Participant
+/// metadata travels inside the JWT, so it has to stay small.
+pub const MAX_METADATA_BYTES: usize = 4096;
+
+fn validate_metadata(metadata: Option<&serde_json::Value>) -> Result<(), crate::error::ApiError> {
+    let size = metadata.map_or(0, |m| m.to_string().len());
+    if size > MAX_METADATA_BYTES {
+        return Err(crate::error::ApiError::bad_request(format!(
+            "metadata is {size} bytes; the limit is {MAX_METADATA_BYTES}."
+        )));
+    }
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -65,27 +122,72 @@ pub async fn create_room(
 ) -> Result<Json<CreateRoomResponse>, crate::error::ApiError> {
     let api_key = crate::auth::require_api_key(&headers, &state.api_keys)
         .await?;
+    crate::drain::ensure_accepting(&state)?;
+
+    if body.lobby && body.room_type != crate::room::RoomType::Conference {
+        return Err(crate::error::ApiError::bad_request(
+            "Lobby mode is only available for conference rooms.",
+        ));
+    }
+
+    if body.single_screen_share && body.room_type == crate::room::RoomType::Broadcast {
+        return Err(crate::error::ApiError::bad_request(
+            "Screen share limits only apply to call and conference rooms.",
+        ));
+    }
+
+    if body.snapshot_interval_secs.is_some_and(|s| s < crate::snapshot::MIN_INTERVAL_SECS) {
+        return Err(crate::error::ApiError::bad_request(format!(
+            "snapshot_interval_secs must be at least {}.",
+            crate::snapshot::MIN_INTERVAL_SECS
+        )));
+    }
+
+    validate_metadata(body.metadata.as_ref())?;
+
+    body.codecs
+        .validate()
+        .map_err(crate::error::ApiError::bad_request)?;
+
+    crate::usage::check_room_quota(&state, &api_key.key).await?;
 
     let room_id = uuid::Uuid::new_v4().to_string();
 
-    let room = Arc::new(crate::room::Room::new(room_id.clone(), body.room_type));
+    let mut room = crate::room::Room::new(room_id.clone(), body.room_type);
+    room.lobby_enabled = body.lobby;
+    room.owner_key = api_key.key.clone();
+    room.codec_policy = body.codecs;
+    room.single_screen_share = body.single_screen_share;
+    room.snapshot_interval = body.snapshot_interval_secs.map(std::time::Duration::from_secs);
+    room.metadata = body.metadata;
+    let room = Arc::new(room);
 
     {
+        let max_rooms = state.settings.read().unwrap().max_rooms;
         let mut rooms = state.rooms.write().unwrap();
-        rooms.insert(room_id.clone(), room);
+        if rooms.len() >= max_rooms {
+            return Err(crate::error::ApiError::room_limit_reached());
+        }
+        rooms.insert(room_id.clone(), room.clone());
+    }
+
+    if let Some(interval) = room.snapshot_interval {
+        crate::snapshot::spawn_snapshot_task(state.clone(), &room, interval);
     }
 
     info!("Room '{}' created (type={:?}) by key '{}'", room_id, body.room_type, api_key.name);
 
     // Emit room.created event.
-    let room_type_str = match body.room_type {
-        crate::room::RoomType::Broadcast => "broadcast",
-        crate::room::RoomType::Call => "call",
-        crate::room::RoomType::Conference => "conference",
-    };
-    state.event_bus.emit(crate::events::LiveRelayEvent::room_created(&room_id, room_type_str));
+    state.event_bus.emit(crate::events::LiveRelayEvent::room_created(
+        &room_id,
+        body.room_type.as_str(),
+    ));
 
     const TTL: u64 = 86400; // 24 hours
+    let options = crate::auth::TokenOptions {
+        single_use: body.single_use_tokens,
+        ..crate::auth::TokenOptions::new(TTL)
+    };
 
     let tokens = match body.room_type {
         crate::room::RoomType::Broadcast => {
@@ -94,7 +196,7 @@ pub async fn create_room(
                 &room_id,
                 "publish",
                 &api_key.key,
-                TTL,
+                &options,
             )
             .map_err(|e| {
                 tracing::warn!("Failed to create publish token: {e}");
@@ -106,7 +208,7 @@ pub async fn create_room(
                 &room_id,
                 "subscribe",
                 &api_key.key,
-                TTL,
+                &options,
             )
             .map_err(|e| {
                 tracing::warn!("Failed to create subscribe token: {e}");
@@ -121,7 +223,7 @@ pub async fn create_room(
                 &room_id,
                 "call",
                 &api_key.key,
-                TTL,
+                &options,
             )
             .map_err(|e| {
                 tracing::warn!("Failed to create call token: {e}");
@@ -133,7 +235,7 @@ pub async fn create_room(
                 &room_id,
                 "call",
                 &api_key.key,
-                TTL,
+                &options,
             )
             .map_err(|e| {
                 tracing::warn!("Failed to create call token: {e}");
@@ -152,7 +254,7 @@ pub async fn create_room(
                     &room_id,
                     "conference",
                     &api_key.key,
-                    TTL,
+                    &options,
                 )
                 .map_err(|e| {
                     tracing::warn!("Failed to create conference token: {e}");
@@ -161,7 +263,24 @@ pub async fn create_room(
                 tokens.push(token);
             }
 
-            RoomTokens::Conference { tokens }
+            let host = if body.lobby {
+                let token = crate::auth::create_token(
+                    &state.jwt_secret,
+                    &room_id,
+                    "host",
+                    &api_key.key,
+                    &options,
+                )
+                .map_err(|e| {
+                    tracing::warn!("Failed to create host token: {e}");
+                    crate::error::ApiError::internal("Failed to create token")
+                })?;
+                Some(token)
+            } else {
+                None
+            };
+
+            RoomTokens::Conference { tokens, host }
         }
     };
 
@@ -207,6 +326,25 @@ pub async fn get_room(
     Ok(Json(room.info()))
 }
 
+// ---------------------------------------------------------------------------
+// GET /v1/rooms/:room_id/participants — list publishing participants
+// ---------------------------------------------------------------------------
+
+pub async fn list_participants(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<crate::room::ParticipantInfo>>, crate::error::ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+
+    let rooms = state.rooms.read().unwrap();
+    let room = rooms
+        .get(&room_id)
+        .ok_or_else(|| crate::error::ApiError::room_not_found(&room_id))?;
+
+    Ok(Json(room.participants()))
+}
+
 // ---------------------------------------------------------------------------
 // DELETE /v1/rooms/:room_id — delete a room
 // ---------------------------------------------------------------------------
@@ -225,6 +363,9 @@ pub async fn delete_room(
 
     match room {
         Some(room) => {
+            // Meter before closing: the disconnects shrink the headcount.
+            crate::usage::flush_room(&state, &room).await;
+
             // Close every publisher PeerConnection so media stops flowing.
             let publishers = room.get_publishers();
             for publisher in &publishers {
@@ -243,12 +384,10 @@ pub async fn delete_room(
             );
 
             // Emit room.deleted event.
-            let room_type_str = match room.room_type {
-                crate::room::RoomType::Broadcast => "broadcast",
-                crate::room::RoomType::Call => "call",
-                crate::room::RoomType::Conference => "conference",
-            };
-            state.event_bus.emit(crate::events::LiveRelayEvent::room_deleted(&room_id, room_type_str));
+            state.event_bus.emit(crate::events::LiveRelayEvent::room_deleted(
+                &room_id,
+                room.room_type.as_str(),
+            ));
 
             Ok(StatusCode::NO_CONTENT)
         }
@@ -274,6 +413,8 @@ pub async fn create_room_token(
         return Err(crate::error::ApiError::invalid_role(&body.role));
     }
 
+    validate_metadata(body.metadata.as_ref())?;
+
     // Verify the room exists.
     {
         let rooms = state.rooms.read().unwrap();
@@ -282,12 +423,18 @@ pub async fn create_room_token(
         }
     }
 
+    let options = crate::auth::TokenOptions {
+        not_before: body.not_before,
+        single_use: body.single_use,
+        metadata: body.metadata,
+        ..crate::auth::TokenOptions::new(86400)
+    };
     let token = crate::auth::create_token(
         &state.jwt_secret,
         &room_id,
         &body.role,
         &api_key.key,
-        86400,
+        &options,
     )
     .map_err(|e| {
         tracing::warn!("Failed to create token for room '{}': {e}", room_id);
@@ -303,15 +450,18 @@ pub async fn create_room_token(
 }
 
 // ---------------------------------------------------------------------------
-// POST /v1/keys — create an API key (requires authentication)
+// POST /v1/keys — create an API key (requires the bootstrap key)
 // ---------------------------------------------------------------------------
 
+/// Only the bootstrap key may mint keys: quotas are per key, so a tenant
+/// allowed to mint its own could shed them by switching keys.
 pub async fn create_api_key(
     State(state): State<Arc<crate::AppState>>,
     headers: HeaderMap,
     Json(body): Json<CreateKeyRequest>,
 ) -> Result<Json<CreateKeyResponse>, crate::error::ApiError> {
-    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+    crate::auth::require_admin_key(&headers, &state.api_keys, &state.config.bootstrap_api_key)
+        .await?;
 
     let key = crate::auth::generate_api_key();
     let now = std::time::SystemTime::now()
@@ -323,6 +473,7 @@ pub async fn create_api_key(
         key: key.clone(),
         name: body.name.clone(),
         created_at: now,
+        quotas: body.quotas.clone(),
     };
 
     {
@@ -335,5 +486,6 @@ pub async fn create_api_key(
     Ok(Json(CreateKeyResponse {
         key,
         name: body.name,
+        quotas: body.quotas,
     }))
 }
@@ -18,6 +18,8 @@ pub struct ApiKey {
     pub key: String,
     pub name: String,
     pub created_at: u64,
+    #[serde(default)]
+    pub quotas: crate::usage::KeyQuotas,
 }
 
 /// Generate an API key in the form `lr_` followed by 32 random hex characters.
@@ -40,7 +42,7 @@ pub struct TokenClaims {
     /// Peer ID (UUID).
     pub sub: String,
     pub room_id: String,
-    /// One of "publish", "subscribe", or "call".
+    /// One of "publish", "subscribe", "call", "conference", or "host".
     pub role: String,
     /// Opaque key identifier (first 8 characters of the API key).
     pub key_id: String,
@@ -48,11 +50,49 @@ pub struct TokenClaims {
     pub exp: usize,
     /// Issued-at (unix timestamp).
     pub iat: usize,
+    /// Not-before (unix timestamp).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    /// Token id, the key of the revocation list.  Empty on tokens issued
+    /// before revocation existed.
+    #[serde(default)]
+    pub jti: String,
+    /// Admits a single peer; see `crate::revocation`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub single_use: bool,
+    /// Caller-supplied participant metadata (e.g. the app's user id),
+    /// echoed in participant listings and events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Optional claims of a new token.
+#[derive(Debug, Clone, Default)]
+pub struct TokenOptions {
+    pub ttl_secs: u64,
+    /// Unix timestamp before which the token is rejected.
+    pub not_before: Option<u64>,
+    pub single_use: bool,
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl TokenOptions {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs,
+            ..Default::default()
+        }
+    }
+}
+
+/// The short form of an API key that tokens carry as `key_id`.
+pub fn key_id(api_key: &str) -> String {
+    api_key.chars().take(8).collect()
 }
 
 /// Create a signed JWT for a new peer.
 ///
-/// A fresh UUID is generated for the `sub` (peer_id) claim.
+/// Fresh UUIDs are generated for the `sub` (peer_id) and `jti` claims.
 /// Only the first 8 characters of `api_key` are stored in the token
 /// as `key_id` to avoid leaking the full key.
 pub fn create_token(
@@ -60,7 +100,7 @@ pub fn create_token(
     room_id: &str,
     role: &str,
     api_key: &str,
-    ttl_secs: u64,
+    options: &TokenOptions,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -71,9 +111,13 @@ pub fn create_token(
         sub: Uuid::new_v4().to_string(),
         room_id: room_id.to_string(),
         role: role.to_string(),
-        key_id: api_key.chars().take(8).collect(),
-        exp: (now + ttl_secs) as usize,
+        key_id: key_id(api_key),
+        exp: (now + options.ttl_secs) as usize,
         iat: now as usize,
+        nbf: options.not_before.map(|t| t as usize),
+        jti: Uuid::new_v4().to_string(),
+        single_use: options.single_use,
+        metadata: options.metadata.clone(),
     };
 
     encode(
@@ -88,21 +132,38 @@ pub fn verify_token(
     secret: &str,
     token: &str,
 ) -> Result<TokenClaims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::default(); // HS256 + exp validation
+    validation.validate_nbf = true;
     let token_data = decode::<TokenClaims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(), // HS256 + exp validation
+        &validation,
     )?;
     Ok(token_data.claims)
 }
 
+/// Verify a room token and check it against the revocation list.
+pub fn verify_room_token(
+    secret: &str,
+    token: &str,
+    revocations: &crate::revocation::RevocationList,
+) -> Result<TokenClaims, ApiError> {
+    let claims = verify_token(secret, token).map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
+        jsonwebtoken::errors::ErrorKind::ImmatureSignature => ApiError::token_not_yet_valid(),
+        _ => ApiError::token_invalid(),
+    })?;
+    revocations.check(&claims)?;
+    Ok(claims)
+}
+
 // ---------------------------------------------------------------------------
 // Role validation
 // ---------------------------------------------------------------------------
 
 /// Returns `true` if the role is one of the allowed values.
 pub fn validate_role(role: &str) -> bool {
-    matches!(role, "publish" | "subscribe" | "call" | "conference")
+    matches!(role, "publish" | "subscribe" | "call" | "conference" | "host")
 }
 
 // ---------------------------------------------------------------------------
@@ -129,6 +190,38 @@ pub async fn require_api_key(
     keys.get(token).cloned().ok_or_else(ApiError::api_key_invalid)
 }
 
+/// Like [`require_api_key`], but only accepts `admin_key`, the node's
+/// bootstrap key.  Guards operations that affect every tenant: minting
+/// keys and setting their quotas, draining the node, reloading its config.
+pub async fn require_admin_key(
+    headers: &axum::http::HeaderMap,
+    api_keys: &RwLock<HashMap<String, ApiKey>>,
+    admin_key: &str,
+) -> Result<ApiKey, ApiError> {
+    let api_key = require_api_key(headers, api_keys).await?;
+    if api_key.key != admin_key {
+        return Err(ApiError::admin_key_required());
+    }
+    Ok(api_key)
+}
+
+/// Validate the `Authorization: Bearer <jwt>` header of a peer request.
+///
+/// Returns the token's [`TokenClaims`] or an [`ApiError`].
+pub fn require_room_token(
+    headers: &axum::http::HeaderMap,
+    secret: &str,
+    revocations: &crate::revocation::RevocationList,
+) -> Result<TokenClaims, ApiError> {
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(ApiError::auth_header_missing)?;
+
+    verify_room_token(secret, token, revocations)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -149,7 +242,7 @@ mod tests {
     #[test]
     fn roundtrip_token() {
         let secret = "test-secret";
-        let token = create_token(secret, "room-1", "publish", "lr_abc", 3600).unwrap();
+        let token = create_token(secret, "room-1", "publish", "lr_abc", &TokenOptions::new(3600)).unwrap();
         let claims = verify_token(secret, &token).unwrap();
 
         assert_eq!(claims.room_id, "room-1");
@@ -159,9 +252,44 @@ mod tests {
         assert!(claims.exp > claims.iat);
     }
 
+    #[test]
+    fn token_carries_metadata() {
+        let meta = serde_json::json!({ "user_id": 42 });
+        let options = TokenOptions {
+            metadata: Some(meta.clone()),
+            ..TokenOptions::new(60)
+        };
+        let token = create_token("s", "room-1", "call", "lr_x", &options).unwrap();
+        assert_eq!(verify_token("s", &token).unwrap().metadata, Some(meta));
+
+        let token = create_token("s", "room-1", "call", "lr_x", &TokenOptions::new(60)).unwrap();
+        assert_eq!(verify_token("s", &token).unwrap().metadata, None);
+    }
+
+    #[test]
+    fn not_before_is_enforced() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let options = TokenOptions {
+            not_before: Some(now + 3600),
+            ..TokenOptions::new(7200)
+        };
+        let token = create_token("s", "room-1", "call", "lr_x", &options).unwrap();
+        let revocations = crate::revocation::RevocationList::new();
+        let err = verify_room_token("s", &token, &revocations).unwrap_err();
+        assert_eq!(err.code, "token_not_yet_valid");
+
+        let options = TokenOptions {
+            not_before: Some(now),
+            ..TokenOptions::new(60)
+        };
+        let token = create_token("s", "room-1", "call", "lr_x", &options).unwrap();
+        let claims = verify_room_token("s", &token, &revocations).unwrap();
+        assert!(!claims.jti.is_empty());
+    }
+
     #[test]
     fn bad_secret_rejects() {
-        let token = create_token("secret-a", "room-1", "call", "lr_x", 60).unwrap();
+        let token = create_token("secret-a", "room-1", "call", "lr_x", &TokenOptions::new(60)).unwrap();
         assert!(verify_token("secret-b", &token).is_err());
     }
 
@@ -170,6 +298,7 @@ mod tests {
         assert!(validate_role("publish"));
         assert!(validate_role("subscribe"));
         assert!(validate_role("call"));
+        assert!(validate_role("host"));
     }
 
     #[test]
@@ -189,6 +318,7 @@ mod tests {
                 key: key.clone(),
                 name: "test".into(),
                 created_at: 0,
+                quotas: Default::default(),
             },
         );
 
@@ -203,6 +333,40 @@ mod tests {
         assert_eq!(result.unwrap().key, key);
     }
 
+    #[tokio::test]
+    async fn require_admin_key_only_accepts_the_admin_key() {
+        let api_keys: RwLock<HashMap<String, ApiKey>> = RwLock::new(HashMap::new());
+        for key in ["lr_admin", "lr_tenant"] {
+            api_keys.write().unwrap().insert(
+                key.into(),
+                ApiKey {
+                    key: key.into(),
+                    name: key.into(),
+                    created_at: 0,
+                    quotas: Default::default(),
+                },
+            );
+        }
+        let bearer = |key: &str| {
+            let mut headers = axum::http::HeaderMap::new();
+            headers.insert("authorization", format!("Bearer {key}").parse().unwrap());
+            headers
+        };
+
+        let admin = require_admin_key(&bearer("lr_admin"), &api_keys, "lr_admin").await;
+        assert_eq!(admin.unwrap().key, "lr_admin");
+
+        let err = require_admin_key(&bearer("lr_tenant"), &api_keys, "lr_admin")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "admin_key_required");
+
+        let err = require_admin_key(&bearer("lr_unknown"), &api_keys, "lr_admin")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "api_key_invalid");
+    }
+
     #[tokio::test]
     async fn require_api_key_missing_header() {
         let api_keys: RwLock<HashMap<String, ApiKey>> = RwLock::new(HashMap::new());
@@ -0,0 +1,821 @@
+// src/bin/loadtest.rs
+//
+// Synthetic load generator for the LiveRelay SFU.
+//
+// ─ What it does ─────────────────────────────────────────────────────────────
+//
+//   For each of N publishers:
+//     POST /v1/rooms {room_type: "broadcast"}      (API key)
+//     POST /sfu/publish                            (publish token)
+//     → send VP8 + Opus RTP: generated frames, or packets replayed from a
+//       `.lrr` recording (`--replay`).
+//
+//   Then M subscribers are spread round-robin over the rooms:
+//     POST /v1/rooms/:id/token {role: "subscribe"}
+//     POST /sfu/subscribe
+//     → read every RTP packet and account for it.
+//
+//   Every sent RTP payload ends with a 16-byte trailer
+//   (`LRLT` + send time in µs + publisher index) so subscribers measure the
+//   end-to-end delay through the SFU.  The payloads are therefore not
+//   decodable media; nothing in the path decodes them.
+//
+// ─ Report ───────────────────────────────────────────────────────────────────
+//
+//   Join latency (signalling round-trip, ICE/DTLS connected, first packet),
+//   packets and bytes sent / received, fan-out throughput, end-to-end delay
+//   percentiles and RTP loss computed from sequence numbers.
+//
+// ─ Running on one box ───────────────────────────────────────────────────────
+//
+//   webrtc-rs never gathers loopback candidates, so both the SFU and this
+//   tool use the machine's host addresses; traffic still never leaves the
+//   box.  Start the SFU with LIVERELAY_PUBLIC_HOST set to that address:
+//
+//     LIVERELAY_PUBLIC_HOST=10.0.0.5 LIVERELAY_API_KEY=lr_test cargo run --release
+//     cargo run --release --bin loadtest -- \
+//         --url http://127.0.0.1:8080 --api-key lr_test \
+//         --publishers 4 --subscribers 200 --duration 60
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+#[path = "../lrr.rs"]
+#[allow(dead_code)]
+mod lrr;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use clap::Parser;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use webrtc::api::{APIBuilder, API};
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp::header::Header;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
+use webrtc::rtp_transceiver::RTCRtpTransceiverInit;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+use webrtc::util::marshal::Unmarshal;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Magic that starts the timing trailer appended to every RTP payload.
+const TRAILER_MAGIC: &[u8; 4] = b"LRLT";
+const TRAILER_LEN: usize = 16;
+/// Largest RTP payload the synthetic video generator emits.
+const MAX_PAYLOAD: usize = 1200;
+
+// ─── CLI ────────────────────────────────────────────────────────────────────
+
+#[derive(Parser, Debug)]
+#[command(name = "loadtest", about = "Synthetic publishers and subscribers against a LiveRelay SFU")]
+struct Args {
+    /// Base URL of the SFU.
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    url: String,
+    /// API key used to create rooms and mint tokens.
+    #[arg(long, env = "LIVERELAY_API_KEY")]
+    api_key: String,
+    /// Number of publishers (one broadcast room each).
+    #[arg(long, default_value_t = 1)]
+    publishers: usize,
+    /// Total number of subscribers, spread round-robin over the rooms.
+    #[arg(long, default_value_t = 10)]
+    subscribers: usize,
+    /// Seconds of media to send once everyone has joined.
+    #[arg(long, default_value_t = 30)]
+    duration: u64,
+    /// Synthetic video bitrate in kbit/s (ignored with --replay).
+    #[arg(long, default_value_t = 1000)]
+    video_kbps: u64,
+    /// Replay RTP from an `.lrr` recording instead of generated frames.
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Delay between consecutive joins, in milliseconds.
+    #[arg(long, default_value_t = 20)]
+    join_interval_ms: u64,
+}
+
+// ─── HTTP signalling ────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct RoomResponse {
+    id: String,
+    tokens: BroadcastTokens,
+}
+
+#[derive(Deserialize)]
+struct BroadcastTokens {
+    publish: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct SdpAnswer {
+    sdp: String,
+}
+
+struct Signalling {
+    http: reqwest::Client,
+    url: String,
+    api_key: String,
+}
+
+impl Signalling {
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        bearer: &str,
+        body: serde_json::Value,
+    ) -> Result<T, BoxError> {
+        let resp = self
+            .http
+            .post(format!("{}{path}", self.url))
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("POST {path} → {status}: {text}").into());
+        }
+        Ok(resp.json().await?)
+    }
+
+    async fn create_room(&self) -> Result<RoomResponse, BoxError> {
+        self.post(
+            "/v1/rooms",
+            &self.api_key,
+            serde_json::json!({ "room_type": "broadcast" }),
+        )
+        .await
+    }
+
+    async fn subscribe_token(&self, room_id: &str) -> Result<String, BoxError> {
+        let resp: TokenResponse = self
+            .post(
+                &format!("/v1/rooms/{room_id}/token"),
+                &self.api_key,
+                serde_json::json!({ "role": "subscribe" }),
+            )
+            .await?;
+        Ok(resp.token)
+    }
+
+    /// Full offer/answer exchange.  Returns the time spent waiting for the
+    /// SFU's answer.
+    async fn negotiate(
+        &self,
+        pc: &Arc<RTCPeerConnection>,
+        path: &str,
+        token: &str,
+    ) -> Result<Duration, BoxError> {
+        let offer = pc.create_offer(None).await?;
+        let mut gathered = pc.gathering_complete_promise().await;
+        pc.set_local_description(offer).await?;
+        let _ = gathered.recv().await;
+        let local = pc
+            .local_description()
+            .await
+            .ok_or("local description missing after ICE gathering")?;
+
+        let started = Instant::now();
+        let answer: SdpAnswer = self
+            .post(path, token, serde_json::json!({ "sdp": local.sdp, "type": "offer" }))
+            .await?;
+        let elapsed = started.elapsed();
+
+        pc.set_remote_description(RTCSessionDescription::answer(answer.sdp)?)
+            .await?;
+        Ok(elapsed)
+    }
+}
+
+// ─── WebRTC helpers ─────────────────────────────────────────────────────────
+
+fn build_api() -> Result<API, BoxError> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    Ok(APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build())
+}
+
+/// Resolves once the PeerConnection reaches `Connected`.
+fn on_connected(pc: &Arc<RTCPeerConnection>) -> oneshot::Receiver<()> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    pc.on_peer_connection_state_change(Box::new(move |s| {
+        if s == RTCPeerConnectionState::Connected {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+        }
+        Box::pin(async {})
+    }));
+    rx
+}
+
+fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros() as u64
+}
+
+// ─── Timing trailer ─────────────────────────────────────────────────────────
+
+fn append_trailer(payload: &mut BytesMut, publisher: u32) {
+    payload.put_slice(TRAILER_MAGIC);
+    payload.put_u64_le(now_us());
+    payload.put_u32_le(publisher);
+}
+
+/// Returns (send time in µs, publisher index) if the payload carries a
+/// trailer.
+fn read_trailer(payload: &[u8]) -> Option<(u64, u32)> {
+    let t = payload.get(payload.len().checked_sub(TRAILER_LEN)?..)?;
+    if &t[0..4] != TRAILER_MAGIC {
+        return None;
+    }
+    let sent = u64::from_le_bytes(t[4..12].try_into().ok()?);
+    let publisher = u32::from_le_bytes(t[12..16].try_into().ok()?);
+    Some((sent, publisher))
+}
+
+// ─── Statistics ─────────────────────────────────────────────────────────────
+
+/// Sequence-number accounting for one received RTP stream.
+#[derive(Default)]
+struct StreamLoss {
+    first: Option<u64>,
+    highest: u64,
+    received: u64,
+}
+
+impl StreamLoss {
+    fn record(&mut self, seq: u16) {
+        self.received += 1;
+        if self.first.is_none() {
+            self.first = Some(seq as u64);
+            self.highest = seq as u64;
+            return;
+        }
+        // Extend the 16-bit sequence number relative to the highest one.
+        let cycle = self.highest & !0xFFFF;
+        let mut ext = cycle | seq as u64;
+        if ext + 0x8000 < self.highest {
+            ext += 0x1_0000;
+        } else if ext > self.highest + 0x8000 && ext >= 0x1_0000 {
+            ext -= 0x1_0000;
+        }
+        self.highest = self.highest.max(ext);
+    }
+
+    fn expected(&self) -> u64 {
+        self.first.map_or(0, |f| self.highest - f + 1)
+    }
+}
+
+#[derive(Default)]
+struct SubscriberStats {
+    packets: u64,
+    bytes: u64,
+    delays_us: Vec<u32>,
+    streams: HashMap<u32, StreamLoss>,
+    first_packet: Option<Instant>,
+}
+
+#[derive(Default)]
+struct JoinTimes {
+    signalling: Vec<Duration>,
+    connected: Vec<Duration>,
+    first_packet: Vec<Duration>,
+    failures: Vec<String>,
+}
+
+#[derive(Default)]
+struct SendCounters {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+}
+
+fn percentile<T: Copy + Ord>(sorted: &[T], p: f64) -> Option<T> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+    Some(sorted[idx])
+}
+
+fn fmt_ms(d: Option<Duration>) -> String {
+    d.map_or("-".into(), |d| format!("{:.1}", d.as_secs_f64() * 1000.0))
+}
+
+fn summary(label: &str, mut values: Vec<Duration>) {
+    values.sort();
+    println!(
+        "  {label:<14} n={:<5} p50={:>8} ms  p95={:>8} ms  max={:>8} ms",
+        values.len(),
+        fmt_ms(percentile(&values, 0.5)),
+        fmt_ms(percentile(&values, 0.95)),
+        fmt_ms(values.last().copied()),
+    );
+}
+
+// ─── Media sources ──────────────────────────────────────────────────────────
+
+struct Tracks {
+    video: Arc<TrackLocalStaticRTP>,
+    audio: Arc<TrackLocalStaticRTP>,
+}
+
+/// Generated VP8-shaped video at 30 fps plus 20 ms Opus-sized audio frames.
+/// Runs until the task is aborted.
+async fn send_synthetic(tracks: &Tracks, index: u32, video_kbps: u64, sent: &SendCounters) {
+    let frame_bytes = (video_kbps * 1000 / 8 / 30).max(TRAILER_LEN as u64 + 1) as usize;
+    let mut video_tick = tokio::time::interval(Duration::from_millis(33));
+    let mut audio_tick = tokio::time::interval(Duration::from_millis(20));
+    let (mut vseq, mut vts, mut aseq, mut ats) = (0u16, 0u32, 0u16, 0u32);
+
+    loop {
+        tokio::select! {
+            _ = video_tick.tick() => {
+                let mut remaining = frame_bytes;
+                let mut first = true;
+                while remaining > 0 {
+                    let chunk = remaining.clamp(TRAILER_LEN + 1, MAX_PAYLOAD);
+                    remaining = remaining.saturating_sub(chunk);
+                    let mut payload = BytesMut::with_capacity(chunk);
+                    // VP8 payload descriptor: S bit on the first partition.
+                    payload.put_u8(if first { 0x10 } else { 0x00 });
+                    payload.put_bytes(0, chunk - 1 - TRAILER_LEN);
+                    append_trailer(&mut payload, index);
+                    first = false;
+
+                    let pkt = rtp_packet(vseq, vts, remaining == 0, payload.freeze());
+                    vseq = vseq.wrapping_add(1);
+                    write(&tracks.video, &pkt, sent).await;
+                }
+                vts = vts.wrapping_add(3000);
+            }
+            _ = audio_tick.tick() => {
+                let mut payload = BytesMut::with_capacity(80 + TRAILER_LEN);
+                payload.put_bytes(0, 80);
+                append_trailer(&mut payload, index);
+                let pkt = rtp_packet(aseq, ats, true, payload.freeze());
+                aseq = aseq.wrapping_add(1);
+                ats = ats.wrapping_add(960);
+                write(&tracks.audio, &pkt, sent).await;
+            }
+        }
+    }
+}
+
+/// Replay a `.lrr` recording in a loop, keeping its original pacing.  Screen
+/// packets are sent on the video track.  Sequence numbers are rewritten so
+/// loops do not look like loss.  Runs until the task is aborted.
+async fn send_replay(
+    tracks: &Tracks,
+    index: u32,
+    records: &[lrr::LrrRecord],
+    sent: &SendCounters,
+) {
+    let span = records
+        .last()
+        .map_or(0, |r| r.relative_timestamp_us as u64)
+        + 20_000;
+    let (mut vseq, mut aseq) = (0u16, 0u16);
+    let mut loop_start = Instant::now();
+
+    loop {
+        for record in records {
+            let at = loop_start + Duration::from_micros(record.relative_timestamp_us as u64);
+            tokio::time::sleep_until(at.into()).await;
+
+            let Ok(mut pkt) = Packet::unmarshal(&mut &record.rtp_data[..]) else {
+                continue;
+            };
+            let mut payload = BytesMut::from(&pkt.payload[..]);
+            append_trailer(&mut payload, index);
+            pkt.payload = payload.freeze();
+
+            let (track, seq) = if record.track_kind == lrr::TRACK_AUDIO {
+                (&tracks.audio, &mut aseq)
+            } else {
+                (&tracks.video, &mut vseq)
+            };
+            pkt.header.sequence_number = *seq;
+            *seq = seq.wrapping_add(1);
+            write(track, &pkt, sent).await;
+        }
+        loop_start += Duration::from_micros(span);
+    }
+}
+
+fn rtp_packet(seq: u16, timestamp: u32, marker: bool, payload: Bytes) -> Packet {
+    Packet {
+        header: Header {
+            version: 2,
+            marker,
+            sequence_number: seq,
+            timestamp,
+            ..Default::default()
+        },
+        payload,
+    }
+}
+
+async fn write(track: &TrackLocalStaticRTP, pkt: &Packet, sent: &SendCounters) {
+    if track.write_rtp(pkt).await.is_ok() {
+        sent.packets.fetch_add(1, Ordering::Relaxed);
+        sent.bytes.fetch_add(pkt.payload.len() as u64, Ordering::Relaxed);
+    }
+}
+
+// ─── Peers ──────────────────────────────────────────────────────────────────
+
+struct PublisherPeer {
+    room_id: String,
+    pc: Arc<RTCPeerConnection>,
+    tracks: Tracks,
+}
+
+async fn join_publisher(
+    api: &API,
+    sig: &Signalling,
+    joins: &Mutex<JoinTimes>,
+) -> Result<PublisherPeer, BoxError> {
+    let room = sig.create_room().await?;
+    let pc = Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+    let connected = on_connected(&pc);
+
+    let video = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            clock_rate: 90000,
+            ..Default::default()
+        },
+        "video".into(),
+        "loadtest".into(),
+    ));
+    let audio = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_OPUS.to_owned(),
+            clock_rate: 48000,
+            channels: 2,
+            ..Default::default()
+        },
+        "audio".into(),
+        "loadtest".into(),
+    ));
+    for track in [video.clone(), audio.clone()] {
+        let sender = pc
+            .add_track(track as Arc<dyn TrackLocal + Send + Sync>)
+            .await?;
+        // Drain RTCP so the interceptors keep working.
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 1500];
+            while sender.read(&mut buf).await.is_ok() {}
+        });
+    }
+
+    let started = Instant::now();
+    let signalling = sig.negotiate(&pc, "/sfu/publish", &room.tokens.publish).await?;
+    tokio::time::timeout(Duration::from_secs(15), connected)
+        .await
+        .map_err(|_| "publisher did not connect within 15 s")??;
+
+    let mut j = joins.lock().unwrap();
+    j.signalling.push(signalling);
+    j.connected.push(started.elapsed());
+    drop(j);
+
+    Ok(PublisherPeer {
+        room_id: room.id,
+        pc,
+        tracks: Tracks { video, audio },
+    })
+}
+
+async fn join_subscriber(
+    api: &API,
+    sig: &Signalling,
+    room_id: &str,
+    stats: Arc<Mutex<SubscriberStats>>,
+    joins: &Mutex<JoinTimes>,
+) -> Result<Arc<RTCPeerConnection>, BoxError> {
+    let token = sig.subscribe_token(room_id).await?;
+    let pc = Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+    let connected = on_connected(&pc);
+
+    for kind in [RTPCodecType::Video, RTPCodecType::Audio] {
+        pc.add_transceiver_from_kind(
+            kind,
+            Some(RTCRtpTransceiverInit {
+                direction: RTCRtpTransceiverDirection::Recvonly,
+                send_encodings: vec![],
+            }),
+        )
+        .await?;
+    }
+
+    pc.on_track(Box::new(move |track, _receiver, _transceiver| {
+        let stats = stats.clone();
+        // The next `on_track` only fires once this future completes.
+        tokio::spawn(async move {
+            while let Ok((pkt, _)) = track.read_rtp().await {
+                let received = now_us();
+                let mut s = stats.lock().unwrap();
+                s.packets += 1;
+                s.bytes += pkt.payload.len() as u64;
+                s.first_packet.get_or_insert_with(Instant::now);
+                s.streams
+                    .entry(pkt.header.ssrc)
+                    .or_default()
+                    .record(pkt.header.sequence_number);
+                if let Some((sent, _)) = read_trailer(&pkt.payload) {
+                    s.delays_us.push(received.saturating_sub(sent).min(u32::MAX as u64) as u32);
+                }
+            }
+        });
+        Box::pin(async {})
+    }));
+
+    let started = Instant::now();
+    let signalling = sig.negotiate(&pc, "/sfu/subscribe", &token).await?;
+    tokio::time::timeout(Duration::from_secs(15), connected)
+        .await
+        .map_err(|_| "subscriber did not connect within 15 s")??;
+
+    let mut j = joins.lock().unwrap();
+    j.signalling.push(signalling);
+    j.connected.push(started.elapsed());
+    drop(j);
+
+    Ok(pc)
+}
+
+// ─── Main ───────────────────────────────────────────────────────────────────
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .map_err(|_| "failed to install rustls crypto provider")?;
+
+    let args = Args::parse();
+    if args.publishers == 0 {
+        return Err("--publishers must be at least 1".into());
+    }
+
+    let records = match &args.replay {
+        Some(path) => {
+            let data = std::fs::read(path)?;
+            lrr::read_lrr_header(&data)?;
+            let records = lrr::read_lrr_records(&data);
+            if records.is_empty() {
+                return Err(format!("{} contains no RTP packets", path.display()).into());
+            }
+            println!("Replaying {} packets from {}", records.len(), path.display());
+            Arc::new(records)
+        }
+        None => Arc::new(Vec::new()),
+    };
+
+    let api = Arc::new(build_api()?);
+    let sig = Arc::new(Signalling {
+        http: reqwest::Client::new(),
+        url: args.url.trim_end_matches('/').to_string(),
+        api_key: args.api_key.clone(),
+    });
+    let pub_joins = Arc::new(Mutex::new(JoinTimes::default()));
+    let sub_joins = Arc::new(Mutex::new(JoinTimes::default()));
+    let join_interval = Duration::from_millis(args.join_interval_ms);
+
+    // 1. Publishers: join, then start sending right away so the SFU knows
+    //    their codecs before subscribers arrive.
+    let sent = Arc::new(SendCounters::default());
+    let mut publishers = Vec::new();
+    let mut senders = Vec::new();
+    for index in 0..args.publishers {
+        match join_publisher(&api, &sig, &pub_joins).await {
+            Ok(peer) => {
+                let tracks = Tracks {
+                    video: peer.tracks.video.clone(),
+                    audio: peer.tracks.audio.clone(),
+                };
+                let records = records.clone();
+                let sent = sent.clone();
+                let kbps = args.video_kbps;
+                senders.push(tokio::spawn(async move {
+                    if records.is_empty() {
+                        send_synthetic(&tracks, index as u32, kbps, &sent).await;
+                    } else {
+                        send_replay(&tracks, index as u32, &records, &sent).await;
+                    }
+                }));
+                publishers.push(peer);
+            }
+            Err(e) => pub_joins.lock().unwrap().failures.push(e.to_string()),
+        }
+        tokio::time::sleep(join_interval).await;
+    }
+    if publishers.is_empty() {
+        return Err("no publisher could join".into());
+    }
+    println!("{} publisher(s) joined", publishers.len());
+
+    // 2. Subscribers, round-robin over the rooms, joined concurrently.
+    let mut joining = Vec::new();
+    let mut sub_stats = Vec::new();
+    for i in 0..args.subscribers {
+        let room_id = publishers[i % publishers.len()].room_id.clone();
+        let stats = Arc::new(Mutex::new(SubscriberStats::default()));
+        sub_stats.push(stats.clone());
+        let (api, sig, joins) = (api.clone(), sig.clone(), sub_joins.clone());
+        joining.push(tokio::spawn(async move {
+            let started = Instant::now();
+            let result = join_subscriber(&api, &sig, &room_id, stats, &joins).await;
+            (started, result)
+        }));
+        tokio::time::sleep(join_interval).await;
+    }
+    let mut subscribers = Vec::new();
+    let mut join_starts = Vec::new();
+    for handle in joining {
+        match handle.await? {
+            (started, Ok(pc)) => {
+                subscribers.push(pc);
+                join_starts.push(Some(started));
+            }
+            (_, Err(e)) => {
+                sub_joins.lock().unwrap().failures.push(e.to_string());
+                join_starts.push(None);
+            }
+        }
+    }
+    println!("{} subscriber(s) joined", subscribers.len());
+
+    // 3. Measure over the configured duration.
+    let (sent_packets0, sent_bytes0) = (
+        sent.packets.load(Ordering::Relaxed),
+        sent.bytes.load(Ordering::Relaxed),
+    );
+    let (recv_packets0, recv_bytes0) = totals(&sub_stats);
+    let window = Instant::now();
+    tokio::time::sleep(Duration::from_secs(args.duration)).await;
+    let elapsed = window.elapsed().as_secs_f64();
+    let (recv_packets1, recv_bytes1) = totals(&sub_stats);
+    let (sent_packets1, sent_bytes1) = (
+        sent.packets.load(Ordering::Relaxed),
+        sent.bytes.load(Ordering::Relaxed),
+    );
+
+    for pc in subscribers.iter().chain(publishers.iter().map(|p| &p.pc)) {
+        let _ = pc.close().await;
+    }
+    for handle in senders {
+        handle.abort();
+    }
+
+    // 4. Report.
+    {
+        let mut j = sub_joins.lock().unwrap();
+        for (stats, started) in sub_stats.iter().zip(&join_starts) {
+            if let (Some(first), Some(started)) = (stats.lock().unwrap().first_packet, started) {
+                j.first_packet.push(first.duration_since(*started));
+            }
+        }
+    }
+
+    let mut delays: Vec<u32> = Vec::new();
+    let (mut expected, mut received) = (0u64, 0u64);
+    for stats in &sub_stats {
+        let s = stats.lock().unwrap();
+        delays.extend_from_slice(&s.delays_us);
+        for stream in s.streams.values() {
+            expected += stream.expected();
+            received += stream.received.min(stream.expected());
+        }
+    }
+    delays.sort_unstable();
+
+    let pub_joins = std::mem::take(&mut *pub_joins.lock().unwrap());
+    let sub_joins = std::mem::take(&mut *sub_joins.lock().unwrap());
+    let mbps = |bytes: u64| bytes as f64 * 8.0 / elapsed / 1_000_000.0;
+    let delay_ms = |p: f64| {
+        percentile(&delays, p).map_or("-".into(), |us| format!("{:.1}", us as f64 / 1000.0))
+    };
+
+    println!();
+    println!("──── LiveRelay load test ────");
+    println!(
+        "  target         : {}  ({} publisher(s), {} subscriber(s), {}s, source={})",
+        sig.url,
+        args.publishers,
+        args.subscribers,
+        args.duration,
+        args.replay
+            .as_ref()
+            .map_or("synthetic".into(), |p| p.display().to_string()),
+    );
+    println!("Publisher joins ({} failed)", pub_joins.failures.len());
+    summary("signalling", pub_joins.signalling);
+    summary("connected", pub_joins.connected);
+    println!("Subscriber joins ({} failed)", sub_joins.failures.len());
+    summary("signalling", sub_joins.signalling);
+    summary("connected", sub_joins.connected);
+    summary("first packet", sub_joins.first_packet);
+    for failure in pub_joins.failures.iter().chain(&sub_joins.failures).take(5) {
+        println!("  error: {failure}");
+    }
+    println!("Media (over {elapsed:.1}s)");
+    println!(
+        "  sent           : {} pkts, {:.2} Mbit/s",
+        sent_packets1 - sent_packets0,
+        mbps(sent_bytes1 - sent_bytes0)
+    );
+    println!(
+        "  received       : {} pkts, {:.2} Mbit/s (fan-out)",
+        recv_packets1 - recv_packets0,
+        mbps(recv_bytes1 - recv_bytes0)
+    );
+    println!(
+        "  delay          : p50={} ms  p95={} ms  p99={} ms  max={} ms",
+        delay_ms(0.5),
+        delay_ms(0.95),
+        delay_ms(0.99),
+        delay_ms(1.0)
+    );
+    let loss = if expected > 0 {
+        100.0 * (expected - received) as f64 / expected as f64
+    } else {
+        0.0
+    };
+    println!("  loss           : {loss:.2}% ({received}/{expected} packets)");
+
+    Ok(())
+}
+
+fn totals(stats: &[Arc<Mutex<SubscriberStats>>]) -> (u64, u64) {
+    stats.iter().fold((0, 0), |(p, b), s| {
+        let s = s.lock().unwrap();
+        (p + s.packets, b + s.bytes)
+    })
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailer_roundtrip() {
+        let mut payload = BytesMut::from(&b"media"[..]);
+        append_trailer(&mut payload, 7);
+        let (sent, publisher) = read_trailer(&payload).unwrap();
+        assert_eq!(publisher, 7);
+        assert!(sent <= now_us());
+        assert!(read_trailer(b"short").is_none());
+        assert!(read_trailer(&[0u8; 32]).is_none());
+    }
+
+    #[test]
+    fn loss_across_sequence_wrap() {
+        let mut s = StreamLoss::default();
+        for seq in [65533u16, 65534, 0, 1, 3] {
+            s.record(seq);
+        }
+        assert_eq!(s.received, 5);
+        assert_eq!(s.expected(), 7); // 65533..=65539, 2 missing
+    }
+
+    #[test]
+    fn percentiles() {
+        let v = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        assert_eq!(percentile(&v, 0.5), Some(6));
+        assert_eq!(percentile(&v, 1.0), Some(10));
+        assert_eq!(percentile::<u32>(&[], 0.5), None);
+    }
+}
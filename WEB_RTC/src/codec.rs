@@ -0,0 +1,536 @@
+// src/codec.rs
+//
+// Per-room codec policy.
+//
+// ─ Request ──────────────────────────────────────────────────────────────────
+//
+//   POST /v1/rooms
+//   {
+//     "room_type": "conference",
+//     "codecs": {
+//       "video": ["h264", "vp8"],          // preference order
+//       "audio": ["opus"],
+//       "h264_profile": "42e01f",          // optional: profile_idc + flags
+//       "vp9_profile": 0                   // optional: VP9 profile-id
+//     }
+//   }
+//
+//   Every field is optional; an omitted list allows every codec the SFU
+//   supports, in its default order.
+//
+// ─ Enforcement ──────────────────────────────────────────────────────────────
+//
+//   * Every PeerConnection in the room gets a `MediaEngine` holding only the
+//     allowed codecs, registered in preference order.
+//   * Publisher offers are checked before any PeerConnection is created: an
+//     offer that sends video (or audio) without a single allowed codec is
+//     rejected with `codec_not_allowed`.
+//   * Subscriber tracks whose publisher codec is not known yet fall back to
+//     the room's first allowed codec instead of a hardcoded VP8/Opus.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use webrtc::api::media_engine::{
+    MediaEngine, MIME_TYPE_AV1, MIME_TYPE_G722, MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_PCMA,
+    MIME_TYPE_PCMU, MIME_TYPE_VP8, MIME_TYPE_VP9,
+};
+use webrtc::rtp_transceiver::rtp_codec::{
+    RTCRtpCodecCapability, RTCRtpCodecParameters, RTPCodecType,
+};
+use webrtc::rtp_transceiver::RTCPFeedback;
+
+// ─── Codec names ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    Vp8,
+    Vp9,
+    H264,
+    Av1,
+}
+
+impl VideoCodec {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Vp8 => MIME_TYPE_VP8,
+            Self::Vp9 => MIME_TYPE_VP9,
+            Self::H264 => MIME_TYPE_H264,
+            Self::Av1 => MIME_TYPE_AV1,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Vp8 => "vp8",
+            Self::Vp9 => "vp9",
+            Self::H264 => "h264",
+            Self::Av1 => "av1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Opus,
+    G722,
+    Pcmu,
+    Pcma,
+}
+
+impl AudioCodec {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Opus => MIME_TYPE_OPUS,
+            Self::G722 => MIME_TYPE_G722,
+            Self::Pcmu => MIME_TYPE_PCMU,
+            Self::Pcma => MIME_TYPE_PCMA,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Opus => "opus",
+            Self::G722 => "g722",
+            Self::Pcmu => "pcmu",
+            Self::Pcma => "pcma",
+        }
+    }
+}
+
+// ─── CodecPolicy ────────────────────────────────────────────────────────────
+
+/// Codecs a room accepts, in preference order, plus optional profile
+/// constraints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodecPolicy {
+    pub video: Vec<VideoCodec>,
+    pub audio: Vec<AudioCodec>,
+    /// H264 `profile-level-id`.  Only the profile part (first four hex
+    /// digits: profile_idc + constraint flags) is enforced; the level is
+    /// left to the browsers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub h264_profile: Option<String>,
+    /// VP9 `profile-id` (0 or 1).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vp9_profile: Option<u8>,
+}
+
+impl Default for CodecPolicy {
+    fn default() -> Self {
+        Self {
+            video: vec![
+                VideoCodec::Vp8,
+                VideoCodec::Vp9,
+                VideoCodec::H264,
+                VideoCodec::Av1,
+            ],
+            audio: vec![
+                AudioCodec::Opus,
+                AudioCodec::G722,
+                AudioCodec::Pcmu,
+                AudioCodec::Pcma,
+            ],
+            h264_profile: None,
+            vp9_profile: None,
+        }
+    }
+}
+
+impl CodecPolicy {
+    /// Check the policy when a room is created.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.video.is_empty() || self.audio.is_empty() {
+            return Err("Codec policy must allow at least one video and one audio codec.".into());
+        }
+        if let Some(profile) = &self.h264_profile {
+            if profile.len() != 6 || !profile.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "Invalid h264_profile '{profile}': expected a 6-digit hex profile-level-id."
+                ));
+            }
+            if !self.video.contains(&VideoCodec::H264) {
+                return Err("h264_profile is set but h264 is not an allowed codec.".into());
+            }
+        }
+        if let Some(profile) = self.vp9_profile {
+            if profile > 1 {
+                return Err(format!("Unsupported vp9_profile {profile}: expected 0 or 1."));
+            }
+            if !self.video.contains(&VideoCodec::Vp9) {
+                return Err("vp9_profile is set but vp9 is not an allowed codec.".into());
+            }
+        }
+        if self.catalogue(RTPCodecType::Video).is_empty() {
+            return Err("No supported video codec matches the profile constraints.".into());
+        }
+        Ok(())
+    }
+
+    /// Register the allowed codecs, in preference order, on a fresh
+    /// `MediaEngine`.
+    pub fn register(&self, media_engine: &mut MediaEngine) -> Result<(), webrtc::Error> {
+        for kind in [RTPCodecType::Audio, RTPCodecType::Video] {
+            for codec in self.catalogue(kind) {
+                media_engine.register_codec(codec, kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Track codec to use when the publisher's codec is not known yet.
+    pub fn fallback(&self, kind: RTPCodecType) -> RTCRtpCodecCapability {
+        self.catalogue(kind)
+            .into_iter()
+            .next()
+            .map(|c| c.capability)
+            .unwrap_or_default()
+    }
+
+    /// Comma-separated allowed codec names, for error messages.
+    pub fn allowed_names(&self, kind: RTPCodecType) -> String {
+        let names: Vec<&str> = match kind {
+            RTPCodecType::Audio => self.audio.iter().map(|c| c.as_str()).collect(),
+            _ => self.video.iter().map(|c| c.as_str()).collect(),
+        };
+        names.join(", ")
+    }
+
+    /// Check that every audio / video section an offer sends on contains at
+    /// least one allowed codec.  Returns the offending kind otherwise.
+    pub fn check_offer(&self, sdp: &str) -> Result<(), RTPCodecType> {
+        for section in parse_media_sections(sdp) {
+            if !section.sends {
+                continue;
+            }
+            let allowed = section.codecs.iter().any(|(mime, fmtp)| {
+                self.allows(section.kind, mime, fmtp)
+            });
+            if !allowed {
+                return Err(section.kind);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a codec (mime type + fmtp line) satisfies the policy.
+    fn allows(&self, kind: RTPCodecType, mime: &str, fmtp: &str) -> bool {
+        match kind {
+            RTPCodecType::Audio => self
+                .audio
+                .iter()
+                .any(|c| c.mime_type().eq_ignore_ascii_case(mime)),
+            RTPCodecType::Video => {
+                let Some(codec) = self
+                    .video
+                    .iter()
+                    .find(|c| c.mime_type().eq_ignore_ascii_case(mime))
+                else {
+                    return false;
+                };
+                match codec {
+                    VideoCodec::H264 => self.h264_profile.as_deref().is_none_or(|want| {
+                        fmtp_param(fmtp, "profile-level-id").is_some_and(|have| {
+                            have.len() >= 4 && have[..4].eq_ignore_ascii_case(&want[..4])
+                        })
+                    }),
+                    VideoCodec::Vp9 => self.vp9_profile.is_none_or(|want| {
+                        let have = fmtp_param(fmtp, "profile-id")
+                            .and_then(|p| p.parse::<u8>().ok())
+                            .unwrap_or(0);
+                        have == want
+                    }),
+                    _ => true,
+                }
+            }
+            RTPCodecType::Unspecified => false,
+        }
+    }
+
+    /// Supported codec parameters of one kind that satisfy the policy,
+    /// ordered by preference.
+    fn catalogue(&self, kind: RTPCodecType) -> Vec<RTCRtpCodecParameters> {
+        let supported = supported_codecs(kind);
+        let order: Vec<&'static str> = match kind {
+            RTPCodecType::Audio => self.audio.iter().map(|c| c.mime_type()).collect(),
+            _ => self.video.iter().map(|c| c.mime_type()).collect(),
+        };
+
+        order
+            .into_iter()
+            .flat_map(|mime| {
+                supported
+                    .iter()
+                    .filter(move |c| c.capability.mime_type == mime)
+                    .filter(|c| self.allows(kind, mime, &c.capability.sdp_fmtp_line))
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+// ─── Supported codecs ───────────────────────────────────────────────────────
+
+/// The codecs webrtc-rs registers in `register_default_codecs()`.
+fn supported_codecs(kind: RTPCodecType) -> Vec<RTCRtpCodecParameters> {
+    let codec = |mime: &str, clock_rate, channels, fmtp: &str, rtcp_feedback, payload_type| {
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: mime.to_owned(),
+                clock_rate,
+                channels,
+                sdp_fmtp_line: fmtp.to_owned(),
+                rtcp_feedback,
+            },
+            payload_type,
+            ..Default::default()
+        }
+    };
+
+    match kind {
+        RTPCodecType::Audio => vec![
+            codec(MIME_TYPE_OPUS, 48000, 2, "minptime=10;useinbandfec=1", vec![], 111),
+            codec(MIME_TYPE_G722, 8000, 0, "", vec![], 9),
+            codec(MIME_TYPE_PCMU, 8000, 0, "", vec![], 0),
+            codec(MIME_TYPE_PCMA, 8000, 0, "", vec![], 8),
+        ],
+        RTPCodecType::Video => {
+            let fb = || {
+                [("goog-remb", ""), ("ccm", "fir"), ("nack", ""), ("nack", "pli")]
+                    .into_iter()
+                    .map(|(typ, parameter)| RTCPFeedback {
+                        typ: typ.to_owned(),
+                        parameter: parameter.to_owned(),
+                    })
+                    .collect::<Vec<_>>()
+            };
+            vec![
+                codec(MIME_TYPE_VP8, 90000, 0, "", fb(), 96),
+                codec(MIME_TYPE_VP9, 90000, 0, "profile-id=0", fb(), 98),
+                codec(MIME_TYPE_VP9, 90000, 0, "profile-id=1", fb(), 100),
+                codec(
+                    MIME_TYPE_H264,
+                    90000,
+                    0,
+                    "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42001f",
+                    fb(),
+                    102,
+                ),
+                codec(
+                    MIME_TYPE_H264,
+                    90000,
+                    0,
+                    "level-asymmetry-allowed=1;packetization-mode=0;profile-level-id=42001f",
+                    fb(),
+                    127,
+                ),
+                codec(
+                    MIME_TYPE_H264,
+                    90000,
+                    0,
+                    "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f",
+                    fb(),
+                    125,
+                ),
+                codec(
+                    MIME_TYPE_H264,
+                    90000,
+                    0,
+                    "level-asymmetry-allowed=1;packetization-mode=0;profile-level-id=42e01f",
+                    fb(),
+                    108,
+                ),
+                codec(
+                    MIME_TYPE_H264,
+                    90000,
+                    0,
+                    "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=640032",
+                    fb(),
+                    123,
+                ),
+                codec(MIME_TYPE_AV1, 90000, 0, "profile-id=0", fb(), 41),
+            ]
+        }
+        RTPCodecType::Unspecified => vec![],
+    }
+}
+
+// ─── SDP parsing ────────────────────────────────────────────────────────────
+
+/// One `m=audio` / `m=video` section of an SDP offer.
+struct MediaSection {
+    kind: RTPCodecType,
+    /// `false` for rejected (port 0), `recvonly` and `inactive` sections.
+    sends: bool,
+    /// (mime type, fmtp line) for every payload type in the section.
+    codecs: Vec<(String, String)>,
+}
+
+fn parse_media_sections(sdp: &str) -> Vec<MediaSection> {
+    struct Pending {
+        kind: RTPCodecType,
+        sends: bool,
+        rtpmap: Vec<(String, String)>,
+        fmtp: HashMap<String, String>,
+    }
+
+    fn finish(pending: Pending) -> MediaSection {
+        let codecs = pending
+            .rtpmap
+            .into_iter()
+            .map(|(pt, mime)| {
+                let fmtp = pending.fmtp.get(&pt).cloned().unwrap_or_default();
+                (mime, fmtp)
+            })
+            .collect();
+        MediaSection {
+            kind: pending.kind,
+            sends: pending.sends,
+            codecs,
+        }
+    }
+
+    let mut sections = Vec::new();
+    let mut current: Option<Pending> = None;
+
+    for line in sdp.lines().map(str::trim) {
+        if let Some(media) = line.strip_prefix("m=") {
+            if let Some(pending) = current.take() {
+                sections.push(finish(pending));
+            }
+            let mut fields = media.split_whitespace();
+            let kind = match fields.next() {
+                Some("audio") => RTPCodecType::Audio,
+                Some("video") => RTPCodecType::Video,
+                _ => continue, // data channels etc.
+            };
+            let port_open = fields.next().is_some_and(|p| p != "0");
+            current = Some(Pending {
+                kind,
+                sends: port_open,
+                rtpmap: Vec::new(),
+                fmtp: HashMap::new(),
+            });
+            continue;
+        }
+
+        let Some(pending) = current.as_mut() else {
+            continue;
+        };
+        if line == "a=recvonly" || line == "a=inactive" {
+            pending.sends = false;
+        } else if let Some(rest) = line.strip_prefix("a=rtpmap:") {
+            if let Some((pt, encoding)) = rest.split_once(' ') {
+                let name = encoding.split('/').next().unwrap_or_default();
+                let prefix = match pending.kind {
+                    RTPCodecType::Audio => "audio",
+                    _ => "video",
+                };
+                pending
+                    .rtpmap
+                    .push((pt.to_string(), format!("{prefix}/{name}")));
+            }
+        } else if let Some(rest) = line.strip_prefix("a=fmtp:") {
+            if let Some((pt, params)) = rest.split_once(' ') {
+                pending.fmtp.insert(pt.to_string(), params.to_string());
+            }
+        }
+    }
+    if let Some(pending) = current {
+        sections.push(finish(pending));
+    }
+
+    sections
+}
+
+/// Value of `key` in an fmtp parameter list (`a=1;b=2`).
+fn fmtp_param<'a>(fmtp: &'a str, key: &str) -> Option<&'a str> {
+    fmtp.split(';')
+        .filter_map(|kv| kv.trim().split_once('='))
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 1 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+        a=sendrecv\r\n\
+        a=rtpmap:111 opus/48000/2\r\n\
+        a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+        a=rtpmap:0 PCMU/8000\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96 97 102\r\n\
+        a=sendrecv\r\n\
+        a=rtpmap:96 VP8/90000\r\n\
+        a=rtpmap:97 rtx/90000\r\n\
+        a=fmtp:97 apt=96\r\n\
+        a=rtpmap:102 H264/90000\r\n\
+        a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\r\n\
+        m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n";
+
+    fn policy(json: &str) -> CodecPolicy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn default_policy_allows_everything() {
+        let p = CodecPolicy::default();
+        assert!(p.validate().is_ok());
+        assert!(p.check_offer(OFFER).is_ok());
+        assert_eq!(p.fallback(RTPCodecType::Video).mime_type, MIME_TYPE_VP8);
+        assert_eq!(p.fallback(RTPCodecType::Audio).mime_type, MIME_TYPE_OPUS);
+    }
+
+    #[test]
+    fn partial_policy_keeps_defaults() {
+        let p = policy(r#"{"video":["h264","vp8"]}"#);
+        assert_eq!(p.video, vec![VideoCodec::H264, VideoCodec::Vp8]);
+        assert_eq!(p.audio, CodecPolicy::default().audio);
+        assert_eq!(p.fallback(RTPCodecType::Video).mime_type, MIME_TYPE_H264);
+    }
+
+    #[test]
+    fn offer_without_allowed_video_is_rejected() {
+        let p = policy(r#"{"video":["av1","vp9"]}"#);
+        assert_eq!(p.check_offer(OFFER), Err(RTPCodecType::Video));
+        assert_eq!(p.allowed_names(RTPCodecType::Video), "av1, vp9");
+    }
+
+    #[test]
+    fn h264_profile_constraint() {
+        let baseline = policy(r#"{"video":["h264"],"h264_profile":"42e01f"}"#);
+        assert!(baseline.check_offer(OFFER).is_ok());
+
+        let high = policy(r#"{"video":["h264"],"h264_profile":"640032"}"#);
+        assert_eq!(high.check_offer(OFFER), Err(RTPCodecType::Video));
+        let registered = high.catalogue(RTPCodecType::Video);
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].payload_type, 123);
+    }
+
+    #[test]
+    fn recvonly_sections_are_not_checked() {
+        let p = policy(r#"{"video":["av1"]}"#);
+        let offer = OFFER.replace("a=sendrecv\r\na=rtpmap:96", "a=recvonly\r\na=rtpmap:96");
+        assert!(p.check_offer(&offer).is_ok());
+    }
+
+    #[test]
+    fn validation_errors() {
+        assert!(policy(r#"{"video":[]}"#).validate().is_err());
+        assert!(policy(r#"{"h264_profile":"xyz"}"#).validate().is_err());
+        assert!(policy(r#"{"video":["vp8"],"vp9_profile":0}"#).validate().is_err());
+        assert!(policy(r#"{"vp9_profile":2}"#).validate().is_err());
+        assert!(serde_json::from_str::<CodecPolicy>(r#"{"video":["h265"]}"#).is_err());
+    }
+}
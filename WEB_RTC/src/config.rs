@@ -1,18 +1,35 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 use tracing::{info, warn};
 
+use crate::analytics::QualityThresholds;
+use crate::hls::{HlsConfig, S3Config};
+use crate::webhook::RetryPolicy;
+
+/// Config file read when `LIVERELAY_CONFIG` is not set, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "liverelay.toml";
+
 // ---------------------------------------------------------------------------
-// Production configuration — loaded from environment variables
+// Production configuration — TOML file layered under environment variables
 // ---------------------------------------------------------------------------
 
 /// Complete server configuration loaded at startup.
 ///
-/// Every field can be set via an environment variable prefixed with
-/// `LIVERELAY_`.  Defaults are suitable for local development; production
-/// deployments MUST override at least `jwt_secret` and the TLS / TURN
-/// settings.
+/// Each setting is taken from its `LIVERELAY_` environment variable, else
+/// from the TOML config file (`LIVERELAY_CONFIG`, or `./liverelay.toml`),
+/// else from the default.  Defaults are suitable for local development;
+/// production deployments MUST override at least `jwt_secret` and the
+/// TLS / TURN settings.  Secrets are only read from the environment.
+///
+/// The settings in [`RuntimeSettings`] can be reloaded without a restart;
+/// see `reload.rs`.
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// Config file the settings were layered over, if any.
+    pub config_path: Option<String>,
+
     // ── Network ─────────────────────────────────────────────────────────
     /// Address to bind the HTTP(S) listener to.
     pub bind_addr: String,
@@ -32,10 +49,11 @@ pub struct Config {
     pub turn_embedded: bool,
     /// UDP port for the embedded TURN relay.
     pub turn_port: u16,
-    /// TURN username (shared-secret / long-term credentials).
-    pub turn_username: String,
-    /// TURN password.
-    pub turn_password: String,
+    /// Shared secret for time-limited TURN REST credentials.  Must match
+    /// coturn's `static-auth-secret` when an external TURN is used.
+    pub turn_secret: String,
+    /// Lifetime of minted TURN credentials, in seconds.
+    pub turn_credential_ttl_secs: u64,
     /// TURN realm (usually the domain).
     pub turn_realm: String,
 
@@ -46,6 +64,8 @@ pub struct Config {
 
     // ── Auth ─────────────────────────────────────────────────────────────
     pub jwt_secret: String,
+    /// API key registered at startup (`LIVERELAY_API_KEY`, else random).
+    pub bootstrap_api_key: String,
 
     // ── Limits ───────────────────────────────────────────────────────────
     /// Maximum number of rooms that can exist simultaneously.
@@ -59,78 +79,288 @@ pub struct Config {
     /// Maximum UDP port for WebRTC ICE candidates (0 = OS picks).
     pub udp_port_max: u16,
 
+    // ── Drain ────────────────────────────────────────────────────────────
+    /// How long a draining node waits for its rooms to empty before exiting.
+    pub drain_deadline_secs: u64,
+
     // ── CORS ─────────────────────────────────────────────────────────────
+    /// `*`, or a comma-separated list of origins.
     pub allowed_origins: String,
 
+    // ── Recording ────────────────────────────────────────────────────────
+    /// Directory recordings are written to.
+    pub recording_dir: String,
+    /// Recordings stop automatically after this many seconds (0 = never).
+    pub recording_max_duration_secs: u64,
+
+    // ── HLS egress ───────────────────────────────────────────────────────
+    pub hls: HlsConfig,
+
+    // ── Moderation snapshots ─────────────────────────────────────────────
+    /// Directory snapshots are written to.
+    pub snapshot_dir: String,
+    /// Snapshots kept per room; older files are deleted.
+    pub snapshot_retain_per_room: usize,
+
+    // ── Analytics ────────────────────────────────────────────────────────
+    /// How often WebRTC stats are collected from every peer.
+    pub stats_interval_secs: u64,
+    /// Limits that trigger `quality.degraded` events.
+    pub quality_thresholds: QualityThresholds,
+
+    // ── Webhooks ─────────────────────────────────────────────────────────
+    pub webhook_retry: RetryPolicy,
+
     // ── Logging ──────────────────────────────────────────────────────────
     pub log_level: String,
 }
 
 impl Config {
-    /// Load configuration from environment variables.
+    /// Load and validate the startup configuration, logging a summary.
     ///
     /// Automatically loads a `.env` file if present (via `dotenvy`).
-    pub fn from_env() -> Self {
+    pub fn load() -> Result<Self, ConfigError> {
         // Best-effort .env loading — ignore errors.
         let _ = dotenvy::dotenv();
 
-        let jwt_secret = match std::env::var("LIVERELAY_JWT_SECRET") {
-            Ok(s) if !s.is_empty() => {
-                info!("JWT secret loaded from LIVERELAY_JWT_SECRET");
-                s
-            }
-            _ => {
-                let secret = uuid::Uuid::new_v4().to_string();
-                warn!(
-                    "LIVERELAY_JWT_SECRET not set — using random value (not suitable for production)"
-                );
-                secret
-            }
+        let config = Self::read()?;
+
+        if env_set("LIVERELAY_JWT_SECRET") {
+            info!("JWT secret loaded from LIVERELAY_JWT_SECRET");
+        } else {
+            warn!(
+                "LIVERELAY_JWT_SECRET not set — using random value (not suitable for production)"
+            );
+        }
+        if !env_set("LIVERELAY_TURN_SECRET") && !config.turn_urls.is_empty() {
+            warn!(
+                "LIVERELAY_TURN_SECRET not set — using random value (external TURN servers will reject credentials)"
+            );
+        }
+
+        config.log_summary();
+        Ok(config)
+    }
+
+    /// Read the config file and the environment, without logging.  Used
+    /// at startup and on reload.
+    pub fn read() -> Result<Self, ConfigError> {
+        let config_path = std::env::var("LIVERELAY_CONFIG")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .or_else(|| {
+                std::path::Path::new(DEFAULT_CONFIG_FILE)
+                    .exists()
+                    .then(|| DEFAULT_CONFIG_FILE.to_string())
+            });
+
+        let file = match &config_path {
+            Some(path) => FileConfig::read(path)?,
+            None => FileConfig::default(),
         };
 
-        let bind_addr = env_or("LIVERELAY_BIND_ADDR", "0.0.0.0:8080");
-        let public_host = env_or("LIVERELAY_PUBLIC_HOST", "localhost");
+        let mut config = Self::from_layers(file, &|key| std::env::var(key).ok())?;
+        config.config_path = config_path;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Layer `env` over `file` over the defaults.
+    fn from_layers(file: FileConfig, env: &Env) -> Result<Self, ConfigError> {
+        let jwt_secret = match env("LIVERELAY_JWT_SECRET") {
+            Some(s) if !s.is_empty() => s,
+            _ => uuid::Uuid::new_v4().to_string(),
+        };
+        let turn_secret = match env("LIVERELAY_TURN_SECRET") {
+            Some(s) if !s.is_empty() => s,
+            _ => uuid::Uuid::new_v4().to_string(),
+        };
+        let bootstrap_api_key = match env("LIVERELAY_API_KEY") {
+            Some(s) if !s.is_empty() => s,
+            _ => crate::auth::generate_api_key(),
+        };
+
+        let bind_addr = layer(env, "LIVERELAY_BIND_ADDR", file.bind_addr, "0.0.0.0:8080".into())?;
+        let public_host =
+            layer(env, "LIVERELAY_PUBLIC_HOST", file.public_host, "localhost".into())?;
 
         // TLS
-        let tls_enabled = env_bool("LIVERELAY_TLS_ENABLED", false);
-        let tls_cert_path = std::env::var("LIVERELAY_TLS_CERT_PATH").ok();
-        let tls_key_path = std::env::var("LIVERELAY_TLS_KEY_PATH").ok();
+        let tls_enabled = layer_bool(env, "LIVERELAY_TLS_ENABLED", file.tls.enabled, false);
+        let tls_cert_path = env("LIVERELAY_TLS_CERT_PATH").or(file.tls.cert_path);
+        let tls_key_path = env("LIVERELAY_TLS_KEY_PATH").or(file.tls.key_path);
 
         // TURN
-        let turn_embedded = env_bool("LIVERELAY_TURN_EMBEDDED", false);
-        let turn_port = env_or("LIVERELAY_TURN_PORT", "3478")
-            .parse::<u16>()
-            .unwrap_or(3478);
-        let turn_username = env_or("LIVERELAY_TURN_USERNAME", "liverelay");
-        let turn_password = env_or("LIVERELAY_TURN_PASSWORD", "liverelay-secret");
-        let turn_realm = env_or("LIVERELAY_TURN_REALM", &public_host);
-
-        let stun_urls = env_csv(
+        let turn_embedded = layer_bool(env, "LIVERELAY_TURN_EMBEDDED", file.turn.embedded, false);
+        let turn_port = layer(env, "LIVERELAY_TURN_PORT", file.turn.port, 3478)?;
+        let turn_credential_ttl_secs = layer(
+            env,
+            "LIVERELAY_TURN_CREDENTIAL_TTL",
+            file.turn.credential_ttl_secs,
+            86400,
+        )?;
+        let turn_realm = layer(env, "LIVERELAY_TURN_REALM", file.turn.realm, public_host.clone())?;
+
+        let stun_urls = layer_csv(
+            env,
             "LIVERELAY_STUN_URLS",
+            file.turn.stun_urls,
             &["stun:stun.l.google.com:19302"],
         );
-        let turn_urls = env_csv("LIVERELAY_TURN_URLS", &[]);
+        let turn_urls = layer_csv(env, "LIVERELAY_TURN_URLS", file.turn.urls, &[]);
 
         // Limits
-        let max_rooms = env_or("LIVERELAY_MAX_ROOMS", "100")
-            .parse::<usize>()
-            .unwrap_or(100);
-        let max_subscribers_per_room =
-            env_or("LIVERELAY_MAX_SUBSCRIBERS_PER_ROOM", "1000")
-                .parse::<u64>()
-                .unwrap_or(1000);
-
-        let allowed_origins = env_or("LIVERELAY_ALLOWED_ORIGINS", "*");
-        let log_level = env_or("LIVERELAY_LOG_LEVEL", "info");
-
-        let udp_port_min = env_or("LIVERELAY_UDP_PORT_MIN", "0")
-            .parse::<u16>()
-            .unwrap_or(0);
-        let udp_port_max = env_or("LIVERELAY_UDP_PORT_MAX", "0")
-            .parse::<u16>()
-            .unwrap_or(0);
+        let max_rooms = layer(env, "LIVERELAY_MAX_ROOMS", file.limits.max_rooms, 100)?;
+        let max_subscribers_per_room = layer(
+            env,
+            "LIVERELAY_MAX_SUBSCRIBERS_PER_ROOM",
+            file.limits.max_subscribers_per_room,
+            1000,
+        )?;
 
-        let config = Config {
+        let drain_deadline_secs = layer(
+            env,
+            "LIVERELAY_DRAIN_DEADLINE_SECS",
+            file.drain.deadline_secs,
+            600,
+        )?;
+
+        let allowed_origins = layer(
+            env,
+            "LIVERELAY_ALLOWED_ORIGINS",
+            file.cors.allowed_origins.map(|o| o.join(",")),
+            "*".into(),
+        )?;
+        let log_level = env("LIVERELAY_LOG_LEVEL").unwrap_or_else(|| "info".into());
+
+        let udp_port_min = layer(env, "LIVERELAY_UDP_PORT_MIN", file.webrtc.udp_port_min, 0)?;
+        let udp_port_max = layer(env, "LIVERELAY_UDP_PORT_MAX", file.webrtc.udp_port_max, 0)?;
+
+        // Recording
+        let recording_dir = layer(
+            env,
+            "LIVERELAY_RECORDING_DIR",
+            file.recording.dir,
+            "./recordings".into(),
+        )?;
+        let recording_max_duration_secs = layer(
+            env,
+            "LIVERELAY_RECORDING_MAX_SECS",
+            file.recording.max_duration_secs,
+            0,
+        )?;
+
+        // HLS egress
+        let defaults = HlsConfig::default();
+        let hls_file = file.hls;
+        let millis = |key, file: Option<u64>, default: Duration| {
+            layer(env, key, file, default.as_millis() as u64).map(Duration::from_millis)
+        };
+        let s3 = match (
+            env("LIVERELAY_HLS_S3_ENDPOINT").or(hls_file.s3.endpoint),
+            env("LIVERELAY_HLS_S3_BUCKET").or(hls_file.s3.bucket),
+        ) {
+            (None, None) => None,
+            (endpoint, bucket) => Some(S3Config {
+                endpoint: endpoint.unwrap_or_default().trim_end_matches('/').to_string(),
+                bucket: bucket.unwrap_or_default(),
+                region: layer(
+                    env,
+                    "LIVERELAY_HLS_S3_REGION",
+                    hls_file.s3.region,
+                    "us-east-1".into(),
+                )?,
+                access_key: env("LIVERELAY_HLS_S3_ACCESS_KEY").unwrap_or_default(),
+                secret_key: env("LIVERELAY_HLS_S3_SECRET_KEY").unwrap_or_default(),
+                prefix: layer(env, "LIVERELAY_HLS_S3_PREFIX", hls_file.s3.prefix, String::new())?,
+            }),
+        };
+        let hls = HlsConfig {
+            part_target: millis("LIVERELAY_HLS_PART_MS", hls_file.part_ms, defaults.part_target)?,
+            segment_target: millis(
+                "LIVERELAY_HLS_SEGMENT_MS",
+                hls_file.segment_ms,
+                defaults.segment_target,
+            )?,
+            window_segments: layer(
+                env,
+                "LIVERELAY_HLS_WINDOW",
+                hls_file.window_segments,
+                defaults.window_segments,
+            )?,
+            s3,
+        };
+
+        // Snapshots
+        let snapshot_dir = layer(
+            env,
+            "LIVERELAY_SNAPSHOT_DIR",
+            file.snapshots.dir,
+            "./snapshots".into(),
+        )?;
+        let snapshot_retain_per_room = layer(
+            env,
+            "LIVERELAY_SNAPSHOT_RETAIN",
+            file.snapshots.retain_per_room,
+            100,
+        )?;
+
+        // Analytics
+        let stats_interval_secs = layer(
+            env,
+            "LIVERELAY_STATS_INTERVAL_SECS",
+            file.analytics.stats_interval_secs,
+            5,
+        )?;
+        let defaults = QualityThresholds::default();
+        let thresholds = file.analytics.thresholds;
+        let quality_thresholds = QualityThresholds {
+            max_rtt_ms: layer(
+                env,
+                "LIVERELAY_QUALITY_MAX_RTT_MS",
+                thresholds.max_rtt_ms,
+                defaults.max_rtt_ms,
+            )?,
+            max_packet_loss_pct: layer(
+                env,
+                "LIVERELAY_QUALITY_MAX_PACKET_LOSS_PCT",
+                thresholds.max_packet_loss_pct,
+                defaults.max_packet_loss_pct,
+            )?,
+            min_mos: layer(env, "LIVERELAY_QUALITY_MIN_MOS", thresholds.min_mos, defaults.min_mos)?,
+            max_jitter_ms: layer(
+                env,
+                "LIVERELAY_QUALITY_MAX_JITTER_MS",
+                thresholds.max_jitter_ms,
+                defaults.max_jitter_ms,
+            )?,
+        };
+
+        // Webhooks
+        let defaults = RetryPolicy::default();
+        let retry = file.webhooks.retry;
+        let webhook_retry = RetryPolicy {
+            max_attempts: layer(
+                env,
+                "LIVERELAY_WEBHOOK_MAX_ATTEMPTS",
+                retry.max_attempts,
+                defaults.max_attempts,
+            )?,
+            base_delay: Duration::from_millis(layer(
+                env,
+                "LIVERELAY_WEBHOOK_BASE_DELAY_MS",
+                retry.base_delay_ms,
+                defaults.base_delay.as_millis() as u64,
+            )?),
+            max_delay: Duration::from_millis(layer(
+                env,
+                "LIVERELAY_WEBHOOK_MAX_DELAY_MS",
+                retry.max_delay_ms,
+                defaults.max_delay.as_millis() as u64,
+            )?),
+        };
+
+        Ok(Config {
+            config_path: None,
             bind_addr,
             public_host,
             tls_enabled,
@@ -138,22 +368,143 @@ impl Config {
             tls_key_path,
             turn_embedded,
             turn_port,
-            turn_username,
-            turn_password,
+            turn_secret,
+            turn_credential_ttl_secs,
             turn_realm,
             stun_urls,
             turn_urls,
             jwt_secret,
+            bootstrap_api_key,
             max_rooms,
             max_subscribers_per_room,
             udp_port_min,
             udp_port_max,
+            drain_deadline_secs,
             allowed_origins,
+            recording_dir,
+            recording_max_duration_secs,
+            hls,
+            snapshot_dir,
+            snapshot_retain_per_room,
+            stats_interval_secs,
+            quality_thresholds,
+            webhook_retry,
             log_level,
-        };
+        })
+    }
 
-        config.log_summary();
-        config
+    /// Check the layered settings, reporting every problem at once.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.tls_enabled && (self.tls_cert_path.is_none() || self.tls_key_path.is_none()) {
+            problems.push("tls: cert_path and key_path are required when TLS is enabled".into());
+        }
+        if self.turn_embedded && self.turn_port == 0 {
+            problems.push("turn: port must be set when the embedded TURN server is enabled".into());
+        }
+        if (self.udp_port_min == 0) != (self.udp_port_max == 0)
+            || self.udp_port_min > self.udp_port_max
+        {
+            problems.push(format!(
+                "webrtc: invalid UDP port range {}-{}",
+                self.udp_port_min, self.udp_port_max
+            ));
+        }
+        if self.bootstrap_api_key.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            problems.push("auth: LIVERELAY_API_KEY must not contain whitespace".into());
+        }
+        if self.recording_dir.is_empty() {
+            problems.push("recording: dir must not be empty".into());
+        }
+        problems.extend(hls_problems(&self.hls));
+        if self.snapshot_dir.is_empty() {
+            problems.push("snapshots: dir must not be empty".into());
+        }
+        if self.snapshot_retain_per_room == 0 {
+            problems.push("snapshots: retain_per_room must be at least 1".into());
+        }
+        if self.stats_interval_secs == 0 {
+            problems.push("analytics: stats_interval_secs must be at least 1".into());
+        }
+        if self.webhook_retry.max_attempts == 0 {
+            problems.push("webhooks.retry: max_attempts must be at least 1".into());
+        }
+        if self.webhook_retry.base_delay > self.webhook_retry.max_delay {
+            problems.push("webhooks.retry: base_delay_ms must not exceed max_delay_ms".into());
+        }
+        problems.extend(self.runtime_settings().problems());
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// The settings that can change at runtime, as loaded.
+    pub fn runtime_settings(&self) -> RuntimeSettings {
+        RuntimeSettings {
+            max_rooms: self.max_rooms,
+            max_subscribers_per_room: self.max_subscribers_per_room,
+            allowed_origins: self.allowed_origins.clone(),
+            quality_thresholds: self.quality_thresholds.clone(),
+        }
+    }
+
+    /// Settings that differ from `other` but only take effect on restart.
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let mut check = |name, differs: bool| {
+            if differs {
+                changed.push(name);
+            }
+        };
+        check("bind_addr", self.bind_addr != other.bind_addr);
+        check("public_host", self.public_host != other.public_host);
+        check(
+            "tls",
+            self.tls_enabled != other.tls_enabled
+                || self.tls_cert_path != other.tls_cert_path
+                || self.tls_key_path != other.tls_key_path,
+        );
+        check(
+            "turn",
+            self.turn_embedded != other.turn_embedded
+                || self.turn_port != other.turn_port
+                || self.turn_credential_ttl_secs != other.turn_credential_ttl_secs
+                || self.turn_realm != other.turn_realm
+                || self.stun_urls != other.stun_urls
+                || self.turn_urls != other.turn_urls,
+        );
+        check(
+            "webrtc",
+            self.udp_port_min != other.udp_port_min || self.udp_port_max != other.udp_port_max,
+        );
+        check("drain", self.drain_deadline_secs != other.drain_deadline_secs);
+        check(
+            "recording",
+            self.recording_dir != other.recording_dir
+                || self.recording_max_duration_secs != other.recording_max_duration_secs,
+        );
+        check("hls", self.hls != other.hls);
+        check(
+            "snapshots",
+            self.snapshot_dir != other.snapshot_dir
+                || self.snapshot_retain_per_room != other.snapshot_retain_per_room,
+        );
+        check(
+            "analytics.stats_interval_secs",
+            self.stats_interval_secs != other.stats_interval_secs,
+        );
+        check("webhooks.retry", self.webhook_retry != other.webhook_retry);
+        // The permissive CORS layer is installed at startup and cannot be
+        // narrowed later.
+        check(
+            "cors.allowed_origins",
+            self.allowed_origins == "*" && other.allowed_origins != "*",
+        );
+        changed
     }
 
     /// Build the list of ICE servers that the server-side WebRTC agent
@@ -180,8 +531,15 @@ impl Config {
     }
 
     /// Build the full ICE server list including TURN (for client API responses).
-    pub fn ice_servers_with_turn(&self) -> Vec<IceServerConfig> {
+    ///
+    /// TURN entries carry credentials freshly minted for `peer_id`.
+    pub fn ice_servers_with_turn(&self, peer_id: &str) -> Vec<IceServerConfig> {
         let mut servers = self.ice_servers_for_server();
+        let creds = crate::turn_server::TurnCredentials::mint(
+            &self.turn_secret,
+            peer_id,
+            self.turn_credential_ttl_secs,
+        );
 
         // TURN (embedded or external) — only for clients behind NAT
         if self.turn_embedded {
@@ -191,15 +549,15 @@ impl Config {
             );
             servers.push(IceServerConfig {
                 urls: vec![turn_url],
-                username: Some(self.turn_username.clone()),
-                credential: Some(self.turn_password.clone()),
+                username: Some(creds.username),
+                credential: Some(creds.credential),
             });
         } else {
             for url in &self.turn_urls {
                 servers.push(IceServerConfig {
                     urls: vec![url.clone()],
-                    username: Some(self.turn_username.clone()),
-                    credential: Some(self.turn_password.clone()),
+                    username: Some(creds.username.clone()),
+                    credential: Some(creds.credential.clone()),
                 });
             }
         }
@@ -211,8 +569,8 @@ impl Config {
     ///
     /// This is a JSON-serialisable format compatible with the W3C
     /// `RTCIceServer` dictionary.
-    pub fn ice_servers_for_client(&self) -> Vec<ClientIceServer> {
-        self.ice_servers_with_turn()
+    pub fn ice_servers_for_client(&self, peer_id: &str) -> Vec<ClientIceServer> {
+        self.ice_servers_with_turn(peer_id)
             .into_iter()
             .map(|s| ClientIceServer {
                 urls: s.urls,
@@ -244,6 +602,7 @@ impl Config {
         }
         info!("  stun_urls          : {:?}", self.stun_urls);
         info!("  turn_urls          : {:?}", self.turn_urls);
+        info!("  turn_cred_ttl      : {}s", self.turn_credential_ttl_secs);
         info!("  max_rooms          : {}", self.max_rooms);
         info!(
             "  max_subs_per_room  : {}",
@@ -257,11 +616,56 @@ impl Config {
                 &self.allowed_origins
             }
         );
+        info!("  drain_deadline     : {}s", self.drain_deadline_secs);
+        info!("  recording_dir      : {}", self.recording_dir);
+        info!(
+            "  hls                : part {:?}, segment {:?}, window {}",
+            self.hls.part_target, self.hls.segment_target, self.hls.window_segments
+        );
+        if let Some(s3) = &self.hls.s3 {
+            info!("  hls_s3             : {}/{}", s3.endpoint, s3.bucket);
+        }
+        info!(
+            "  snapshot_dir       : {} (keep {} per room)",
+            self.snapshot_dir, self.snapshot_retain_per_room
+        );
+        info!("  stats_interval     : {}s", self.stats_interval_secs);
+        info!("  quality_thresholds : {:?}", self.quality_thresholds);
+        info!("  webhook_retry      : {:?}", self.webhook_retry);
+        info!(
+            "  config_file        : {}",
+            self.config_path.as_deref().unwrap_or("(none)")
+        );
         info!("  log_level          : {}", self.log_level);
         info!("────────────────────────────────");
     }
 }
 
+fn hls_problems(hls: &HlsConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    if hls.part_target.is_zero() {
+        problems.push("hls: part_ms must be at least 1".into());
+    }
+    if hls.segment_target < hls.part_target {
+        problems.push("hls: segment_ms must not be shorter than part_ms".into());
+    }
+    if hls.window_segments == 0 {
+        problems.push("hls: window_segments must be at least 1".into());
+    }
+    if let Some(s3) = &hls.s3 {
+        if s3.bucket.is_empty() {
+            problems.push("hls.s3: bucket is required when endpoint is set".into());
+        }
+        if !(s3.endpoint.starts_with("http://") || s3.endpoint.starts_with("https://")) {
+            problems.push(format!("hls.s3: invalid endpoint '{}'", s3.endpoint));
+        }
+        if s3.access_key.is_empty() != s3.secret_key.is_empty() {
+            problems.push("hls.s3: access key and secret key must be set together".into());
+        }
+    }
+    problems
+}
+
 // ---------------------------------------------------------------------------
 // ICE server configuration types
 // ---------------------------------------------------------------------------
@@ -287,28 +691,323 @@ pub struct ClientIceServer {
 }
 
 // ---------------------------------------------------------------------------
-// Environment helpers
+// Runtime settings
 // ---------------------------------------------------------------------------
 
-fn env_or(key: &str, default: &str) -> String {
-    std::env::var(key).unwrap_or_else(|_| default.to_string())
+/// Settings applied without a restart when the configuration is reloaded
+/// (SIGHUP or `POST /v1/admin/config/reload`).  The live copy is
+/// `AppState::settings`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuntimeSettings {
+    pub max_rooms: usize,
+    pub max_subscribers_per_room: u64,
+    pub allowed_origins: String,
+    pub quality_thresholds: QualityThresholds,
 }
 
-fn env_bool(key: &str, default: bool) -> bool {
-    match std::env::var(key) {
-        Ok(v) => matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"),
-        Err(_) => default,
+impl RuntimeSettings {
+    /// Whether CORS requests from `origin` are allowed.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins == "*"
+            || self
+                .allowed_origins
+                .split(',')
+                .any(|allowed| allowed.trim() == origin)
     }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.max_rooms == 0 {
+            problems.push("limits: max_rooms must be at least 1".into());
+        }
+        if self.max_subscribers_per_room == 0 {
+            problems.push("limits: max_subscribers_per_room must be at least 1".into());
+        }
+
+        let origins: Vec<&str> = self
+            .allowed_origins
+            .split(',')
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .collect();
+        if origins.is_empty() {
+            problems.push("cors: allowed_origins must not be empty".into());
+        } else if self.allowed_origins != "*" {
+            for origin in origins {
+                let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                    && axum::http::HeaderValue::from_str(origin).is_ok();
+                if !valid {
+                    problems.push(format!("cors: invalid origin '{origin}'"));
+                }
+            }
+        }
+
+        let t = &self.quality_thresholds;
+        for (name, value) in [
+            ("max_rtt_ms", t.max_rtt_ms),
+            ("max_packet_loss_pct", t.max_packet_loss_pct),
+            ("min_mos", t.min_mos),
+            ("max_jitter_ms", t.max_jitter_ms),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                problems.push(format!("analytics.thresholds: {name} must be a positive number"));
+            }
+        }
+        if t.max_packet_loss_pct > 100.0 {
+            problems.push("analytics.thresholds: max_packet_loss_pct must not exceed 100".into());
+        }
+        if t.min_mos > 5.0 {
+            problems.push("analytics.thresholds: min_mos must not exceed 5".into());
+        }
+        problems
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Errors
+// ---------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    Read { path: String, error: std::io::Error },
+    /// The config file is not valid TOML or has unknown keys.
+    Parse { path: String, error: Box<toml::de::Error> },
+    /// One or more settings are out of range or unparsable.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } => {
+                write!(f, "cannot read config file '{path}': {error}")
+            }
+            ConfigError::Parse { path, error } => {
+                write!(f, "invalid config file '{path}': {error}")
+            }
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// ---------------------------------------------------------------------------
+// Config file
+// ---------------------------------------------------------------------------
+
+/// The TOML config file.  Every key is optional; unknown keys are errors.
+///
+/// ```toml
+/// bind_addr = "0.0.0.0:8080"
+///
+/// [limits]
+/// max_rooms = 500
+///
+/// [cors]
+/// allowed_origins = ["https://app.example.com"]
+///
+/// [analytics.thresholds]
+/// max_rtt_ms = 250.0
+///
+/// [webhooks.retry]
+/// max_attempts = 3
+///
+/// [hls.s3]
+/// endpoint = "http://minio:9000"
+/// bucket = "live"
+///
+/// [snapshots]
+/// retain_per_room = 20
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    bind_addr: Option<String>,
+    public_host: Option<String>,
+    tls: TlsFile,
+    turn: TurnFile,
+    limits: LimitsFile,
+    webrtc: WebRtcFile,
+    drain: DrainFile,
+    cors: CorsFile,
+    recording: RecordingFile,
+    hls: HlsFile,
+    snapshots: SnapshotsFile,
+    analytics: AnalyticsFile,
+    webhooks: WebhooksFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TlsFile {
+    enabled: Option<bool>,
+    cert_path: Option<String>,
+    key_path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TurnFile {
+    embedded: Option<bool>,
+    port: Option<u16>,
+    credential_ttl_secs: Option<u64>,
+    realm: Option<String>,
+    urls: Option<Vec<String>>,
+    stun_urls: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsFile {
+    max_rooms: Option<usize>,
+    max_subscribers_per_room: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WebRtcFile {
+    udp_port_min: Option<u16>,
+    udp_port_max: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DrainFile {
+    deadline_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CorsFile {
+    /// `["*"]` allows every origin.
+    allowed_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RecordingFile {
+    dir: Option<String>,
+    max_duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HlsFile {
+    part_ms: Option<u64>,
+    segment_ms: Option<u64>,
+    window_segments: Option<usize>,
+    /// Credentials are only read from the environment.
+    s3: S3File,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct S3File {
+    endpoint: Option<String>,
+    bucket: Option<String>,
+    region: Option<String>,
+    prefix: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SnapshotsFile {
+    dir: Option<String>,
+    retain_per_room: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnalyticsFile {
+    stats_interval_secs: Option<u64>,
+    thresholds: ThresholdsFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThresholdsFile {
+    max_rtt_ms: Option<f64>,
+    max_packet_loss_pct: Option<f64>,
+    min_mos: Option<f64>,
+    max_jitter_ms: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WebhooksFile {
+    retry: RetryFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetryFile {
+    max_attempts: Option<u32>,
+    base_delay_ms: Option<u64>,
+    max_delay_ms: Option<u64>,
 }
 
-fn env_csv(key: &str, defaults: &[&str]) -> Vec<String> {
-    match std::env::var(key) {
-        Ok(v) if !v.is_empty() => v
+impl FileConfig {
+    fn read(path: &str) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|error| ConfigError::Read {
+            path: path.to_string(),
+            error,
+        })?;
+        Self::parse(path, &text)
+    }
+
+    fn parse(path: &str, text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|error| ConfigError::Parse {
+            path: path.to_string(),
+            error: Box::new(error),
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layering helpers
+// ---------------------------------------------------------------------------
+
+/// Environment lookup; `std::env::var` outside of tests.
+type Env<'a> = dyn Fn(&str) -> Option<String> + 'a;
+
+fn env_set(key: &str) -> bool {
+    std::env::var(key).is_ok_and(|v| !v.is_empty())
+}
+
+/// `key` from the environment, else the file value, else `default`.
+fn layer<T>(env: &Env, key: &str, file: Option<T>, default: T) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match env(key) {
+        Some(v) => v
+            .trim()
+            .parse()
+            .map_err(|e| ConfigError::Invalid(vec![format!("{key}='{v}': {e}")])),
+        None => Ok(file.unwrap_or(default)),
+    }
+}
+
+fn layer_bool(env: &Env, key: &str, file: Option<bool>, default: bool) -> bool {
+    match env(key) {
+        Some(v) => matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"),
+        None => file.unwrap_or(default),
+    }
+}
+
+fn layer_csv(env: &Env, key: &str, file: Option<Vec<String>>, defaults: &[&str]) -> Vec<String> {
+    match env(key) {
+        Some(v) if !v.is_empty() => v
             .split(',')
             .map(|s| s.trim().to_string())
             .filter(|s| !s.is_empty())
             .collect(),
-        _ => defaults.iter().map(|s| s.to_string()).collect(),
+        _ => file.unwrap_or_else(|| defaults.iter().map(|s| s.to_string()).collect()),
     }
 }
 
@@ -328,6 +1027,7 @@ mod tests {
         std::env::remove_var("LIVERELAY_STUN_URLS");
 
         let config = Config {
+            config_path: None,
             bind_addr: "0.0.0.0:8080".into(),
             public_host: "localhost".into(),
             tls_enabled: false,
@@ -335,15 +1035,27 @@ mod tests {
             tls_key_path: None,
             turn_embedded: false,
             turn_port: 3478,
-            turn_username: "test".into(),
-            turn_password: "test".into(),
+            turn_secret: "test".into(),
+            turn_credential_ttl_secs: 3600,
             turn_realm: "localhost".into(),
             stun_urls: vec!["stun:stun.l.google.com:19302".into()],
             turn_urls: vec![],
             jwt_secret: "test".into(),
+            bootstrap_api_key: "lr_test".into(),
             max_rooms: 100,
             max_subscribers_per_room: 1000,
+            udp_port_min: 0,
+            udp_port_max: 0,
+            drain_deadline_secs: 600,
             allowed_origins: "*".into(),
+            recording_dir: "./recordings".into(),
+            recording_max_duration_secs: 0,
+            hls: HlsConfig::default(),
+            snapshot_dir: "./snapshots".into(),
+            snapshot_retain_per_room: 100,
+            stats_interval_secs: 5,
+            quality_thresholds: QualityThresholds::default(),
+            webhook_retry: RetryPolicy::default(),
             log_level: "info".into(),
         };
 
@@ -355,6 +1067,7 @@ mod tests {
     #[test]
     fn embedded_turn_generates_url() {
         let config = Config {
+            config_path: None,
             bind_addr: "0.0.0.0:8080".into(),
             public_host: "sfu.example.com".into(),
             tls_enabled: false,
@@ -362,27 +1075,40 @@ mod tests {
             tls_key_path: None,
             turn_embedded: true,
             turn_port: 3478,
-            turn_username: "user".into(),
-            turn_password: "pass".into(),
+            turn_secret: "north".into(),
+            turn_credential_ttl_secs: 3600,
             turn_realm: "sfu.example.com".into(),
             stun_urls: vec![],
             turn_urls: vec![],
             jwt_secret: "test".into(),
+            bootstrap_api_key: "lr_test".into(),
             max_rooms: 100,
             max_subscribers_per_room: 1000,
+            udp_port_min: 0,
+            udp_port_max: 0,
+            drain_deadline_secs: 600,
             allowed_origins: "*".into(),
+            recording_dir: "./recordings".into(),
+            recording_max_duration_secs: 0,
+            hls: HlsConfig::default(),
+            snapshot_dir: "./snapshots".into(),
+            snapshot_retain_per_room: 100,
+            stats_interval_secs: 5,
+            quality_thresholds: QualityThresholds::default(),
+            webhook_retry: RetryPolicy::default(),
             log_level: "info".into(),
         };
 
-        let servers = config.ice_servers_for_server();
+        let servers = config.ice_servers_with_turn("peer-1");
         let turn_server = servers
             .iter()
             .find(|s| s.urls[0].starts_with("turn:"))
             .expect("expected a TURN server entry");
 
         assert_eq!(turn_server.urls[0], "turn:sfu.example.com:3478");
-        assert_eq!(turn_server.username.as_deref(), Some("user"));
-        assert_eq!(turn_server.credential.as_deref(), Some("pass"));
+        let username = turn_server.username.as_deref().unwrap();
+        assert!(username.ends_with(":peer-1"));
+        assert!(turn_server.credential.is_some());
     }
 
     #[test]
@@ -396,4 +1122,160 @@ mod tests {
         assert!(json.contains("turn:example.com:3478"));
         assert!(json.contains("\"username\""));
     }
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: std::collections::HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn environment_overrides_file_overrides_defaults() {
+        let file = FileConfig::parse(
+            "test.toml",
+            r#"
+                bind_addr = "127.0.0.1:9000"
+
+                [limits]
+                max_rooms = 20
+                max_subscribers_per_room = 50
+
+                [cors]
+                allowed_origins = ["https://a.example", "https://b.example"]
+
+                [analytics]
+                stats_interval_secs = 2
+
+                [analytics.thresholds]
+                max_rtt_ms = 150.0
+
+                [webhooks.retry]
+                max_attempts = 3
+                base_delay_ms = 200
+            "#,
+        )
+        .unwrap();
+        let env = env_from(&[("LIVERELAY_MAX_ROOMS", "7")]);
+
+        let config = Config::from_layers(file, &env).unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.max_rooms, 7);
+        assert_eq!(config.max_subscribers_per_room, 50);
+        assert_eq!(config.bind_addr, "127.0.0.1:9000");
+        assert_eq!(config.public_host, "localhost");
+        assert_eq!(config.allowed_origins, "https://a.example,https://b.example");
+        assert_eq!(config.stats_interval_secs, 2);
+        assert_eq!(config.quality_thresholds.max_rtt_ms, 150.0);
+        assert_eq!(config.quality_thresholds.min_mos, 3.0);
+        assert_eq!(config.webhook_retry.max_attempts, 3);
+        assert_eq!(config.webhook_retry.base_delay, Duration::from_millis(200));
+        assert_eq!(config.webhook_retry.max_delay, Duration::from_secs(30));
+
+        let settings = config.runtime_settings();
+        assert!(settings.allows_origin("https://b.example"));
+        assert!(!settings.allows_origin("https://evil.example"));
+    }
+
+    #[test]
+    fn invalid_settings_are_reported() {
+        let err = FileConfig::parse("test.toml", "[limits]\nmax_room = 5\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }), "{err}");
+
+        let env = env_from(&[("LIVERELAY_MAX_ROOMS", "many")]);
+        let err = Config::from_layers(FileConfig::default(), &env).unwrap_err();
+        assert!(err.to_string().contains("LIVERELAY_MAX_ROOMS"), "{err}");
+
+        let file = FileConfig::parse(
+            "test.toml",
+            r#"
+                [tls]
+                enabled = true
+
+                [cors]
+                allowed_origins = ["app.example.com"]
+
+                [analytics.thresholds]
+                max_packet_loss_pct = 150.0
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_layers(file, &env_from(&[])).unwrap();
+        let ConfigError::Invalid(problems) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(problems.len(), 3, "{problems:?}");
+    }
+
+    #[test]
+    fn hls_and_snapshot_settings() {
+        let file = FileConfig::parse(
+            "test.toml",
+            r#"
+                [hls]
+                segment_ms = 4000
+
+                [hls.s3]
+                endpoint = "http://minio:9000/"
+                bucket = "live"
+
+                [snapshots]
+                dir = "/var/lib/liverelay/snapshots"
+            "#,
+        )
+        .unwrap();
+        let env = env_from(&[
+            ("LIVERELAY_HLS_WINDOW", "4"),
+            ("LIVERELAY_SNAPSHOT_RETAIN", "20"),
+            ("LIVERELAY_API_KEY", "lr_fixed"),
+        ]);
+        let config = Config::from_layers(file, &env).unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.bootstrap_api_key, "lr_fixed");
+        assert_eq!(config.hls.part_target, Duration::from_millis(500));
+        assert_eq!(config.hls.segment_target, Duration::from_secs(4));
+        assert_eq!(config.hls.window_segments, 4);
+        let s3 = config.hls.s3.as_ref().unwrap();
+        assert_eq!((s3.endpoint.as_str(), s3.bucket.as_str()), ("http://minio:9000", "live"));
+        assert_eq!(s3.region, "us-east-1");
+        assert_eq!(config.snapshot_dir, "/var/lib/liverelay/snapshots");
+        assert_eq!(config.snapshot_retain_per_room, 20);
+
+        // Defaults: no S3, random bootstrap key.
+        let config = Config::from_layers(FileConfig::default(), &env_from(&[])).unwrap();
+        assert!(config.hls.s3.is_none());
+        assert!(config.bootstrap_api_key.starts_with("lr_"));
+
+        let env = env_from(&[
+            ("LIVERELAY_HLS_PART_MS", "0"),
+            ("LIVERELAY_HLS_S3_ENDPOINT", "minio:9000"),
+            ("LIVERELAY_SNAPSHOT_RETAIN", "0"),
+        ]);
+        let config = Config::from_layers(FileConfig::default(), &env).unwrap();
+        let ConfigError::Invalid(problems) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        // part_ms, missing bucket, endpoint scheme, retain_per_room.
+        assert_eq!(problems.len(), 4, "{problems:?}");
+
+        let env = env_from(&[("LIVERELAY_HLS_WINDOW", "-1")]);
+        let err = Config::from_layers(FileConfig::default(), &env).unwrap_err();
+        assert!(err.to_string().contains("LIVERELAY_HLS_WINDOW"), "{err}");
+    }
+
+    #[test]
+    fn restart_required_ignores_runtime_settings() {
+        let base = Config::from_layers(FileConfig::default(), &env_from(&[])).unwrap();
+        let mut next = base.clone();
+        next.max_rooms = 1;
+        next.quality_thresholds.min_mos = 4.0;
+        assert!(base.restart_required(&next).is_empty());
+
+        next.bind_addr = "0.0.0.0:9090".into();
+        next.allowed_origins = "https://a.example".into();
+        assert_eq!(base.restart_required(&next), ["bind_addr", "cors.allowed_origins"]);
+    }
 }
@@ -0,0 +1,285 @@
+// src/drain.rs
+//
+// Graceful drain mode for deploys.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   SIGTERM / Ctrl-C  ──┐
+//                       ├─► begin_drain()
+//   POST /v1/admin/drain┘     ├─ stop accepting rooms and peers (503)
+//                             ├─ GET /health → 503 {"status":"draining"}
+//                             ├─ emit `node.draining` for every room
+//                             └─ finalize active recordings and HLS streams
+//
+//   shutdown_signal() then waits until every room is empty, or until
+//   `drain_deadline_secs` has elapsed, closes whatever is left and returns,
+//   which stops the HTTP server and lets the process exit.
+//
+//   Only the bootstrap API key may start a drain over the API.
+//
+//   Peers already connected keep working while the node drains, including
+//   conference renegotiation (`/sfu/conference/subscribe`) and starting a
+//   screen share (`/sfu/screen`).
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::error::ApiError;
+use crate::events::LiveRelayEvent;
+
+/// How often the drain loop checks whether the rooms are empty.
+const EMPTY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// ─── DrainState ─────────────────────────────────────────────────────────────
+
+/// Drain flag shared by the admin endpoint, the signal handler and every
+/// handler that admits new rooms or peers.
+#[derive(Default)]
+pub struct DrainState {
+    draining: AtomicBool,
+    deadline: std::sync::RwLock<Option<DateTime<Utc>>>,
+    requested: Notify,
+}
+
+impl DrainState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Deadline after which remaining rooms are closed, once draining.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        *self.deadline.read().unwrap()
+    }
+
+    /// Flip into drain mode.  Returns `false` if the node was already
+    /// draining.
+    fn start(&self, deadline: DateTime<Utc>) -> bool {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        *self.deadline.write().unwrap() = Some(deadline);
+        true
+    }
+}
+
+/// Reject the request if the node is draining.  Called by every handler
+/// that creates a room or admits a new peer.
+pub fn ensure_accepting(state: &crate::AppState) -> Result<(), ApiError> {
+    if state.drain.is_draining() {
+        return Err(ApiError::node_draining());
+    }
+    Ok(())
+}
+
+// ─── Drain sequence ─────────────────────────────────────────────────────────
+
+/// Put the node into drain mode: emit `node.draining` for every room and
+/// finalize active recordings and HLS streams.  Idempotent.
+pub async fn begin_drain(state: &crate::AppState, reason: &str) {
+    let deadline = Utc::now()
+        + chrono::Duration::seconds(state.config.drain_deadline_secs as i64);
+    if !state.drain.start(deadline) {
+        return;
+    }
+
+    let room_ids: Vec<String> = {
+        let rooms = state.rooms.read().unwrap();
+        rooms.keys().cloned().collect()
+    };
+    info!(
+        "Draining node ({reason}): {} room(s), deadline {}",
+        room_ids.len(),
+        deadline.to_rfc3339()
+    );
+
+    for room_id in &room_ids {
+        state.event_bus.emit(LiveRelayEvent::node_draining(
+            room_id,
+            &state.config.public_host,
+            deadline,
+        ));
+    }
+
+    if let Some(mgr) = &state.recording {
+        let finalized = mgr.finalize_all().await;
+        if finalized > 0 {
+            info!("Finalized {finalized} recording(s) for drain");
+        }
+    }
+
+    if let Some(mgr) = &state.hls {
+        let finalized = mgr.finalize_all().await;
+        if finalized > 0 {
+            info!("Finalized {finalized} HLS stream(s) for drain");
+        }
+    }
+
+    state.drain.requested.notify_one();
+}
+
+/// Total number of connected participants across all rooms.
+fn participants_remaining(state: &crate::AppState) -> u64 {
+    let rooms = state.rooms.read().unwrap();
+    rooms.values().map(|r| r.participant_count()).sum()
+}
+
+/// Wait until every room is empty or the drain deadline passes.  Returns
+/// `true` if the node emptied in time.
+async fn wait_until_empty(state: &crate::AppState) -> bool {
+    let deadline = state.config.drain_deadline_secs;
+    let wait = async {
+        while participants_remaining(state) > 0 {
+            tokio::time::sleep(EMPTY_POLL_INTERVAL).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(deadline), wait)
+        .await
+        .is_ok()
+}
+
+/// Close every PeerConnection still attached after the deadline.
+async fn close_remaining(state: &crate::AppState) {
+    let rooms: Vec<Arc<crate::room::Room>> = {
+        let mut rooms = state.rooms.write().unwrap();
+        rooms.drain().map(|(_, room)| room).collect()
+    };
+
+    for room in rooms {
+        crate::usage::flush_room(state, &room).await;
+        for publisher in room.get_publishers() {
+            if let Err(e) = publisher.pc.close().await {
+                warn!(
+                    "Failed to close publisher '{}' in room '{}': {e}",
+                    publisher.peer_id, room.room_id
+                );
+            }
+        }
+        state.event_bus.emit(LiveRelayEvent::room_deleted(
+            &room.room_id,
+            room.room_type.as_str(),
+        ));
+    }
+}
+
+/// Resolve once the node has drained.  Passed to the HTTP server so it stops
+/// after a SIGTERM / Ctrl-C or an admin drain request.
+pub async fn shutdown_signal(state: Arc<crate::AppState>) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => begin_drain(&state, "ctrl-c").await,
+        _ = terminate => begin_drain(&state, "SIGTERM").await,
+        _ = state.drain.requested.notified() => {}
+    }
+
+    if wait_until_empty(&state).await {
+        info!("All rooms empty, shutting down");
+    } else {
+        warn!(
+            "Drain deadline reached with {} participant(s) left, closing them",
+            participants_remaining(&state)
+        );
+        close_remaining(&state).await;
+    }
+}
+
+// ─── REST API ───────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+pub struct DrainResponse {
+    pub draining: bool,
+    pub deadline: Option<DateTime<Utc>>,
+    pub rooms_active: usize,
+    pub participants_active: u64,
+}
+
+/// `POST /v1/admin/drain` -- put the node into drain mode.  Requires the
+/// bootstrap API key.
+pub async fn start_drain(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<DrainResponse>), ApiError> {
+    crate::auth::require_admin_key(&headers, &state.api_keys, &state.config.bootstrap_api_key)
+        .await?;
+
+    begin_drain(&state, "admin request").await;
+
+    let rooms_active = state.rooms.read().unwrap().len();
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DrainResponse {
+            draining: true,
+            deadline: state.drain.deadline(),
+            rooms_active,
+            participants_active: participants_remaining(&state),
+        }),
+    ))
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_is_idempotent() {
+        let drain = DrainState::new();
+        assert!(!drain.is_draining());
+        assert!(drain.deadline().is_none());
+
+        let deadline = Utc::now();
+        assert!(drain.start(deadline));
+        assert!(drain.is_draining());
+        assert_eq!(drain.deadline(), Some(deadline));
+
+        assert!(!drain.start(deadline + chrono::Duration::seconds(60)));
+        assert_eq!(drain.deadline(), Some(deadline));
+    }
+
+    #[test]
+    fn draining_event_carries_room() {
+        let deadline = Utc::now();
+        let evt = LiveRelayEvent::node_draining("room-1", "sfu-1.example.com", deadline);
+        assert_eq!(evt.room_id(), "room-1");
+        let json = serde_json::to_string(&evt).unwrap();
+        assert!(json.contains("\"type\":\"node.draining\""));
+        assert!(json.contains("\"node\":\"sfu-1.example.com\""));
+    }
+}
@@ -30,6 +30,7 @@ struct ErrorBody {
 ///   }
 /// }
 /// ```
+#[derive(Debug)]
 pub struct ApiError {
     pub code: &'static str,
     pub message: String,
@@ -191,6 +192,33 @@ impl ApiError {
         }
     }
 
+    /// 401 — the JWT token's `nbf` lies in the future.
+    pub fn token_not_yet_valid() -> Self {
+        Self {
+            code: "token_not_yet_valid",
+            message: "The provided token is not valid yet.".into(),
+            status: StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    /// 401 — the JWT token has been revoked.
+    pub fn token_revoked() -> Self {
+        Self {
+            code: "token_revoked",
+            message: "The provided token has been revoked.".into(),
+            status: StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    /// 401 — a single-use JWT token has already admitted a peer.
+    pub fn token_used() -> Self {
+        Self {
+            code: "token_used",
+            message: "The provided single-use token has already been used.".into(),
+            status: StatusCode::UNAUTHORIZED,
+        }
+    }
+
     /// 403 — the peer's role does not permit this operation.
     pub fn role_insufficient(role: &str) -> Self {
         Self {
@@ -200,6 +228,15 @@ impl ApiError {
         }
     }
 
+    /// 403 — the operation is reserved for the node's bootstrap API key.
+    pub fn admin_key_required() -> Self {
+        Self {
+            code: "admin_key_required",
+            message: "This operation requires the bootstrap API key.".into(),
+            status: StatusCode::FORBIDDEN,
+        }
+    }
+
     /// 404 — the requested room does not exist.
     pub fn room_not_found(room_id: &str) -> Self {
         Self {
@@ -209,6 +246,15 @@ impl ApiError {
         }
     }
 
+    /// 409 — another participant is already sharing their screen.
+    pub fn screen_share_active(room_id: &str) -> Self {
+        Self {
+            code: "screen_share_active",
+            message: format!("Room '{room_id}' allows one screen share at a time."),
+            status: StatusCode::CONFLICT,
+        }
+    }
+
     /// 409 — the room has reached its maximum capacity.
     pub fn room_full(room_id: &str) -> Self {
         Self {
@@ -247,6 +293,63 @@ impl ApiError {
         }
     }
 
+    /// 404 — the peer is not waiting in the room's lobby.
+    pub fn peer_not_waiting(peer_id: &str) -> Self {
+        Self {
+            code: "peer_not_waiting",
+            message: format!("Peer '{peer_id}' is not waiting in the lobby."),
+            status: StatusCode::NOT_FOUND,
+        }
+    }
+
+    /// 429 — the API key has reached one of its quotas.
+    pub fn quota_exceeded(quota: &str) -> Self {
+        Self {
+            code: "quota_exceeded",
+            message: format!("The API key has exceeded its '{quota}' quota."),
+            status: StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// 503 — the node is draining and no longer accepts rooms or peers.
+    pub fn node_draining() -> Self {
+        Self {
+            code: "node_draining",
+            message: "This node is draining and does not accept new rooms or peers.".into(),
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// 422 — the config file or environment does not validate; the running
+    /// configuration is unchanged.
+    pub fn config_invalid(detail: &str) -> Self {
+        Self {
+            code: "config_invalid",
+            message: detail.to_string(),
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// 503 — the node already hosts `max_rooms` rooms.
+    pub fn room_limit_reached() -> Self {
+        Self {
+            code: "room_limit_reached",
+            message: "This node has reached its room limit.".into(),
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// 400 — the SDP offer does not contain any codec the room allows.
+    pub fn codec_not_allowed(kind: &str, allowed: &str) -> Self {
+        Self {
+            code: "codec_not_allowed",
+            message: format!(
+                "The offer contains no {kind} codec allowed in this room (allowed: {allowed})."
+            ),
+            status: StatusCode::BAD_REQUEST,
+        }
+    }
+
     /// 500 — the WebRTC peer connection could not be established.
     pub fn peer_connection_failed() -> Self {
         Self {
@@ -28,12 +28,22 @@ pub enum EventType {
     ParticipantJoined,
     #[serde(rename = "participant.left")]
     ParticipantLeft,
+    #[serde(rename = "participant.waiting")]
+    ParticipantWaiting,
+    #[serde(rename = "participant.admitted")]
+    ParticipantAdmitted,
+    #[serde(rename = "participant.rejected")]
+    ParticipantRejected,
     #[serde(rename = "stream.started")]
     StreamStarted,
     #[serde(rename = "stream.stopped")]
     StreamStopped,
     #[serde(rename = "quality.degraded")]
     QualityDegraded,
+    #[serde(rename = "node.draining")]
+    NodeDraining,
+    #[serde(rename = "snapshot.captured")]
+    SnapshotCaptured,
 }
 
 impl EventType {
@@ -45,9 +55,14 @@ impl EventType {
             Self::RoomDeleted => "room.deleted",
             Self::ParticipantJoined => "participant.joined",
             Self::ParticipantLeft => "participant.left",
+            Self::ParticipantWaiting => "participant.waiting",
+            Self::ParticipantAdmitted => "participant.admitted",
+            Self::ParticipantRejected => "participant.rejected",
             Self::StreamStarted => "stream.started",
             Self::StreamStopped => "stream.stopped",
             Self::QualityDegraded => "quality.degraded",
+            Self::NodeDraining => "node.draining",
+            Self::SnapshotCaptured => "snapshot.captured",
         }
     }
 }
@@ -73,6 +88,9 @@ pub struct ParticipantPayload {
     pub room_id: String,
     pub peer_id: String,
     pub role: String,
+    /// Participant metadata from the peer's token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
 }
 
 /// Metadata attached to stream lifecycle events.
@@ -80,7 +98,10 @@ pub struct ParticipantPayload {
 pub struct StreamPayload {
     pub room_id: String,
     pub peer_id: String,
-    pub kind: String, // "audio" | "video" | "audio+video"
+    pub kind: String, // "audio" | "video" | "audio+video" | "screen"
+    /// Metadata of the participant the stream belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
 }
 
 /// Metadata attached to quality degradation events.
@@ -94,6 +115,26 @@ pub struct QualityPayload {
     pub direction: String,    // "above" | "below"
 }
 
+/// Metadata attached to node lifecycle events.  Emitted once per room hosted
+/// on the node so room-scoped consumers receive it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodePayload {
+    pub room_id: String,
+    pub node: String,
+    pub deadline: DateTime<Utc>,
+}
+
+/// Metadata attached to moderation snapshot events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotPayload {
+    pub room_id: String,
+    pub peer_id: String,
+    pub snapshot_id: String,
+    pub track: String,  // "camera" | "screen"
+    pub format: String, // "ivf" | "h264" | "jpeg"
+    pub url: String,
+}
+
 /// Type-safe union of all possible payloads.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -102,6 +143,8 @@ pub enum EventPayload {
     Participant(ParticipantPayload),
     Stream(StreamPayload),
     Quality(QualityPayload),
+    Node(NodePayload),
+    Snapshot(SnapshotPayload),
 }
 
 // ─── The event envelope ─────────────────────────────────────────────────────
@@ -162,49 +205,127 @@ impl LiveRelayEvent {
     }
 
     /// Build a `participant.joined` event.
-    pub fn participant_joined(room_id: &str, peer_id: &str, role: &str) -> Self {
+    pub fn participant_joined(
+        room_id: &str,
+        peer_id: &str,
+        role: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
         Self::new(
             EventType::ParticipantJoined,
             EventPayload::Participant(ParticipantPayload {
                 room_id: room_id.to_string(),
                 peer_id: peer_id.to_string(),
                 role: role.to_string(),
+                metadata: metadata.cloned(),
             }),
         )
     }
 
     /// Build a `participant.left` event.
-    pub fn participant_left(room_id: &str, peer_id: &str, role: &str) -> Self {
+    pub fn participant_left(
+        room_id: &str,
+        peer_id: &str,
+        role: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
         Self::new(
             EventType::ParticipantLeft,
             EventPayload::Participant(ParticipantPayload {
                 room_id: room_id.to_string(),
                 peer_id: peer_id.to_string(),
                 role: role.to_string(),
+                metadata: metadata.cloned(),
+            }),
+        )
+    }
+
+    /// Build a `participant.waiting` event (peer entered a room lobby).
+    pub fn participant_waiting(
+        room_id: &str,
+        peer_id: &str,
+        role: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
+        Self::new(
+            EventType::ParticipantWaiting,
+            EventPayload::Participant(ParticipantPayload {
+                room_id: room_id.to_string(),
+                peer_id: peer_id.to_string(),
+                role: role.to_string(),
+                metadata: metadata.cloned(),
+            }),
+        )
+    }
+
+    /// Build a `participant.admitted` event (host let a waiting peer in).
+    pub fn participant_admitted(
+        room_id: &str,
+        peer_id: &str,
+        role: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
+        Self::new(
+            EventType::ParticipantAdmitted,
+            EventPayload::Participant(ParticipantPayload {
+                room_id: room_id.to_string(),
+                peer_id: peer_id.to_string(),
+                role: role.to_string(),
+                metadata: metadata.cloned(),
+            }),
+        )
+    }
+
+    /// Build a `participant.rejected` event (host turned a waiting peer away).
+    pub fn participant_rejected(
+        room_id: &str,
+        peer_id: &str,
+        role: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
+        Self::new(
+            EventType::ParticipantRejected,
+            EventPayload::Participant(ParticipantPayload {
+                room_id: room_id.to_string(),
+                peer_id: peer_id.to_string(),
+                role: role.to_string(),
+                metadata: metadata.cloned(),
             }),
         )
     }
 
     /// Build a `stream.started` event.
-    pub fn stream_started(room_id: &str, peer_id: &str, kind: &str) -> Self {
+    pub fn stream_started(
+        room_id: &str,
+        peer_id: &str,
+        kind: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
         Self::new(
             EventType::StreamStarted,
             EventPayload::Stream(StreamPayload {
                 room_id: room_id.to_string(),
                 peer_id: peer_id.to_string(),
                 kind: kind.to_string(),
+                metadata: metadata.cloned(),
             }),
         )
     }
 
     /// Build a `stream.stopped` event.
-    pub fn stream_stopped(room_id: &str, peer_id: &str, kind: &str) -> Self {
+    pub fn stream_stopped(
+        room_id: &str,
+        peer_id: &str,
+        kind: &str,
+        metadata: Option<&serde_json::Value>,
+    ) -> Self {
         Self::new(
             EventType::StreamStopped,
             EventPayload::Stream(StreamPayload {
                 room_id: room_id.to_string(),
                 peer_id: peer_id.to_string(),
                 kind: kind.to_string(),
+                metadata: metadata.cloned(),
             }),
         )
     }
@@ -231,6 +352,33 @@ impl LiveRelayEvent {
         )
     }
 
+    /// Build a `node.draining` event for one room hosted on a draining node.
+    pub fn node_draining(room_id: &str, node: &str, deadline: DateTime<Utc>) -> Self {
+        Self::new(
+            EventType::NodeDraining,
+            EventPayload::Node(NodePayload {
+                room_id: room_id.to_string(),
+                node: node.to_string(),
+                deadline,
+            }),
+        )
+    }
+
+    /// Build a `snapshot.captured` event.
+    pub fn snapshot_captured(info: &crate::snapshot::SnapshotInfo) -> Self {
+        Self::new(
+            EventType::SnapshotCaptured,
+            EventPayload::Snapshot(SnapshotPayload {
+                room_id: info.room_id.clone(),
+                peer_id: info.peer_id.clone(),
+                snapshot_id: info.snapshot_id.clone(),
+                track: info.track.to_string(),
+                format: info.format.to_string(),
+                url: info.url.clone(),
+            }),
+        )
+    }
+
     // ── Private ─────────────────────────────────────────────────────────
 
     fn new(event_type: EventType, data: EventPayload) -> Self {
@@ -249,6 +397,8 @@ impl LiveRelayEvent {
             EventPayload::Participant(p) => &p.room_id,
             EventPayload::Stream(p) => &p.room_id,
             EventPayload::Quality(p) => &p.room_id,
+            EventPayload::Node(p) => &p.room_id,
+            EventPayload::Snapshot(p) => &p.room_id,
         }
     }
 }
@@ -325,6 +475,22 @@ mod tests {
         assert!(evt.id.starts_with("evt_"));
     }
 
+    #[test]
+    fn participant_metadata_roundtrip() {
+        let meta = serde_json::json!({ "user_id": "u-1" });
+        let evt = LiveRelayEvent::participant_joined("r1", "p1", "call", Some(&meta));
+        let parsed: LiveRelayEvent =
+            serde_json::from_str(&serde_json::to_string(&evt).unwrap()).unwrap();
+        let EventPayload::Participant(p) = parsed.data else {
+            panic!("expected a participant payload");
+        };
+        assert_eq!(p.metadata, Some(meta));
+
+        // Absent metadata is omitted, not serialised as null.
+        let evt = LiveRelayEvent::stream_started("r1", "p1", "screen", None);
+        assert!(!serde_json::to_string(&evt).unwrap().contains("metadata"));
+    }
+
     #[tokio::test]
     async fn bus_fanout() {
         let bus = EventBus::new();
@@ -342,7 +508,7 @@ mod tests {
 
     #[test]
     fn room_id_extraction() {
-        let e = LiveRelayEvent::participant_joined("room-42", "peer-7", "publish");
+        let e = LiveRelayEvent::participant_joined("room-42", "peer-7", "publish", None);
         assert_eq!(e.room_id(), "room-42");
 
         let e = LiveRelayEvent::quality_degraded("room-99", "peer-3", "packet_loss", 12.5, 5.0, "above");
@@ -0,0 +1,641 @@
+// src/fmp4.rs
+//
+// Minimal fragmented-MP4 (CMAF) writer for the HLS egress.
+//
+// Only what a live H264 + Opus stream needs is written: one init segment
+// (`ftyp` + `moov` with an empty sample table and `mvex`) and, per CMAF
+// chunk, one `moof` + `mdat` pair carrying the samples of both tracks.
+// WebRTC publishers never send B-frames, so decode time = presentation time
+// and `trun` carries no composition offsets.
+//
+// ─ Layout ───────────────────────────────────────────────────────────────────
+//
+//   init.mp4   ftyp │ moov ─ mvhd
+//                          ├ trak (1: avc1 + avcC, 90 kHz)
+//                          ├ trak (2: Opus + dOps, 48 kHz)   — optional
+//                          └ mvex ─ trex × tracks
+//
+//   chunk      moof ─ mfhd (sequence)
+//                   └ traf × tracks ─ tfhd │ tfdt │ trun
+//              mdat   video samples, then audio samples
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+pub const VIDEO_TRACK_ID: u32 = 1;
+pub const AUDIO_TRACK_ID: u32 = 2;
+pub const VIDEO_TIMESCALE: u32 = 90_000;
+pub const AUDIO_TIMESCALE: u32 = 48_000;
+
+/// `sample_depends_on = 2` (an IDR frame).
+const SAMPLE_FLAGS_SYNC: u32 = 0x0200_0000;
+/// `sample_depends_on = 1`, `sample_is_non_sync_sample = 1`.
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x0101_0000;
+
+/// Opus decoder delay signalled in `dOps` (the libopus default).
+const OPUS_PRE_SKIP: u16 = 312;
+
+// ─── Track descriptions ─────────────────────────────────────────────────────
+
+/// H264 decoder configuration, taken from the stream's SPS / PPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcConfig {
+    pub sps: Bytes,
+    pub pps: Bytes,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl AvcConfig {
+    /// Build the configuration from raw SPS / PPS NAL units.  Returns `None`
+    /// when the SPS cannot be parsed.
+    pub fn new(sps: Bytes, pps: Bytes) -> Option<Self> {
+        let (width, height) = sps_dimensions(&sps)?;
+        Some(Self {
+            sps,
+            pps,
+            width,
+            height,
+        })
+    }
+}
+
+/// One access unit / Opus packet ready to be muxed.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub data: Bytes,
+    /// Duration in the track's timescale.
+    pub duration: u32,
+    pub keyframe: bool,
+}
+
+/// The samples of one track inside a chunk.
+pub struct TrackRun<'a> {
+    pub track_id: u32,
+    /// `tfdt` decode time of the first sample, in the track's timescale.
+    pub base_decode_time: u64,
+    pub samples: &'a [Sample],
+}
+
+// ─── Init segment ───────────────────────────────────────────────────────────
+
+/// `ftyp` + `moov` for an H264 track and, with `audio_channels`, an Opus one.
+pub fn init_segment(video: &AvcConfig, audio_channels: Option<u8>) -> Bytes {
+    let mut out = BytesMut::new();
+    write_box(&mut out, b"ftyp", |b| {
+        b.put_slice(b"iso6");
+        b.put_u32(0);
+        for brand in [b"iso6", b"cmfc", b"mp41"] {
+            b.put_slice(brand);
+        }
+    });
+    write_box(&mut out, b"moov", |moov| {
+        let next_track_id = if audio_channels.is_some() { 3 } else { 2 };
+        write_full_box(moov, b"mvhd", 0, 0, |b| {
+            b.put_u32(0); // creation_time
+            b.put_u32(0); // modification_time
+            b.put_u32(1000); // timescale
+            b.put_u32(0); // duration
+            b.put_u32(0x0001_0000); // rate 1.0
+            b.put_u16(0x0100); // volume 1.0
+            b.put_bytes(0, 10);
+            put_matrix(b);
+            b.put_bytes(0, 24);
+            b.put_u32(next_track_id);
+        });
+        write_video_trak(moov, video);
+        if let Some(channels) = audio_channels {
+            write_audio_trak(moov, channels);
+        }
+        write_box(moov, b"mvex", |mvex| {
+            let mut ids = vec![VIDEO_TRACK_ID];
+            if audio_channels.is_some() {
+                ids.push(AUDIO_TRACK_ID);
+            }
+            for id in ids {
+                write_full_box(mvex, b"trex", 0, 0, |b| {
+                    b.put_u32(id);
+                    b.put_u32(1); // default_sample_description_index
+                    b.put_u32(0); // default_sample_duration
+                    b.put_u32(0); // default_sample_size
+                    b.put_u32(0); // default_sample_flags
+                });
+            }
+        });
+    });
+    out.freeze()
+}
+
+fn write_video_trak(moov: &mut BytesMut, cfg: &AvcConfig) {
+    write_box(moov, b"trak", |trak| {
+        write_tkhd(trak, VIDEO_TRACK_ID, 0, cfg.width, cfg.height);
+        write_box(trak, b"mdia", |mdia| {
+            write_mdhd(mdia, VIDEO_TIMESCALE);
+            write_hdlr(mdia, b"vide", b"VideoHandler");
+            write_box(mdia, b"minf", |minf| {
+                write_full_box(minf, b"vmhd", 0, 1, |b| {
+                    b.put_u16(0); // graphicsmode
+                    b.put_bytes(0, 6); // opcolor
+                });
+                write_dinf(minf);
+                write_stbl(minf, |stsd| {
+                    write_box(stsd, b"avc1", |b| {
+                        put_visual_sample_entry(b, cfg.width, cfg.height);
+                        write_box(b, b"avcC", |c| {
+                            c.put_u8(1); // configurationVersion
+                            c.put_slice(&cfg.sps[1..4]); // profile, compat, level
+                            c.put_u8(0xFF); // lengthSizeMinusOne = 3
+                            c.put_u8(0xE1); // one SPS
+                            c.put_u16(cfg.sps.len() as u16);
+                            c.put_slice(&cfg.sps);
+                            c.put_u8(1); // one PPS
+                            c.put_u16(cfg.pps.len() as u16);
+                            c.put_slice(&cfg.pps);
+                        });
+                    });
+                });
+            });
+        });
+    });
+}
+
+fn write_audio_trak(moov: &mut BytesMut, channels: u8) {
+    write_box(moov, b"trak", |trak| {
+        write_tkhd(trak, AUDIO_TRACK_ID, 0x0100, 0, 0);
+        write_box(trak, b"mdia", |mdia| {
+            write_mdhd(mdia, AUDIO_TIMESCALE);
+            write_hdlr(mdia, b"soun", b"SoundHandler");
+            write_box(mdia, b"minf", |minf| {
+                write_full_box(minf, b"smhd", 0, 0, |b| {
+                    b.put_u16(0); // balance
+                    b.put_u16(0);
+                });
+                write_dinf(minf);
+                write_stbl(minf, |stsd| {
+                    write_box(stsd, b"Opus", |b| {
+                        b.put_bytes(0, 6);
+                        b.put_u16(1); // data_reference_index
+                        b.put_bytes(0, 8);
+                        b.put_u16(channels as u16);
+                        b.put_u16(16); // samplesize
+                        b.put_u32(0);
+                        b.put_u32(AUDIO_TIMESCALE << 16);
+                        write_box(b, b"dOps", |d| {
+                            d.put_u8(0); // Version
+                            d.put_u8(channels);
+                            d.put_u16(OPUS_PRE_SKIP);
+                            d.put_u32(AUDIO_TIMESCALE); // InputSampleRate
+                            d.put_i16(0); // OutputGain
+                            d.put_u8(0); // ChannelMappingFamily
+                        });
+                    });
+                });
+            });
+        });
+    });
+}
+
+fn write_tkhd(trak: &mut BytesMut, track_id: u32, volume: u16, width: u16, height: u16) {
+    // flags: track_enabled | track_in_movie
+    write_full_box(trak, b"tkhd", 0, 0x3, |b| {
+        b.put_u32(0); // creation_time
+        b.put_u32(0); // modification_time
+        b.put_u32(track_id);
+        b.put_u32(0);
+        b.put_u32(0); // duration
+        b.put_bytes(0, 8);
+        b.put_u16(0); // layer
+        b.put_u16(0); // alternate_group
+        b.put_u16(volume);
+        b.put_u16(0);
+        put_matrix(b);
+        b.put_u32((width as u32) << 16);
+        b.put_u32((height as u32) << 16);
+    });
+}
+
+fn write_mdhd(mdia: &mut BytesMut, timescale: u32) {
+    write_full_box(mdia, b"mdhd", 0, 0, |b| {
+        b.put_u32(0); // creation_time
+        b.put_u32(0); // modification_time
+        b.put_u32(timescale);
+        b.put_u32(0); // duration
+        b.put_u16(0x55C4); // language: "und"
+        b.put_u16(0);
+    });
+}
+
+fn write_hdlr(mdia: &mut BytesMut, handler: &[u8; 4], name: &[u8]) {
+    write_full_box(mdia, b"hdlr", 0, 0, |b| {
+        b.put_u32(0); // pre_defined
+        b.put_slice(handler);
+        b.put_bytes(0, 12);
+        b.put_slice(name);
+        b.put_u8(0);
+    });
+}
+
+fn write_dinf(minf: &mut BytesMut) {
+    write_box(minf, b"dinf", |dinf| {
+        write_full_box(dinf, b"dref", 0, 0, |b| {
+            b.put_u32(1);
+            // flags = 1: media data is in the same file.
+            write_full_box(b, b"url ", 0, 1, |_| {});
+        });
+    });
+}
+
+/// `stbl` with a single sample entry and empty sample tables — samples live
+/// in the fragments.
+fn write_stbl(minf: &mut BytesMut, sample_entry: impl FnOnce(&mut BytesMut)) {
+    write_box(minf, b"stbl", |stbl| {
+        write_full_box(stbl, b"stsd", 0, 0, |b| {
+            b.put_u32(1);
+            sample_entry(b);
+        });
+        for kind in [b"stts", b"stsc", b"stco"] {
+            write_full_box(stbl, kind, 0, 0, |b| b.put_u32(0));
+        }
+        write_full_box(stbl, b"stsz", 0, 0, |b| {
+            b.put_u32(0); // sample_size
+            b.put_u32(0); // sample_count
+        });
+    });
+}
+
+fn put_visual_sample_entry(b: &mut BytesMut, width: u16, height: u16) {
+    b.put_bytes(0, 6);
+    b.put_u16(1); // data_reference_index
+    b.put_bytes(0, 16);
+    b.put_u16(width);
+    b.put_u16(height);
+    b.put_u32(0x0048_0000); // horizresolution: 72 dpi
+    b.put_u32(0x0048_0000); // vertresolution
+    b.put_u32(0);
+    b.put_u16(1); // frame_count
+    b.put_bytes(0, 32); // compressorname
+    b.put_u16(0x0018); // depth
+    b.put_i16(-1);
+}
+
+fn put_matrix(b: &mut BytesMut) {
+    for v in [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000] {
+        b.put_u32(v);
+    }
+}
+
+// ─── Media chunks ───────────────────────────────────────────────────────────
+
+/// One `moof` + `mdat` holding `runs` (tracks without samples are skipped).
+pub fn chunk(sequence: u32, runs: &[TrackRun<'_>]) -> Bytes {
+    let runs: Vec<&TrackRun> = runs.iter().filter(|r| !r.samples.is_empty()).collect();
+
+    // `trun.data_offset` is relative to the start of the moof, whose size
+    // does not depend on the offsets themselves: write it once to measure.
+    let moof_len = write_moof(sequence, &runs, 0).len();
+    let moof = write_moof(sequence, &runs, moof_len as u32 + 8);
+
+    let mut out = BytesMut::with_capacity(moof.len() + 8 + payload_len(&runs));
+    out.put_slice(&moof);
+    write_box(&mut out, b"mdat", |mdat| {
+        for run in &runs {
+            for sample in run.samples {
+                mdat.put_slice(&sample.data);
+            }
+        }
+    });
+    out.freeze()
+}
+
+fn payload_len(runs: &[&TrackRun<'_>]) -> usize {
+    runs.iter()
+        .flat_map(|r| r.samples.iter())
+        .map(|s| s.data.len())
+        .sum()
+}
+
+fn write_moof(sequence: u32, runs: &[&TrackRun<'_>], mdat_payload_offset: u32) -> BytesMut {
+    let mut out = BytesMut::new();
+    write_box(&mut out, b"moof", |moof| {
+        write_full_box(moof, b"mfhd", 0, 0, |b| b.put_u32(sequence));
+        let mut data_offset = mdat_payload_offset;
+        for run in runs {
+            write_box(moof, b"traf", |traf| {
+                // flags: default-base-is-moof
+                write_full_box(traf, b"tfhd", 0, 0x02_0000, |b| b.put_u32(run.track_id));
+                write_full_box(traf, b"tfdt", 1, 0, |b| b.put_u64(run.base_decode_time));
+                // flags: data-offset | sample-duration | sample-size | sample-flags
+                write_full_box(traf, b"trun", 0, 0x000701, |b| {
+                    b.put_u32(run.samples.len() as u32);
+                    b.put_u32(data_offset);
+                    for sample in run.samples {
+                        b.put_u32(sample.duration);
+                        b.put_u32(sample.data.len() as u32);
+                        b.put_u32(if sample.keyframe {
+                            SAMPLE_FLAGS_SYNC
+                        } else {
+                            SAMPLE_FLAGS_NON_SYNC
+                        });
+                    }
+                });
+            });
+            data_offset += run.samples.iter().map(|s| s.data.len() as u32).sum::<u32>();
+        }
+    });
+    out
+}
+
+// ─── Box helpers ────────────────────────────────────────────────────────────
+
+fn write_box(out: &mut BytesMut, kind: &[u8; 4], body: impl FnOnce(&mut BytesMut)) {
+    let start = out.len();
+    out.put_u32(0);
+    out.put_slice(kind);
+    body(out);
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+fn write_full_box(
+    out: &mut BytesMut,
+    kind: &[u8; 4],
+    version: u8,
+    flags: u32,
+    body: impl FnOnce(&mut BytesMut),
+) {
+    write_box(out, kind, |b| {
+        b.put_u32(((version as u32) << 24) | (flags & 0x00FF_FFFF));
+        body(b);
+    });
+}
+
+// ─── H264 helpers ───────────────────────────────────────────────────────────
+
+/// NAL units of a length-prefixed (AVC) access unit.
+pub fn avc_nalus(mut au: &[u8]) -> impl Iterator<Item = &[u8]> {
+    std::iter::from_fn(move || {
+        if au.len() < 4 {
+            return None;
+        }
+        let len = u32::from_be_bytes([au[0], au[1], au[2], au[3]]) as usize;
+        let nalu = au.get(4..4 + len)?;
+        au = &au[4 + len..];
+        Some(nalu)
+    })
+}
+
+/// Coded picture size from an SPS NAL unit (header byte included).
+pub fn sps_dimensions(sps: &[u8]) -> Option<(u16, u16)> {
+    // Strip emulation-prevention bytes (00 00 03 → 00 00).
+    let mut rbsp = Vec::with_capacity(sps.len());
+    let mut zeros = 0;
+    for &byte in sps.get(1..)? {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+
+    let mut r = BitReader::new(&rbsp);
+    let profile_idc = r.bits(8)?;
+    r.bits(16)?; // constraint flags + level_idc
+    r.ue()?; // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+        chroma_format_idc = r.ue()?;
+        if chroma_format_idc == 3 {
+            r.bits(1)?; // separate_colour_plane_flag
+        }
+        r.ue()?; // bit_depth_luma_minus8
+        r.ue()?; // bit_depth_chroma_minus8
+        r.bits(1)?; // qpprime_y_zero_transform_bypass_flag
+        if r.bits(1)? == 1 {
+            // seq_scaling_matrix_present_flag
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..lists {
+                if r.bits(1)? == 1 {
+                    skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+
+    r.ue()?; // log2_max_frame_num_minus4
+    match r.ue()? {
+        0 => {
+            r.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            r.bits(1)?; // delta_pic_order_always_zero_flag
+            r.se()?; // offset_for_non_ref_pic
+            r.se()?; // offset_for_top_to_bottom_field
+            for _ in 0..r.ue()? {
+                r.se()?;
+            }
+        }
+        _ => {}
+    }
+    r.ue()?; // max_num_ref_frames
+    r.bits(1)?; // gaps_in_frame_num_value_allowed_flag
+
+    let width_mbs = r.ue()? + 1;
+    let height_map_units = r.ue()? + 1;
+    let frame_mbs_only = r.bits(1)?;
+    if frame_mbs_only == 0 {
+        r.bits(1)?; // mb_adaptive_frame_field_flag
+    }
+    r.bits(1)?; // direct_8x8_inference_flag
+
+    let mut width = width_mbs * 16;
+    let mut height = (2 - frame_mbs_only) * height_map_units * 16;
+    if r.bits(1)? == 1 {
+        // frame_cropping_flag
+        let (left, right, top, bottom) = (r.ue()?, r.ue()?, r.ue()?, r.ue()?);
+        let (crop_x, crop_y) = match chroma_format_idc {
+            0 | 3 => (1, 2 - frame_mbs_only),
+            2 => (2, 2 - frame_mbs_only),
+            _ => (2, 2 * (2 - frame_mbs_only)),
+        };
+        width = width.checked_sub((left + right) * crop_x)?;
+        height = height.checked_sub((top + bottom) * crop_y)?;
+    }
+    Some((u16::try_from(width).ok()?, u16::try_from(height).ok()?))
+}
+
+fn skip_scaling_list(r: &mut BitReader<'_>, size: usize) -> Option<()> {
+    let (mut last, mut next) = (8i64, 8i64);
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + r.se()? + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+    Some(())
+}
+
+/// MSB-first bit reader over an RBSP / uncompressed header.
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(crate) fn bits(&mut self, n: u32) -> Option<u32> {
+        let mut v = 0u32;
+        for _ in 0..n {
+            let byte = *self.data.get(self.pos / 8)?;
+            v = (v << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u32;
+            self.pos += 1;
+        }
+        Some(v)
+    }
+
+    /// Unsigned Exp-Golomb.
+    fn ue(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while self.bits(1)? == 0 {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        Some((1u32 << zeros) - 1 + self.bits(zeros)?)
+    }
+
+    /// Signed Exp-Golomb.
+    fn se(&mut self) -> Option<i64> {
+        let k = self.ue()? as i64;
+        Some(if k % 2 == 1 { (k + 1) / 2 } else { -(k / 2) })
+    }
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Writes Exp-Golomb bitstreams so tests can build SPS NAL units.
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        bits: usize,
+    }
+
+    impl BitWriter {
+        fn bits(&mut self, n: u32, v: u32) -> &mut Self {
+            for i in (0..n).rev() {
+                if self.bits.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                let bit = ((v >> i) & 1) as u8;
+                *self.bytes.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+                self.bits += 1;
+            }
+            self
+        }
+
+        fn ue(&mut self, v: u32) -> &mut Self {
+            let n = 32 - (v + 1).leading_zeros();
+            self.bits(n - 1, 0).bits(n, v + 1)
+        }
+    }
+
+    /// A baseline-profile SPS for a `width` × `height` picture.
+    pub(crate) fn baseline_sps(width: u32, height: u32) -> Bytes {
+        let (wmb, hmb) = (width.div_ceil(16), height.div_ceil(16));
+        let mut w = BitWriter::default();
+        w.bits(8, 66).bits(8, 0xC0).bits(8, 31); // profile, constraints, level
+        w.ue(0).ue(0).ue(0).ue(0); // sps id, frame_num, poc type 0, poc lsb
+        w.ue(1).bits(1, 0); // ref frames, gaps
+        w.ue(wmb - 1).ue(hmb - 1).bits(1, 1).bits(1, 1); // size, frame_mbs_only, 8x8
+        let (crop_r, crop_b) = ((wmb * 16 - width) / 2, (hmb * 16 - height) / 2);
+        if crop_r + crop_b > 0 {
+            w.bits(1, 1).ue(0).ue(crop_r).ue(0).ue(crop_b);
+        } else {
+            w.bits(1, 0);
+        }
+        w.bits(1, 0).bits(1, 1); // vui absent, rbsp stop bit
+        let mut sps = vec![0x67];
+        sps.extend_from_slice(&w.bytes);
+        Bytes::from(sps)
+    }
+
+    fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+        let (kind, rest) = path.split_first()?;
+        let mut at = 0;
+        while at + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+            if &data[at + 4..at + 8] == *kind {
+                let body = &data[at + 8..at + size];
+                return if rest.is_empty() { Some(body) } else { find_box(body, rest) };
+            }
+            at += size.max(8);
+        }
+        None
+    }
+
+    #[test]
+    fn sps_dimensions_apply_cropping() {
+        assert_eq!(sps_dimensions(&baseline_sps(640, 480)), Some((640, 480)));
+        assert_eq!(sps_dimensions(&baseline_sps(1280, 720)), Some((1280, 720)));
+        assert_eq!(sps_dimensions(&baseline_sps(1920, 1080)), Some((1920, 1080)));
+        assert_eq!(sps_dimensions(&[0x67, 0x42]), None);
+    }
+
+    #[test]
+    fn init_segment_describes_both_tracks() {
+        let cfg = AvcConfig::new(baseline_sps(1280, 720), Bytes::from_static(&[0x68, 0xCE])).unwrap();
+        let init = init_segment(&cfg, Some(2));
+
+        assert_eq!(&init[4..8], b"ftyp");
+        let moov = find_box(&init, &[b"moov"]).unwrap();
+        let mvex = find_box(moov, &[b"mvex"]).unwrap();
+        assert_eq!(mvex.windows(4).filter(|w| w == b"trex").count(), 2);
+
+        let stsd = find_box(moov, &[b"trak", b"mdia", b"minf", b"stbl", b"stsd"]).unwrap();
+        // stsd header, avc1 header, then the 78-byte visual sample entry.
+        let avcc = find_box(&stsd[8 + 8 + 78..], &[b"avcC"]).unwrap();
+        assert_eq!(&avcc[1..4], &cfg.sps[1..4]);
+        assert!(moov.windows(4).any(|w| w == b"dOps"));
+
+        let video_only = init_segment(&cfg, None);
+        assert!(!video_only.windows(4).any(|w| w == b"Opus"));
+    }
+
+    #[test]
+    fn chunk_offsets_point_into_mdat() {
+        let video = [
+            Sample { data: Bytes::from_static(b"key"), duration: 3000, keyframe: true },
+            Sample { data: Bytes::from_static(b"delta"), duration: 3000, keyframe: false },
+        ];
+        let audio = [Sample { data: Bytes::from_static(b"opus"), duration: 960, keyframe: true }];
+        let out = chunk(
+            7,
+            &[
+                TrackRun { track_id: VIDEO_TRACK_ID, base_decode_time: 0, samples: &video },
+                TrackRun { track_id: AUDIO_TRACK_ID, base_decode_time: 960, samples: &audio },
+            ],
+        );
+
+        let moof = find_box(&out, &[b"moof"]).unwrap();
+        assert_eq!(&find_box(moof, &[b"mfhd"]).unwrap()[4..8], &7u32.to_be_bytes());
+
+        // First traf's trun: version/flags, count, data_offset.
+        let trun = find_box(moof, &[b"traf", b"trun"]).unwrap();
+        assert_eq!(u32::from_be_bytes(trun[4..8].try_into().unwrap()), 2);
+        let offset = u32::from_be_bytes(trun[8..12].try_into().unwrap()) as usize;
+        assert_eq!(&out[offset..offset + 8], b"keydelta");
+        assert_eq!(&out[offset + 8..], b"opus");
+    }
+}
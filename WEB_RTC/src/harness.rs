@@ -0,0 +1,1373 @@
+// src/harness.rs
+//
+// In-process integration harness: the real axum router on a loopback TCP
+// port, the SFU's PeerConnections and the test peers on a webrtc-rs virtual
+// network.  No media ever touches a host interface, so the tests run the
+// same on a laptop, in CI and inside a container.
+//
+//   reqwest ──HTTP (127.0.0.1:0)──▶ build_router(AppState)
+//                                        │ create_peer_connection
+//                                        ▼
+//   test peers (10.0.0.2) ◀──vnet──▶ SFU PeerConnections (10.0.0.1)
+//
+// Peers send VP8/Opus RTP whose payload is `<tag>-video` / `<tag>-audio`,
+// so a receiver can tell which publisher a packet came from.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use bytes::Bytes;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use tokio_util::sync::CancellationToken;
+use util::vnet::net::{Net, NetConfig};
+use util::vnet::router::{Router as VnetRouter, RouterConfig};
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::api::{APIBuilder, API};
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp::header::Header;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
+use webrtc::rtp_transceiver::RTCRtpTransceiverInit;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+
+use crate::events::{EventPayload, EventType, LiveRelayEvent};
+use crate::AppState;
+
+const SFU_IP: &str = "10.0.0.1";
+const PEER_IP: &str = "10.0.0.2";
+const API_KEY: &str = "lr_harness";
+
+/// How long any single step (connect, first packet, cleanup) may take.
+const STEP_TIMEOUT: Duration = Duration::from_secs(20);
+
+// ─── Harness ────────────────────────────────────────────────────────────────
+
+pub struct Harness {
+    pub state: Arc<AppState>,
+    base_url: String,
+    http: reqwest::Client,
+    peer_api: API,
+    recording_dir: PathBuf,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl Harness {
+    /// Build an `AppState` wired to a fresh virtual network and serve the
+    /// router on an ephemeral loopback port.
+    pub async fn start() -> Self {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let wan = Arc::new(Mutex::new(
+            VnetRouter::new(RouterConfig {
+                cidr: "10.0.0.0/24".to_string(),
+                ..Default::default()
+            })
+            .expect("vnet router"),
+        ));
+        let sfu_net = attach(&wan, SFU_IP).await;
+        let peer_net = attach(&wan, PEER_IP).await;
+        wan.lock().await.start().await.expect("vnet start");
+
+        let recording_dir =
+            std::env::temp_dir().join(format!("liverelay-harness-{}", uuid::Uuid::new_v4()));
+
+        let mut api_keys = HashMap::new();
+        api_keys.insert(
+            API_KEY.to_string(),
+            crate::auth::ApiKey {
+                key: API_KEY.to_string(),
+                name: "harness".to_string(),
+                created_at: 0,
+                quotas: Default::default(),
+            },
+        );
+
+        let cfg = test_config();
+        let state = Arc::new(AppState {
+            rooms: std::sync::RwLock::new(HashMap::new()),
+            api_keys: std::sync::RwLock::new(api_keys),
+            jwt_secret: cfg.jwt_secret.clone(),
+            settings: std::sync::RwLock::new(cfg.runtime_settings()),
+            config: cfg,
+            event_bus: crate::events::EventBus::new(),
+            webhooks: crate::webhook::WebhookStore::new(),
+            analytics: crate::analytics::AnalyticsStore::new(),
+            recording: Some(Arc::new(crate::recording::RecordingManager::new(
+                crate::recording::RecordingConfig {
+                    base_dir: recording_dir.clone(),
+                    max_duration_secs: 0,
+                },
+            ))),
+            hls: Some(Arc::new(crate::hls::HlsManager::new(Default::default()))),
+            snapshots: Some(Arc::new(crate::snapshot::SnapshotStore::new(
+                crate::snapshot::SnapshotConfig {
+                    base_dir: recording_dir.join("snapshots"),
+                    retain_per_room: 10,
+                },
+            ))),
+            usage: crate::usage::UsageStore::new(),
+            drain: crate::drain::DrainState::new(),
+            revocations: crate::revocation::RevocationList::new(),
+            turn_server: None,
+            // Not installed globally: tests share the process.
+            metrics_handle: metrics_exporter_prometheus::PrometheusBuilder::new()
+                .build_recorder()
+                .handle(),
+            vnet: Some(sfu_net),
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = crate::build_router(state.clone());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self {
+            state,
+            base_url,
+            http: reqwest::Client::new(),
+            peer_api: build_peer_api(peer_net),
+            recording_dir,
+            server,
+        }
+    }
+
+    /// POST with a bearer credential; returns the status and JSON body
+    /// (`Value::Null` for empty responses).
+    pub async fn post(&self, path: &str, bearer: &str, body: Value) -> (u16, Value) {
+        let resp = self
+            .http
+            .post(format!("{}{path}", self.base_url))
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        let text = resp.text().await.unwrap();
+        (status, serde_json::from_str(&text).unwrap_or(Value::Null))
+    }
+
+    /// POST with the bootstrap API key, asserting success.
+    pub async fn api(&self, path: &str, body: Value) -> Value {
+        let (status, json) = self.post(path, API_KEY, body).await;
+        assert!((200..300).contains(&status), "POST {path} → {status}: {json}");
+        json
+    }
+
+    pub async fn get(&self, path: &str, bearer: &str) -> (u16, Value) {
+        let resp = self
+            .http
+            .get(format!("{}{path}", self.base_url))
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// GET without credentials; returns the status and raw body.
+    pub async fn get_bytes(&self, path: &str) -> (u16, Bytes) {
+        let resp = self
+            .http
+            .get(format!("{}{path}", self.base_url))
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        (status, resp.bytes().await.unwrap())
+    }
+
+    /// GET with a bearer credential; returns the status and raw body.
+    pub async fn get_bytes_as(&self, path: &str, bearer: &str) -> (u16, Bytes) {
+        let resp = self
+            .http
+            .get(format!("{}{path}", self.base_url))
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        (status, resp.bytes().await.unwrap())
+    }
+
+    pub async fn delete(&self, path: &str) -> u16 {
+        self.delete_as(path, API_KEY).await
+    }
+
+    pub async fn delete_as(&self, path: &str, bearer: &str) -> u16 {
+        self.http
+            .delete(format!("{}{path}", self.base_url))
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+            .status()
+            .as_u16()
+    }
+
+    pub async fn create_room(&self, body: Value) -> Value {
+        self.api("/v1/rooms", body).await
+    }
+
+    pub async fn token(&self, room_id: &str, role: &str) -> String {
+        let resp = self
+            .api(&format!("/v1/rooms/{room_id}/token"), json!({ "role": role }))
+            .await;
+        resp["token"].as_str().unwrap().to_string()
+    }
+
+    /// A peer that sends VP8 + Opus on sendrecv transceivers.
+    pub async fn sending_peer(&self) -> Peer {
+        self.peer_sending(MIME_TYPE_VP8, "", true).await
+    }
+
+    /// A peer that sends VP8 only, the way a screen share does.
+    pub async fn screen_peer(&self) -> Peer {
+        self.peer_sending(MIME_TYPE_VP8, "", false).await
+    }
+
+    /// A peer that sends constrained-baseline H264 + Opus; drive it with
+    /// [`Peer::start_sending_h264`].
+    pub async fn h264_peer(&self) -> Peer {
+        let fmtp = "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f";
+        self.peer_sending(MIME_TYPE_H264, fmtp, true).await
+    }
+
+    async fn peer_sending(&self, video_mime: &str, fmtp: &str, with_audio: bool) -> Peer {
+        let pc = self.new_pc().await;
+        let video = Arc::new(TrackLocalStaticRTP::new(
+            RTCRtpCodecCapability {
+                mime_type: video_mime.to_string(),
+                clock_rate: 90000,
+                sdp_fmtp_line: fmtp.to_string(),
+                ..Default::default()
+            },
+            "video".to_string(),
+            "harness".to_string(),
+        ));
+        let audio = Arc::new(TrackLocalStaticRTP::new(
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_OPUS.to_string(),
+                clock_rate: 48000,
+                channels: 2,
+                ..Default::default()
+            },
+            "audio".to_string(),
+            "harness".to_string(),
+        ));
+        let tracks = if with_audio {
+            vec![video.clone(), audio.clone()]
+        } else {
+            vec![video.clone()]
+        };
+        for track in tracks {
+            let sender = pc
+                .add_track(track as Arc<dyn TrackLocal + Send + Sync>)
+                .await
+                .unwrap();
+            // Drain RTCP so the interceptors keep working.
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 1500];
+                while sender.read(&mut buf).await.is_ok() {}
+            });
+        }
+        Peer::new(pc, Some((video, audio)))
+    }
+
+    /// A peer with recvonly video + audio transceivers.
+    pub async fn receiving_peer(&self) -> Peer {
+        let pc = self.new_pc().await;
+        for kind in [RTPCodecType::Video, RTPCodecType::Audio] {
+            pc.add_transceiver_from_kind(
+                kind,
+                Some(RTCRtpTransceiverInit {
+                    direction: RTCRtpTransceiverDirection::Recvonly,
+                    send_encodings: vec![],
+                }),
+            )
+            .await
+            .unwrap();
+        }
+        Peer::new(pc, None)
+    }
+
+    /// Offer/answer against a `/sfu/*` endpoint.  `extra` is merged into the
+    /// request body; the SFU's JSON answer is returned.
+    pub async fn negotiate(&self, peer: &Peer, path: &str, token: &str, extra: Value) -> Value {
+        let offer = peer.pc.create_offer(None).await.unwrap();
+        let mut gathered = peer.pc.gathering_complete_promise().await;
+        peer.pc.set_local_description(offer).await.unwrap();
+        let _ = gathered.recv().await;
+        let local = peer.pc.local_description().await.unwrap();
+
+        let mut body = json!({ "sdp": local.sdp, "type": "offer" });
+        if let Value::Object(extra) = extra {
+            body.as_object_mut().unwrap().extend(extra);
+        }
+        let (status, answer) = self.post(path, token, body).await;
+        assert_eq!(status, 200, "POST {path}: {answer}");
+
+        let sdp = answer["sdp"].as_str().unwrap().to_string();
+        peer.pc
+            .set_remote_description(RTCSessionDescription::answer(sdp).unwrap())
+            .await
+            .unwrap();
+        answer
+    }
+
+    /// Negotiate, wait for ICE/DTLS and start sending media tagged `tag`.
+    pub async fn join(&self, peer: &Peer, path: &str, token: &str, tag: &str) -> Value {
+        let answer = self.negotiate(peer, path, token, Value::Null).await;
+        peer.connected().await;
+        peer.start_sending(tag);
+        answer
+    }
+
+    pub fn room(&self, room_id: &str) -> Option<Arc<crate::room::Room>> {
+        self.state.rooms.read().unwrap().get(room_id).cloned()
+    }
+
+    async fn new_pc(&self) -> Arc<RTCPeerConnection> {
+        Arc::new(
+            self.peer_api
+                .new_peer_connection(RTCConfiguration::default())
+                .await
+                .unwrap(),
+        )
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.server.abort();
+        let _ = std::fs::remove_dir_all(&self.recording_dir);
+    }
+}
+
+async fn attach(wan: &Arc<Mutex<VnetRouter>>, ip: &str) -> Arc<Net> {
+    let net = Arc::new(Net::new(Some(NetConfig {
+        static_ips: vec![ip.to_string()],
+        ..Default::default()
+    })));
+    let nic = net.get_nic().unwrap();
+    wan.lock().await.add_net(Arc::clone(&nic)).await.unwrap();
+    nic.lock().await.set_router(Arc::clone(wan)).await.unwrap();
+    net
+}
+
+fn build_peer_api(net: Arc<Net>) -> API {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs().unwrap();
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine).unwrap();
+    let mut setting_engine = SettingEngine::default();
+    setting_engine.set_vnet(Some(net));
+    APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .with_setting_engine(setting_engine)
+        .build()
+}
+
+fn test_config() -> crate::config::Config {
+    crate::config::Config {
+        config_path: None,
+        bind_addr: "127.0.0.1:0".into(),
+        // NAT 1:1 maps the SFU's candidates onto its own vnet address.
+        public_host: SFU_IP.into(),
+        tls_enabled: false,
+        tls_cert_path: None,
+        tls_key_path: None,
+        turn_embedded: false,
+        turn_port: 3478,
+        turn_secret: "harness".into(),
+        turn_credential_ttl_secs: 3600,
+        turn_realm: "localhost".into(),
+        // No STUN: the vnet has no route to a public server.
+        stun_urls: vec![],
+        turn_urls: vec![],
+        jwt_secret: uuid::Uuid::new_v4().to_string(),
+        bootstrap_api_key: API_KEY.into(),
+        max_rooms: 100,
+        max_subscribers_per_room: 1000,
+        udp_port_min: 0,
+        udp_port_max: 0,
+        drain_deadline_secs: 600,
+        allowed_origins: "*".into(),
+        recording_dir: "./recordings".into(),
+        recording_max_duration_secs: 0,
+        hls: Default::default(),
+        snapshot_dir: "./snapshots".into(),
+        snapshot_retain_per_room: 10,
+        stats_interval_secs: 5,
+        quality_thresholds: Default::default(),
+        webhook_retry: Default::default(),
+        log_level: "info".into(),
+    }
+}
+
+// ─── Peer ───────────────────────────────────────────────────────────────────
+
+type Tracks = (Arc<TrackLocalStaticRTP>, Arc<TrackLocalStaticRTP>);
+
+pub struct Peer {
+    pub pc: Arc<RTCPeerConnection>,
+    tracks: Option<Tracks>,
+    state: watch::Receiver<RTCPeerConnectionState>,
+    media: mpsc::UnboundedReceiver<(RTPCodecType, Bytes)>,
+    stop: CancellationToken,
+}
+
+impl Peer {
+    fn new(pc: Arc<RTCPeerConnection>, tracks: Option<Tracks>) -> Self {
+        let (state_tx, state) = watch::channel(RTCPeerConnectionState::New);
+        pc.on_peer_connection_state_change(Box::new(move |s| {
+            let _ = state_tx.send(s);
+            Box::pin(async {})
+        }));
+
+        let (media_tx, media) = mpsc::unbounded_channel();
+        pc.on_track(Box::new(move |track, _receiver, _transceiver| {
+            let media_tx = media_tx.clone();
+            // The next `on_track` only fires once this future completes.
+            tokio::spawn(async move {
+                let kind = track.kind();
+                while let Ok((pkt, _)) = track.read_rtp().await {
+                    if media_tx.send((kind, pkt.payload)).is_err() {
+                        break;
+                    }
+                }
+            });
+            Box::pin(async {})
+        }));
+
+        Self {
+            pc,
+            tracks,
+            state,
+            media,
+            stop: CancellationToken::new(),
+        }
+    }
+
+    pub async fn connected(&self) {
+        let mut state = self.state.clone();
+        tokio::time::timeout(
+            STEP_TIMEOUT,
+            state.wait_for(|s| *s == RTCPeerConnectionState::Connected),
+        )
+        .await
+        .expect("peer did not connect")
+        .unwrap();
+    }
+
+    /// Send one VP8 and one Opus packet every 20 ms until the peer drops.
+    /// A screen peer's Opus track is never bound, so its writes go nowhere.
+    pub fn start_sending(&self, tag: &str) {
+        let (video, audio) = self.tracks.clone().expect("not a sending peer");
+        let stop = self.stop.clone();
+        let video_payload = Bytes::from(format!("{tag}-video"));
+        let audio_payload = Bytes::from(format!("{tag}-audio"));
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(Duration::from_millis(20));
+            for seq in 0u16.. {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = tick.tick() => {}
+                }
+                let ts = seq as u32;
+                let _ = video.write_rtp(&packet(seq, ts * 1800, video_payload.clone())).await;
+                let _ = audio.write_rtp(&packet(seq, ts * 960, audio_payload.clone())).await;
+            }
+        });
+    }
+
+    /// Send a 30 fps H264 stream of single-NAL packets (SPS, PPS and IDR
+    /// once per second, slices in between) plus Opus, until the peer drops.
+    pub fn start_sending_h264(&self) {
+        let (video, audio) = self.tracks.clone().expect("not a sending peer");
+        let stop = self.stop.clone();
+        let sps = crate::fmp4::tests::baseline_sps(320, 240);
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(Duration::from_millis(33));
+            let mut seq = 0u16;
+            for frame in 0u32.. {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = tick.tick() => {}
+                }
+                let nalus: Vec<Bytes> = if frame % 30 == 0 {
+                    vec![
+                        sps.clone(),
+                        Bytes::from_static(&[0x68, 0xCE, 0x38, 0x80]),
+                        Bytes::from_static(&[0x65, 0x88, 0x84, 0x00]),
+                    ]
+                } else {
+                    vec![Bytes::from_static(&[0x41, 0x9A, 0x02, 0x00])]
+                };
+                let last = nalus.len() - 1;
+                for (i, nalu) in nalus.into_iter().enumerate() {
+                    let mut pkt = packet(seq, frame * 3000, nalu);
+                    pkt.header.marker = i == last;
+                    let _ = video.write_rtp(&pkt).await;
+                    seq = seq.wrapping_add(1);
+                }
+                let opus = packet(frame as u16, frame * 1584, Bytes::from_static(b"opus"));
+                let _ = audio.write_rtp(&opus).await;
+            }
+        });
+    }
+
+    /// Wait for a packet of `kind` whose payload is `<tag>-<kind>`.
+    pub async fn expect_media(&mut self, tag: &str, kind: RTPCodecType) {
+        let want = format!("{tag}-{}", if kind == RTPCodecType::Video { "video" } else { "audio" });
+        tokio::time::timeout(STEP_TIMEOUT, async {
+            while let Some((k, payload)) = self.media.recv().await {
+                if k == kind && payload == want.as_bytes() {
+                    return;
+                }
+            }
+            panic!("media channel closed before '{want}' arrived");
+        })
+        .await
+        .unwrap_or_else(|_| panic!("no '{want}' packet within {STEP_TIMEOUT:?}"));
+    }
+
+    /// Drop everything buffered, then return the kinds of the packets that
+    /// arrive during `window`.
+    pub async fn received_during(&mut self, window: Duration) -> Vec<RTPCodecType> {
+        while self.media.try_recv().is_ok() {}
+        let mut kinds = Vec::new();
+        let _ = tokio::time::timeout(window, async {
+            while let Some((kind, _)) = self.media.recv().await {
+                kinds.push(kind);
+            }
+        })
+        .await;
+        kinds
+    }
+
+    pub async fn expect_av(&mut self, tag: &str) {
+        self.expect_media(tag, RTPCodecType::Video).await;
+        self.expect_media(tag, RTPCodecType::Audio).await;
+    }
+
+    pub async fn close(&self) {
+        self.stop.cancel();
+        self.pc.close().await.unwrap();
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+fn packet(seq: u16, timestamp: u32, payload: Bytes) -> Packet {
+    Packet {
+        header: Header {
+            version: 2,
+            marker: true,
+            sequence_number: seq,
+            timestamp,
+            ..Default::default()
+        },
+        payload,
+    }
+}
+
+/// Wait for the next event of type `want`, skipping everything else.
+pub async fn next_event(
+    rx: &mut broadcast::Receiver<LiveRelayEvent>,
+    want: EventType,
+) -> LiveRelayEvent {
+    tokio::time::timeout(STEP_TIMEOUT, async {
+        loop {
+            match rx.recv().await {
+                Ok(evt) if evt.event_type == want => return evt,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => panic!("event bus closed"),
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("no {want} event within {STEP_TIMEOUT:?}"))
+}
+
+/// Poll `cond` every 100 ms until it holds.
+pub async fn eventually(what: &str, mut cond: impl FnMut() -> bool) {
+    tokio::time::timeout(STEP_TIMEOUT, async {
+        while !cond() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {what}"));
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrr::{read_lrr_header, read_lrr_records, TRACK_AUDIO, TRACK_VIDEO};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn broadcast_fans_out_to_every_subscriber() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap();
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", room["tokens"]["publish"].as_str().unwrap(), "pub")
+            .await;
+
+        let mut subscribers = Vec::new();
+        for _ in 0..2 {
+            let sub = h.receiving_peer().await;
+            let token = h.token(room_id, "subscribe").await;
+            h.negotiate(&sub, "/sfu/subscribe", &token, Value::Null).await;
+            sub.connected().await;
+            subscribers.push(sub);
+        }
+        for sub in &mut subscribers {
+            sub.expect_av("pub").await;
+        }
+
+        let room = h.room(room_id).unwrap();
+        assert_eq!(room.publisher_count(), 1);
+        assert_eq!(room.subscriber_count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn paused_tracks_stop_flowing_until_resumed() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap();
+        let publish = room["tokens"]["publish"].as_str().unwrap();
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", publish, "pub").await;
+
+        let mut sub = h.receiving_peer().await;
+        let token = h.token(room_id, "subscribe").await;
+        let answer = h.negotiate(&sub, "/sfu/subscribe", &token, Value::Null).await;
+        let subscription = answer["subscription_id"].as_str().unwrap();
+        sub.connected().await;
+        sub.expect_av("pub").await;
+
+        let path = format!("/sfu/subscriptions/{subscription}");
+        let (status, info) = h
+            .post(
+                &path,
+                &token,
+                json!({ "pause": ["video"], "max_resolution": { "width": 320, "height": 180 } }),
+            )
+            .await;
+        assert_eq!(status, 200, "{info}");
+        assert_eq!(info["tracks"].as_array().unwrap().len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let kinds = sub.received_during(Duration::from_millis(500)).await;
+        assert!(kinds.contains(&RTPCodecType::Audio));
+        assert!(!kinds.contains(&RTPCodecType::Video));
+
+        let (_, demand) = h.get("/sfu/demand", publish).await;
+        assert_eq!(demand["video_subscribers"], 0);
+        assert_eq!(demand["audio_subscribers"], 1);
+
+        let (status, _) = h.post(&path, &token, json!({ "resume": ["video"] })).await;
+        assert_eq!(status, 200);
+        sub.expect_media("pub", RTPCodecType::Video).await;
+        let (_, demand) = h.get("/sfu/demand", publish).await;
+        assert_eq!(demand["video_subscribers"], 1);
+        assert_eq!(demand["max_resolution"], json!({ "width": 320, "height": 180 }));
+
+        let (status, _) = h.post("/sfu/subscriptions/nope", &token, json!({})).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn call_forwards_first_peer_to_second() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "call" })).await;
+        let tokens = &room["tokens"];
+
+        let caller = h.sending_peer().await;
+        h.join(&caller, "/sfu/call", tokens["caller"].as_str().unwrap(), "caller")
+            .await;
+
+        let mut callee = h.sending_peer().await;
+        h.join(&callee, "/sfu/call", tokens["callee"].as_str().unwrap(), "callee")
+            .await;
+        callee.expect_av("caller").await;
+
+        assert_eq!(h.room(room["id"].as_str().unwrap()).unwrap().publisher_count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn conference_joiner_and_late_subscription() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "conference" })).await;
+        let room_id = room["id"].as_str().unwrap();
+        let tokens = room["tokens"]["tokens"].as_array().unwrap();
+
+        let mut alice = h.sending_peer().await;
+        let joined = h.join(&alice, "/sfu/conference", tokens[0].as_str().unwrap(), "alice").await;
+        assert_eq!(joined["participants"], json!([]));
+        let alice_id = joined["peer_id"].as_str().unwrap().to_string();
+
+        // Bob gets Alice's tracks in his join answer.
+        let mut bob = h.sending_peer().await;
+        let joined = h.join(&bob, "/sfu/conference", tokens[1].as_str().unwrap(), "bob").await;
+        assert_eq!(joined["participants"], json!([alice_id]));
+        assert_eq!(joined["waiting"], json!(false));
+        let bob_id = joined["peer_id"].as_str().unwrap().to_string();
+        bob.expect_av("alice").await;
+
+        // Alice picks up Bob on a subscribe-only PeerConnection.
+        let mut alice_sub = h.receiving_peer().await;
+        h.negotiate(
+            &alice_sub,
+            "/sfu/conference/subscribe",
+            tokens[0].as_str().unwrap(),
+            json!({ "target_peer_id": bob_id }),
+        )
+        .await;
+        alice_sub.connected().await;
+        alice_sub.expect_av("bob").await;
+
+        // Nothing of Bob's leaks onto Alice's main connection.
+        alice.media.close();
+        while let Some((_, payload)) = alice.media.recv().await {
+            assert!(!payload.starts_with(b"bob"));
+        }
+
+        let room = h.room(room_id).unwrap();
+        assert_eq!(room.publisher_count(), 2);
+        assert_eq!(room.subscriber_count(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn publisher_disconnect_removes_broadcast_room() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap().to_string();
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", room["tokens"]["publish"].as_str().unwrap(), "pub")
+            .await;
+        assert_eq!(h.room(&room_id).unwrap().publisher_count(), 1);
+
+        publisher.close().await;
+        eventually("room removal", || h.room(&room_id).is_none()).await;
+
+        let token = room["tokens"]["subscribe"].as_str().unwrap();
+        let sub = h.receiving_peer().await;
+        let offer = sub.pc.create_offer(None).await.unwrap();
+        let (status, _) = h
+            .post("/sfu/subscribe", token, json!({ "sdp": offer.sdp, "type": "offer" }))
+            .await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn call_room_removed_after_both_peers_leave() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "call" })).await;
+        let room_id = room["id"].as_str().unwrap().to_string();
+        let tokens = &room["tokens"];
+
+        let caller = h.sending_peer().await;
+        h.join(&caller, "/sfu/call", tokens["caller"].as_str().unwrap(), "caller")
+            .await;
+        let callee = h.sending_peer().await;
+        h.join(&callee, "/sfu/call", tokens["callee"].as_str().unwrap(), "callee")
+            .await;
+
+        caller.close().await;
+        eventually("caller removal", || {
+            h.room(&room_id).is_some_and(|r| r.publisher_count() == 1)
+        })
+        .await;
+
+        callee.close().await;
+        eventually("room removal", || h.room(&room_id).is_none()).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn room_deleted_before_first_tick_is_metered() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap().to_string();
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", room["tokens"]["publish"].as_str().unwrap(), "pub")
+            .await;
+        let mut sub = h.receiving_peer().await;
+        let token = h.token(&room_id, "subscribe").await;
+        h.negotiate(&sub, "/sfu/subscribe", &token, Value::Null).await;
+        sub.connected().await;
+        sub.expect_av("pub").await;
+
+        // The harness never starts the meter, so only the final flush on
+        // deletion can have recorded anything.
+        assert!(h.state.usage.buckets(API_KEY, 0).await.is_empty());
+        assert_eq!(h.delete(&format!("/v1/rooms/{room_id}")).await, 204);
+
+        let buckets = h.state.usage.buckets(API_KEY, 0).await;
+        assert_eq!(buckets.len(), 1);
+        let bucket = &buckets[0];
+        assert!(bucket.participant_minutes["publish"] > 0.0);
+        assert!(bucket.participant_minutes["subscribe"] > 0.0);
+        assert!(bucket.egress_bytes > 0);
+
+        // The publisher's disconnect after the delete must not count twice.
+        publisher.close().await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let after = h.state.usage.buckets(API_KEY, 0).await;
+        assert_eq!(after[0].egress_bytes, bucket.egress_bytes);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn recording_captures_forwarded_rtp() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap();
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", room["tokens"]["publish"].as_str().unwrap(), "rec")
+            .await;
+        let publisher_ready = || {
+            h.room(room_id).unwrap().get_publishers()[0]
+                .video_codec
+                .read()
+                .unwrap()
+                .is_some()
+        };
+        eventually("publisher tracks", publisher_ready).await;
+
+        let started = h
+            .api(&format!("/v1/rooms/{room_id}/recording/start"), json!({}))
+            .await;
+        let path = PathBuf::from(started["file_path"].as_str().unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        h.api(&format!("/v1/rooms/{room_id}/recording/stop"), Value::Null)
+            .await;
+
+        let manager = h.state.recording.clone().unwrap();
+        let handles: Vec<_> = manager.active.read().unwrap().values().cloned().collect();
+        for handle in handles {
+            tokio::time::timeout(STEP_TIMEOUT, handle.done.cancelled())
+                .await
+                .expect("recording writer did not finish");
+        }
+
+        let data = std::fs::read(&path).unwrap();
+        read_lrr_header(&data).unwrap();
+        let records = read_lrr_records(&data);
+        let has = |kind: u8, payload: &[u8]| {
+            records
+                .iter()
+                .any(|r| r.track_kind == kind && r.rtp_data.ends_with(payload))
+        };
+        assert!(has(TRACK_VIDEO, b"rec-video"), "no video in {} records", records.len());
+        assert!(has(TRACK_AUDIO, b"rec-audio"), "no audio in {} records", records.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn room_and_lobby_events() {
+        let h = Harness::start().await;
+        let mut events = h.state.event_bus.subscribe();
+
+        let room = h
+            .create_room(json!({ "room_type": "conference", "lobby": true }))
+            .await;
+        let room_id = room["id"].as_str().unwrap();
+        let evt = next_event(&mut events, EventType::RoomCreated).await;
+        assert_eq!(evt.room_id(), room_id);
+
+        let guest = h.sending_peer().await;
+        let token = room["tokens"]["tokens"][0].as_str().unwrap();
+        let joined = h.join(&guest, "/sfu/conference", token, "guest").await;
+        assert_eq!(joined["waiting"], json!(true));
+        let guest_id = joined["peer_id"].as_str().unwrap();
+
+        let evt = next_event(&mut events, EventType::ParticipantWaiting).await;
+        assert_eq!(evt.room_id(), room_id);
+
+        let host = room["tokens"]["host"].as_str().unwrap();
+        let (status, _) = h
+            .post(&format!("/v1/rooms/{room_id}/lobby/{guest_id}/admit"), host, Value::Null)
+            .await;
+        assert_eq!(status, 204);
+        let evt = next_event(&mut events, EventType::ParticipantAdmitted).await;
+        assert_eq!(evt.room_id(), room_id);
+        let EventPayload::Participant(admitted) = &evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(admitted.role, "conference");
+
+        let late = h.sending_peer().await;
+        let token = room["tokens"]["tokens"][1].as_str().unwrap();
+        let joined = h.join(&late, "/sfu/conference", token, "late").await;
+        let late_id = joined["peer_id"].as_str().unwrap();
+        next_event(&mut events, EventType::ParticipantWaiting).await;
+        let (status, _) = h
+            .post(&format!("/v1/rooms/{room_id}/lobby/{late_id}/reject"), host, Value::Null)
+            .await;
+        assert_eq!(status, 204);
+        let evt = next_event(&mut events, EventType::ParticipantRejected).await;
+        let EventPayload::Participant(rejected) = &evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!((rejected.peer_id.as_str(), rejected.role.as_str()), (late_id, "conference"));
+
+        // Another tenant's key does not host the room.
+        let lobby = format!("/v1/rooms/{room_id}/lobby");
+        let tenant = h.api("/v1/keys", json!({ "name": "tenant" })).await;
+        let (status, err) = h.get(&lobby, tenant["key"].as_str().unwrap()).await;
+        assert_eq!(status, 403);
+        assert_eq!(err["error"]["code"], "forbidden");
+        let (status, waiting) = h.get(&lobby, API_KEY).await;
+        assert_eq!(status, 200);
+        assert_eq!(waiting["waiting"], json!([]));
+
+        assert_eq!(h.delete(&format!("/v1/rooms/{room_id}")).await, 204);
+        let evt = next_event(&mut events, EventType::RoomDeleted).await;
+        assert_eq!(evt.room_id(), room_id);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn conference_screen_share_reaches_existing_and_late_joiners() {
+        let h = Harness::start().await;
+        let mut events = h.state.event_bus.subscribe();
+        let room = h
+            .create_room(json!({ "room_type": "conference", "single_screen_share": true }))
+            .await;
+        let room_id = room["id"].as_str().unwrap();
+        let tokens = room["tokens"]["tokens"].as_array().unwrap();
+        let token = |i: usize| tokens[i].as_str().unwrap();
+
+        let alice = h.sending_peer().await;
+        let joined = h.join(&alice, "/sfu/conference", token(0), "alice").await;
+        let alice_id = joined["peer_id"].as_str().unwrap().to_string();
+        let bob = h.sending_peer().await;
+        let joined = h.join(&bob, "/sfu/conference", token(1), "bob").await;
+        assert_eq!(joined["screen_shares"], json!([]));
+
+        // Sharing before joining is refused.
+        let (status, _) = h
+            .post("/sfu/screen", token(2), json!({ "sdp": "", "type": "offer" }))
+            .await;
+        assert_eq!(status, 403);
+
+        let screen = h.screen_peer().await;
+        h.join(&screen, "/sfu/screen", token(0), "alice-screen").await;
+        let evt = next_event(&mut events, EventType::StreamStarted).await;
+        let EventPayload::Stream(started) = &evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!((started.peer_id.as_str(), started.kind.as_str()), (alice_id.as_str(), "screen"));
+
+        let screen_id = format!("{alice_id}-screen");
+        let (_, shares) = h.get("/sfu/screen", token(1)).await;
+        assert_eq!(shares["screen_shares"], json!([screen_id]));
+
+        // Bob already sits in the room: he picks the share up on its own PC.
+        let mut bob_screen = h.receiving_peer().await;
+        h.negotiate(
+            &bob_screen,
+            "/sfu/conference/subscribe",
+            token(1),
+            json!({ "target_peer_id": screen_id }),
+        )
+        .await;
+        bob_screen.connected().await;
+        bob_screen.expect_media("alice-screen", RTPCodecType::Video).await;
+
+        // Carol joins later and learns about the share from her answer.
+        let carol = h.sending_peer().await;
+        let joined = h.join(&carol, "/sfu/conference", token(2), "carol").await;
+        assert_eq!(joined["screen_shares"], json!([screen_id]));
+        assert!(!joined["participants"].as_array().unwrap().contains(&json!(screen_id)));
+
+        // One share at a time in this room.
+        let second = h.screen_peer().await;
+        let offer = second.pc.create_offer(None).await.unwrap();
+        let (status, err) = h
+            .post("/sfu/screen", token(2), json!({ "sdp": offer.sdp, "type": "offer" }))
+            .await;
+        assert_eq!(status, 409);
+        assert_eq!(err["error"]["code"], "screen_share_active");
+
+        let room = h.room(room_id).unwrap();
+        assert_eq!(room.participant_count(), 3);
+
+        assert_eq!(h.delete_as("/sfu/screen", token(0)).await, 204);
+        let evt = next_event(&mut events, EventType::StreamStopped).await;
+        let EventPayload::Stream(stopped) = &evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(stopped.kind, "screen");
+        assert!(room.screen_shares().is_empty());
+        assert_eq!(h.delete_as("/sfu/screen", token(0)).await, 404);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn draining_node_refuses_joins_but_not_screen_shares() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "conference" })).await;
+        let tokens = room["tokens"]["tokens"].as_array().unwrap();
+        let token = |i: usize| tokens[i].as_str().unwrap();
+
+        let alice = h.sending_peer().await;
+        h.join(&alice, "/sfu/conference", token(0), "alice").await;
+
+        crate::drain::begin_drain(&h.state, "test").await;
+
+        let bob = h.sending_peer().await;
+        let offer = bob.pc.create_offer(None).await.unwrap();
+        let (status, err) = h
+            .post("/sfu/conference", token(1), json!({ "sdp": offer.sdp, "type": "offer" }))
+            .await;
+        assert_eq!(status, 503);
+        assert_eq!(err["error"]["code"], "node_draining");
+
+        let screen = h.screen_peer().await;
+        h.join(&screen, "/sfu/screen", token(0), "alice-screen").await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn call_screen_share_ends_with_its_sharer() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "call" })).await;
+        let room_id = room["id"].as_str().unwrap().to_string();
+        let tokens = &room["tokens"];
+        let caller_token = tokens["caller"].as_str().unwrap();
+
+        let caller = h.sending_peer().await;
+        h.join(&caller, "/sfu/call", caller_token, "caller").await;
+        let callee = h.sending_peer().await;
+        h.join(&callee, "/sfu/call", tokens["callee"].as_str().unwrap(), "callee")
+            .await;
+
+        let screen = h.screen_peer().await;
+        h.join(&screen, "/sfu/screen", caller_token, "share").await;
+        let (_, shares) = h.get("/sfu/screen", tokens["callee"].as_str().unwrap()).await;
+        let screen_id = shares["screen_shares"][0].as_str().unwrap().to_string();
+        let mut view = h.receiving_peer().await;
+        h.negotiate(
+            &view,
+            "/sfu/conference/subscribe",
+            tokens["callee"].as_str().unwrap(),
+            json!({ "target_peer_id": screen_id }),
+        )
+        .await;
+        view.connected().await;
+        view.expect_media("share", RTPCodecType::Video).await;
+
+        // The screen share does not count as a call participant.
+        assert_eq!(h.room(&room_id).unwrap().participant_count(), 2);
+
+        caller.close().await;
+        eventually("screen share removal", || {
+            h.room(&room_id).is_some_and(|r| r.screen_shares().is_empty())
+        })
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn hls_egress_serves_playlist_and_media() {
+        let h = Harness::start().await;
+        let room = h
+            .create_room(json!({ "room_type": "broadcast", "codecs": { "video": ["h264"] } }))
+            .await;
+        let room_id = room["id"].as_str().unwrap();
+
+        let publisher = h.h264_peer().await;
+        let publish = room["tokens"]["publish"].as_str().unwrap();
+        h.negotiate(&publisher, "/sfu/publish", publish, Value::Null).await;
+        publisher.connected().await;
+        publisher.start_sending_h264();
+
+        let started = h.api(&format!("/v1/rooms/{room_id}/hls/start"), json!({})).await;
+        let playlist_url = started["playlist_url"].as_str().unwrap().to_string();
+        assert_eq!(playlist_url, format!("/hls/{room_id}/playlist.m3u8"));
+
+        // Blocking reload: returns once segment 0 is complete.
+        let (status, playlist) = h.get_bytes(&format!("{playlist_url}?_HLS_msn=1")).await;
+        assert_eq!(status, 200);
+        let playlist = String::from_utf8(playlist.to_vec()).unwrap();
+        assert!(playlist.contains("#EXT-X-PART-INF:PART-TARGET="), "{playlist}");
+        assert!(playlist.contains("\nseg0.m4s\n"), "{playlist}");
+        assert!(playlist.contains("#EXT-X-PRELOAD-HINT:TYPE=PART"), "{playlist}");
+
+        let (status, init) = h.get_bytes(&format!("/hls/{room_id}/init.mp4")).await;
+        assert_eq!(status, 200);
+        assert_eq!(&init[4..8], b"ftyp");
+        assert!(init.windows(4).any(|w| w == b"avcC"));
+        assert!(init.windows(4).any(|w| w == b"dOps"));
+
+        let (status, segment) = h.get_bytes(&format!("/hls/{room_id}/seg0.m4s")).await;
+        assert_eq!(status, 200);
+        assert_eq!(&segment[4..8], b"moof");
+        let (status, part) = h.get_bytes(&format!("/hls/{room_id}/seg0.0.m4s")).await;
+        assert_eq!(status, 200);
+        assert!(segment.starts_with(&part));
+
+        let stopped = h.api(&format!("/v1/rooms/{room_id}/hls/stop"), Value::Null).await;
+        assert_eq!(stopped["is_active"], json!(false));
+        let (_, playlist) = h.get_bytes(&playlist_url).await;
+        assert!(playlist.ends_with(b"#EXT-X-ENDLIST\n"));
+
+        // VP8 cannot be segmented without transcoding.
+        let vp8_room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let vp8_id = vp8_room["id"].as_str().unwrap();
+        let vp8 = h.sending_peer().await;
+        h.join(&vp8, "/sfu/publish", vp8_room["tokens"]["publish"].as_str().unwrap(), "vp8")
+            .await;
+        let (status, err) = h
+            .post(&format!("/v1/rooms/{vp8_id}/hls/start"), API_KEY, json!({}))
+            .await;
+        assert_eq!(status, 400, "{err}");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn snapshots_capture_publisher_keyframes() {
+        let h = Harness::start().await;
+        let (status, _) = h
+            .post(
+                "/v1/rooms",
+                API_KEY,
+                json!({ "room_type": "broadcast", "snapshot_interval_secs": 1 }),
+            )
+            .await;
+        assert_eq!(status, 400);
+
+        let mut events = h.state.event_bus.subscribe();
+        let room = h
+            .create_room(json!({
+                "room_type": "broadcast",
+                "codecs": { "video": ["h264"] },
+                "snapshot_interval_secs": 2,
+            }))
+            .await;
+        let room_id = room["id"].as_str().unwrap();
+
+        let publisher = h.h264_peer().await;
+        let publish = room["tokens"]["publish"].as_str().unwrap();
+        h.negotiate(&publisher, "/sfu/publish", publish, Value::Null).await;
+        publisher.connected().await;
+        publisher.start_sending_h264();
+
+        let evt = next_event(&mut events, EventType::SnapshotCaptured).await;
+        let EventPayload::Snapshot(snapshot) = evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(snapshot.room_id, room_id);
+        assert_eq!(snapshot.track, "camera");
+        assert_eq!(snapshot.format, "h264");
+
+        let (status, file) = h.get_bytes_as(&snapshot.url, API_KEY).await;
+        assert_eq!(status, 200);
+        assert!(file.starts_with(&[0, 0, 0, 1, 0x67]), "{file:?}");
+        assert!(file.windows(5).any(|w| w == [0, 0, 0, 1, 0x65]));
+        let (status, _) = h.get_bytes(&snapshot.url).await;
+        assert_eq!(status, 401);
+
+        let (status, list) = h.get(&format!("/v1/rooms/{room_id}/snapshots"), API_KEY).await;
+        assert_eq!(status, 200);
+        assert_eq!(list[0]["snapshot_id"], json!(snapshot.snapshot_id));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn metadata_reaches_room_info_listings_and_events() {
+        let h = Harness::start().await;
+        let mut events = h.state.event_bus.subscribe();
+        let room = h
+            .create_room(json!({ "room_type": "conference", "metadata": { "topic": "math" } }))
+            .await;
+        let room_id = room["id"].as_str().unwrap();
+
+        let token_path = format!("/v1/rooms/{room_id}/token");
+        let token = |user: &'static str| {
+            h.api(
+                &token_path,
+                json!({ "role": "conference", "metadata": { "user_id": user } }),
+            )
+        };
+        let alice_token = token("alice").await["token"].as_str().unwrap().to_string();
+        let bob_token = token("bob").await["token"].as_str().unwrap().to_string();
+
+        let alice = h.sending_peer().await;
+        h.join(&alice, "/sfu/conference", &alice_token, "alice").await;
+        let evt = next_event(&mut events, EventType::ParticipantJoined).await;
+        let EventPayload::Participant(joined) = evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(joined.metadata, Some(json!({ "user_id": "alice" })));
+
+        let bob = h.sending_peer().await;
+        let answer = h.join(&bob, "/sfu/conference", &bob_token, "bob").await;
+        let alice_id = answer["participants"][0].as_str().unwrap();
+        assert_eq!(answer["participant_metadata"][alice_id], json!({ "user_id": "alice" }));
+
+        let (_, info) = h.get(&format!("/v1/rooms/{room_id}"), API_KEY).await;
+        assert_eq!(info["metadata"], json!({ "topic": "math" }));
+        let (_, participants) = h.get(&format!("/v1/rooms/{room_id}/participants"), API_KEY).await;
+        let mut users: Vec<_> = participants
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["metadata"]["user_id"].as_str().unwrap())
+            .collect();
+        users.sort();
+        assert_eq!(users, ["alice", "bob"]);
+
+        alice.close().await;
+        let evt = next_event(&mut events, EventType::ParticipantLeft).await;
+        let EventPayload::Participant(left) = evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(left.peer_id, alice_id);
+        assert_eq!(left.metadata, Some(json!({ "user_id": "alice" })));
+
+        let (status, _) = h
+            .post(
+                &token_path,
+                API_KEY,
+                json!({ "role": "conference", "metadata": "x".repeat(5000) }),
+            )
+            .await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn revoked_and_single_use_tokens() {
+        let h = Harness::start().await;
+        let mut events = h.state.event_bus.subscribe();
+        let room = h
+            .create_room(json!({ "room_type": "call", "single_use_tokens": true }))
+            .await;
+        let caller_token = room["tokens"]["caller"].as_str().unwrap();
+        let callee_token = room["tokens"]["callee"].as_str().unwrap();
+
+        let caller = h.sending_peer().await;
+        h.join(&caller, "/sfu/call", caller_token, "caller").await;
+
+        // The token admitted the caller; it cannot admit anyone else, but
+        // still serves the caller's own follow-up requests.
+        let intruder = h.sending_peer().await;
+        let offer = intruder.pc.create_offer(None).await.unwrap();
+        intruder.pc.set_local_description(offer.clone()).await.unwrap();
+        let body = json!({ "sdp": offer.sdp, "type": "offer" });
+        let (status, err) = h.post("/sfu/call", caller_token, body.clone()).await;
+        assert_eq!(status, 401);
+        assert_eq!(err["error"]["code"], "token_used");
+        let (status, _) = h.get("/sfu/screen", caller_token).await;
+        assert_eq!(status, 200);
+
+        let callee = h.sending_peer().await;
+        h.join(&callee, "/sfu/call", callee_token, "callee").await;
+        next_event(&mut events, EventType::ParticipantJoined).await;
+        next_event(&mut events, EventType::ParticipantJoined).await;
+
+        // Revoking kicks the peer holding the token.
+        h.api("/v1/tokens/revoke", json!({ "token": callee_token })).await;
+        let evt = next_event(&mut events, EventType::ParticipantLeft).await;
+        let callee_id = crate::auth::verify_token(&h.state.jwt_secret, callee_token).unwrap().sub;
+        assert_eq!(evt.room_id(), room["id"].as_str().unwrap());
+        let EventPayload::Participant(left) = evt.data else {
+            panic!("unexpected payload {:?}", evt.data);
+        };
+        assert_eq!(left.peer_id, callee_id);
+
+        let (status, err) = h.post("/sfu/call", callee_token, body).await;
+        assert_eq!(status, 401);
+        assert_eq!(err["error"]["code"], "token_revoked");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn reloaded_limits_apply_to_new_rooms_and_subscribers() {
+        let h = Harness::start().await;
+        let room = h.create_room(json!({ "room_type": "broadcast" })).await;
+        let room_id = room["id"].as_str().unwrap();
+
+        {
+            let mut settings = h.state.settings.write().unwrap();
+            settings.max_rooms = 1;
+            settings.max_subscribers_per_room = 1;
+        }
+
+        let (status, err) = h.post("/v1/rooms", API_KEY, json!({ "room_type": "call" })).await;
+        assert_eq!(status, 503);
+        assert_eq!(err["error"]["code"], "room_limit_reached");
+
+        let publisher = h.sending_peer().await;
+        h.join(&publisher, "/sfu/publish", room["tokens"]["publish"].as_str().unwrap(), "pub")
+            .await;
+        let sub = h.receiving_peer().await;
+        let token = h.token(room_id, "subscribe").await;
+        h.negotiate(&sub, "/sfu/subscribe", &token, Value::Null).await;
+        sub.connected().await;
+
+        let late = h.receiving_peer().await;
+        let offer = late.pc.create_offer(None).await.unwrap();
+        let body = json!({ "sdp": offer.sdp, "type": "offer" });
+        let (status, err) = h.post("/sfu/subscribe", &token, body).await;
+        assert_eq!(status, 409);
+        assert_eq!(err["error"]["code"], "room_full");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn only_the_bootstrap_key_mints_keys() {
+        let h = Harness::start().await;
+        let tenant = h
+            .api("/v1/keys", json!({ "name": "tenant", "quotas": { "max_concurrent_rooms": 1 } }))
+            .await;
+        assert_eq!(tenant["quotas"]["max_concurrent_rooms"], 1);
+        let tenant_key = tenant["key"].as_str().unwrap();
+
+        let (status, err) = h.post("/v1/keys", tenant_key, json!({ "name": "escape" })).await;
+        assert_eq!(status, 403);
+        assert_eq!(err["error"]["code"], "admin_key_required");
+        assert_eq!(h.state.api_keys.read().unwrap().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn admin_endpoints_need_the_bootstrap_key() {
+        let h = Harness::start().await;
+        let tenant = h.api("/v1/keys", json!({ "name": "tenant" })).await;
+        let tenant_key = tenant["key"].as_str().unwrap();
+
+        let (status, err) = h.post("/v1/admin/drain", tenant_key, Value::Null).await;
+        assert_eq!(status, 403);
+        assert_eq!(err["error"]["code"], "admin_key_required");
+        assert!(!h.state.drain.is_draining());
+        let (status, err) = h.post("/v1/admin/config/reload", tenant_key, Value::Null).await;
+        assert_eq!(status, 403);
+        assert_eq!(err["error"]["code"], "admin_key_required");
+
+        let (status, _) = h.post("/v1/admin/drain", API_KEY, Value::Null).await;
+        assert_eq!(status, 202);
+        assert!(h.state.drain.is_draining());
+    }
+}
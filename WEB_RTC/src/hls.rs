@@ -0,0 +1,1330 @@
+// src/hls.rs
+//
+// Low-latency HLS egress for broadcast rooms.
+//
+// WebRTC fan-out costs one PeerConnection per viewer; past a few thousand
+// viewers it is cheaper to hand the publisher's stream to a CDN.  This
+// module turns a broadcast publisher into LL-HLS without transcoding: H264
+// access units and Opus packets are copied into CMAF parts (see `fmp4.rs`).
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   Publisher.video_tx ─▶ SampleBuilder<H264Packet> ─┐
+//   Publisher.audio_tx ─▶ SampleBuilder<OpusPacket> ─┤
+//                                                    ▼
+//                                     Segmenter — parts of ≤ part_target,
+//                                     segments cut on keyframes
+//                                                    │
+//               ┌────────────────────────────────────┴──────────┐
+//               ▼                                               ▼
+//   HlsStream (in-memory window)                 S3 uploader (optional)
+//   GET /hls/:room_id/playlist.m3u8              PUT init, segments and a
+//       …/init.mp4 │ segN.m4s │ segN.P.m4s       plain-HLS playlist
+//
+// ─ API ──────────────────────────────────────────────────────────────────────
+//
+//   POST /v1/rooms/:room_id/hls/start    API key; broadcast rooms only
+//   POST /v1/rooms/:room_id/hls/stop
+//   GET  /v1/rooms/:room_id/hls
+//
+//   Playback URLs need no credentials, like a CDN origin: the room id is
+//   the unguessable part of the URL.
+//
+// ─ Notes ────────────────────────────────────────────────────────────────────
+//
+//   * Only H264 can be segmented; rooms meant for HLS should be created with
+//     `"codecs": { "video": ["h264"] }`.  Non-Opus audio is left out.
+//   * Segments start on IDR frames.  Once a segment reaches its target the
+//     publisher is sent a PLI so the next keyframe comes on time.
+//   * Audio and video are aligned on arrival time: RTP timestamps of the two
+//     tracks share no clock without RTCP sender reports.
+//   * Parts are too short-lived for object storage, so the S3 copy is plain
+//     HLS (whole segments only).  Old segments are left to a bucket
+//     lifecycle rule.  Uploads queue up to `UPLOAD_QUEUE` objects; past
+//     that, new objects are dropped with a warning.
+//   * The stream ends with its publisher; the window stays readable (with
+//     `#EXT-X-ENDLIST`) for one window length.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::{Bytes, BytesMut};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use webrtc::media::io::sample_builder::SampleBuilder;
+use webrtc::rtp::codecs::h264::H264Packet;
+use webrtc::rtp::codecs::opus::OpusPacket;
+
+use crate::error::ApiError;
+use crate::fmp4::{self, AvcConfig, TrackRun, AUDIO_TIMESCALE, VIDEO_TIMESCALE};
+use crate::room::{Publisher, Room, RoomType};
+
+const PLAYLIST: &str = "playlist.m3u8";
+const INIT: &str = "init.mp4";
+const MIME_PLAYLIST: &str = "application/vnd.apple.mpegurl";
+const MIME_MP4: &str = "video/mp4";
+
+/// Segments whose parts are still listed in the playlist (the one being
+/// written plus the two before it).
+const PART_SEGMENTS: u64 = 3;
+
+/// Objects queued for the S3 uploader per stream.  When S3 falls this far
+/// behind, new objects are dropped instead of piling up in memory.
+const UPLOAD_QUEUE: usize = 64;
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// HLS settings; loaded as part of [`crate::config::Config`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsConfig {
+    /// Upper bound for a part's duration (`PART-TARGET`).
+    pub part_target: Duration,
+    /// Minimum segment duration; segments close on the next keyframe after it.
+    pub segment_target: Duration,
+    /// Complete segments kept in the playlist.
+    pub window_segments: usize,
+    /// Optional push to S3-compatible storage.
+    pub s3: Option<S3Config>,
+}
+
+impl Default for HlsConfig {
+    fn default() -> Self {
+        Self {
+            part_target: Duration::from_millis(500),
+            segment_target: Duration::from_secs(2),
+            window_segments: 6,
+            s3: None,
+        }
+    }
+}
+
+impl HlsConfig {
+    /// How long a blocking playlist / part request may wait.
+    fn block_timeout(&self) -> Duration {
+        self.segment_target * 3
+    }
+}
+
+/// S3-compatible bucket the HLS output is copied to (path-style URLs, so
+/// MinIO, R2 and friends work as well as AWS).
+#[derive(Clone, PartialEq)]
+pub struct S3Config {
+    /// e.g. `https://s3.eu-west-1.amazonaws.com` or `http://minio:9000`.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Key prefix; objects land under `{prefix}{room_id}/`.
+    pub prefix: String,
+}
+
+impl std::fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Config {
+    fn object_url(&self, room_id: &str, name: &str) -> String {
+        format!(
+            "{}/{}/{}{room_id}/{name}",
+            self.endpoint, self.bucket, self.prefix
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+// HlsStream — the live playlist window of one room
+// ---------------------------------------------------------------------------
+
+struct Part {
+    data: Bytes,
+    /// Seconds.
+    duration: f64,
+    independent: bool,
+}
+
+struct Segment {
+    msn: u64,
+    parts: Vec<Part>,
+    complete: bool,
+}
+
+impl Segment {
+    fn duration(&self) -> f64 {
+        self.parts.iter().map(|p| p.duration).sum()
+    }
+
+    fn data(&self) -> Bytes {
+        let mut out = BytesMut::with_capacity(self.parts.iter().map(|p| p.data.len()).sum());
+        for part in &self.parts {
+            out.extend_from_slice(&part.data);
+        }
+        out.freeze()
+    }
+}
+
+#[derive(Default)]
+struct Window {
+    init: Option<Bytes>,
+    segments: VecDeque<Segment>,
+    ended: bool,
+}
+
+/// How far the stream has got: `parts` parts of segment `msn` exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Progress {
+    msn: u64,
+    parts: usize,
+    ended: bool,
+}
+
+impl Progress {
+    /// Whether part `part` of segment `msn` (the whole segment when `None`)
+    /// is available.
+    fn reached(&self, msn: u64, part: Option<usize>) -> bool {
+        match part {
+            Some(part) => msn < self.msn || (msn == self.msn && part < self.parts),
+            None => msn < self.msn,
+        }
+    }
+}
+
+pub struct HlsStream {
+    pub room_id: String,
+    pub started_at: Instant,
+    pub started_at_unix: u64,
+    pub cancel: CancellationToken,
+    /// Cancelled by the egress task once the stream has ended.
+    pub done: CancellationToken,
+    config: HlsConfig,
+    window: std::sync::RwLock<Window>,
+    progress: watch::Sender<Progress>,
+}
+
+/// A segment that just closed.
+struct ClosedSegment {
+    msn: u64,
+    data: Bytes,
+}
+
+impl HlsStream {
+    fn new(room_id: String, config: HlsConfig) -> Self {
+        Self {
+            room_id,
+            started_at: Instant::now(),
+            started_at_unix: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            cancel: CancellationToken::new(),
+            done: CancellationToken::new(),
+            config,
+            window: std::sync::RwLock::new(Window::default()),
+            progress: watch::channel(Progress::default()).0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.progress.borrow().ended
+    }
+
+    fn set_init(&self, init: Bytes) {
+        self.window.write().unwrap().init = Some(init);
+    }
+
+    /// Append a part, opening a new segment first when `new_segment` is set.
+    /// Returns the segment that was closed, if any.
+    fn push_part(&self, part: Part, new_segment: bool) -> Option<ClosedSegment> {
+        let mut w = self.window.write().unwrap();
+        let mut closed = None;
+        if new_segment || w.segments.is_empty() {
+            if let Some(last) = w.segments.back_mut() {
+                last.complete = true;
+                closed = Some(ClosedSegment {
+                    msn: last.msn,
+                    data: last.data(),
+                });
+            }
+            let msn = w.segments.back().map_or(0, |s| s.msn + 1);
+            w.segments.push_back(Segment {
+                msn,
+                parts: Vec::new(),
+                complete: false,
+            });
+            while w.segments.len() > self.config.window_segments + 1 {
+                w.segments.pop_front();
+            }
+        }
+        let current = w.segments.back_mut().unwrap();
+        current.parts.push(part);
+        let progress = Progress {
+            msn: current.msn,
+            parts: current.parts.len(),
+            ended: false,
+        };
+        drop(w);
+        self.progress.send_replace(progress);
+        closed
+    }
+
+    /// Close the last segment and stop advertising new parts.
+    fn end(&self) -> Option<ClosedSegment> {
+        let mut w = self.window.write().unwrap();
+        w.ended = true;
+        let closed = w.segments.back_mut().and_then(|last| {
+            if last.complete {
+                return None;
+            }
+            last.complete = true;
+            Some(ClosedSegment {
+                msn: last.msn,
+                data: last.data(),
+            })
+        });
+        drop(w);
+        // Everything written so far is now a complete segment.
+        self.progress.send_modify(|p| {
+            p.ended = true;
+            if closed.is_some() {
+                p.msn += 1;
+                p.parts = 0;
+            }
+        });
+        closed
+    }
+
+    /// Render the media playlist.  `low_latency` adds parts, blocking-reload
+    /// support and the preload hint; without it only complete segments are
+    /// listed (the copy pushed to S3).
+    fn playlist(&self, low_latency: bool) -> String {
+        let w = self.window.read().unwrap();
+        let part_target = self.config.part_target.as_secs_f64();
+        let target_duration = w
+            .segments
+            .iter()
+            .filter(|s| s.complete)
+            .map(|s| s.duration())
+            .fold(self.config.segment_target.as_secs_f64(), f64::max)
+            .ceil() as u64;
+        let first_msn = w.segments.front().map_or(0, |s| s.msn);
+        let last_msn = w.segments.back().map_or(0, |s| s.msn);
+
+        let mut out = String::new();
+        out.push_str("#EXTM3U\n#EXT-X-VERSION:9\n");
+        let _ = writeln!(out, "#EXT-X-TARGETDURATION:{target_duration}");
+        if low_latency {
+            let _ = writeln!(
+                out,
+                "#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.3}",
+                part_target * 3.0
+            );
+            let _ = writeln!(out, "#EXT-X-PART-INF:PART-TARGET={part_target:.3}");
+        }
+        let _ = writeln!(out, "#EXT-X-MEDIA-SEQUENCE:{first_msn}");
+        if w.init.is_some() {
+            let _ = writeln!(out, "#EXT-X-MAP:URI=\"{INIT}\"");
+        }
+
+        for segment in &w.segments {
+            if low_latency && segment.msn + PART_SEGMENTS > last_msn {
+                for (i, part) in segment.parts.iter().enumerate() {
+                    let _ = write!(
+                        out,
+                        "#EXT-X-PART:DURATION={:.3},URI=\"seg{}.{i}.m4s\"",
+                        part.duration, segment.msn
+                    );
+                    out.push_str(if part.independent { ",INDEPENDENT=YES\n" } else { "\n" });
+                }
+            }
+            if segment.complete {
+                let _ = writeln!(out, "#EXTINF:{:.3},\nseg{}.m4s", segment.duration(), segment.msn);
+            }
+        }
+
+        if w.ended {
+            out.push_str("#EXT-X-ENDLIST\n");
+        } else if low_latency {
+            if let Some(current) = w.segments.back().filter(|s| !s.complete) {
+                let _ = writeln!(
+                    out,
+                    "#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"seg{}.{}.m4s\"",
+                    current.msn,
+                    current.parts.len()
+                );
+            }
+        }
+        out
+    }
+
+    fn init(&self) -> Option<Bytes> {
+        self.window.read().unwrap().init.clone()
+    }
+
+    fn segment(&self, msn: u64) -> Option<Bytes> {
+        let w = self.window.read().unwrap();
+        w.segments
+            .iter()
+            .find(|s| s.msn == msn && s.complete)
+            .map(Segment::data)
+    }
+
+    fn part(&self, msn: u64, part: usize) -> Option<Bytes> {
+        let w = self.window.read().unwrap();
+        w.segments
+            .iter()
+            .find(|s| s.msn == msn)
+            .and_then(|s| s.parts.get(part))
+            .map(|p| p.data.clone())
+    }
+
+    /// Wait until `(msn, part)` exists or the stream ends.  Returns false on
+    /// timeout.
+    async fn wait_for(&self, msn: u64, part: Option<usize>) -> bool {
+        let mut rx = self.progress.subscribe();
+        let reached = rx.wait_for(|p| p.ended || p.reached(msn, part));
+        let result = tokio::time::timeout(self.config.block_timeout(), reached).await;
+        matches!(result, Ok(Ok(_)))
+    }
+
+    fn progress(&self) -> Progress {
+        *self.progress.borrow()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Segmenter — samples in, CMAF parts out
+// ---------------------------------------------------------------------------
+
+enum Output {
+    Init(Bytes),
+    Part { part: Part, new_segment: bool },
+    /// The segment reached its target: ask the publisher for a keyframe.
+    NeedKeyframe,
+}
+
+/// Turns an RTP timestamp sequence into a monotonic decode-time clock.
+struct TrackClock {
+    timescale: u32,
+    last_rtp: Option<u32>,
+    dts: u64,
+}
+
+impl TrackClock {
+    fn new(timescale: u32) -> Self {
+        Self {
+            timescale,
+            last_rtp: None,
+            dts: 0,
+        }
+    }
+
+    /// Decode time of a sample with RTP timestamp `rtp`.  The first sample
+    /// is placed at `offset` (time since the stream started).
+    fn dts(&mut self, rtp: u32, offset: Duration) -> u64 {
+        match self.last_rtp {
+            None => self.dts = (offset.as_secs_f64() * self.timescale as f64) as u64,
+            // Reordering is resolved by the SampleBuilder, so a step of
+            // more than 2^31 ticks is a backwards jump (publisher restart):
+            // hold the clock rather than leap hours ahead.
+            Some(last) => {
+                let delta = rtp.wrapping_sub(last);
+                if delta <= i32::MAX as u32 {
+                    self.dts += delta as u64;
+                }
+            }
+        }
+        self.last_rtp = Some(rtp);
+        self.dts
+    }
+}
+
+/// A sample whose duration is known once the next one arrives.
+struct Pending {
+    data: Bytes,
+    dts: u64,
+    keyframe: bool,
+}
+
+struct Segmenter {
+    part_target: u64,
+    segment_target: u64,
+    with_audio: bool,
+    started: Option<Instant>,
+    video_clock: TrackClock,
+    audio_clock: TrackClock,
+    video_pending: Option<Pending>,
+    audio_pending: Option<Pending>,
+    part_video: Vec<fmp4::Sample>,
+    part_video_dts: u64,
+    part_audio: Vec<fmp4::Sample>,
+    part_audio_dts: u64,
+    segment_start: u64,
+    new_segment: bool,
+    keyframe_requested: bool,
+    sequence: u32,
+}
+
+impl Segmenter {
+    fn new(config: &HlsConfig, with_audio: bool) -> Self {
+        let ticks = |d: Duration| (d.as_secs_f64() * VIDEO_TIMESCALE as f64) as u64;
+        Self {
+            part_target: ticks(config.part_target),
+            segment_target: ticks(config.segment_target),
+            with_audio,
+            started: None,
+            video_clock: TrackClock::new(VIDEO_TIMESCALE),
+            audio_clock: TrackClock::new(AUDIO_TIMESCALE),
+            video_pending: None,
+            audio_pending: None,
+            part_video: Vec::new(),
+            part_video_dts: 0,
+            part_audio: Vec::new(),
+            part_audio_dts: 0,
+            segment_start: 0,
+            new_segment: true,
+            keyframe_requested: false,
+            sequence: 0,
+        }
+    }
+
+    /// Feed one H264 access unit (AVC, length-prefixed NAL units).
+    fn push_video(&mut self, data: Bytes, rtp: u32, now: Instant) -> Vec<Output> {
+        let mut out = Vec::new();
+        let keyframe = fmp4::avc_nalus(&data).any(|n| n.first().map(|b| b & 0x1F) == Some(5));
+
+        let started = match self.started {
+            Some(started) => started,
+            // Wait for a keyframe carrying SPS + PPS: it opens the stream.
+            None => match keyframe.then(|| avc_config(&data)).flatten() {
+                Some(cfg) => {
+                    let channels = self.with_audio.then_some(2);
+                    out.push(Output::Init(fmp4::init_segment(&cfg, channels)));
+                    self.started = Some(now);
+                    now
+                }
+                None => return out,
+            },
+        };
+
+        let dts = self.video_clock.dts(rtp, now - started);
+        if let Some(prev) = self.video_pending.replace(Pending { data, dts, keyframe }) {
+            let duration = dts.saturating_sub(prev.dts).max(1);
+            self.add_video(prev, duration, &mut out);
+        }
+        out
+    }
+
+    /// Feed one Opus packet.
+    fn push_audio(&mut self, data: Bytes, rtp: u32, now: Instant) {
+        let Some(started) = self.started.filter(|_| self.with_audio) else {
+            return;
+        };
+        let dts = self.audio_clock.dts(rtp, now - started);
+        if let Some(prev) = self.audio_pending.replace(Pending {
+            data,
+            dts,
+            keyframe: true,
+        }) {
+            if self.part_audio.is_empty() {
+                self.part_audio_dts = prev.dts;
+            }
+            self.part_audio.push(fmp4::Sample {
+                data: prev.data,
+                duration: dts.saturating_sub(prev.dts).max(1) as u32,
+                keyframe: true,
+            });
+        }
+    }
+
+    fn add_video(&mut self, frame: Pending, duration: u64, out: &mut Vec<Output>) {
+        let part_duration: u64 = self.part_video.iter().map(|s| s.duration as u64).sum();
+        let segment_duration = frame.dts.saturating_sub(self.segment_start);
+
+        if frame.keyframe && segment_duration >= self.segment_target {
+            self.flush_part(out);
+            self.new_segment = true;
+        } else if !self.part_video.is_empty() && part_duration + duration > self.part_target {
+            self.flush_part(out);
+        }
+        if segment_duration >= self.segment_target && !self.keyframe_requested && !frame.keyframe {
+            self.keyframe_requested = true;
+            out.push(Output::NeedKeyframe);
+        }
+
+        if self.part_video.is_empty() {
+            self.part_video_dts = frame.dts;
+            if self.new_segment {
+                self.segment_start = frame.dts;
+                self.keyframe_requested = false;
+            }
+        }
+        self.part_video.push(fmp4::Sample {
+            data: frame.data,
+            duration: duration as u32,
+            keyframe: frame.keyframe,
+        });
+    }
+
+    fn flush_part(&mut self, out: &mut Vec<Output>) {
+        if self.part_video.is_empty() {
+            return;
+        }
+        self.sequence += 1;
+        let data = fmp4::chunk(
+            self.sequence,
+            &[
+                TrackRun {
+                    track_id: fmp4::VIDEO_TRACK_ID,
+                    base_decode_time: self.part_video_dts,
+                    samples: &self.part_video,
+                },
+                TrackRun {
+                    track_id: fmp4::AUDIO_TRACK_ID,
+                    base_decode_time: self.part_audio_dts,
+                    samples: &self.part_audio,
+                },
+            ],
+        );
+        let ticks: u64 = self.part_video.iter().map(|s| s.duration as u64).sum();
+        out.push(Output::Part {
+            part: Part {
+                data,
+                duration: ticks as f64 / VIDEO_TIMESCALE as f64,
+                independent: self.part_video[0].keyframe,
+            },
+            new_segment: std::mem::take(&mut self.new_segment),
+        });
+        self.part_video.clear();
+        self.part_audio.clear();
+    }
+
+    /// Flush everything still buffered.
+    fn finish(&mut self) -> Vec<Output> {
+        let mut out = Vec::new();
+        if let Some(last) = self.video_pending.take() {
+            let duration = self
+                .part_video
+                .last()
+                .map_or(VIDEO_TIMESCALE as u64 / 30, |s| s.duration as u64);
+            self.add_video(last, duration, &mut out);
+        }
+        self.flush_part(&mut out);
+        out
+    }
+}
+
+/// SPS + PPS from a keyframe access unit.
+fn avc_config(au: &[u8]) -> Option<AvcConfig> {
+    let find = |kind: u8| {
+        fmp4::avc_nalus(au)
+            .find(|n| n.first().map(|b| b & 0x1F) == Some(kind))
+            .map(Bytes::copy_from_slice)
+    };
+    AvcConfig::new(find(7)?, find(8)?)
+}
+
+// ---------------------------------------------------------------------------
+// Egress task
+// ---------------------------------------------------------------------------
+
+/// One object for the S3 uploader.
+struct Upload {
+    name: String,
+    body: Bytes,
+    content_type: &'static str,
+}
+
+async fn hls_egress_task(
+    stream: Arc<HlsStream>,
+    room: Weak<Room>,
+    publisher: Arc<Publisher>,
+    with_audio: bool,
+    uploads: Option<mpsc::Sender<Upload>>,
+) {
+    let mut video_rx = publisher.video_tx.subscribe();
+    let mut audio_rx = publisher.audio_tx.subscribe();
+    // Only a weak handle from here on: the stream must not keep the
+    // publisher (and its channels) alive after it leaves.
+    let publisher = Arc::downgrade(&publisher);
+
+    let mut depacketizer = H264Packet::default();
+    depacketizer.is_avc = true;
+    let mut video = SampleBuilder::new(64, depacketizer, VIDEO_TIMESCALE);
+    let mut audio = SampleBuilder::new(16, OpusPacket, AUDIO_TIMESCALE);
+    let mut segmenter = Segmenter::new(&stream.config, with_audio);
+    let mut liveness = tokio::time::interval(Duration::from_secs(1));
+
+    let upload = |name: String, body: Bytes, content_type| {
+        if let Some(tx) = &uploads {
+            let upload = Upload {
+                name,
+                body,
+                content_type,
+            };
+            if let Err(mpsc::error::TrySendError::Full(upload)) = tx.try_send(upload) {
+                warn!(
+                    "HLS upload queue for room '{}' is full; dropping '{}'",
+                    stream.room_id, upload.name
+                );
+            }
+        }
+    };
+    let handle = |outputs: Vec<Output>| {
+        for output in outputs {
+            match output {
+                Output::Init(init) => {
+                    stream.set_init(init.clone());
+                    upload(INIT.to_string(), init, MIME_MP4);
+                    info!("HLS stream for room '{}' is live", stream.room_id);
+                }
+                Output::Part { part, new_segment } => {
+                    if let Some(closed) = stream.push_part(part, new_segment) {
+                        upload(format!("seg{}.m4s", closed.msn), closed.data, MIME_MP4);
+                        upload(PLAYLIST.to_string(), stream.playlist(false).into(), MIME_PLAYLIST);
+                    }
+                }
+                Output::NeedKeyframe => {
+                    if let Some(publisher) = publisher.upgrade() {
+                        tokio::spawn(async move {
+                            crate::sfu::request_keyframe(&publisher).await;
+                        });
+                    }
+                }
+            }
+        }
+    };
+
+    // Start the first segment as soon as possible.
+    if let Some(publisher) = publisher.upgrade() {
+        crate::sfu::request_keyframe(&publisher).await;
+    }
+
+    loop {
+        tokio::select! {
+            _ = stream.cancel.cancelled() => break,
+            _ = liveness.tick() => {
+                // The publisher left the room (its channels may outlive it).
+                let present = publisher.upgrade().zip(room.upgrade()).is_some_and(|(p, room)| {
+                    room.publishers
+                        .read()
+                        .unwrap()
+                        .get(&p.peer_id)
+                        .is_some_and(|current| Arc::ptr_eq(current, &p))
+                });
+                if !present {
+                    break;
+                }
+            }
+            result = video_rx.recv() => match result {
+                Ok(pkt) => {
+                    video.push(pkt);
+                    while let Some((sample, rtp)) = video.pop_with_timestamp() {
+                        handle(segmenter.push_video(sample.data, rtp, Instant::now()));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("HLS egress for room '{}' lagged, skipped {n} video packets", stream.room_id);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            result = audio_rx.recv(), if with_audio => match result {
+                Ok(pkt) => {
+                    audio.push(pkt);
+                    while let Some((sample, rtp)) = audio.pop_with_timestamp() {
+                        segmenter.push_audio(sample.data, rtp, Instant::now());
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("HLS egress for room '{}' lagged, skipped {n} audio packets", stream.room_id);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+
+    handle(segmenter.finish());
+    if let Some(closed) = stream.end() {
+        upload(format!("seg{}.m4s", closed.msn), closed.data, MIME_MP4);
+    }
+    upload(PLAYLIST.to_string(), stream.playlist(false).into(), MIME_PLAYLIST);
+    info!("HLS stream for room '{}' ended", stream.room_id);
+}
+
+// ---------------------------------------------------------------------------
+// S3 upload (AWS Signature Version 4)
+// ---------------------------------------------------------------------------
+
+/// PUT every object in order; a failed upload is logged and skipped.
+async fn s3_uploader(
+    http: reqwest::Client,
+    config: S3Config,
+    room_id: String,
+    mut rx: mpsc::Receiver<Upload>,
+) {
+    while let Some(upload) = rx.recv().await {
+        let url = config.object_url(&room_id, &upload.name);
+        if let Err(e) = s3_put(&http, &config, &url, &upload).await {
+            warn!("HLS upload of '{url}' failed: {e}");
+        }
+    }
+}
+
+async fn s3_put(
+    http: &reqwest::Client,
+    config: &S3Config,
+    url: &str,
+    upload: &Upload,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parsed = reqwest::Url::parse(url)?;
+    let host = match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err("S3 endpoint has no host".into()),
+    };
+    let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let payload_hash = hex::encode(Sha256::digest(&upload.body));
+    let authorization = sigv4_authorization(
+        config,
+        "PUT",
+        parsed.path(),
+        &host,
+        &payload_hash,
+        &amz_date,
+    );
+
+    // Playlists change every segment; segments never do.
+    let cache_control = if upload.content_type == MIME_PLAYLIST {
+        "max-age=1"
+    } else {
+        "max-age=31536000, immutable"
+    };
+    let resp = http
+        .put(url)
+        .header("x-amz-date", &amz_date)
+        .header("x-amz-content-sha256", &payload_hash)
+        .header(header::AUTHORIZATION, authorization)
+        .header(header::CONTENT_TYPE, upload.content_type)
+        .header(header::CACHE_CONTROL, cache_control)
+        .body(upload.body.clone())
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(format!("status {}", resp.status()).into());
+    }
+    Ok(())
+}
+
+/// `Authorization` header for a request signing `host`,
+/// `x-amz-content-sha256` and `x-amz-date`.
+fn sigv4_authorization(
+    config: &S3Config,
+    method: &str,
+    path: &str,
+    host: &str,
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{method}\n{path}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}"
+    );
+    let scope = format!("{date}/{}/s3/aws4_request", config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = sigv4_signing_key(&config.secret_key, date, &config.region, "s3");
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        config.access_key
+    )
+}
+
+fn sigv4_signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{secret}").as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// ---------------------------------------------------------------------------
+// HlsManager — owns the streams of all rooms
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HlsInfo {
+    pub room_id: String,
+    pub playlist_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s3_playlist_url: Option<String>,
+    pub is_active: bool,
+    /// Media sequence number of the segment being written.
+    pub media_sequence: u64,
+    pub duration_secs: u64,
+    pub started_at_unix: u64,
+}
+
+pub struct HlsManager {
+    pub config: HlsConfig,
+    streams: std::sync::RwLock<HashMap<String, Arc<HlsStream>>>,
+    http: reqwest::Client,
+}
+
+impl HlsManager {
+    pub fn new(config: HlsConfig) -> Self {
+        Self {
+            config,
+            streams: std::sync::RwLock::new(HashMap::new()),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn stream(&self, room_id: &str) -> Option<Arc<HlsStream>> {
+        self.streams.read().unwrap().get(room_id).cloned()
+    }
+
+    /// Start segmenting the broadcast publisher of `room`.
+    pub async fn start(self: &Arc<Self>, room: &Arc<Room>) -> Result<HlsInfo, ApiError> {
+        let room_id = &room.room_id;
+        if room.room_type != RoomType::Broadcast {
+            return Err(ApiError::room_type_mismatch("broadcast", room.room_type.as_str()));
+        }
+        if self.stream(room_id).is_some_and(|s| s.is_active()) {
+            return Err(ApiError::conflict(format!(
+                "Room '{room_id}' is already streaming HLS."
+            )));
+        }
+
+        let publisher = room
+            .get_publishers()
+            .into_iter()
+            .find(|p| !p.is_screen())
+            .ok_or_else(|| ApiError::no_publisher(room_id))?;
+        crate::sfu::wait_for_publisher_ready(&publisher, 5).await;
+
+        let video_mime = publisher
+            .video_codec
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.mime_type.clone())
+            .ok_or_else(|| ApiError::conflict("The publisher is not sending video yet."))?;
+        if !video_mime.eq_ignore_ascii_case(webrtc::api::media_engine::MIME_TYPE_H264) {
+            return Err(ApiError::bad_request(format!(
+                "HLS egress needs H264 video, but the publisher sends '{video_mime}'."
+            )));
+        }
+        let with_audio = publisher.audio_codec.read().unwrap().as_ref().is_some_and(|c| {
+            c.mime_type
+                .eq_ignore_ascii_case(webrtc::api::media_engine::MIME_TYPE_OPUS)
+        });
+        if !with_audio {
+            info!("HLS stream for room '{room_id}' carries no audio (publisher does not send Opus)");
+        }
+
+        let stream = Arc::new(HlsStream::new(room_id.clone(), self.config.clone()));
+        self.streams
+            .write()
+            .unwrap()
+            .insert(room_id.clone(), stream.clone());
+
+        let uploads = self.config.s3.clone().map(|s3| {
+            let (tx, rx) = mpsc::channel(UPLOAD_QUEUE);
+            tokio::spawn(s3_uploader(self.http.clone(), s3, room_id.clone(), rx));
+            tx
+        });
+
+        let manager = Arc::clone(self);
+        let task_stream = stream.clone();
+        let room_weak = Arc::downgrade(room);
+        tokio::spawn(async move {
+            hls_egress_task(task_stream.clone(), room_weak, publisher, with_audio, uploads).await;
+            task_stream.done.cancel();
+
+            // Keep the ended window readable for one window length.
+            let linger = manager.config.segment_target * manager.config.window_segments as u32;
+            tokio::time::sleep(linger).await;
+            let mut streams = manager.streams.write().unwrap();
+            if streams
+                .get(&task_stream.room_id)
+                .is_some_and(|s| Arc::ptr_eq(s, &task_stream))
+            {
+                streams.remove(&task_stream.room_id);
+            }
+        });
+
+        info!("HLS egress started for room '{room_id}'");
+        Ok(self.info(&stream))
+    }
+
+    /// Stop the room's stream; returns its final state.
+    pub async fn stop(&self, room_id: &str) -> Result<HlsInfo, ApiError> {
+        let stream = self
+            .stream(room_id)
+            .filter(|s| s.is_active())
+            .ok_or_else(|| ApiError::not_found(format!("Room '{room_id}' is not streaming HLS.")))?;
+        stream.cancel.cancel();
+        stream.done.cancelled().await;
+        info!("HLS egress stopped for room '{room_id}'");
+        Ok(self.info(&stream))
+    }
+
+    /// Stop every stream and wait for their final segments.
+    pub async fn finalize_all(&self) -> usize {
+        let pending: Vec<CancellationToken> = {
+            let streams = self.streams.read().unwrap();
+            streams
+                .values()
+                .filter(|s| s.is_active())
+                .map(|s| {
+                    s.cancel.cancel();
+                    s.done.clone()
+                })
+                .collect()
+        };
+        for done in &pending {
+            done.cancelled().await;
+        }
+        pending.len()
+    }
+
+    pub fn info(&self, stream: &HlsStream) -> HlsInfo {
+        HlsInfo {
+            room_id: stream.room_id.clone(),
+            playlist_url: format!("/hls/{}/{PLAYLIST}", stream.room_id),
+            s3_playlist_url: self
+                .config
+                .s3
+                .as_ref()
+                .map(|s3| s3.object_url(&stream.room_id, PLAYLIST)),
+            is_active: stream.is_active(),
+            media_sequence: stream.progress().msn,
+            duration_secs: stream.started_at.elapsed().as_secs(),
+            started_at_unix: stream.started_at_unix,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// API Handlers
+// ---------------------------------------------------------------------------
+
+fn hls_manager(state: &crate::AppState) -> Result<&Arc<HlsManager>, ApiError> {
+    state
+        .hls
+        .as_ref()
+        .ok_or_else(|| ApiError::internal("HLS subsystem not initialized"))
+}
+
+/// POST /v1/rooms/:room_id/hls/start
+pub async fn start_hls(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<HlsInfo>, ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+
+    let room = {
+        let rooms = state.rooms.read().unwrap();
+        rooms.get(&room_id).cloned()
+    };
+    let room = room.ok_or_else(|| ApiError::room_not_found(&room_id))?;
+
+    let info = hls_manager(&state)?.start(&room).await?;
+    Ok(Json(info))
+}
+
+/// POST /v1/rooms/:room_id/hls/stop
+pub async fn stop_hls(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<HlsInfo>, ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+    let info = hls_manager(&state)?.stop(&room_id).await?;
+    Ok(Json(info))
+}
+
+/// GET /v1/rooms/:room_id/hls
+pub async fn get_hls(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<HlsInfo>, ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+    let manager = hls_manager(&state)?;
+    let stream = manager
+        .stream(&room_id)
+        .ok_or_else(|| ApiError::not_found(format!("Room '{room_id}' is not streaming HLS.")))?;
+    Ok(Json(manager.info(&stream)))
+}
+
+/// GET /hls/:room_id/:file — playlist (with LL-HLS blocking reload),
+/// init segment, segments and parts.
+pub async fn serve_hls(
+    State(state): State<Arc<crate::AppState>>,
+    Path((room_id, file)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let stream = hls_manager(&state)?
+        .stream(&room_id)
+        .ok_or_else(|| ApiError::not_found(format!("Room '{room_id}' is not streaming HLS.")))?;
+    let missing = || ApiError::not_found(format!("'{file}' is not available."));
+
+    match file.as_str() {
+        PLAYLIST => {
+            let msn = query.get("_HLS_msn").and_then(|v| v.parse::<u64>().ok());
+            let part = query.get("_HLS_part").and_then(|v| v.parse::<usize>().ok());
+            if let Some(msn) = msn {
+                // Requests more than two segments ahead are rejected by spec.
+                if msn > stream.progress().msn + 2 {
+                    return Err(ApiError::bad_request("_HLS_msn is too far in the future."));
+                }
+                // On timeout the current playlist is as good as any.
+                stream.wait_for(msn, part).await;
+            }
+            Ok(media_response(MIME_PLAYLIST, "no-cache", stream.playlist(true).into()))
+        }
+        INIT => stream
+            .init()
+            .map(|init| media_response(MIME_MP4, "max-age=3600", init))
+            .ok_or_else(missing),
+        _ => {
+            let name = file
+                .strip_prefix("seg")
+                .and_then(|f| f.strip_suffix(".m4s"))
+                .ok_or_else(missing)?;
+            let data = match name.split_once('.') {
+                Some((msn, part)) => {
+                    let msn = msn.parse::<u64>().map_err(|_| missing())?;
+                    let part = part.parse::<usize>().map_err(|_| missing())?;
+                    // The preload hint points at the part being written.
+                    if stream.part(msn, part).is_none() {
+                        stream.wait_for(msn, Some(part)).await;
+                    }
+                    stream.part(msn, part)
+                }
+                None => {
+                    let msn = name.parse::<u64>().map_err(|_| missing())?;
+                    stream.segment(msn)
+                }
+            };
+            data.map(|d| media_response(MIME_MP4, "max-age=60", d))
+                .ok_or_else(missing)
+        }
+    }
+}
+
+fn media_response(content_type: &'static str, cache_control: &'static str, body: Bytes) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fmp4::tests::baseline_sps;
+
+    fn avc(nalus: &[&[u8]]) -> Bytes {
+        let mut out = BytesMut::new();
+        for n in nalus {
+            out.extend_from_slice(&(n.len() as u32).to_be_bytes());
+            out.extend_from_slice(n);
+        }
+        out.freeze()
+    }
+
+    fn test_config() -> HlsConfig {
+        HlsConfig {
+            part_target: Duration::from_millis(200),
+            segment_target: Duration::from_secs(1),
+            window_segments: 3,
+            s3: None,
+        }
+    }
+
+    /// Feed `secs` of 30 fps video with a keyframe every `gop` frames and
+    /// 20 ms Opus packets; returns everything the segmenter produced.
+    fn run(segmenter: &mut Segmenter, secs: u32, gop: u32) -> Vec<Output> {
+        let sps = baseline_sps(640, 360);
+        let start = Instant::now();
+        let mut out = Vec::new();
+        for frame in 0..secs * 30 {
+            let now = start + Duration::from_millis(frame as u64 * 1000 / 30);
+            let data = if frame % gop == 0 {
+                avc(&[&sps, &[0x68, 0xCE], &[0x65, 0x88]])
+            } else {
+                avc(&[&[0x41, 0x9A]])
+            };
+            out.extend(segmenter.push_video(data, frame * 3000, now));
+            for i in 0..2 {
+                let pkt = frame * 2 + i;
+                segmenter.push_audio(Bytes::from_static(b"opus"), pkt * 960, now);
+            }
+        }
+        out.extend(segmenter.finish());
+        out
+    }
+
+    fn parts(outputs: &[Output]) -> Vec<(f64, bool, bool)> {
+        outputs
+            .iter()
+            .filter_map(|o| match o {
+                Output::Part { part, new_segment } => {
+                    Some((part.duration, part.independent, *new_segment))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segmenter_waits_for_a_keyframe() {
+        let mut segmenter = Segmenter::new(&test_config(), true);
+        let out = segmenter.push_video(avc(&[&[0x41, 0x9A]]), 0, Instant::now());
+        assert!(out.is_empty());
+        assert!(segmenter.started.is_none());
+    }
+
+    #[test]
+    fn segments_start_on_keyframes_and_parts_respect_target() {
+        let mut segmenter = Segmenter::new(&test_config(), true);
+        let outputs = run(&mut segmenter, 4, 30);
+        assert!(matches!(outputs[0], Output::Init(_)));
+
+        let parts = parts(&outputs);
+        assert!(parts.iter().all(|(duration, _, _)| *duration <= 0.2 + 1e-9));
+        // Every segment opens with an independent part, one per keyframe.
+        let segments: Vec<_> = parts.iter().filter(|(_, _, new)| *new).collect();
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|(_, independent, _)| *independent));
+        let total: f64 = parts.iter().map(|(d, _, _)| d).sum();
+        assert!((total - 4.0).abs() < 0.05, "total {total}");
+    }
+
+    #[test]
+    fn long_gop_asks_for_a_keyframe() {
+        let mut segmenter = Segmenter::new(&test_config(), false);
+        let outputs = run(&mut segmenter, 3, 90);
+        let requests = outputs.iter().filter(|o| matches!(o, Output::NeedKeyframe)).count();
+        assert_eq!(requests, 1);
+        assert_eq!(parts(&outputs).iter().filter(|(_, _, new)| *new).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn playlist_window_and_blocking_reload() {
+        let stream = Arc::new(HlsStream::new("room".into(), test_config()));
+        stream.set_init(Bytes::from_static(b"init"));
+        let part = |independent| Part {
+            data: Bytes::from_static(b"part"),
+            duration: 0.2,
+            independent,
+        };
+
+        for seg in 0..5u64 {
+            for i in 0..5 {
+                let closed = stream.push_part(part(i == 0), i == 0);
+                assert_eq!(closed.map(|c| c.msn), (i == 0 && seg > 0).then(|| seg - 1));
+            }
+        }
+
+        let playlist = stream.playlist(true);
+        // Window of 3 complete segments plus the one being written.
+        assert!(playlist.contains("#EXT-X-MEDIA-SEQUENCE:1\n"));
+        assert!(playlist.contains("#EXT-X-MAP:URI=\"init.mp4\""));
+        assert!(playlist.contains("#EXTINF:1.000,\nseg3.m4s"));
+        assert!(!playlist.contains("seg4.m4s\n"));
+        assert!(!playlist.contains("seg1.0.m4s"));
+        assert!(playlist.contains("#EXT-X-PART:DURATION=0.200,URI=\"seg2.0.m4s\",INDEPENDENT=YES"));
+        assert!(playlist.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"seg4.5.m4s\""));
+        assert!(!stream.playlist(false).contains("EXT-X-PART"));
+
+        assert_eq!(stream.segment(3).unwrap().len(), 20);
+        assert!(stream.segment(4).is_none());
+
+        // A blocking reload for the next part returns once it is written.
+        let waiter = {
+            let stream = stream.clone();
+            tokio::spawn(async move { stream.wait_for(4, Some(5)).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        stream.push_part(part(false), false);
+        assert!(waiter.await.unwrap());
+
+        let closed = stream.end().unwrap();
+        assert_eq!(closed.msn, 4);
+        assert!(stream.playlist(true).ends_with("#EXT-X-ENDLIST\n"));
+        assert!(!stream.is_active());
+    }
+
+    #[test]
+    fn track_clock_holds_on_backwards_step() {
+        let mut clock = TrackClock::new(VIDEO_TIMESCALE);
+        assert_eq!(clock.dts(1_000, Duration::ZERO), 0);
+        assert_eq!(clock.dts(4_000, Duration::ZERO), 3_000);
+        // Publisher restart: the RTP timestamp jumps back.
+        assert_eq!(clock.dts(500, Duration::ZERO), 3_000);
+        assert_eq!(clock.dts(3_500, Duration::ZERO), 6_000);
+        // Forward wraparound of the 32-bit timestamp is still a small step.
+        let mut clock = TrackClock::new(VIDEO_TIMESCALE);
+        clock.dts(u32::MAX - 999, Duration::ZERO);
+        assert_eq!(clock.dts(2_000, Duration::ZERO), 3_000);
+    }
+
+    #[test]
+    fn sigv4_signing_key_matches_aws_example() {
+        // From the AWS Signature Version 4 documentation.
+        let key = sigv4_signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
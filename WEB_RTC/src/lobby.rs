@@ -0,0 +1,373 @@
+// src/lobby.rs
+//
+// Waiting room / lobby for conference rooms.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   POST /v1/rooms { room_type: "conference", lobby: true }
+//        → the response includes a `host` token next to the regular ones.
+//
+//   Peer joins with a `conference` token
+//        → PeerConnection is fully negotiated, but `Publisher::admitted` is
+//          false: its RTP is not forwarded into the room and the fan-out
+//          tasks towards it drop every packet.
+//        → `participant.waiting` is emitted and pushed to every host.
+//
+//   Host admits (REST or DataChannel)
+//        → `admitted` flips to true, forwarding starts in both directions,
+//          keyframes are requested and `participant.admitted` is emitted.
+//
+//   Host rejects
+//        → the waiting peer's PeerConnection is closed and
+//          `participant.rejected` is emitted.
+//
+// ─ Host DataChannel protocol ────────────────────────────────────────────────
+//
+//   The host opens a DataChannel labelled `lobby` on its conference
+//   PeerConnection.  The server pushes notices:
+//
+//     {"type":"participant.waiting","peer_id":"..."}
+//     {"type":"participant.admitted","peer_id":"..."}
+//
+//   and accepts commands:
+//
+//     {"action":"admit","peer_id":"..."}
+//     {"action":"reject","peer_id":"..."}
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::error::ApiError;
+use crate::events::LiveRelayEvent;
+use crate::room::Room;
+
+/// Label of the DataChannel a host opens to drive the lobby.
+pub const LOBBY_CHANNEL_LABEL: &str = "lobby";
+
+// ─── DataChannel messages ───────────────────────────────────────────────────
+
+/// Command sent by a host over the `lobby` DataChannel.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum LobbyCommand {
+    Admit { peer_id: String },
+    Reject { peer_id: String },
+}
+
+/// Notice pushed to hosts over the `lobby` DataChannel.
+#[derive(Debug, Serialize)]
+pub struct LobbyNotice<'a> {
+    #[serde(rename = "type")]
+    pub notice_type: &'a str,
+    pub peer_id: &'a str,
+}
+
+/// Send a notice to every host DataChannel open on the room.
+pub async fn notify_hosts(room: &Room, notice_type: &str, peer_id: &str) {
+    let channels: Vec<Arc<RTCDataChannel>> = {
+        let map = room.lobby_channels.read().unwrap();
+        map.values().cloned().collect()
+    };
+    if channels.is_empty() {
+        return;
+    }
+
+    let text = serde_json::to_string(&LobbyNotice { notice_type, peer_id })
+        .expect("lobby notice serialization cannot fail");
+    for dc in channels {
+        if let Err(e) = dc.send_text(text.clone()).await {
+            warn!("Lobby notice to host failed in room '{}': {e}", room.room_id);
+        }
+    }
+}
+
+// ─── Admission ──────────────────────────────────────────────────────────────
+
+/// Admit a waiting peer: enable forwarding, request fresh keyframes in both
+/// directions and emit `participant.admitted`.
+pub async fn admit_peer(
+    state: &crate::AppState,
+    room: &Arc<Room>,
+    peer_id: &str,
+) -> Result<(), ApiError> {
+    let admitted = room
+        .admit(peer_id)
+        .map_err(|_| ApiError::peer_not_waiting(peer_id))?;
+
+    // The admitted peer needs keyframes from everyone, and everyone who
+    // subscribes to it next needs one from the admitted peer.
+    crate::sfu::request_keyframe(&admitted).await;
+    for other in room.get_other_publishers(peer_id) {
+        crate::sfu::request_keyframe(&other).await;
+    }
+
+    info!("Peer '{peer_id}' admitted to room '{}'", room.room_id);
+    state.event_bus.emit(LiveRelayEvent::participant_admitted(
+        &room.room_id,
+        peer_id,
+        &admitted.role,
+        admitted.metadata.as_ref(),
+    ));
+    state.event_bus.emit(LiveRelayEvent::participant_joined(
+        &room.room_id,
+        peer_id,
+        &admitted.role,
+        admitted.metadata.as_ref(),
+    ));
+    notify_hosts(room, "participant.admitted", peer_id).await;
+    Ok(())
+}
+
+/// Reject a waiting peer: remove it from the room, close its
+/// PeerConnection and emit `participant.rejected`.
+pub async fn reject_peer(
+    state: &crate::AppState,
+    room: &Arc<Room>,
+    peer_id: &str,
+) -> Result<(), ApiError> {
+    let waiting = {
+        let pubs = room.publishers.read().unwrap();
+        pubs.get(peer_id).filter(|p| !p.is_admitted()).cloned()
+    };
+    let waiting = waiting.ok_or_else(|| ApiError::peer_not_waiting(peer_id))?;
+
+    room.remove_publisher(peer_id);
+    if let Err(e) = waiting.pc.close().await {
+        warn!("Failed to close rejected peer '{peer_id}': {e}");
+    }
+
+    info!("Peer '{peer_id}' rejected from room '{}'", room.room_id);
+    state.event_bus.emit(LiveRelayEvent::participant_rejected(
+        &room.room_id,
+        peer_id,
+        &waiting.role,
+        waiting.metadata.as_ref(),
+    ));
+    Ok(())
+}
+
+// ─── Host DataChannel wiring ────────────────────────────────────────────────
+
+/// Register the `on_data_channel` handler on a host's PeerConnection so the
+/// host can receive lobby notices and send admit/reject commands.
+pub fn attach_host_channel(
+    pc: &Arc<RTCPeerConnection>,
+    room: &Arc<Room>,
+    state: &Arc<crate::AppState>,
+    host_peer_id: &str,
+) {
+    let room = room.clone();
+    let state = state.clone();
+    let host_id = host_peer_id.to_string();
+
+    pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+        let room = room.clone();
+        let state = state.clone();
+        let host_id = host_id.clone();
+
+        Box::pin(async move {
+            if dc.label() != LOBBY_CHANNEL_LABEL {
+                return;
+            }
+            info!("Host '{host_id}' opened lobby channel in room '{}'", room.room_id);
+
+            room.lobby_channels
+                .write()
+                .unwrap()
+                .insert(host_id.clone(), dc.clone());
+
+            // Catch the host up on everyone already waiting.
+            {
+                let dc = dc.clone();
+                let room = room.clone();
+                dc.clone().on_open(Box::new(move || {
+                    Box::pin(async move {
+                        for peer_id in room.waiting_peers() {
+                            let text = serde_json::to_string(&LobbyNotice {
+                                notice_type: "participant.waiting",
+                                peer_id: &peer_id,
+                            })
+                            .expect("lobby notice serialization cannot fail");
+                            let _ = dc.send_text(text).await;
+                        }
+                    })
+                }));
+            }
+
+            {
+                let room = room.clone();
+                let host_id = host_id.clone();
+                dc.on_close(Box::new(move || {
+                    room.lobby_channels.write().unwrap().remove(&host_id);
+                    Box::pin(async {})
+                }));
+            }
+
+            dc.on_message(Box::new(move |msg: DataChannelMessage| {
+                let room = room.clone();
+                let state = state.clone();
+                let host_id = host_id.clone();
+                Box::pin(async move {
+                    let cmd = match serde_json::from_slice::<LobbyCommand>(&msg.data) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            warn!("Invalid lobby command from host '{host_id}': {e}");
+                            return;
+                        }
+                    };
+                    let result = match cmd {
+                        LobbyCommand::Admit { peer_id } => {
+                            admit_peer(&state, &room, &peer_id).await
+                        }
+                        LobbyCommand::Reject { peer_id } => {
+                            reject_peer(&state, &room, &peer_id).await
+                        }
+                    };
+                    if let Err(e) = result {
+                        warn!("Lobby command from host '{host_id}' failed: {}", e.message);
+                    }
+                })
+            }));
+        })
+    }));
+}
+
+// ─── REST API ───────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+pub struct LobbyResponse {
+    pub room_id: String,
+    pub waiting: Vec<String>,
+}
+
+/// Authorise a lobby operation and return the room: either the API key
+/// that created the room, or a `host` JWT issued for this very room by
+/// that key.
+fn require_host(
+    headers: &HeaderMap,
+    state: &crate::AppState,
+    room_id: &str,
+) -> Result<Arc<Room>, ApiError> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(ApiError::auth_header_missing)?;
+
+    if bearer.starts_with("lr_") {
+        if !state.api_keys.read().unwrap().contains_key(bearer) {
+            return Err(ApiError::api_key_invalid());
+        }
+        let room = lobby_room(state, room_id)?;
+        if room.owner_key != bearer {
+            return Err(ApiError::forbidden("Room was created by another API key."));
+        }
+        return Ok(room);
+    }
+
+    let claims = crate::auth::verify_room_token(&state.jwt_secret, bearer, &state.revocations)?;
+    if claims.role != "host" {
+        return Err(ApiError::role_insufficient(&claims.role));
+    }
+    if claims.room_id != room_id {
+        return Err(ApiError::forbidden("Token was not issued for this room."));
+    }
+    let room = lobby_room(state, room_id)?;
+    if claims.key_id != crate::auth::key_id(&room.owner_key) {
+        return Err(ApiError::forbidden("Token was issued by another API key."));
+    }
+    Ok(room)
+}
+
+fn lobby_room(state: &crate::AppState, room_id: &str) -> Result<Arc<Room>, ApiError> {
+    let room = {
+        let rooms = state.rooms.read().unwrap();
+        rooms.get(room_id).cloned()
+    };
+    let room = room.ok_or_else(|| ApiError::room_not_found(room_id))?;
+    if !room.lobby_enabled {
+        return Err(ApiError::bad_request(format!(
+            "Room '{room_id}' does not have a lobby."
+        )));
+    }
+    Ok(room)
+}
+
+/// `GET /v1/rooms/:room_id/lobby` -- list peers waiting for admission.
+pub async fn list_waiting(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<LobbyResponse>, ApiError> {
+    let room = require_host(&headers, &state, &room_id)?;
+
+    Ok(Json(LobbyResponse {
+        room_id,
+        waiting: room.waiting_peers(),
+    }))
+}
+
+/// `POST /v1/rooms/:room_id/lobby/:peer_id/admit`
+pub async fn admit(
+    State(state): State<Arc<crate::AppState>>,
+    Path((room_id, peer_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let room = require_host(&headers, &state, &room_id)?;
+
+    admit_peer(&state, &room, &peer_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `POST /v1/rooms/:room_id/lobby/:peer_id/reject`
+pub async fn reject(
+    State(state): State<Arc<crate::AppState>>,
+    Path((room_id, peer_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let room = require_host(&headers, &state, &room_id)?;
+
+    reject_peer(&state, &room, &peer_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let cmd: LobbyCommand =
+            serde_json::from_str(r#"{"action":"admit","peer_id":"p1"}"#).unwrap();
+        assert!(matches!(cmd, LobbyCommand::Admit { peer_id } if peer_id == "p1"));
+
+        let cmd: LobbyCommand =
+            serde_json::from_str(r#"{"action":"reject","peer_id":"p2"}"#).unwrap();
+        assert!(matches!(cmd, LobbyCommand::Reject { peer_id } if peer_id == "p2"));
+
+        assert!(serde_json::from_str::<LobbyCommand>(r#"{"action":"kick"}"#).is_err());
+    }
+
+    #[test]
+    fn notice_json() {
+        let json = serde_json::to_string(&LobbyNotice {
+            notice_type: "participant.waiting",
+            peer_id: "p1",
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"participant.waiting","peer_id":"p1"}"#);
+    }
+}
@@ -0,0 +1,113 @@
+// src/lrr.rs
+//
+// LiveRelay RTP Dump (.lrr) file format, shared by the recorder and the
+// `loadtest` binary (which replays recordings as publisher traffic).  This
+// module must not depend on the rest of the crate.
+//
+//   File header (16 bytes):
+//     [0..4]   magic: b"LRR1"
+//     [4..8]   version: u32 LE = 1
+//     [8..16]  start_timestamp_us: u64 LE (microseconds since UNIX epoch)
+//
+//   Per-packet record:
+//     [0..4]   relative_timestamp_us: u32 LE (microseconds since recording start)
+//     [4..5]   track_kind: u8 (0 = video, 1 = audio, 2 = screen)
+//     [5..7]   packet_len: u16 LE
+//     [7..7+N] raw RTP packet bytes
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+pub const LRR_MAGIC: &[u8; 4] = b"LRR1";
+pub const LRR_VERSION: u32 = 1;
+pub const TRACK_VIDEO: u8 = 0;
+pub const TRACK_AUDIO: u8 = 1;
+pub const TRACK_SCREEN: u8 = 2;
+
+// ---------------------------------------------------------------------------
+// LRR file reader (for playback/conversion tools)
+// ---------------------------------------------------------------------------
+
+/// A single record from an .lrr file.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct LrrRecord {
+    pub relative_timestamp_us: u32,
+    pub track_kind: u8,
+    pub rtp_data: Vec<u8>,
+}
+
+/// Read and parse an .lrr file header. Returns (version, start_timestamp_us).
+#[allow(dead_code)]
+pub fn read_lrr_header(data: &[u8]) -> Result<(u32, u64), &'static str> {
+    if data.len() < 16 {
+        return Err("File too small for LRR header");
+    }
+    if &data[0..4] != LRR_MAGIC {
+        return Err("Invalid LRR magic bytes");
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let start_ts = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    Ok((version, start_ts))
+}
+
+/// Parse all records from an .lrr file (after the 16-byte header).
+#[allow(dead_code)]
+pub fn read_lrr_records(data: &[u8]) -> Vec<LrrRecord> {
+    let mut records = Vec::new();
+    let mut offset = 16; // skip header
+
+    while offset + 7 <= data.len() {
+        let relative_ts = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let track_kind = data[offset + 4];
+        let pkt_len = u16::from_le_bytes(data[offset + 5..offset + 7].try_into().unwrap()) as usize;
+
+        if offset + 7 + pkt_len > data.len() {
+            break;
+        }
+
+        let rtp_data = data[offset + 7..offset + 7 + pkt_len].to_vec();
+        records.push(LrrRecord {
+            relative_timestamp_us: relative_ts,
+            track_kind,
+            rtp_data,
+        });
+
+        offset += 7 + pkt_len;
+    }
+
+    records
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_and_records_roundtrip() {
+        let mut data = Vec::new();
+        data.extend_from_slice(LRR_MAGIC);
+        data.extend_from_slice(&LRR_VERSION.to_le_bytes());
+        data.extend_from_slice(&1_700_000_000_000_000u64.to_le_bytes());
+        for (ts, kind, payload) in [(0u32, TRACK_VIDEO, &b"abc"[..]), (20_000, TRACK_AUDIO, b"de")] {
+            data.extend_from_slice(&ts.to_le_bytes());
+            data.push(kind);
+            data.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+            data.extend_from_slice(payload);
+        }
+        // Truncated trailing record is ignored.
+        data.extend_from_slice(&[0, 0, 0, 0, TRACK_SCREEN, 9, 0, 1]);
+
+        assert_eq!(read_lrr_header(&data), Ok((1, 1_700_000_000_000_000)));
+        let records = read_lrr_records(&data);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].relative_timestamp_us, 20_000);
+        assert_eq!(records[1].track_kind, TRACK_AUDIO);
+        assert_eq!(records[1].rtp_data, b"de");
+
+        assert!(read_lrr_header(b"NOPE").is_err());
+    }
+}
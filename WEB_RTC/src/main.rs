@@ -1,19 +1,34 @@
 mod analytics;
 mod auth;
+mod codec;
 mod config;
+mod drain;
 mod events;
+mod fmp4;
+mod hls;
+mod lobby;
+mod lrr;
 mod recording;
+mod reload;
+mod revocation;
 mod room;
 mod api;
 mod sfu;
+mod snapshot;
 mod sse;
+mod subscription;
+mod telemetry;
 mod error;
 mod turn_server;
+mod usage;
 mod webhook;
 
+#[cfg(test)]
+mod harness;
+
 use axum::{
     extract::{Request, State},
-    http::{HeaderName, HeaderValue, Method},
+    http::{HeaderName, HeaderValue, Method, StatusCode},
     middleware::{self, Next},
     response::{Html, IntoResponse, Json},
     routing::{delete, get, post},
@@ -21,7 +36,7 @@ use axum::{
 };
 use std::collections::HashMap;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing::{info, warn, error};
 use tracing_subscriber::EnvFilter;
@@ -33,10 +48,22 @@ pub struct AppState {
     pub api_keys: std::sync::RwLock<HashMap<String, auth::ApiKey>>,
     pub jwt_secret: String,
     pub config: config::Config,
+    /// Reloadable subset of `config`; see `reload.rs`.
+    pub settings: std::sync::RwLock<config::RuntimeSettings>,
     pub event_bus: events::EventBus,
     pub webhooks: webhook::WebhookStore,
     pub analytics: analytics::AnalyticsStore,
     pub recording: Option<Arc<recording::RecordingManager>>,
+    pub hls: Option<Arc<hls::HlsManager>>,
+    pub snapshots: Option<Arc<snapshot::SnapshotStore>>,
+    pub usage: usage::UsageStore,
+    pub drain: drain::DrainState,
+    pub revocations: revocation::RevocationList,
+    pub turn_server: Option<Arc<turn::server::Server>>,
+    pub metrics_handle: metrics_exporter_prometheus::PrometheusHandle,
+    /// Virtual network the SFU's PeerConnections bind to instead of the
+    /// host interfaces.  Only set by the in-process test harness.
+    pub vnet: Option<Arc<util::vnet::net::Net>>,
 }
 
 // ─── Page handlers ─────────────────────────────────────────────────────────
@@ -59,6 +86,7 @@ async fn docs_handler() -> Html<&'static str> {
 
 // ─── Health endpoint ────────────────────────────────────────────────────────
 
+/// Returns 503 while the node drains so load balancers stop routing to it.
 async fn health_handler(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
@@ -69,14 +97,24 @@ async fn health_handler(
         .map(|r| r.subscriber_count())
         .sum();
 
-    Json(serde_json::json!({
-        "status": "ok",
-        "version": "0.3.0",
-        "rooms_active": rooms_active,
-        "subscribers_active": subscribers_active,
-        "tls_enabled": state.config.tls_enabled,
-        "turn_embedded": state.config.turn_embedded,
-    }))
+    let (status, label) = if state.drain.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+
+    (
+        status,
+        Json(serde_json::json!({
+            "status": label,
+            "version": "0.3.0",
+            "rooms_active": rooms_active,
+            "subscribers_active": subscribers_active,
+            "tls_enabled": state.config.tls_enabled,
+            "turn_embedded": state.config.turn_embedded,
+            "drain_deadline": state.drain.deadline(),
+        })),
+    )
 }
 
 // ─── Version header middleware ──────────────────────────────────────────────
@@ -95,22 +133,22 @@ async fn version_header_middleware(
 
 // ─── CORS configuration ────────────────────────────────────────────────────
 
-fn build_cors_layer(allowed_origins: &str) -> CorsLayer {
-    if allowed_origins == "*" {
+/// Permissive when started with `*`.  Otherwise origins are checked against
+/// the live settings, so a reload can change them.
+fn build_cors_layer(state: &Arc<AppState>) -> CorsLayer {
+    if state.config.allowed_origins == "*" {
         warn!("CORS: permissive mode (allow all origins) — not suitable for production");
         CorsLayer::permissive()
     } else {
-        let origins: Vec<HeaderValue> = allowed_origins
-            .split(',')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| s.parse::<HeaderValue>().expect("invalid origin header value"))
-            .collect();
-
-        info!("CORS: restricted to {} origin(s)", origins.len());
+        info!("CORS: restricted to {}", state.config.allowed_origins);
 
+        let state = state.clone();
         CorsLayer::new()
-            .allow_origin(origins)
+            .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+                origin
+                    .to_str()
+                    .is_ok_and(|o| state.settings.read().unwrap().allows_origin(o))
+            }))
             .allow_methods([Method::GET, Method::POST, Method::DELETE])
             .allow_headers([
                 HeaderName::from_static("content-type"),
@@ -119,6 +157,80 @@ fn build_cors_layer(allowed_origins: &str) -> CorsLayer {
     }
 }
 
+// ─── Router ─────────────────────────────────────────────────────────────────
+
+fn build_router(state: Arc<AppState>) -> Router {
+    let cors = build_cors_layer(&state);
+
+    Router::new()
+        // Frontend pages
+        .route("/", get(landing_handler))
+        .route("/playground", get(playground_handler))
+        .route("/dashboard", get(dashboard_handler))
+        .route("/docs", get(docs_handler))
+        .nest_service("/static", ServeDir::new("static"))
+        // Health + metrics (no auth required)
+        .route("/health", get(health_handler))
+        .route("/metrics", get(telemetry::metrics_handler))
+        // ICE server config (requires JWT)
+        .route("/v1/ice-servers", get(turn_server::get_ice_servers))
+        // REST API (v1)
+        .route("/v1/rooms", post(api::create_room))
+        .route("/v1/rooms", get(api::list_rooms))
+        .route("/v1/rooms/:room_id", get(api::get_room))
+        .route("/v1/rooms/:room_id", delete(api::delete_room))
+        .route("/v1/rooms/:room_id/token", post(api::create_room_token))
+        .route("/v1/rooms/:room_id/participants", get(api::list_participants))
+        .route("/v1/tokens/revoke", post(revocation::revoke_token))
+        // Lobby (conference rooms created with `lobby: true`)
+        .route("/v1/rooms/:room_id/lobby", get(lobby::list_waiting))
+        .route("/v1/rooms/:room_id/lobby/:peer_id/admit", post(lobby::admit))
+        .route("/v1/rooms/:room_id/lobby/:peer_id/reject", post(lobby::reject))
+        .route("/v1/keys", post(api::create_api_key))
+        // Drain mode (deploys)
+        .route("/v1/admin/drain", post(drain::start_drain))
+        .route("/v1/admin/config/reload", post(reload::reload_config))
+        // Webhooks API
+        .route("/v1/webhooks", post(webhook::create_webhook))
+        .route("/v1/webhooks", get(webhook::list_webhooks))
+        .route("/v1/webhooks/:webhook_id", delete(webhook::delete_webhook))
+        // Server-Sent Events (real-time event stream)
+        .route("/v1/events", get(sse::sse_events))
+        // Analytics API
+        .route("/v1/analytics", get(analytics::get_analytics))
+        // Usage metering (per API key)
+        .route("/v1/usage", get(usage::get_usage))
+        // Recording API
+        .route("/v1/rooms/:room_id/recording/start", post(recording::start_recording))
+        .route("/v1/rooms/:room_id/recording/stop", post(recording::stop_recording))
+        .route("/v1/rooms/:room_id/recordings", get(recording::list_room_recordings))
+        // LL-HLS egress (broadcast rooms)
+        .route("/v1/rooms/:room_id/hls/start", post(hls::start_hls))
+        .route("/v1/rooms/:room_id/hls/stop", post(hls::stop_hls))
+        .route("/v1/rooms/:room_id/hls", get(hls::get_hls))
+        .route("/hls/:room_id/:file", get(hls::serve_hls))
+        // Moderation snapshots
+        .route("/v1/rooms/:room_id/snapshots", get(snapshot::list_snapshots))
+        .route("/v1/rooms/:room_id/snapshots/:snapshot_id", get(snapshot::get_snapshot))
+        // SFU WebRTC signaling
+        .route("/sfu/publish", post(sfu::sfu_publish))
+        .route("/sfu/subscribe", post(sfu::sfu_subscribe))
+        .route("/sfu/call", post(sfu::sfu_call))
+        .route("/sfu/conference", post(sfu::sfu_conference))
+        .route("/sfu/conference/subscribe", post(sfu::sfu_conference_subscribe))
+        .route("/sfu/screen", post(sfu::sfu_screen_start))
+        .route("/sfu/screen", get(sfu::sfu_screen_list))
+        .route("/sfu/screen", delete(sfu::sfu_screen_stop))
+        // Subscriber track control (pause / resume, max resolution)
+        .route("/sfu/subscriptions/:subscription_id", post(subscription::update_subscription))
+        .route("/sfu/demand", get(subscription::publisher_demand))
+        // Middleware
+        .layer(middleware::from_fn(telemetry::metrics_middleware))
+        .layer(middleware::from_fn(version_header_middleware))
+        .layer(cors)
+        .with_state(state)
+}
+
 // ─── TLS configuration ─────────────────────────────────────────────────────
 
 /// Load TLS certificate and key from PEM files and build an
@@ -164,11 +276,20 @@ async fn main() {
         )
         .init();
 
-    let cfg = config::Config::from_env();
+    let cfg = match config::Config::load() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Initialize Prometheus metrics
+    let metrics_handle = telemetry::init_metrics();
 
     // ── Start embedded TURN server (if configured) ──────────────────────
 
-    let _turn_handle = if cfg.turn_embedded {
+    let turn_handle = if cfg.turn_embedded {
         match turn_server::start_embedded_turn(&cfg).await {
             Ok(server) => {
                 info!("Embedded TURN server is running");
@@ -189,8 +310,7 @@ async fn main() {
 
     // ── Bootstrap API key ───────────────────────────────────────────────
 
-    let bootstrap_key = std::env::var("LIVERELAY_API_KEY")
-        .unwrap_or_else(|_| auth::generate_api_key());
+    let bootstrap_key = cfg.bootstrap_api_key.clone();
     let mut initial_keys = HashMap::new();
     initial_keys.insert(
         bootstrap_key.clone(),
@@ -201,6 +321,7 @@ async fn main() {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
+            quotas: Default::default(),
         },
     );
     info!("Bootstrap API key: {bootstrap_key}");
@@ -209,37 +330,48 @@ async fn main() {
     let tls_enabled = cfg.tls_enabled;
     let tls_cert_path = cfg.tls_cert_path.clone();
     let tls_key_path = cfg.tls_key_path.clone();
-    let allowed_origins = cfg.allowed_origins.clone();
 
     let event_bus = events::EventBus::new();
     let webhook_store = webhook::WebhookStore::new();
     let analytics_store = analytics::AnalyticsStore::new();
 
     // ── Recording subsystem ────────────────────────────────────────────
-    let recording_dir = std::env::var("LIVERELAY_RECORDING_DIR")
-        .unwrap_or_else(|_| "./recordings".to_string());
-    let recording_max_secs = std::env::var("LIVERELAY_RECORDING_MAX_SECS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .unwrap_or(0);
-
     let recording_mgr = Arc::new(recording::RecordingManager::new(
         recording::RecordingConfig {
-            base_dir: std::path::PathBuf::from(&recording_dir),
-            max_duration_secs: recording_max_secs,
+            base_dir: std::path::PathBuf::from(&cfg.recording_dir),
+            max_duration_secs: cfg.recording_max_duration_secs,
         },
     ));
-    info!("Recording directory: {recording_dir}");
+    let webhook_retry = cfg.webhook_retry.clone();
+    let stats_interval = std::time::Duration::from_secs(cfg.stats_interval_secs);
+
+    // ── HLS egress ─────────────────────────────────────────────────────
+    let hls_mgr = Arc::new(hls::HlsManager::new(cfg.hls.clone()));
+
+    // ── Moderation snapshots ───────────────────────────────────────────
+    let snapshot_store = Arc::new(snapshot::SnapshotStore::new(snapshot::SnapshotConfig {
+        base_dir: std::path::PathBuf::from(&cfg.snapshot_dir),
+        retain_per_room: cfg.snapshot_retain_per_room,
+    }));
 
     let state = Arc::new(AppState {
         rooms: std::sync::RwLock::new(HashMap::new()),
         api_keys: std::sync::RwLock::new(initial_keys),
         jwt_secret: cfg.jwt_secret.clone(),
+        settings: std::sync::RwLock::new(cfg.runtime_settings()),
         config: cfg,
         event_bus: event_bus.clone(),
         webhooks: webhook_store.clone(),
         analytics: analytics_store.clone(),
         recording: Some(recording_mgr),
+        hls: Some(hls_mgr),
+        snapshots: Some(snapshot_store),
+        usage: usage::UsageStore::new(),
+        drain: drain::DrainState::new(),
+        revocations: revocation::RevocationList::new(),
+        turn_server: turn_handle,
+        metrics_handle,
+        vnet: None,
     });
 
     // ── Start background event consumers ────────────────────────────────
@@ -248,62 +380,25 @@ async fn main() {
     let _webhook_handle = webhook::spawn_webhook_dispatcher(
         event_bus.clone(),
         webhook_store,
-        webhook::RetryPolicy::default(),
+        webhook_retry,
     );
 
-    // Analytics stats collector: gathers WebRTC quality metrics every 5s.
-    let _stats_handle = analytics::spawn_stats_collector(
-        state.clone(),
-        std::time::Duration::from_secs(5),
-        analytics::QualityThresholds::default(),
-    );
+    // Analytics stats collector: gathers WebRTC quality metrics.
+    let _stats_handle = analytics::spawn_stats_collector(state.clone(), stats_interval);
 
-    // ── Build CORS layer ────────────────────────────────────────────────
+    // Configuration reload on SIGHUP.
+    reload::spawn_sighup_reloader(state.clone());
 
-    let cors = build_cors_layer(&allowed_origins);
+    // Usage meter: bills participant/recording minutes and egress per key.
+    let _usage_handle = usage::spawn_usage_meter(
+        state.clone(),
+        std::time::Duration::from_secs(60),
+    );
 
-    // ── Build router ────────────────────────────────────────────────────
+    let app = build_router(state.clone());
 
-    let app = Router::new()
-        // Frontend pages
-        .route("/", get(landing_handler))
-        .route("/playground", get(playground_handler))
-        .route("/dashboard", get(dashboard_handler))
-        .route("/docs", get(docs_handler))
-        .nest_service("/static", ServeDir::new("static"))
-        // Health (no auth required)
-        .route("/health", get(health_handler))
-        // ICE server config (requires JWT)
-        .route("/v1/ice-servers", get(turn_server::get_ice_servers))
-        // REST API (v1)
-        .route("/v1/rooms", post(api::create_room))
-        .route("/v1/rooms", get(api::list_rooms))
-        .route("/v1/rooms/:room_id", get(api::get_room))
-        .route("/v1/rooms/:room_id", delete(api::delete_room))
-        .route("/v1/rooms/:room_id/token", post(api::create_room_token))
-        .route("/v1/keys", post(api::create_api_key))
-        // Webhooks API
-        .route("/v1/webhooks", post(webhook::create_webhook))
-        .route("/v1/webhooks", get(webhook::list_webhooks))
-        .route("/v1/webhooks/:webhook_id", delete(webhook::delete_webhook))
-        // Server-Sent Events (real-time event stream)
-        .route("/v1/events", get(sse::sse_events))
-        // Analytics API
-        .route("/v1/analytics", get(analytics::get_analytics))
-        // Recording API
-        .route("/v1/rooms/:room_id/recording/start", post(recording::start_recording))
-        .route("/v1/rooms/:room_id/recording/stop", post(recording::stop_recording))
-        .route("/v1/rooms/:room_id/recordings", get(recording::list_room_recordings))
-        // SFU WebRTC signaling
-        .route("/sfu/publish", post(sfu::sfu_publish))
-        .route("/sfu/subscribe", post(sfu::sfu_subscribe))
-        .route("/sfu/call", post(sfu::sfu_call))
-        .route("/sfu/conference", post(sfu::sfu_conference))
-        .route("/sfu/conference/subscribe", post(sfu::sfu_conference_subscribe))
-        // Middleware
-        .layer(middleware::from_fn(version_header_middleware))
-        .layer(cors)
-        .with_state(state);
+    // Resolves once the node has drained (SIGTERM, Ctrl-C or admin request).
+    let shutdown = drain::shutdown_signal(state);
 
     // ── Start server (plain HTTP or HTTPS) ──────────────────────────────
 
@@ -322,15 +417,21 @@ async fn main() {
         info!("LiveRelay SFU listening on https://{bind_addr}");
         let addr: std::net::SocketAddr = bind_addr.parse().expect("invalid bind address");
 
-        axum_server::bind_rustls(addr, tls_config)
-            .serve(app.into_make_service())
-            .await
-            .unwrap();
+        let server = axum_server::bind_rustls(addr, tls_config).serve(app.into_make_service());
+        tokio::select! {
+            result = server => result.unwrap(),
+            _ = shutdown => info!("LiveRelay SFU drained, exiting"),
+        }
     } else {
         let protocol = "http";
         info!("LiveRelay SFU listening on {protocol}://{bind_addr}");
 
         let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
-        axum::serve(listener, app).await.unwrap();
+        // Not `with_graceful_shutdown`: open SSE streams would keep the
+        // server alive after the drain.
+        tokio::select! {
+            result = axum::serve(listener, app) => result.unwrap(),
+            _ = shutdown => info!("LiveRelay SFU drained, exiting"),
+        }
     }
 }
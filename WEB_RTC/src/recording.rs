@@ -17,6 +17,7 @@ use tracing::{info, warn};
 use webrtc::util::marshal::Marshal;
 
 use crate::error::ApiError;
+use crate::lrr::{LRR_MAGIC, LRR_VERSION, TRACK_AUDIO, TRACK_SCREEN, TRACK_VIDEO};
 
 // ---------------------------------------------------------------------------
 // Architecture Decision: Option A — Raw RTP dump
@@ -47,30 +48,9 @@ use crate::error::ApiError;
 // provide an optional Option C hook (spawn FFmpeg subprocess) for users
 // who want direct WebM output.
 //
-// File format: LiveRelay RTP Dump (.lrr)
-//
-//   File header (16 bytes):
-//     [0..4]   magic: b"LRR1"
-//     [4..8]   version: u32 LE = 1
-//     [8..16]  start_timestamp_us: u64 LE (microseconds since UNIX epoch)
-//
-//   Per-packet record:
-//     [0..4]   relative_timestamp_us: u32 LE (microseconds since recording start)
-//     [4..5]   track_kind: u8 (0 = video, 1 = audio, 2 = screen)
-//     [5..7]   packet_len: u16 LE
-//     [7..7+N] raw RTP packet bytes
+// File format: LiveRelay RTP Dump (.lrr), see `lrr.rs`.
 //
 
-// ---------------------------------------------------------------------------
-// Constants
-// ---------------------------------------------------------------------------
-
-const LRR_MAGIC: &[u8; 4] = b"LRR1";
-const LRR_VERSION: u32 = 1;
-const TRACK_VIDEO: u8 = 0;
-const TRACK_AUDIO: u8 = 1;
-const TRACK_SCREEN: u8 = 2;
-
 // ---------------------------------------------------------------------------
 // RecordingConfig
 // ---------------------------------------------------------------------------
@@ -107,6 +87,8 @@ pub struct RecordingHandle {
     pub started_at_unix: u64,
     pub cancel: CancellationToken,
     pub is_active: Arc<AtomicBool>,
+    /// Cancelled by the writer task once the file is flushed and closed.
+    pub done: CancellationToken,
     /// Optional: PID of external FFmpeg process for Option C.
     pub ffmpeg_pid: Option<u32>,
 }
@@ -193,6 +175,7 @@ impl RecordingManager {
         })?;
 
         let cancel = CancellationToken::new();
+        let done = CancellationToken::new();
         let is_active = Arc::new(AtomicBool::new(true));
 
         let handle = Arc::new(RecordingHandle {
@@ -203,6 +186,7 @@ impl RecordingManager {
             started_at_unix: now_unix / 1_000_000, // seconds
             cancel: cancel.clone(),
             is_active: is_active.clone(),
+            done: done.clone(),
             ffmpeg_pid: None,
         });
 
@@ -237,6 +221,7 @@ impl RecordingManager {
             } else {
                 info!("Recording '{rec_id}' completed");
             }
+            done.cancel();
         });
 
         let info = RecordingInfo {
@@ -311,12 +296,33 @@ impl RecordingManager {
         stopped
     }
 
+    /// Stop every active recording and wait until each writer has flushed
+    /// its file.  Returns the number of recordings that were stopped.
+    pub async fn finalize_all(&self) -> usize {
+        let pending: Vec<CancellationToken> = {
+            let active = self.active.read().unwrap();
+            active
+                .values()
+                .filter(|h| h.is_active.load(Ordering::Relaxed))
+                .map(|h| {
+                    h.cancel.cancel();
+                    h.done.clone()
+                })
+                .collect()
+        };
+
+        for done in &pending {
+            done.cancelled().await;
+        }
+        pending.len()
+    }
+
     /// List all recordings (active and completed).
     pub fn list_recordings(&self, room_id: Option<&str>) -> Vec<RecordingInfo> {
         let active = self.active.read().unwrap();
         active
             .values()
-            .filter(|h| room_id.map_or(true, |rid| h.room_id == rid))
+            .filter(|h| room_id.is_none_or(|rid| h.room_id == rid))
             .map(|h| RecordingInfo {
                 recording_id: h.recording_id.clone(),
                 room_id: h.room_id.clone(),
@@ -638,57 +644,3 @@ pub async fn list_room_recordings(
     Ok(Json(recordings))
 }
 
-// ---------------------------------------------------------------------------
-// LRR file reader (for playback/conversion tools)
-// ---------------------------------------------------------------------------
-
-/// A single record from an .lrr file.
-#[derive(Debug)]
-#[allow(dead_code)]
-pub struct LrrRecord {
-    pub relative_timestamp_us: u32,
-    pub track_kind: u8,
-    pub rtp_data: Vec<u8>,
-}
-
-/// Read and parse an .lrr file header. Returns (version, start_timestamp_us).
-#[allow(dead_code)]
-pub fn read_lrr_header(data: &[u8]) -> Result<(u32, u64), &'static str> {
-    if data.len() < 16 {
-        return Err("File too small for LRR header");
-    }
-    if &data[0..4] != LRR_MAGIC {
-        return Err("Invalid LRR magic bytes");
-    }
-    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
-    let start_ts = u64::from_le_bytes(data[8..16].try_into().unwrap());
-    Ok((version, start_ts))
-}
-
-/// Parse all records from an .lrr file (after the 16-byte header).
-#[allow(dead_code)]
-pub fn read_lrr_records(data: &[u8]) -> Vec<LrrRecord> {
-    let mut records = Vec::new();
-    let mut offset = 16; // skip header
-
-    while offset + 7 <= data.len() {
-        let relative_ts = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
-        let track_kind = data[offset + 4];
-        let pkt_len = u16::from_le_bytes(data[offset + 5..offset + 7].try_into().unwrap()) as usize;
-
-        if offset + 7 + pkt_len > data.len() {
-            break;
-        }
-
-        let rtp_data = data[offset + 7..offset + 7 + pkt_len].to_vec();
-        records.push(LrrRecord {
-            relative_timestamp_us: relative_ts,
-            track_kind,
-            rtp_data,
-        });
-
-        offset += 7 + pkt_len;
-    }
-
-    records
-}
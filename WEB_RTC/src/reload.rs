@@ -0,0 +1,98 @@
+// src/reload.rs
+//
+// Configuration reload without a restart.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   SIGHUP                       ──┐
+//                                  ├─► reload()
+//   POST /v1/admin/config/reload ──┘     ├─ re-read the config file and env
+//                                        ├─ validate (invalid → nothing applied)
+//                                        ├─ swap AppState::settings:
+//                                        │    limits, quality thresholds,
+//                                        │    CORS origins
+//                                        └─ report changed settings that only
+//                                           apply after a restart
+//
+//   Environment variables still take precedence over the file, so a reload
+//   only changes settings the environment does not pin.
+//
+//   The endpoint requires the bootstrap API key.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::config::{Config, ConfigError, RuntimeSettings};
+use crate::error::ApiError;
+
+#[derive(Debug, Serialize)]
+pub struct ReloadResponse {
+    /// The settings now in effect.
+    pub settings: RuntimeSettings,
+    /// Changed settings that were not applied.
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Re-read the configuration and apply its runtime settings.
+pub fn reload(state: &crate::AppState) -> Result<ReloadResponse, ConfigError> {
+    let config = Config::read()?;
+    let settings = config.runtime_settings();
+    let restart_required = state.config.restart_required(&config);
+
+    let previous = std::mem::replace(&mut *state.settings.write().unwrap(), settings.clone());
+    if previous != settings {
+        info!("Configuration reloaded: {settings:?}");
+    } else {
+        info!("Configuration reloaded, no runtime settings changed");
+    }
+    if !restart_required.is_empty() {
+        warn!("Changed settings need a restart to apply: {}", restart_required.join(", "));
+    }
+
+    Ok(ReloadResponse {
+        settings,
+        restart_required,
+    })
+}
+
+/// Reload on every SIGHUP.  Invalid configurations are logged and ignored.
+pub fn spawn_sighup_reloader(state: Arc<crate::AppState>) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        let mut hangup =
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(sig) => sig,
+                Err(e) => {
+                    warn!("Failed to listen for SIGHUP: {e}");
+                    return;
+                }
+            };
+        while hangup.recv().await.is_some() {
+            info!("SIGHUP received, reloading configuration");
+            if let Err(e) = reload(&state) {
+                error!("Configuration not reloaded: {e}");
+            }
+        }
+    });
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+// ─── POST /v1/admin/config/reload ───────────────────────────────────────────
+
+pub async fn reload_config(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ReloadResponse>, ApiError> {
+    crate::auth::require_admin_key(&headers, &state.api_keys, &state.config.bootstrap_api_key)
+        .await?;
+
+    reload(&state).map(Json).map_err(|e| {
+        error!("Configuration not reloaded: {e}");
+        ApiError::config_invalid(&e.to_string())
+    })
+}
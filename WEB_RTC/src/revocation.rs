@@ -0,0 +1,355 @@
+// src/revocation.rs
+//
+// Token revocation and single-use join tokens.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   Every room token carries a `jti`.  Each PeerConnection a token opens
+//   (publish, subscribe, call, conference, conference/subscribe, screen) is
+//   registered under that jti.
+//
+//   POST /v1/tokens/revoke   API key
+//     {"token": "<jwt>"}  or  {"jti": "<id>"}
+//     → a key may only revoke the tokens it issued: a token of another key
+//       is refused (403), a bare jti only ever matches the caller's tokens
+//     → the jti is refused by every /sfu/* handler until the token expires
+//     → every PeerConnection opened with it is closed; the usual disconnect
+//       handlers remove the peer and emit `participant.left`
+//
+//   Single-use tokens (`"single_use": true`) are spent by the first join
+//   (/sfu/publish, /sfu/subscribe, /sfu/call, /sfu/conference) that gets
+//   its peer into the room; a join that fails earlier gives the token back
+//   (`Consumed` is released on drop unless committed).  After that
+//   they only authorise the joined peer's follow-up requests — screen
+//   share, conference/subscribe, subscriptions — while it is connected.
+//
+//   Entries live in memory and are dropped once the token has expired.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Json};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::auth::TokenClaims;
+use crate::error::ApiError;
+
+/// How long a jti revoked without its token is remembered: the longest TTL
+/// the API issues.
+const MAX_TOKEN_TTL_SECS: u64 = 86400;
+
+// ─── RevocationList ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spent {
+    Revoked,
+    /// A single-use token that admitted its peer.
+    Used,
+}
+
+/// A jti scoped to the API key that issued it: `(key_id, jti)`.
+type TokenId = (String, String);
+
+fn token_id(claims: &TokenClaims) -> TokenId {
+    (claims.key_id.clone(), claims.jti.clone())
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Token → (unix expiry, why it no longer admits peers).
+    spent: HashMap<TokenId, (u64, Spent)>,
+    /// PeerConnections opened with each token.
+    sessions: HashMap<TokenId, Vec<Weak<RTCPeerConnection>>>,
+}
+
+impl Inner {
+    fn has_live_session(&self, id: &TokenId) -> bool {
+        self.sessions
+            .get(id)
+            .is_some_and(|pcs| pcs.iter().filter_map(Weak::upgrade).any(|pc| is_live(&pc)))
+    }
+
+    fn spend(&mut self, id: TokenId, expires_at: u64, why: Spent) {
+        let now = unix_now();
+        self.spent.retain(|_, (exp, _)| *exp > now);
+        self.spent.insert(id, (expires_at, why));
+    }
+}
+
+/// Revoked and spent token ids, plus the PeerConnections each token opened.
+#[derive(Default)]
+pub struct RevocationList {
+    inner: Mutex<Inner>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject revoked tokens, and spent single-use tokens whose peer is no
+    /// longer connected.
+    pub fn check(&self, claims: &TokenClaims) -> Result<(), ApiError> {
+        if claims.jti.is_empty() {
+            return Ok(());
+        }
+        let id = token_id(claims);
+        let inner = self.inner.lock().unwrap();
+        match inner.spent.get(&id) {
+            Some((_, Spent::Revoked)) => Err(ApiError::token_revoked()),
+            Some((_, Spent::Used)) if !inner.has_live_session(&id) => Err(ApiError::token_used()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reserve a single-use token for a joining peer, so a concurrent join
+    /// with the same token is refused.  The join handler commits the
+    /// reservation once the peer is in the room; if it bails out first the
+    /// reservation is released.  A no-op for regular tokens.
+    pub fn consume(&self, claims: &TokenClaims) -> Result<Consumed<'_>, ApiError> {
+        if !claims.single_use || claims.jti.is_empty() {
+            return Ok(Consumed {
+                list: self,
+                id: None,
+            });
+        }
+        let id = token_id(claims);
+        let mut inner = self.inner.lock().unwrap();
+        match inner.spent.get(&id) {
+            Some((_, Spent::Revoked)) => Err(ApiError::token_revoked()),
+            Some((_, Spent::Used)) => Err(ApiError::token_used()),
+            None => {
+                inner.spend(id.clone(), claims.exp as u64, Spent::Used);
+                Ok(Consumed {
+                    list: self,
+                    id: Some(id),
+                })
+            }
+        }
+    }
+
+    /// Remember that `pc` was opened with `claims`, so revoking the token
+    /// closes it.
+    pub fn track(&self, claims: &TokenClaims, pc: &Arc<RTCPeerConnection>) {
+        if claims.jti.is_empty() {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .sessions
+            .retain(|_, pcs| pcs.iter().any(|pc| pc.strong_count() > 0));
+        inner
+            .sessions
+            .entry(token_id(claims))
+            .or_default()
+            .push(Arc::downgrade(pc));
+    }
+
+    /// Revoke `jti` of the key `key_id` and return the PeerConnections still
+    /// open with it.
+    pub fn revoke(&self, key_id: &str, jti: &str, expires_at: u64) -> Vec<Arc<RTCPeerConnection>> {
+        let id = (key_id.to_string(), jti.to_string());
+        let mut inner = self.inner.lock().unwrap();
+        inner.spend(id.clone(), expires_at, Spent::Revoked);
+        inner
+            .sessions
+            .remove(&id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+/// A single-use token reserved by [`RevocationList::consume`].
+#[must_use = "commit once the peer is in the room, or the token is released"]
+pub struct Consumed<'a> {
+    list: &'a RevocationList,
+    /// `None` once committed, or for regular tokens.
+    id: Option<TokenId>,
+}
+
+impl Consumed<'_> {
+    /// The peer made it into the room: the token stays spent.
+    pub fn commit(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for Consumed<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        let mut inner = self.list.inner.lock().unwrap();
+        // A revocation in the meantime wins.
+        if matches!(inner.spent.get(&id), Some((_, Spent::Used))) {
+            inner.spent.remove(&id);
+        }
+    }
+}
+
+fn is_live(pc: &RTCPeerConnection) -> bool {
+    !matches!(
+        pc.connection_state(),
+        RTCPeerConnectionState::Closed
+            | RTCPeerConnectionState::Failed
+            | RTCPeerConnectionState::Disconnected
+    )
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before unix epoch")
+        .as_secs()
+}
+
+// ─── POST /v1/tokens/revoke ─────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    /// The token itself.  Its signature is verified.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Or just its id.
+    #[serde(default)]
+    pub jti: Option<String>,
+}
+
+pub async fn revoke_token(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<RevokeRequest>,
+) -> Result<StatusCode, ApiError> {
+    let api_key = crate::auth::require_api_key(&headers, &state.api_keys).await?;
+    let key_id = crate::auth::key_id(&api_key.key);
+
+    let (jti, expires_at) = match (body.token, body.jti) {
+        (Some(token), _) => match crate::auth::verify_token(&state.jwt_secret, &token) {
+            Ok(claims) if claims.key_id != key_id => {
+                return Err(ApiError::forbidden("Token was issued by another API key."))
+            }
+            Ok(claims) if !claims.jti.is_empty() => (claims.jti, claims.exp as u64),
+            Ok(_) => {
+                return Err(ApiError::bad_request(
+                    "Token predates revocation support and has no jti.",
+                ))
+            }
+            // Nothing to revoke: it is no longer accepted anyway.
+            Err(e) if *e.kind() == jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                return Ok(StatusCode::NO_CONTENT)
+            }
+            Err(_) => return Err(ApiError::token_invalid()),
+        },
+        // Scoped to the caller's key: it cannot reach another key's tokens.
+        (None, Some(jti)) if !jti.is_empty() => (jti, unix_now() + MAX_TOKEN_TTL_SECS),
+        _ => return Err(ApiError::bad_request("Provide either 'token' or 'jti'.")),
+    };
+
+    let sessions = state.revocations.revoke(&key_id, &jti, expires_at);
+    info!(
+        "Token '{jti}' revoked — closing {} PeerConnection(s)",
+        sessions.len()
+    );
+    for pc in sessions {
+        if let Err(e) = pc.close().await {
+            warn!("Failed to close PeerConnection of revoked token '{jti}': {e}");
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(jti: &str, single_use: bool) -> TokenClaims {
+        TokenClaims {
+            sub: "peer".into(),
+            room_id: "room".into(),
+            role: "call".into(),
+            key_id: "lr_x".into(),
+            exp: (unix_now() + 60) as usize,
+            iat: unix_now() as usize,
+            nbf: None,
+            jti: jti.into(),
+            single_use,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn revoked_tokens_are_rejected() {
+        let list = RevocationList::new();
+        let c = claims("t1", false);
+        assert!(list.check(&c).is_ok());
+        assert!(list.revoke("lr_x", "t1", unix_now() + 60).is_empty());
+        assert_eq!(list.check(&c).unwrap_err().code, "token_revoked");
+        assert_eq!(
+            list.consume(&claims("t1", true)).err().unwrap().code,
+            "token_revoked"
+        );
+    }
+
+    #[test]
+    fn single_use_tokens_admit_one_peer() {
+        let list = RevocationList::new();
+        let c = claims("t2", true);
+        let spent = list.consume(&c).unwrap();
+        // Reserved while the first join is in flight.
+        assert_eq!(list.consume(&c).err().unwrap().code, "token_used");
+        spent.commit();
+        assert_eq!(list.consume(&c).err().unwrap().code, "token_used");
+        // No connected peer holds the token any more.
+        assert_eq!(list.check(&c).unwrap_err().code, "token_used");
+
+        // Regular tokens are never spent.
+        let regular = claims("t3", false);
+        list.consume(&regular).unwrap().commit();
+        list.consume(&regular).unwrap().commit();
+    }
+
+    #[test]
+    fn failed_join_gives_the_token_back() {
+        let list = RevocationList::new();
+        let c = claims("t4", true);
+        drop(list.consume(&c).unwrap());
+        assert!(list.check(&c).is_ok());
+        list.consume(&c).unwrap().commit();
+        assert_eq!(list.consume(&c).err().unwrap().code, "token_used");
+
+        // Revoked while the join was in flight: dropping keeps it revoked.
+        let c = claims("t5", true);
+        let spent = list.consume(&c).unwrap();
+        list.revoke("lr_x", "t5", unix_now() + 60);
+        drop(spent);
+        assert_eq!(list.check(&c).unwrap_err().code, "token_revoked");
+    }
+
+    #[test]
+    fn expired_entries_are_pruned() {
+        let list = RevocationList::new();
+        list.revoke("lr_x", "old", unix_now() - 1);
+        list.revoke("lr_x", "new", unix_now() + 60);
+        let inner = list.inner.lock().unwrap();
+        assert!(!inner.spent.contains_key(&("lr_x".into(), "old".into())));
+        assert!(inner.spent.contains_key(&("lr_x".into(), "new".into())));
+    }
+
+    #[test]
+    fn revocations_are_scoped_to_the_issuing_key() {
+        let list = RevocationList::new();
+        list.revoke("lr_other", "t6", unix_now() + 60);
+        assert!(list.check(&claims("t6", false)).is_ok());
+        list.consume(&claims("t6", true)).unwrap().commit();
+    }
+}
@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
+use webrtc::data_channel::RTCDataChannel;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
 
+use crate::codec::CodecPolicy;
+use crate::subscription::Subscription;
+
 // ---------------------------------------------------------------------------
 // TrackSource — distinguishes camera from screen share
 // ---------------------------------------------------------------------------
@@ -38,6 +42,17 @@ pub enum RoomType {
     Conference,
 }
 
+impl RoomType {
+    /// Lowercase name, as used in JSON payloads and metric labels.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Broadcast => "broadcast",
+            Self::Call => "call",
+            Self::Conference => "conference",
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Publisher
 // ---------------------------------------------------------------------------
@@ -67,6 +82,17 @@ pub struct Publisher {
 
     /// What this publisher is sending (camera, screen, or both).
     pub track_source: std::sync::RwLock<TrackSource>,
+
+    /// Whether this peer has been let into the room.  Peers waiting in a
+    /// lobby keep their PeerConnection up, but nothing is forwarded to or
+    /// from them until a host admits them.
+    pub admitted: Arc<AtomicBool>,
+
+    /// Participant metadata from the peer's token.
+    pub metadata: Option<serde_json::Value>,
+
+    /// Role claim of the peer's token (`publish`, `call`, `conference`, ...).
+    pub role: String,
 }
 
 impl Publisher {
@@ -91,9 +117,24 @@ impl Publisher {
             screen_ssrc: AtomicU64::new(0),
             screen_codec: std::sync::RwLock::new(None),
             track_source: std::sync::RwLock::new(TrackSource::Camera),
+            admitted: Arc::new(AtomicBool::new(true)),
+            metadata: None,
+            role: String::new(),
         }
     }
 
+    /// Attach the participant metadata carried by the peer's token.
+    pub fn with_metadata(mut self, metadata: Option<serde_json::Value>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Record the role the peer's token was issued for.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.role = role.into();
+        self
+    }
+
     /// Create a publisher specifically for screen sharing.
     pub fn new_screen(peer_id: String, pc: Arc<RTCPeerConnection>) -> Self {
         let p = Self::new(peer_id, pc);
@@ -105,6 +146,17 @@ impl Publisher {
     pub fn has_screen(&self) -> bool {
         self.screen_ssrc.load(Ordering::Relaxed) != 0
     }
+
+    /// Returns true for the dedicated `<peer_id>-screen` publisher of a
+    /// screen share.
+    pub fn is_screen(&self) -> bool {
+        *self.track_source.read().unwrap() == TrackSource::Screen
+    }
+
+    /// Returns true once the peer is allowed to exchange media.
+    pub fn is_admitted(&self) -> bool {
+        self.admitted.load(Ordering::Relaxed)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -119,6 +171,28 @@ pub struct Room {
     pub publishers: std::sync::RwLock<HashMap<String, Arc<Publisher>>>,
     pub subscriber_count: AtomicU64,
     pub created_at: std::time::Instant,
+    /// Lobby mode: non-host peers wait until a host admits them.
+    pub lobby_enabled: bool,
+    /// Open host DataChannels (keyed by host peer_id) that receive lobby
+    /// notifications and accept admit/reject commands.
+    pub lobby_channels: std::sync::RwLock<HashMap<String, Arc<RTCDataChannel>>>,
+    /// API key that created the room (usage is billed to it).
+    pub owner_key: String,
+    /// RTP bytes forwarded to subscribers since the usage meter last ran.
+    pub egress_bytes: Arc<AtomicU64>,
+    /// When the usage meter last sampled the room (creation until then).
+    pub metered_at: std::sync::Mutex<std::time::Instant>,
+    /// Codecs every PeerConnection in the room may negotiate.
+    pub codec_policy: CodecPolicy,
+    /// Allow at most one active screen share at a time.
+    pub single_screen_share: bool,
+    /// Receiving PeerConnections and their per-track pause state, keyed by
+    /// subscription id.
+    pub subscriptions: std::sync::RwLock<HashMap<String, Arc<Subscription>>>,
+    /// Capture a moderation snapshot of every publisher this often.
+    pub snapshot_interval: Option<std::time::Duration>,
+    /// Caller-supplied metadata set at creation.
+    pub metadata: Option<serde_json::Value>,
 }
 
 impl Room {
@@ -141,6 +215,16 @@ impl Room {
             publishers: std::sync::RwLock::new(HashMap::new()),
             subscriber_count: AtomicU64::new(0),
             created_at: std::time::Instant::now(),
+            lobby_enabled: false,
+            lobby_channels: std::sync::RwLock::new(HashMap::new()),
+            owner_key: String::new(),
+            egress_bytes: Arc::new(AtomicU64::new(0)),
+            metered_at: std::sync::Mutex::new(std::time::Instant::now()),
+            codec_policy: CodecPolicy::default(),
+            single_screen_share: false,
+            subscriptions: std::sync::RwLock::new(HashMap::new()),
+            snapshot_interval: None,
+            metadata: None,
         }
     }
 
@@ -152,9 +236,10 @@ impl Room {
     }
 
     /// Returns `true` when the room still has capacity for another publisher.
+    /// Screen shares do not count against the limit.
     pub fn can_publish(&self) -> bool {
         let pubs = self.publishers.read().unwrap();
-        pubs.len() < self.max_publishers
+        camera_count(&pubs) < self.max_publishers
     }
 
     /// Insert a publisher into the room.
@@ -163,17 +248,17 @@ impl Room {
     /// reached.
     pub fn add_publisher(&self, publisher: Arc<Publisher>) -> Result<(), &'static str> {
         let mut pubs = self.publishers.write().unwrap();
-        if pubs.len() >= self.max_publishers {
+        if camera_count(&pubs) >= self.max_publishers {
             return Err("room is full");
         }
         pubs.insert(publisher.peer_id.clone(), publisher);
         Ok(())
     }
 
-    /// Remove a publisher by its peer id (no-op if absent).
-    pub fn remove_publisher(&self, peer_id: &str) {
+    /// Remove a publisher by its peer id, returning it if it was present.
+    pub fn remove_publisher(&self, peer_id: &str) -> Option<Arc<Publisher>> {
         let mut pubs = self.publishers.write().unwrap();
-        pubs.remove(peer_id);
+        pubs.remove(peer_id)
     }
 
     /// Snapshot of every publisher currently in the room.
@@ -182,16 +267,51 @@ impl Room {
         pubs.values().cloned().collect()
     }
 
-    /// Get all publishers except the one with `exclude_peer_id`.
+    /// Get all admitted publishers except the one with `exclude_peer_id`.
     /// Useful for conference mode: subscribe to everyone but yourself.
+    /// Peers still waiting in the lobby are not visible to others.
     pub fn get_other_publishers(&self, exclude_peer_id: &str) -> Vec<Arc<Publisher>> {
         let pubs = self.publishers.read().unwrap();
         pubs.values()
-            .filter(|p| p.peer_id != exclude_peer_id)
+            .filter(|p| p.peer_id != exclude_peer_id && p.is_admitted())
             .cloned()
             .collect()
     }
 
+    /// Publisher ids (`<peer_id>-screen`) of the active screen shares.
+    pub fn screen_shares(&self) -> Vec<String> {
+        let pubs = self.publishers.read().unwrap();
+        let mut shares: Vec<String> = pubs
+            .values()
+            .filter(|p| p.is_screen())
+            .map(|p| p.peer_id.clone())
+            .collect();
+        shares.sort();
+        shares
+    }
+
+    /// Peer IDs of everyone currently waiting in the lobby.
+    pub fn waiting_peers(&self) -> Vec<String> {
+        let pubs = self.publishers.read().unwrap();
+        pubs.values()
+            .filter(|p| !p.is_admitted())
+            .map(|p| p.peer_id.clone())
+            .collect()
+    }
+
+    /// Admit a waiting peer, turning on forwarding in both directions.
+    ///
+    /// Fails with `"peer not waiting"` when the peer is unknown or already
+    /// admitted.
+    pub fn admit(&self, peer_id: &str) -> Result<Arc<Publisher>, &'static str> {
+        let pubs = self.publishers.read().unwrap();
+        let publisher = pubs.get(peer_id).ok_or("peer not waiting")?;
+        if publisher.admitted.swap(true, Ordering::Relaxed) {
+            return Err("peer not waiting");
+        }
+        Ok(publisher.clone())
+    }
+
     /// Current number of publishers.
     pub fn publisher_count(&self) -> usize {
         let pubs = self.publishers.read().unwrap();
@@ -204,6 +324,19 @@ impl Room {
         self.subscriber_count.load(Ordering::Relaxed)
     }
 
+    /// Number of distinct participants, for quota accounting.
+    ///
+    /// In call and conference rooms every participant is a publisher (their
+    /// subscriber connections are not separate people); in broadcast rooms
+    /// subscribers are counted on top of the publisher.
+    pub fn participant_count(&self) -> u64 {
+        let publishers = camera_count(&self.publishers.read().unwrap()) as u64;
+        match self.room_type {
+            RoomType::Broadcast => publishers + self.subscriber_count(),
+            RoomType::Call | RoomType::Conference => publishers,
+        }
+    }
+
     /// Build a serialisable summary of this room for API responses.
     pub fn info(&self) -> RoomInfo {
         RoomInfo {
@@ -212,10 +345,59 @@ impl Room {
             publisher_count: self.publisher_count(),
             subscriber_count: self.subscriber_count(),
             created_at_secs: self.created_at.elapsed().as_secs(),
+            lobby_enabled: self.lobby_enabled,
+            waiting_count: if self.lobby_enabled { self.waiting_peers().len() } else { 0 },
+            codecs: self.negotiated_codecs(),
+            single_screen_share: self.single_screen_share,
+            screen_shares: self.screen_shares(),
+            snapshot_interval_secs: self.snapshot_interval.map(|d| d.as_secs()),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Publishing participants (screen shares excluded) with their metadata.
+    pub fn participants(&self) -> Vec<ParticipantInfo> {
+        let pubs = self.publishers.read().unwrap();
+        let mut list: Vec<ParticipantInfo> = pubs
+            .values()
+            .filter(|p| !p.is_screen())
+            .map(|p| ParticipantInfo {
+                peer_id: p.peer_id.clone(),
+                waiting: !p.is_admitted(),
+                screen_sharing: p.has_screen() || pubs.contains_key(&format!("{}-screen", p.peer_id)),
+                metadata: p.metadata.clone(),
+            })
+            .collect();
+        list.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        list
+    }
+
+    /// Distinct codecs currently negotiated by the room's publishers.
+    pub fn negotiated_codecs(&self) -> NegotiatedCodecs {
+        let mut codecs = NegotiatedCodecs::default();
+        let add = |list: &mut Vec<String>, slot: &std::sync::RwLock<Option<RTCRtpCodecCapability>>| {
+            if let Some(c) = slot.read().unwrap().as_ref() {
+                if !list.contains(&c.mime_type) {
+                    list.push(c.mime_type.clone());
+                }
+            }
+        };
+        for publisher in self.get_publishers() {
+            add(&mut codecs.video, &publisher.video_codec);
+            add(&mut codecs.video, &publisher.screen_codec);
+            add(&mut codecs.audio, &publisher.audio_codec);
         }
+        codecs.video.sort();
+        codecs.audio.sort();
+        codecs
     }
 }
 
+/// Publishers that are not screen shares.
+fn camera_count(pubs: &HashMap<String, Arc<Publisher>>) -> usize {
+    pubs.values().filter(|p| !p.is_screen()).count()
+}
+
 // ---------------------------------------------------------------------------
 // RoomInfo  (serialisable snapshot for the REST / JSON API)
 // ---------------------------------------------------------------------------
@@ -228,4 +410,35 @@ pub struct RoomInfo {
     pub subscriber_count: u64,
     /// Seconds elapsed since the room was created.
     pub created_at_secs: u64,
+    pub lobby_enabled: bool,
+    /// Peers waiting in the lobby for a host to admit them.
+    pub waiting_count: usize,
+    /// Codecs negotiated by the current publishers.
+    pub codecs: NegotiatedCodecs,
+    pub single_screen_share: bool,
+    /// Publisher ids of the active screen shares.
+    pub screen_shares: Vec<String>,
+    /// Seconds between moderation snapshots, when enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_interval_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// One publishing participant, for `GET /v1/rooms/:room_id/participants`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParticipantInfo {
+    pub peer_id: String,
+    /// Held in the lobby.
+    pub waiting: bool,
+    pub screen_sharing: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Mime types (e.g. `video/H264`) in use in a room.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NegotiatedCodecs {
+    pub video: Vec<String>,
+    pub audio: Vec<String>,
 }
@@ -1,16 +1,17 @@
 use axum::{
     extract::State,
-    http::HeaderMap,
+    http::{HeaderMap, StatusCode},
     Json,
 };
+use metrics::counter;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use webrtc::api::interceptor_registry::register_default_interceptors;
-use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
 use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::interceptor::registry::Registry;
@@ -19,23 +20,14 @@ use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
-use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
 use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
 
-use crate::config::Config;
+use crate::codec::CodecPolicy;
 use crate::error::ApiError;
-use crate::room::Publisher;
-
-// ─── JWT extraction helper ───────────────────────────────────────────────────
-
-fn extract_bearer_token(headers: &HeaderMap) -> Result<&str, ApiError> {
-    headers
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.strip_prefix("Bearer "))
-        .ok_or_else(ApiError::auth_header_missing)
-}
+use crate::room::{Publisher, Room};
+use crate::subscription::{Subscription, TrackKind};
 
 // ─── DTOs ────────────────────────────────────────────────────────────────────
 
@@ -57,15 +49,23 @@ pub struct SdpAnswer {
     pub sdp: String,
     #[serde(rename = "type")]
     pub sdp_type: String,
+    /// Set on answers that forward media: the id to pause / resume tracks
+    /// through `POST /sfu/subscriptions/:id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_id: Option<String>,
 }
 
 // ─── PeerConnection factory ─────────────────────────────────────────────────
 
 /// Create a new `RTCPeerConnection` using the ICE servers from the
-/// production configuration (STUN + TURN).
-async fn create_peer_connection(cfg: &Config) -> Result<Arc<RTCPeerConnection>, webrtc::Error> {
+/// production configuration (STUN + TURN) and the room's codec policy.
+async fn create_peer_connection(
+    state: &crate::AppState,
+    codecs: &CodecPolicy,
+) -> Result<Arc<RTCPeerConnection>, webrtc::Error> {
+    let cfg = &state.config;
     let mut media_engine = MediaEngine::default();
-    media_engine.register_default_codecs()?;
+    codecs.register(&mut media_engine)?;
 
     let mut registry = Registry::new();
     registry = register_default_interceptors(registry, &mut media_engine)?;
@@ -90,6 +90,9 @@ async fn create_peer_connection(cfg: &Config) -> Result<Arc<RTCPeerConnection>,
         vec![nat_ip],
         webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType::Host,
     );
+    if let Some(net) = &state.vnet {
+        setting_engine.set_vnet(Some(Arc::clone(net)));
+    }
 
     let api = APIBuilder::new()
         .with_media_engine(media_engine)
@@ -114,8 +117,16 @@ async fn create_peer_connection(cfg: &Config) -> Result<Arc<RTCPeerConnection>,
         ..Default::default()
     };
 
-    let pc = api.new_peer_connection(config).await?;
-    Ok(Arc::new(pc))
+    let pc = Arc::new(api.new_peer_connection(config).await?);
+    crate::telemetry::instrument_peer_connection(&pc);
+    Ok(pc)
+}
+
+/// Reject a publisher offer that carries no codec allowed in the room.
+fn check_offer_codecs(room: &Room, sdp: &str) -> Result<(), ApiError> {
+    room.codec_policy.check_offer(sdp).map_err(|kind| {
+        ApiError::codec_not_allowed(&kind.to_string(), &room.codec_policy.allowed_names(kind))
+    })
 }
 
 // ─── ICE gathering helper ───────────────────────────────────────────────────
@@ -173,6 +184,7 @@ async fn exchange_sdp(
     Ok(SdpAnswer {
         sdp: local_desc.sdp,
         sdp_type: "answer".to_string(),
+        subscription_id: None,
     })
 }
 
@@ -187,14 +199,20 @@ async fn exchange_sdp(
 /// (whichever comes first).  A timeout is NOT an error — it just means we
 /// fall through to the default VP8 codec (which may work, but might produce
 /// a black frame until a keyframe arrives).
-async fn wait_for_publisher_ready(publisher: &Publisher, timeout_secs: u64) {
-    if publisher.video_codec.read().unwrap().is_some() {
+pub(crate) async fn wait_for_publisher_ready(publisher: &Publisher, timeout_secs: u64) {
+    // Screen-share publishers only carry a screen track.
+    let codec = if publisher.is_screen() {
+        &publisher.screen_codec
+    } else {
+        &publisher.video_codec
+    };
+    if codec.read().unwrap().is_some() {
         return; // Already ready.
     }
     let max_wait = std::time::Duration::from_secs(timeout_secs);
     let poll_interval = std::time::Duration::from_millis(100);
     let start = std::time::Instant::now();
-    while publisher.video_codec.read().unwrap().is_none() {
+    while codec.read().unwrap().is_none() {
         if start.elapsed() > max_wait {
             warn!(
                 "Timed out waiting for publisher '{}' video codec ({}s)",
@@ -204,7 +222,7 @@ async fn wait_for_publisher_ready(publisher: &Publisher, timeout_secs: u64) {
         }
         tokio::time::sleep(poll_interval).await;
     }
-    if publisher.video_codec.read().unwrap().is_some() {
+    if codec.read().unwrap().is_some() {
         info!(
             "Publisher '{}' video codec ready after {:?}",
             publisher.peer_id,
@@ -220,7 +238,15 @@ fn spawn_fanout_task(
     track: Arc<TrackLocalStaticRTP>,
     cancel: CancellationToken,
     label: &'static str,
+    paused: Arc<AtomicBool>,
+    egress: Arc<AtomicU64>,
 ) {
+    let kind = crate::telemetry::fanout_kind(label);
+    let packets_total = counter!("sfu_forwarded_packets_total", "kind" => kind);
+    let bytes_total = counter!("sfu_forwarded_bytes_total", "kind" => kind);
+    let lagged_total = counter!("sfu_fanout_lagged_total", "kind" => kind);
+    let dropped_total = counter!("sfu_fanout_dropped_packets_total", "kind" => kind);
+
     tokio::spawn(async move {
         loop {
             tokio::select! {
@@ -231,13 +257,25 @@ fn spawn_fanout_task(
                 result = rx.recv() => {
                     match result {
                         Ok(pkt) => {
-                            if let Err(e) = track.write_rtp(&pkt).await {
-                                warn!("{label} write_rtp error: {e}");
-                                break;
+                            if paused.load(Ordering::Relaxed) {
+                                continue;
+                            }
+                            match track.write_rtp(&pkt).await {
+                                Ok(n) => {
+                                    egress.fetch_add(n as u64, Ordering::Relaxed);
+                                    packets_total.increment(1);
+                                    bytes_total.increment(n as u64);
+                                }
+                                Err(e) => {
+                                    warn!("{label} write_rtp error: {e}");
+                                    break;
+                                }
                             }
                         }
                         Err(broadcast::error::RecvError::Lagged(n)) => {
                             warn!("{label} subscriber lagged, skipped {n} packets");
+                            lagged_total.increment(1);
+                            dropped_total.increment(n);
                         }
                         Err(broadcast::error::RecvError::Closed) => {
                             info!("{label} publisher closed channel");
@@ -252,12 +290,24 @@ fn spawn_fanout_task(
 
 /// Dynamic fan-out variant that accepts an owned String label.
 /// Used for conference mode where labels are built at runtime.
+///
+/// Packets are dropped while `admitted` is false (receiver in a lobby) or
+/// the subscriber paused the track.
 fn spawn_fanout_task_dynamic(
     mut rx: broadcast::Receiver<webrtc::rtp::packet::Packet>,
     track: Arc<TrackLocalStaticRTP>,
     cancel: CancellationToken,
     label: String,
+    admitted: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    egress: Arc<AtomicU64>,
 ) {
+    let kind = crate::telemetry::fanout_kind(&label);
+    let packets_total = counter!("sfu_forwarded_packets_total", "kind" => kind);
+    let bytes_total = counter!("sfu_forwarded_bytes_total", "kind" => kind);
+    let lagged_total = counter!("sfu_fanout_lagged_total", "kind" => kind);
+    let dropped_total = counter!("sfu_fanout_dropped_packets_total", "kind" => kind);
+
     tokio::spawn(async move {
         loop {
             tokio::select! {
@@ -268,13 +318,25 @@ fn spawn_fanout_task_dynamic(
                 result = rx.recv() => {
                     match result {
                         Ok(pkt) => {
-                            if let Err(e) = track.write_rtp(&pkt).await {
-                                warn!("{label} write_rtp error: {e}");
-                                break;
+                            if !admitted.load(Ordering::Relaxed) || paused.load(Ordering::Relaxed) {
+                                continue;
+                            }
+                            match track.write_rtp(&pkt).await {
+                                Ok(n) => {
+                                    egress.fetch_add(n as u64, Ordering::Relaxed);
+                                    packets_total.increment(1);
+                                    bytes_total.increment(n as u64);
+                                }
+                                Err(e) => {
+                                    warn!("{label} write_rtp error: {e}");
+                                    break;
+                                }
                             }
                         }
                         Err(broadcast::error::RecvError::Lagged(n)) => {
                             warn!("{label} subscriber lagged, skipped {n} packets");
+                            lagged_total.increment(1);
+                            dropped_total.increment(n);
                         }
                         Err(broadcast::error::RecvError::Closed) => {
                             info!("{label} publisher closed channel");
@@ -291,6 +353,7 @@ fn spawn_fanout_task_dynamic(
 
 /// Configure the on_track handler for a publisher. If `is_screen` is true,
 /// incoming video RTP is routed to `screen_tx` instead of `video_tx`.
+/// Nothing is forwarded while the publisher waits in a lobby.
 fn setup_publisher_on_track(
     pc: &Arc<RTCPeerConnection>,
     publisher: &Arc<Publisher>,
@@ -319,10 +382,15 @@ fn setup_publisher_on_track(
                         .store(track.ssrc() as u64, Ordering::Relaxed);
 
                     let tx = publisher.screen_tx.clone();
+                    let admitted = publisher.admitted.clone();
                     tokio::spawn(async move {
                         loop {
                             match track.read_rtp().await {
-                                Ok((pkt, _)) => { let _ = tx.send(pkt); }
+                                Ok((pkt, _)) => {
+                                    if admitted.load(Ordering::Relaxed) {
+                                        let _ = tx.send(pkt);
+                                    }
+                                }
                                 Err(e) => {
                                     warn!("RTP read error (screen): {e}");
                                     break;
@@ -338,10 +406,15 @@ fn setup_publisher_on_track(
                         .store(track.ssrc() as u64, Ordering::Relaxed);
 
                     let tx = publisher.video_tx.clone();
+                    let admitted = publisher.admitted.clone();
                     tokio::spawn(async move {
                         loop {
                             match track.read_rtp().await {
-                                Ok((pkt, _)) => { let _ = tx.send(pkt); }
+                                Ok((pkt, _)) => {
+                                    if admitted.load(Ordering::Relaxed) {
+                                        let _ = tx.send(pkt);
+                                    }
+                                }
                                 Err(e) => {
                                     warn!("RTP read error (video): {e}");
                                     break;
@@ -355,10 +428,15 @@ fn setup_publisher_on_track(
                     Some(track.codec().capability.clone());
 
                 let tx = publisher.audio_tx.clone();
+                let admitted = publisher.admitted.clone();
                 tokio::spawn(async move {
                     loop {
                         match track.read_rtp().await {
-                            Ok((pkt, _)) => { let _ = tx.send(pkt); }
+                            Ok((pkt, _)) => {
+                                if admitted.load(Ordering::Relaxed) {
+                                    let _ = tx.send(pkt);
+                                }
+                            }
                             Err(e) => {
                                 warn!("RTP read error (audio): {e}");
                                 break;
@@ -407,6 +485,20 @@ fn spawn_pli_sender(publisher: &Arc<Publisher>) {
     });
 }
 
+/// Ask a publisher for an immediate keyframe on its camera and screen tracks.
+pub async fn request_keyframe(publisher: &Publisher) {
+    for ssrc in [&publisher.video_ssrc, &publisher.screen_ssrc] {
+        let ssrc = ssrc.load(Ordering::Relaxed);
+        if ssrc != 0 {
+            let pli = PictureLossIndication {
+                sender_ssrc: 0,
+                media_ssrc: ssrc as u32,
+            };
+            let _ = publisher.pc.write_rtcp(&[Box::new(pli)]).await;
+        }
+    }
+}
+
 // ─── POST /sfu/publish ──────────────────────────────────────────────────────
 
 pub async fn sfu_publish(
@@ -415,13 +507,11 @@ pub async fn sfu_publish(
     Json(offer): Json<SdpOffer>,
 ) -> Result<Json<SdpAnswer>, ApiError> {
     // 1. Verify JWT token.
-    let token_str = extract_bearer_token(&headers)?;
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let claims = crate::auth::verify_token(&state.jwt_secret, token_str)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
-            _ => ApiError::token_invalid(),
-        })?;
+    // New peers go to another node while this one drains.
+    crate::drain::ensure_accepting(&state)?;
 
     if claims.role != "publish" && claims.role != "call" {
         return Err(ApiError::role_insufficient(&claims.role));
@@ -441,6 +531,10 @@ pub async fn sfu_publish(
         ApiError::room_not_found(&room_id)
     })?;
 
+    if !is_screen {
+        crate::usage::check_participant_quota(&state, &room).await?;
+    }
+
     // 3. Screen shares get a separate peer_id suffix so they don't
     //    collide with the camera publisher entry.
     let effective_peer_id = if is_screen {
@@ -455,18 +549,33 @@ pub async fn sfu_publish(
         return Err(ApiError::room_full(&room_id));
     }
 
+    check_offer_codecs(&room, &offer.sdp)?;
+
+    // Single-use tokens are reserved here and spent once the peer is in.
+    let spent = if is_screen {
+        None
+    } else {
+        Some(state.revocations.consume(&claims)?)
+    };
+
     // 4. Create PeerConnection (using dynamic ICE config).
-    let pc = create_peer_connection(&state.config).await.map_err(|e| {
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
         warn!("sfu_publish: failed to create PeerConnection: {e}");
         ApiError::peer_connection_failed()
     })?;
+    state.revocations.track(&claims, &pc);
 
     // 5. Create Publisher.
     let publisher = if is_screen {
-        Arc::new(Publisher::new_screen(effective_peer_id.clone(), pc.clone()))
+        Publisher::new_screen(effective_peer_id.clone(), pc.clone())
     } else {
-        Arc::new(Publisher::new(effective_peer_id.clone(), pc.clone()))
+        Publisher::new(effective_peer_id.clone(), pc.clone())
     };
+    let publisher = Arc::new(
+        publisher
+            .with_metadata(claims.metadata.clone())
+            .with_role(claims.role.clone()),
+    );
 
     // 6. on_track — forward incoming RTP to broadcast channels.
     setup_publisher_on_track(&pc, &publisher, &room_id, is_screen);
@@ -476,11 +585,13 @@ pub async fn sfu_publish(
         let room_clone = room.clone();
         let pid = effective_peer_id.clone();
         let rid = room_id.clone();
+        let role = claims.role.clone();
         let state_clone = state.clone();
         pc.on_peer_connection_state_change(Box::new(move |conn_state| {
             let room = room_clone.clone();
             let pid = pid.clone();
             let rid = rid.clone();
+            let role = role.clone();
             let state = state_clone.clone();
             Box::pin(async move {
                 match conn_state {
@@ -488,12 +599,14 @@ pub async fn sfu_publish(
                     | RTCPeerConnectionState::Disconnected
                     | RTCPeerConnectionState::Closed => {
                         info!("Publisher '{pid}' disconnected from room '{rid}'");
-                        room.remove_publisher(&pid);
+                        remove_participant(&state, &room, &pid, &role);
                         if room.publisher_count() == 0
                             && room.room_type == crate::room::RoomType::Broadcast
                         {
-                            let mut rooms = state.rooms.write().unwrap();
-                            rooms.remove(&rid);
+                            let removed = state.rooms.write().unwrap().remove(&rid);
+                            if let Some(removed) = removed {
+                                crate::usage::flush_room(&state, &removed).await;
+                            }
                             info!("Room '{rid}' removed (no publishers left)");
                         }
                     }
@@ -516,6 +629,15 @@ pub async fn sfu_publish(
             warn!("sfu_publish: room '{room_id}' became full during SDP exchange");
             ApiError::room_full(&room_id)
         })?;
+        if let Some(spent) = spent {
+            spent.commit();
+        }
+        state.event_bus.emit(crate::events::LiveRelayEvent::participant_joined(
+            &room_id,
+            &peer_id,
+            &claims.role,
+            claims.metadata.as_ref(),
+        ));
     }
 
     // 10. Spawn periodic PLI sender.
@@ -535,13 +657,11 @@ pub async fn sfu_subscribe(
     Json(offer): Json<SdpOffer>,
 ) -> Result<Json<SdpAnswer>, ApiError> {
     // 1. Verify JWT token.
-    let token_str = extract_bearer_token(&headers)?;
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let claims = crate::auth::verify_token(&state.jwt_secret, token_str)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
-            _ => ApiError::token_invalid(),
-        })?;
+    // New peers go to another node while this one drains.
+    crate::drain::ensure_accepting(&state)?;
 
     if claims.role != "subscribe" && claims.role != "call" {
         return Err(ApiError::role_insufficient(&claims.role));
@@ -559,6 +679,14 @@ pub async fn sfu_subscribe(
         ApiError::room_not_found(&room_id)
     })?;
 
+    crate::usage::check_participant_quota(&state, &room).await?;
+
+    let max_subscribers = state.settings.read().unwrap().max_subscribers_per_room;
+    if room.subscriber_count() >= max_subscribers {
+        warn!("sfu_subscribe: room '{room_id}' has {max_subscribers} subscriber(s)");
+        return Err(ApiError::room_full(&room_id));
+    }
+
     let publishers = room.get_publishers();
     if publishers.is_empty() {
         warn!("sfu_subscribe: room '{room_id}' has no publishers");
@@ -592,22 +720,19 @@ pub async fn sfu_subscribe(
     // 3. Get codecs from publisher.
     let video_codec = publisher
         .video_codec.read().unwrap().clone()
-        .unwrap_or_else(|| RTCRtpCodecCapability {
-            mime_type: MIME_TYPE_VP8.to_string(),
-            ..Default::default()
-        });
+        .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
     let audio_codec = publisher
         .audio_codec.read().unwrap().clone()
-        .unwrap_or_else(|| RTCRtpCodecCapability {
-            mime_type: MIME_TYPE_OPUS.to_string(),
-            ..Default::default()
-        });
+        .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Audio));
+
+    let spent = state.revocations.consume(&claims)?;
 
     // 4. Create PeerConnection.
-    let pc = create_peer_connection(&state.config).await.map_err(|e| {
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
         warn!("sfu_subscribe: failed to create PeerConnection: {e}");
         ApiError::peer_connection_failed()
     })?;
+    state.revocations.track(&claims, &pc);
 
     // 5. Create local tracks — camera video + audio.
     let video_track = Arc::new(TrackLocalStaticRTP::new(
@@ -646,10 +771,7 @@ pub async fn sfu_subscribe(
             };
             let codec = source.screen_codec.read().unwrap().clone()
                 .or_else(|| source.video_codec.read().unwrap().clone())
-                .unwrap_or_else(|| RTCRtpCodecCapability {
-                    mime_type: MIME_TYPE_VP8.to_string(),
-                    ..Default::default()
-                });
+                .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
             let track = Arc::new(TrackLocalStaticRTP::new(
                 codec,
                 "screen".to_string(),
@@ -666,17 +788,21 @@ pub async fn sfu_subscribe(
         }
     };
 
-    // 6. Cancellation token.
+    // 6. Cancellation token + per-track pause gates.
     let cancel = CancellationToken::new();
+    let mut subscription = Subscription::new();
+    let subscription_id = subscription.id.clone();
 
     // 7. Monitor connection state.
     {
         let cancel_clone = cancel.clone();
         let room_clone = room.clone();
+        let sub_id = subscription_id.clone();
         pc.on_peer_connection_state_change(Box::new(
             move |conn_state: RTCPeerConnectionState| {
                 let cancel = cancel_clone.clone();
                 let room = room_clone.clone();
+                let sub_id = sub_id.clone();
                 Box::pin(async move {
                     info!("subscriber connection state: {conn_state}");
                     match conn_state {
@@ -684,6 +810,7 @@ pub async fn sfu_subscribe(
                         | RTCPeerConnectionState::Disconnected
                         | RTCPeerConnectionState::Closed => {
                             cancel.cancel();
+                            room.subscriptions.write().unwrap().remove(&sub_id);
                             room.subscriber_count
                                 .fetch_sub(1, Ordering::Relaxed);
                         }
@@ -695,28 +822,46 @@ pub async fn sfu_subscribe(
     }
 
     // 8. SDP exchange.
-    let answer = exchange_sdp(&pc, offer.sdp).await?;
+    let mut answer = exchange_sdp(&pc, offer.sdp).await?;
 
     // 9. Subscribe to broadcast channels — camera.
     let video_rx = publisher.video_tx.subscribe();
     let audio_rx = publisher.audio_tx.subscribe();
 
     // 10. Spawn fan-out tasks.
-    spawn_fanout_task(video_rx, Arc::clone(&video_track), cancel.clone(), "video");
-    spawn_fanout_task(audio_rx, Arc::clone(&audio_track), cancel.clone(), "audio");
+    spawn_fanout_task(
+        video_rx, Arc::clone(&video_track), cancel.clone(), "video",
+        subscription.track(&publisher.peer_id, TrackKind::Video),
+        room.egress_bytes.clone(),
+    );
+    spawn_fanout_task(
+        audio_rx, Arc::clone(&audio_track), cancel.clone(), "audio",
+        subscription.track(&publisher.peer_id, TrackKind::Audio),
+        room.egress_bytes.clone(),
+    );
 
     // 10b. Screen share fan-out.
     if let Some((track, has_inline, screen_pub_opt)) = screen_track {
-        let screen_rx = if has_inline {
-            publisher.screen_tx.subscribe()
+        let (screen_rx, source_id) = if has_inline {
+            (publisher.screen_tx.subscribe(), &publisher.peer_id)
         } else if let Some(ref sp) = screen_pub_opt {
-            sp.video_tx.subscribe()
+            (sp.screen_tx.subscribe(), &sp.peer_id)
         } else {
-            publisher.screen_tx.subscribe()
+            (publisher.screen_tx.subscribe(), &publisher.peer_id)
         };
-        spawn_fanout_task(screen_rx, track, cancel.clone(), "screen");
+        spawn_fanout_task(
+            screen_rx, track, cancel.clone(), "screen",
+            subscription.track(source_id, TrackKind::Screen),
+            room.egress_bytes.clone(),
+        );
     }
 
+    room.subscriptions
+        .write()
+        .unwrap()
+        .insert(subscription_id.clone(), Arc::new(subscription));
+    answer.subscription_id = Some(subscription_id);
+
     // 11. Request immediate keyframe.
     let ssrc = publisher.video_ssrc.load(Ordering::Relaxed);
     if ssrc != 0 {
@@ -732,6 +877,7 @@ pub async fn sfu_subscribe(
         .subscriber_count
         .fetch_add(1, Ordering::Relaxed)
         + 1;
+    spent.commit();
     info!("Room '{room_id}' now has {count} subscriber(s)");
 
     Ok(Json(answer))
@@ -745,13 +891,11 @@ pub async fn sfu_call(
     Json(offer): Json<SdpOffer>,
 ) -> Result<Json<SdpAnswer>, ApiError> {
     // 1. Verify JWT token.
-    let token_str = extract_bearer_token(&headers)?;
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let claims = crate::auth::verify_token(&state.jwt_secret, token_str)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
-            _ => ApiError::token_invalid(),
-        })?;
+    // New peers go to another node while this one drains.
+    crate::drain::ensure_accepting(&state)?;
 
     if claims.role != "call" {
         return Err(ApiError::role_insufficient(&claims.role));
@@ -770,19 +914,30 @@ pub async fn sfu_call(
         ApiError::room_not_found(&room_id)
     })?;
 
+    crate::usage::check_participant_quota(&state, &room).await?;
+
     if room.room_type != crate::room::RoomType::Call {
         warn!("sfu_call: room '{room_id}' is not a call room");
         return Err(ApiError::room_type_mismatch("call", "broadcast"));
     }
 
+    check_offer_codecs(&room, &offer.sdp)?;
+
+    let spent = state.revocations.consume(&claims)?;
+
     // 3. Create PeerConnection (using dynamic ICE config).
-    let pc = create_peer_connection(&state.config).await.map_err(|e| {
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
         warn!("sfu_call: failed to create PeerConnection: {e}");
         ApiError::peer_connection_failed()
     })?;
+    state.revocations.track(&claims, &pc);
 
     // 4. Create Publisher for this peer (call = each peer publishes).
-    let publisher = Arc::new(Publisher::new(peer_id.clone(), pc.clone()));
+    let publisher = Arc::new(
+        Publisher::new(peer_id.clone(), pc.clone())
+            .with_metadata(claims.metadata.clone())
+            .with_role(claims.role.clone()),
+    );
 
     // 5. Setup on_track for incoming media.
     setup_publisher_on_track(&pc, &publisher, &room_id, false);
@@ -790,25 +945,21 @@ pub async fn sfu_call(
     // 6. Check if the other peer is already in the room — subscribe to them.
     let other_publisher = {
         let pubs = room.get_publishers();
-        pubs.into_iter().find(|p| p.peer_id != peer_id)
+        pubs.into_iter().find(|p| p.peer_id != peer_id && !p.is_screen())
     };
 
     let cancel = CancellationToken::new();
+    let mut subscription = Subscription::new();
+    let subscription_id = subscription.id.clone();
 
     if let Some(other) = &other_publisher {
         // Wait for the other peer's on_track to fire before reading codecs.
         wait_for_publisher_ready(other, 10).await;
 
         let video_codec = other.video_codec.read().unwrap().clone()
-            .unwrap_or_else(|| RTCRtpCodecCapability {
-                mime_type: MIME_TYPE_VP8.to_string(),
-                ..Default::default()
-            });
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
         let audio_codec = other.audio_codec.read().unwrap().clone()
-            .unwrap_or_else(|| RTCRtpCodecCapability {
-                mime_type: MIME_TYPE_OPUS.to_string(),
-                ..Default::default()
-            });
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Audio));
 
         let video_track = Arc::new(TrackLocalStaticRTP::new(
             video_codec, "video".to_string(), "liverelay".to_string(),
@@ -831,8 +982,16 @@ pub async fn sfu_call(
         let video_rx = other.video_tx.subscribe();
         let audio_rx = other.audio_tx.subscribe();
 
-        spawn_fanout_task(video_rx, video_track, cancel.clone(), "call-video");
-        spawn_fanout_task(audio_rx, audio_track, cancel.clone(), "call-audio");
+        spawn_fanout_task(
+            video_rx, video_track, cancel.clone(), "call-video",
+            subscription.track(&other.peer_id, TrackKind::Video),
+            room.egress_bytes.clone(),
+        );
+        spawn_fanout_task(
+            audio_rx, audio_track, cancel.clone(), "call-audio",
+            subscription.track(&other.peer_id, TrackKind::Audio),
+            room.egress_bytes.clone(),
+        );
 
         // Request keyframe from other peer.
         let ssrc = other.video_ssrc.load(Ordering::Relaxed);
@@ -851,24 +1010,34 @@ pub async fn sfu_call(
         let room_clone = room.clone();
         let pid = peer_id.clone();
         let rid = room_id.clone();
+        let role = claims.role.clone();
         let state_clone = state.clone();
+        let sub_id = subscription_id.clone();
         pc.on_peer_connection_state_change(Box::new(move |conn_state| {
             let cancel = cancel_clone.clone();
             let room = room_clone.clone();
             let pid = pid.clone();
             let rid = rid.clone();
+            let role = role.clone();
             let state = state_clone.clone();
+            let sub_id = sub_id.clone();
             Box::pin(async move {
                 match conn_state {
                     RTCPeerConnectionState::Failed
                     | RTCPeerConnectionState::Disconnected
                     | RTCPeerConnectionState::Closed => {
                         cancel.cancel();
-                        room.remove_publisher(&pid);
+                        room.subscriptions.write().unwrap().remove(&sub_id);
+                        if let Some(screen) = end_screen_share(&state, &room, &pid, None) {
+                            let _ = screen.pc.close().await;
+                        }
+                        remove_participant(&state, &room, &pid, &role);
                         info!("Call peer '{pid}' disconnected from room '{rid}'");
                         if room.publisher_count() == 0 {
-                            let mut rooms = state.rooms.write().unwrap();
-                            rooms.remove(&rid);
+                            let removed = state.rooms.write().unwrap().remove(&rid);
+                            if let Some(removed) = removed {
+                                crate::usage::flush_room(&state, &removed).await;
+                            }
                             info!("Call room '{rid}' removed (empty)");
                         }
                     }
@@ -879,15 +1048,30 @@ pub async fn sfu_call(
     }
 
     // 8. SDP exchange.
-    let answer = exchange_sdp(&pc, offer.sdp).await?;
+    let mut answer = exchange_sdp(&pc, offer.sdp).await?;
 
     // 9. Add publisher to room.
     room.add_publisher(publisher).map_err(|_| {
         warn!("sfu_call: room '{room_id}' is full");
         ApiError::room_full(&room_id)
     })?;
+    spent.commit();
+
+    if other_publisher.is_some() {
+        room.subscriptions
+            .write()
+            .unwrap()
+            .insert(subscription_id.clone(), Arc::new(subscription));
+        answer.subscription_id = Some(subscription_id);
+    }
 
     info!("Call peer '{peer_id}' joined room '{room_id}'");
+    state.event_bus.emit(crate::events::LiveRelayEvent::participant_joined(
+        &room_id,
+        &peer_id,
+        &claims.role,
+        claims.metadata.as_ref(),
+    ));
     Ok(Json(answer))
 }
 
@@ -925,8 +1109,21 @@ pub struct ConferenceAnswer {
     /// Peer IDs of publishers present when you joined (whose tracks are
     /// included in this SDP answer).
     pub participants: Vec<String>,
+    /// Token metadata of those participants, keyed by peer_id (peers
+    /// without metadata are omitted).
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub participant_metadata: std::collections::HashMap<String, serde_json::Value>,
     /// Your own peer_id (from the JWT `sub` claim).
     pub peer_id: String,
+    /// True when you are held in the room's lobby.  Media starts flowing
+    /// once a host admits you.
+    pub waiting: bool,
+    /// Pause / resume the tracks of this answer through
+    /// `POST /sfu/subscriptions/:id`.
+    pub subscription_id: String,
+    /// Active screen shares.  They are not part of this answer: receive each
+    /// one via `POST /sfu/conference/subscribe` with it as `target_peer_id`.
+    pub screen_shares: Vec<String>,
 }
 
 /// POST /sfu/conference — join a conference room (publish + subscribe).
@@ -935,20 +1132,19 @@ pub async fn sfu_conference(
     headers: HeaderMap,
     Json(offer): Json<SdpOffer>,
 ) -> Result<Json<ConferenceAnswer>, ApiError> {
-    let token_str = extract_bearer_token(&headers)?;
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let claims = crate::auth::verify_token(&state.jwt_secret, token_str)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
-            _ => ApiError::token_invalid(),
-        })?;
+    // New peers go to another node while this one drains.
+    crate::drain::ensure_accepting(&state)?;
 
-    if claims.role != "conference" && claims.role != "call" {
+    if claims.role != "conference" && claims.role != "call" && claims.role != "host" {
         return Err(ApiError::role_insufficient(&claims.role));
     }
 
     let room_id = claims.room_id.clone();
     let peer_id = claims.sub.clone();
+    let is_host = claims.role == "host";
 
     let room = {
         let rooms = state.rooms.read().unwrap();
@@ -959,6 +1155,8 @@ pub async fn sfu_conference(
         ApiError::room_not_found(&room_id)
     })?;
 
+    crate::usage::check_participant_quota(&state, &room).await?;
+
     if room.room_type != crate::room::RoomType::Conference {
         return Err(ApiError::room_type_mismatch(
             "conference",
@@ -970,41 +1168,62 @@ pub async fn sfu_conference(
         return Err(ApiError::room_full(&room_id));
     }
 
-    // Get existing publishers (everyone except ourselves).
-    let other_publishers = room.get_other_publishers(&peer_id);
+    // Get existing publishers (everyone except ourselves).  Screen shares
+    // are delivered on separate subscribe PeerConnections.
+    let (screen_publishers, other_publishers): (Vec<_>, Vec<_>) = room
+        .get_other_publishers(&peer_id)
+        .into_iter()
+        .partition(|p| p.is_screen());
+
+    check_offer_codecs(&room, &offer.sdp)?;
+
+    let spent = state.revocations.consume(&claims)?;
 
     // Create PeerConnection.
-    let pc = create_peer_connection(&state.config).await.map_err(|e| {
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
         warn!("sfu_conference: failed to create PeerConnection: {e}");
         ApiError::peer_connection_failed()
     })?;
-
-    // Create Publisher for this peer.
-    let publisher = Arc::new(Publisher::new(peer_id.clone(), pc.clone()));
+    state.revocations.track(&claims, &pc);
+
+    // Create Publisher for this peer.  In lobby rooms everyone but a host
+    // starts out waiting for admission.
+    let publisher = Arc::new(
+        Publisher::new(peer_id.clone(), pc.clone())
+            .with_metadata(claims.metadata.clone())
+            .with_role(claims.role.clone()),
+    );
+    let waiting = room.lobby_enabled && !is_host;
+    publisher.admitted.store(!waiting, Ordering::Relaxed);
 
     // Setup on_track (publish path).
     setup_publisher_on_track(&pc, &publisher, &room_id, false);
 
+    // Hosts drive the lobby over a DataChannel.
+    if is_host && room.lobby_enabled {
+        crate::lobby::attach_host_channel(&pc, &room, &state, &peer_id);
+    }
+
     // For each existing publisher, add receive tracks.
     let cancel = CancellationToken::new();
     let mut participant_list: Vec<String> = Vec::new();
+    let mut participant_metadata = std::collections::HashMap::new();
+    let mut subscription = Subscription::new();
+    let subscription_id = subscription.id.clone();
 
     for other in &other_publishers {
         participant_list.push(other.peer_id.clone());
+        if let Some(metadata) = &other.metadata {
+            participant_metadata.insert(other.peer_id.clone(), metadata.clone());
+        }
 
         // Wait for each publisher's codec to be ready.
         wait_for_publisher_ready(other, 10).await;
 
         let video_codec = other.video_codec.read().unwrap().clone()
-            .unwrap_or_else(|| RTCRtpCodecCapability {
-                mime_type: MIME_TYPE_VP8.to_string(),
-                ..Default::default()
-            });
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
         let audio_codec = other.audio_codec.read().unwrap().clone()
-            .unwrap_or_else(|| RTCRtpCodecCapability {
-                mime_type: MIME_TYPE_OPUS.to_string(),
-                ..Default::default()
-            });
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Audio));
 
         let short_id = &other.peer_id[..8.min(other.peer_id.len())];
         let stream_id = format!("lr-{short_id}");
@@ -1037,10 +1256,16 @@ pub async fn sfu_conference(
         spawn_fanout_task_dynamic(
             video_rx, video_track, cancel.clone(),
             format!("conf-video-{short_id}"),
+            publisher.admitted.clone(),
+            subscription.track(&other.peer_id, TrackKind::Video),
+            room.egress_bytes.clone(),
         );
         spawn_fanout_task_dynamic(
             audio_rx, audio_track, cancel.clone(),
             format!("conf-audio-{short_id}"),
+            publisher.admitted.clone(),
+            subscription.track(&other.peer_id, TrackKind::Audio),
+            room.egress_bytes.clone(),
         );
 
         // Request keyframe.
@@ -1060,25 +1285,36 @@ pub async fn sfu_conference(
         let room_clone = room.clone();
         let pid = peer_id.clone();
         let rid = room_id.clone();
+        let role = claims.role.clone();
         let state_clone = state.clone();
+        let sub_id = subscription_id.clone();
         pc.on_peer_connection_state_change(Box::new(move |conn_state| {
             let cancel = cancel_clone.clone();
             let room = room_clone.clone();
             let pid = pid.clone();
             let rid = rid.clone();
+            let role = role.clone();
             let state = state_clone.clone();
+            let sub_id = sub_id.clone();
             Box::pin(async move {
                 match conn_state {
                     RTCPeerConnectionState::Failed
                     | RTCPeerConnectionState::Disconnected
                     | RTCPeerConnectionState::Closed => {
                         cancel.cancel();
-                        room.remove_publisher(&pid);
+                        room.subscriptions.write().unwrap().remove(&sub_id);
+                        if let Some(screen) = end_screen_share(&state, &room, &pid, None) {
+                            let _ = screen.pc.close().await;
+                        }
+                        remove_participant(&state, &room, &pid, &role);
+                        room.lobby_channels.write().unwrap().remove(&pid);
                         room.subscriber_count.fetch_sub(1, Ordering::Relaxed);
                         info!("Conference peer '{pid}' disconnected from room '{rid}'");
                         if room.publisher_count() == 0 {
-                            let mut rooms = state.rooms.write().unwrap();
-                            rooms.remove(&rid);
+                            let removed = state.rooms.write().unwrap().remove(&rid);
+                            if let Some(removed) = removed {
+                                crate::usage::flush_room(&state, &removed).await;
+                            }
                             info!("Conference room '{rid}' removed (empty)");
                         }
                     }
@@ -1096,23 +1332,49 @@ pub async fn sfu_conference(
         warn!("sfu_conference: room '{room_id}' is full");
         ApiError::room_full(&room_id)
     })?;
+    spent.commit();
 
     // Bump subscriber count.
     room.subscriber_count.fetch_add(1, Ordering::Relaxed);
+    room.subscriptions
+        .write()
+        .unwrap()
+        .insert(subscription_id.clone(), Arc::new(subscription));
 
     // Start PLI sender.
     spawn_pli_sender(&publisher);
 
-    info!(
-        "Conference peer '{peer_id}' joined room '{room_id}' — {} other(s) present",
-        other_publishers.len()
-    );
+    if waiting {
+        info!("Conference peer '{peer_id}' waiting in lobby of room '{room_id}'");
+        state.event_bus.emit(crate::events::LiveRelayEvent::participant_waiting(
+            &room_id,
+            &peer_id,
+            &claims.role,
+            claims.metadata.as_ref(),
+        ));
+        crate::lobby::notify_hosts(&room, "participant.waiting", &peer_id).await;
+    } else {
+        info!(
+            "Conference peer '{peer_id}' joined room '{room_id}' — {} other(s) present",
+            other_publishers.len()
+        );
+        state.event_bus.emit(crate::events::LiveRelayEvent::participant_joined(
+            &room_id,
+            &peer_id,
+            &claims.role,
+            claims.metadata.as_ref(),
+        ));
+    }
 
     Ok(Json(ConferenceAnswer {
         sdp: answer.sdp,
         sdp_type: answer.sdp_type,
         participants: participant_list,
+        participant_metadata,
         peer_id,
+        waiting,
+        subscription_id,
+        screen_shares: screen_publishers.into_iter().map(|p| p.peer_id.clone()).collect(),
     }))
 }
 
@@ -1124,7 +1386,8 @@ pub struct ConferenceSubscribeRequest {
     #[serde(rename = "type")]
     #[allow(dead_code)]
     pub sdp_type: String,
-    /// The peer_id of the publisher to subscribe to.
+    /// The peer_id of the publisher to subscribe to, or a screen share
+    /// (`<peer_id>-screen`).
     pub target_peer_id: String,
 }
 
@@ -1133,15 +1396,10 @@ pub async fn sfu_conference_subscribe(
     headers: HeaderMap,
     Json(req): Json<ConferenceSubscribeRequest>,
 ) -> Result<Json<SdpAnswer>, ApiError> {
-    let token_str = extract_bearer_token(&headers)?;
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let claims = crate::auth::verify_token(&state.jwt_secret, token_str)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::token_expired(),
-            _ => ApiError::token_invalid(),
-        })?;
-
-    if claims.role != "conference" && claims.role != "call" {
+    if claims.role != "conference" && claims.role != "call" && claims.role != "host" {
         return Err(ApiError::role_insufficient(&claims.role));
     }
 
@@ -1153,10 +1411,16 @@ pub async fn sfu_conference_subscribe(
     };
     let room = room.ok_or_else(|| ApiError::room_not_found(&room_id))?;
 
-    let target = {
+    let (requester_waiting, target) = {
         let pubs = room.publishers.read().unwrap();
-        pubs.get(&req.target_peer_id).cloned()
+        (
+            pubs.get(&claims.sub).is_some_and(|p| !p.is_admitted()),
+            pubs.get(&req.target_peer_id).filter(|p| p.is_admitted()).cloned(),
+        )
     };
+    if requester_waiting {
+        return Err(ApiError::forbidden("You are still waiting in the lobby."));
+    }
     let target = target.ok_or_else(|| {
         ApiError::not_found(format!(
             "Publisher '{}' not found in room '{room_id}'",
@@ -1167,55 +1431,83 @@ pub async fn sfu_conference_subscribe(
     // Wait for target publisher's codec to be ready.
     wait_for_publisher_ready(&target, 10).await;
 
-    let pc = create_peer_connection(&state.config).await.map_err(|e| {
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
         warn!("sfu_conference_subscribe: PC creation failed: {e}");
         ApiError::peer_connection_failed()
     })?;
-
-    let video_codec = target.video_codec.read().unwrap().clone()
-        .unwrap_or_else(|| RTCRtpCodecCapability {
-            mime_type: MIME_TYPE_VP8.to_string(),
-            ..Default::default()
-        });
-    let audio_codec = target.audio_codec.read().unwrap().clone()
-        .unwrap_or_else(|| RTCRtpCodecCapability {
-            mime_type: MIME_TYPE_OPUS.to_string(),
-            ..Default::default()
-        });
+    state.revocations.track(&claims, &pc);
 
     let short_id = &target.peer_id[..8.min(target.peer_id.len())];
     let stream_id = format!("lr-{short_id}");
 
-    let video_track = Arc::new(TrackLocalStaticRTP::new(
-        video_codec,
-        format!("video-{}", target.peer_id),
-        stream_id.clone(),
-    ));
-    let audio_track = Arc::new(TrackLocalStaticRTP::new(
-        audio_codec,
-        format!("audio-{}", target.peer_id),
-        stream_id,
-    ));
+    // A screen-share target carries a single screen track; a participant
+    // carries camera video + audio.
+    let tracks = if target.is_screen() {
+        let codec = target.screen_codec.read().unwrap().clone()
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
+        vec![(
+            Arc::new(TrackLocalStaticRTP::new(
+                codec,
+                format!("screen-{}", target.peer_id),
+                format!("{stream_id}-screen"),
+            )),
+            target.screen_tx.clone(),
+            TrackKind::Screen,
+            "conf-sub-screen",
+        )]
+    } else {
+        let video_codec = target.video_codec.read().unwrap().clone()
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Video));
+        let audio_codec = target.audio_codec.read().unwrap().clone()
+            .unwrap_or_else(|| room.codec_policy.fallback(RTPCodecType::Audio));
+        vec![
+            (
+                Arc::new(TrackLocalStaticRTP::new(
+                    video_codec,
+                    format!("video-{}", target.peer_id),
+                    stream_id.clone(),
+                )),
+                target.video_tx.clone(),
+                TrackKind::Video,
+                "conf-sub-video",
+            ),
+            (
+                Arc::new(TrackLocalStaticRTP::new(
+                    audio_codec,
+                    format!("audio-{}", target.peer_id),
+                    stream_id,
+                )),
+                target.audio_tx.clone(),
+                TrackKind::Audio,
+                "conf-sub-audio",
+            ),
+        ]
+    };
 
-    pc.add_track(Arc::clone(&video_track) as Arc<dyn TrackLocal + Send + Sync>)
-        .await.map_err(|e| ApiError::internal(format!("add_track(video): {e}")))?;
-    pc.add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal + Send + Sync>)
-        .await.map_err(|e| ApiError::internal(format!("add_track(audio): {e}")))?;
+    for (track, _, _, label) in &tracks {
+        pc.add_track(Arc::clone(track) as Arc<dyn TrackLocal + Send + Sync>)
+            .await.map_err(|e| ApiError::internal(format!("add_track({label}): {e}")))?;
+    }
 
     let cancel = CancellationToken::new();
+    let mut subscription = Subscription::new();
+    let subscription_id = subscription.id.clone();
 
     {
         let cancel_clone = cancel.clone();
         let room_clone = room.clone();
+        let sub_id = subscription_id.clone();
         pc.on_peer_connection_state_change(Box::new(move |conn_state| {
             let cancel = cancel_clone.clone();
             let room = room_clone.clone();
+            let sub_id = sub_id.clone();
             Box::pin(async move {
                 match conn_state {
                     RTCPeerConnectionState::Failed
                     | RTCPeerConnectionState::Disconnected
                     | RTCPeerConnectionState::Closed => {
                         cancel.cancel();
+                        room.subscriptions.write().unwrap().remove(&sub_id);
                         room.subscriber_count.fetch_sub(1, Ordering::Relaxed);
                     }
                     _ => {}
@@ -1224,22 +1516,22 @@ pub async fn sfu_conference_subscribe(
         }));
     }
 
-    let answer = exchange_sdp(&pc, req.sdp).await?;
+    let mut answer = exchange_sdp(&pc, req.sdp).await?;
 
-    let video_rx = target.video_tx.subscribe();
-    let audio_rx = target.audio_tx.subscribe();
-
-    spawn_fanout_task(video_rx, video_track, cancel.clone(), "conf-sub-video");
-    spawn_fanout_task(audio_rx, audio_track, cancel.clone(), "conf-sub-audio");
-
-    let ssrc = target.video_ssrc.load(Ordering::Relaxed);
-    if ssrc != 0 {
-        let pli = PictureLossIndication {
-            sender_ssrc: 0,
-            media_ssrc: ssrc as u32,
-        };
-        let _ = target.pc.write_rtcp(&[Box::new(pli)]).await;
+    for (track, source, kind, label) in tracks {
+        spawn_fanout_task(
+            source.subscribe(), track, cancel.clone(), label,
+            subscription.track(&target.peer_id, kind),
+            room.egress_bytes.clone(),
+        );
     }
+    room.subscriptions
+        .write()
+        .unwrap()
+        .insert(subscription_id.clone(), Arc::new(subscription));
+    answer.subscription_id = Some(subscription_id);
+
+    request_keyframe(&target).await;
 
     room.subscriber_count.fetch_add(1, Ordering::Relaxed);
 
@@ -1250,3 +1542,229 @@ pub async fn sfu_conference_subscribe(
 
     Ok(Json(answer))
 }
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCREEN SHARE — call and conference rooms
+// ═══════════════════════════════════════════════════════════════════════════
+//
+//   POST   /sfu/screen   offer with one video track → a dedicated
+//                        `<peer_id>-screen` publisher on its own PC.
+//   DELETE /sfu/screen   stop sharing (closing the PC does the same).
+//   GET    /sfu/screen   active shares in the caller's room.
+//
+//   Other participants receive a share through POST /sfu/conference/subscribe
+//   with `target_peer_id: "<peer_id>-screen"` — no renegotiation of their
+//   main PeerConnection.  Participants joining a conference later find the
+//   active shares in `ConferenceAnswer::screen_shares`.
+//
+//   A share ends with its sharer: leaving the room closes it too.  Rooms
+//   created with `single_screen_share` accept one share at a time.
+//
+
+#[derive(Serialize)]
+pub struct ScreenShares {
+    pub screen_shares: Vec<String>,
+}
+
+/// Verify a call / conference token and return its claims plus the room.
+fn screen_share_room(
+    state: &crate::AppState,
+    headers: &HeaderMap,
+) -> Result<(crate::auth::TokenClaims, Arc<Room>), ApiError> {
+    let claims =
+        crate::auth::require_room_token(headers, &state.jwt_secret, &state.revocations)?;
+
+    if claims.role != "conference" && claims.role != "call" && claims.role != "host" {
+        return Err(ApiError::role_insufficient(&claims.role));
+    }
+
+    let room = {
+        let rooms = state.rooms.read().unwrap();
+        rooms.get(&claims.room_id).cloned()
+    };
+    let room = room.ok_or_else(|| ApiError::room_not_found(&claims.room_id))?;
+
+    if room.room_type == crate::room::RoomType::Broadcast {
+        return Err(ApiError::room_type_mismatch("call or conference", "broadcast"));
+    }
+    Ok((claims, room))
+}
+
+/// Remove a disconnected peer from the room and emit `participant.left`.
+/// Connection-state callbacks fire more than once; only the call that
+/// actually removes the publisher emits.
+fn remove_participant(state: &crate::AppState, room: &Room, peer_id: &str, role: &str) {
+    let Some(publisher) = room.remove_publisher(peer_id) else {
+        return;
+    };
+    if !publisher.is_screen() {
+        state.event_bus.emit(crate::events::LiveRelayEvent::participant_left(
+            &room.room_id,
+            peer_id,
+            role,
+            publisher.metadata.as_ref(),
+        ));
+    }
+}
+
+/// Remove `peer_id`'s screen share from the room and emit `stream.stopped`.
+/// With `pc` set, only a share still backed by that PeerConnection is
+/// removed (a newer share may have replaced it).  The caller closes the
+/// returned publisher's PeerConnection.
+fn end_screen_share(
+    state: &crate::AppState,
+    room: &Room,
+    peer_id: &str,
+    pc: Option<&Arc<RTCPeerConnection>>,
+) -> Option<Arc<Publisher>> {
+    let screen_id = format!("{peer_id}-screen");
+    let removed = {
+        let mut pubs = room.publishers.write().unwrap();
+        let current = pubs
+            .get(&screen_id)
+            .is_some_and(|p| p.is_screen() && pc.is_none_or(|pc| Arc::ptr_eq(pc, &p.pc)));
+        if current {
+            pubs.remove(&screen_id)
+        } else {
+            None
+        }
+    }?;
+
+    info!("Screen share of '{peer_id}' in room '{}' ended", room.room_id);
+    state.event_bus.emit(crate::events::LiveRelayEvent::stream_stopped(
+        &room.room_id,
+        peer_id,
+        "screen",
+        removed.metadata.as_ref(),
+    ));
+    Some(removed)
+}
+
+/// POST /sfu/screen — start sharing the screen in a call / conference room.
+pub async fn sfu_screen_start(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+    Json(offer): Json<SdpOffer>,
+) -> Result<Json<SdpAnswer>, ApiError> {
+    let (claims, room) = screen_share_room(&state, &headers)?;
+
+    // Not gated by drain mode: the sharer is already connected here.
+    let room_id = room.room_id.clone();
+    let peer_id = claims.sub.clone();
+    let screen_id = format!("{peer_id}-screen");
+
+    // Only admitted participants share; the share is not a new participant.
+    let joined = room
+        .publishers
+        .read()
+        .unwrap()
+        .get(&peer_id)
+        .is_some_and(|p| p.is_admitted());
+    if !joined {
+        return Err(ApiError::forbidden(
+            "Join the room before sharing your screen.",
+        ));
+    }
+    let check_limit = |pubs: &std::collections::HashMap<String, Arc<Publisher>>| {
+        if pubs.contains_key(&screen_id) {
+            return Err(ApiError::conflict("You are already sharing your screen."));
+        }
+        if room.single_screen_share && pubs.values().any(|p| p.is_screen()) {
+            return Err(ApiError::screen_share_active(&room_id));
+        }
+        Ok(())
+    };
+    check_limit(&room.publishers.read().unwrap())?;
+
+    check_offer_codecs(&room, &offer.sdp)?;
+
+    let pc = create_peer_connection(&state, &room.codec_policy).await.map_err(|e| {
+        warn!("sfu_screen_start: failed to create PeerConnection: {e}");
+        ApiError::peer_connection_failed()
+    })?;
+    state.revocations.track(&claims, &pc);
+
+    let publisher = Arc::new(
+        Publisher::new_screen(screen_id.clone(), pc.clone())
+            .with_metadata(claims.metadata.clone())
+            .with_role(claims.role.clone()),
+    );
+    setup_publisher_on_track(&pc, &publisher, &room_id, true);
+
+    {
+        let room_clone = room.clone();
+        let state_clone = state.clone();
+        let pid = peer_id.clone();
+        let pc_weak = Arc::downgrade(&pc);
+        pc.on_peer_connection_state_change(Box::new(move |conn_state| {
+            let room = room_clone.clone();
+            let state = state_clone.clone();
+            let pid = pid.clone();
+            let pc = pc_weak.upgrade();
+            Box::pin(async move {
+                if let (
+                    RTCPeerConnectionState::Failed
+                    | RTCPeerConnectionState::Disconnected
+                    | RTCPeerConnectionState::Closed,
+                    Some(pc),
+                ) = (conn_state, pc)
+                {
+                    end_screen_share(&state, &room, &pid, Some(&pc));
+                }
+            })
+        }));
+    }
+
+    let answer = exchange_sdp(&pc, offer.sdp).await?;
+
+    // Re-check under the write lock: another share may have started while
+    // ICE was gathering.
+    let admitted = {
+        let mut pubs = room.publishers.write().unwrap();
+        check_limit(&pubs).map(|()| {
+            pubs.insert(screen_id, publisher.clone());
+        })
+    };
+    if let Err(e) = admitted {
+        let _ = pc.close().await;
+        return Err(e);
+    }
+
+    spawn_pli_sender(&publisher);
+
+    info!("Peer '{peer_id}' started a screen share in room '{room_id}'");
+    state.event_bus.emit(crate::events::LiveRelayEvent::stream_started(
+        &room_id,
+        &peer_id,
+        "screen",
+        claims.metadata.as_ref(),
+    ));
+
+    Ok(Json(answer))
+}
+
+/// DELETE /sfu/screen — stop the caller's screen share.
+pub async fn sfu_screen_stop(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let (claims, room) = screen_share_room(&state, &headers)?;
+
+    let screen = end_screen_share(&state, &room, &claims.sub, None)
+        .ok_or_else(|| ApiError::not_found("You are not sharing your screen."))?;
+    if let Err(e) = screen.pc.close().await {
+        warn!("Failed to close screen share of '{}': {e}", claims.sub);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /sfu/screen — active screen shares in the caller's room.
+pub async fn sfu_screen_list(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ScreenShares>, ApiError> {
+    let (_, room) = screen_share_room(&state, &headers)?;
+    Ok(Json(ScreenShares {
+        screen_shares: room.screen_shares(),
+    }))
+}
@@ -0,0 +1,615 @@
+// src/snapshot.rs
+//
+// Periodic keyframe snapshots for live moderation.
+//
+// Rooms created with `snapshot_interval_secs` get a capture task.  Trust &
+// safety reviews the thumbnails instead of joining the room.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   every interval, for each admitted publisher and each of its video
+//   tracks (camera, screen share):
+//     subscribe to its video channel ─► PLI ─► SampleBuilder reassembles
+//     RTP until the first keyframe (give up after CAPTURE_TIMEOUT)
+//       ├─ VP8 / VP9 → {dir}/{room_id}/{snapshot_id}.ivf  (one-frame IVF)
+//       └─ H264      → {dir}/{room_id}/{snapshot_id}.h264 (Annex-B)
+//     [feature "snapshot-jpeg"] ffmpeg decodes the file → .jpg
+//     emit `snapshot.captured` { snapshot_id, url, format, … }
+//
+// ─ API ──────────────────────────────────────────────────────────────────────
+//
+//   GET /v1/rooms/:room_id/snapshots                 API key; newest first
+//   GET /v1/rooms/:room_id/snapshots/:snapshot_id    the file itself
+//
+//   The last `retain_per_room` snapshots of a room are kept; older files
+//   are deleted.  When the room goes away its index is dropped and
+//   `{dir}/{room_id}` is deleted once in-flight captures have finished.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use webrtc::media::io::sample_builder::SampleBuilder;
+use webrtc::rtp::codecs::{h264::H264Packet, vp8::Vp8Packet, vp9::Vp9Packet};
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp::packetizer::Depacketizer;
+
+use crate::error::ApiError;
+use crate::room::{Publisher, Room};
+
+/// Shortest interval a room may ask for: every capture forces a keyframe.
+pub const MIN_INTERVAL_SECS: u64 = 2;
+
+/// How long a capture waits for the keyframe after sending the PLI.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// ---------------------------------------------------------------------------
+// SnapshotConfig
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Snapshots are stored as `{base_dir}/{room_id}/{snapshot_id}.{ext}`.
+    pub base_dir: PathBuf,
+    /// Snapshots kept per room; older ones are deleted.
+    pub retain_per_room: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            base_dir: PathBuf::from("./snapshots"),
+            retain_per_room: 100,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SnapshotInfo
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub snapshot_id: String,
+    pub room_id: String,
+    pub peer_id: String,
+    /// "camera" or "screen".
+    pub track: &'static str,
+    /// "ivf", "h264" or "jpeg".
+    pub format: &'static str,
+    /// Mime type of the captured video, e.g. `video/VP8`.
+    pub codec: String,
+    pub size_bytes: u64,
+    pub captured_at: DateTime<Utc>,
+    /// Where to fetch the file (requires an API key).
+    pub url: String,
+    #[serde(skip)]
+    pub file_path: PathBuf,
+}
+
+impl SnapshotInfo {
+    fn content_type(&self) -> &'static str {
+        match self.format {
+            "jpeg" => "image/jpeg",
+            "ivf" => "video/x-ivf",
+            _ => "video/h264",
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SnapshotStore — index of captured snapshots per room
+// ---------------------------------------------------------------------------
+
+pub struct SnapshotStore {
+    pub config: SnapshotConfig,
+    /// Newest last.
+    rooms: std::sync::RwLock<HashMap<String, VecDeque<SnapshotInfo>>>,
+}
+
+impl SnapshotStore {
+    pub fn new(config: SnapshotConfig) -> Self {
+        Self {
+            config,
+            rooms: std::sync::RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Snapshots of a room, newest first.
+    pub fn list(&self, room_id: &str) -> Vec<SnapshotInfo> {
+        let rooms = self.rooms.read().unwrap();
+        rooms
+            .get(room_id)
+            .map(|list| list.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, room_id: &str, snapshot_id: &str) -> Option<SnapshotInfo> {
+        let rooms = self.rooms.read().unwrap();
+        rooms
+            .get(room_id)?
+            .iter()
+            .find(|s| s.snapshot_id == snapshot_id)
+            .cloned()
+    }
+
+    /// Record a snapshot; returns the files that fell out of retention.
+    fn insert(&self, info: SnapshotInfo) -> Vec<PathBuf> {
+        let mut rooms = self.rooms.write().unwrap();
+        let list = rooms.entry(info.room_id.clone()).or_default();
+        list.push_back(info);
+        let excess = list.len().saturating_sub(self.config.retain_per_room);
+        list.drain(..excess).map(|s| s.file_path).collect()
+    }
+
+    /// Drop the index of a room and delete its directory.
+    async fn forget_room(&self, room_id: &str) {
+        self.rooms.write().unwrap().remove(room_id);
+        let room_dir = self.config.base_dir.join(room_id);
+        match tokio::fs::remove_dir_all(&room_dir).await {
+            Ok(()) => debug!("Deleted snapshots of room '{room_id}'"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to delete {}: {e}", room_dir.display()),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Capture task
+// ---------------------------------------------------------------------------
+
+/// Capture every publisher of `room` each `interval` until the room is gone.
+pub fn spawn_snapshot_task(state: Arc<crate::AppState>, room: &Arc<Room>, interval: Duration) {
+    let Some(store) = state.snapshots.clone() else {
+        return;
+    };
+    let room_id = room.room_id.clone();
+    let room: Weak<Room> = Arc::downgrade(room);
+
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        // The first tick fires immediately, before anyone has joined.
+        tick.tick().await;
+        let mut captures = tokio::task::JoinSet::new();
+
+        loop {
+            tick.tick().await;
+            while captures.try_join_next().is_some() {}
+            let Some(room) = room.upgrade().filter(|r| room_is_live(&state, r)) else {
+                break;
+            };
+            for publisher in room.get_publishers() {
+                if !publisher.is_admitted() {
+                    continue;
+                }
+                let state = state.clone();
+                let store = store.clone();
+                let room_id = room_id.clone();
+                captures.spawn(async move {
+                    capture_publisher(&state, &store, &room_id, &publisher).await;
+                });
+            }
+        }
+
+        // Let in-flight captures land before their directory goes.
+        while captures.join_next().await.is_some() {}
+        store.forget_room(&room_id).await;
+        debug!("Snapshot task for room '{room_id}' ended");
+    });
+}
+
+fn room_is_live(state: &crate::AppState, room: &Arc<Room>) -> bool {
+    let rooms = state.rooms.read().unwrap();
+    rooms
+        .get(&room.room_id)
+        .is_some_and(|current| Arc::ptr_eq(current, room))
+}
+
+/// Grab one keyframe from each video track of `publisher`.  Broadcast
+/// publishers carry camera and screen on one PeerConnection; a call or
+/// conference screen share is its own `<peer_id>-screen` publisher.
+async fn capture_publisher(
+    state: &crate::AppState,
+    store: &SnapshotStore,
+    room_id: &str,
+    publisher: &Arc<Publisher>,
+) {
+    let tracks = [
+        ("camera", &publisher.video_tx, &publisher.video_codec),
+        ("screen", &publisher.screen_tx, &publisher.screen_codec),
+    ];
+    let mut pending = Vec::new();
+    for (track, tx, codec) in tracks {
+        let Some(mime) = codec.read().unwrap().as_ref().map(|c| c.mime_type.to_lowercase()) else {
+            continue;
+        };
+        // Subscribe before asking, so the keyframe cannot slip past.
+        pending.push((track, mime, tx.subscribe()));
+    }
+    if pending.is_empty() {
+        return;
+    }
+    crate::sfu::request_keyframe(publisher).await;
+
+    for (track, mime, rx) in pending {
+        capture(state, store, room_id, publisher, track, mime, rx).await;
+    }
+}
+
+/// Store one keyframe from `rx` and emit `snapshot.captured`.
+async fn capture(
+    state: &crate::AppState,
+    store: &SnapshotStore,
+    room_id: &str,
+    publisher: &Publisher,
+    track: &'static str,
+    mime: String,
+    rx: broadcast::Receiver<Packet>,
+) {
+    let frame = match mime.as_str() {
+        "video/vp8" => collect_keyframe(rx, Vp8Packet::default(), vp8_is_keyframe)
+            .await
+            .map(|f| (ivf(b"VP80", vp8_dimensions(&f), &f), "ivf")),
+        "video/vp9" => collect_keyframe(rx, Vp9Packet::default(), vp9_is_keyframe)
+            .await
+            .map(|f| (ivf(b"VP90", vp9_dimensions(&f), &f), "ivf")),
+        "video/h264" => collect_keyframe(rx, H264Packet::default(), h264_is_keyframe)
+            .await
+            .map(|f| (f, "h264")),
+        _ => {
+            debug!("No snapshot of '{}': {mime} is not supported", publisher.peer_id);
+            return;
+        }
+    };
+    let Some((data, ext)) = frame else {
+        debug!("No {track} keyframe from '{}' within {CAPTURE_TIMEOUT:?}", publisher.peer_id);
+        return;
+    };
+
+    let snapshot_id = uuid::Uuid::new_v4().to_string();
+    let room_dir = store.config.base_dir.join(room_id);
+    let file_path = room_dir.join(format!("{snapshot_id}.{ext}"));
+    let written = async {
+        tokio::fs::create_dir_all(&room_dir).await?;
+        tokio::fs::write(&file_path, &data).await
+    };
+    if let Err(e) = written.await {
+        warn!("Failed to write snapshot {}: {e}", file_path.display());
+        return;
+    }
+
+    let (file_path, format, size) = match decode_jpeg(&file_path).await {
+        Some((jpeg, jpeg_size)) => {
+            let _ = tokio::fs::remove_file(&file_path).await;
+            (jpeg, "jpeg", jpeg_size)
+        }
+        None => (file_path, ext, data.len() as u64),
+    };
+
+    let info = SnapshotInfo {
+        url: format!("/v1/rooms/{room_id}/snapshots/{snapshot_id}"),
+        snapshot_id,
+        room_id: room_id.to_string(),
+        peer_id: publisher.peer_id.clone(),
+        track,
+        format,
+        codec: mime,
+        size_bytes: size,
+        captured_at: Utc::now(),
+        file_path,
+    };
+    info!(
+        "Snapshot '{}' of '{}' in room '{room_id}' ({} bytes)",
+        info.snapshot_id, info.peer_id, info.size_bytes
+    );
+    // Index before announcing, so the URL in the event resolves.
+    let event = crate::events::LiveRelayEvent::snapshot_captured(&info);
+    let expired = store.insert(info);
+    state.event_bus.emit(event);
+    for old in expired {
+        let _ = tokio::fs::remove_file(old).await;
+    }
+}
+
+/// Reassemble frames from `rx` until one satisfies `is_keyframe`.
+async fn collect_keyframe<D: Depacketizer>(
+    mut rx: broadcast::Receiver<Packet>,
+    depacketizer: D,
+    is_keyframe: fn(&[u8]) -> bool,
+) -> Option<Bytes> {
+    let mut builder = SampleBuilder::new(128, depacketizer, 90_000);
+    let search = async {
+        loop {
+            match rx.recv().await {
+                Ok(pkt) => {
+                    builder.push(pkt);
+                    while let Some(sample) = builder.pop() {
+                        if is_keyframe(&sample.data) {
+                            return Some(sample.data);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    };
+    tokio::time::timeout(CAPTURE_TIMEOUT, search).await.ok().flatten()
+}
+
+/// Convert a snapshot to JPEG with the `ffmpeg` binary; returns the JPEG
+/// path and size, or `None` (the raw file is kept) when decoding fails.
+#[cfg(feature = "snapshot-jpeg")]
+async fn decode_jpeg(input: &std::path::Path) -> Option<(PathBuf, u64)> {
+    let output = input.with_extension("jpg");
+    let status = tokio::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(input)
+        .args(["-frames:v", "1", "-q:v", "3"])
+        .arg(&output)
+        .stdin(std::process::Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(s) if s.success() => {
+            let size = tokio::fs::metadata(&output).await.ok()?.len();
+            Some((output, size))
+        }
+        Ok(s) => {
+            warn!("ffmpeg could not decode {}: {s}", input.display());
+            None
+        }
+        Err(e) => {
+            warn!("Failed to run ffmpeg for snapshots: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "snapshot-jpeg"))]
+async fn decode_jpeg(_input: &std::path::Path) -> Option<(PathBuf, u64)> {
+    None
+}
+
+// ---------------------------------------------------------------------------
+// Codec helpers
+// ---------------------------------------------------------------------------
+
+/// VP8 frame tag: bit 0 is 0 for key frames (RFC 6386 §9.1).
+fn vp8_is_keyframe(frame: &[u8]) -> bool {
+    frame.first().is_some_and(|b| b & 0x01 == 0)
+}
+
+/// Width and height from a VP8 key frame header.
+fn vp8_dimensions(frame: &[u8]) -> (u16, u16) {
+    match frame.get(6..10) {
+        Some(d) if frame[3..6] == [0x9D, 0x01, 0x2A] => (
+            u16::from_le_bytes([d[0], d[1]]) & 0x3FFF,
+            u16::from_le_bytes([d[2], d[3]]) & 0x3FFF,
+        ),
+        _ => (0, 0),
+    }
+}
+
+/// VP9 uncompressed header: key frame and, when it is one, its size.
+fn vp9_key_header(frame: &[u8]) -> Option<(u16, u16)> {
+    let mut r = crate::fmp4::BitReader::new(frame);
+    if r.bits(2)? != 2 {
+        return None; // frame_marker
+    }
+    let profile = r.bits(1)? | (r.bits(1)? << 1);
+    if profile == 3 {
+        r.bits(1)?;
+    }
+    // show_existing_frame, then frame_type (0 = KEY_FRAME)
+    if r.bits(1)? == 1 || r.bits(1)? != 0 {
+        return None;
+    }
+    r.bits(2)?; // show_frame, error_resilient_mode
+    if r.bits(24)? != 0x49_83_42 {
+        return None; // frame_sync_code
+    }
+    if profile >= 2 {
+        r.bits(1)?; // ten_or_twelve_bit
+    }
+    let color_space = r.bits(3)?;
+    if color_space != 7 {
+        r.bits(1)?; // color_range
+        if profile == 1 || profile == 3 {
+            r.bits(3)?; // subsampling_x, subsampling_y, reserved_zero
+        }
+    } else if profile == 1 || profile == 3 {
+        r.bits(1)?;
+    }
+    Some((r.bits(16)? as u16 + 1, r.bits(16)? as u16 + 1))
+}
+
+fn vp9_is_keyframe(frame: &[u8]) -> bool {
+    vp9_key_header(frame).is_some()
+}
+
+fn vp9_dimensions(frame: &[u8]) -> (u16, u16) {
+    vp9_key_header(frame).unwrap_or((0, 0))
+}
+
+/// Whether an Annex-B access unit contains an IDR slice.
+fn h264_is_keyframe(au: &[u8]) -> bool {
+    au.windows(4)
+        .any(|w| w[..3] == [0, 0, 1] && w[3] & 0x1F == 5)
+}
+
+/// A one-frame IVF file.
+fn ivf(fourcc: &[u8; 4], (width, height): (u16, u16), frame: &[u8]) -> Bytes {
+    let mut out = BytesMut::with_capacity(32 + 12 + frame.len());
+    out.put_slice(b"DKIF");
+    out.put_u16_le(0); // version
+    out.put_u16_le(32); // header size
+    out.put_slice(fourcc);
+    out.put_u16_le(width);
+    out.put_u16_le(height);
+    out.put_u32_le(90_000); // time base denominator
+    out.put_u32_le(1); // time base numerator
+    out.put_u32_le(1); // frame count
+    out.put_u32_le(0);
+    out.put_u32_le(frame.len() as u32);
+    out.put_u64_le(0); // pts
+    out.put_slice(frame);
+    out.freeze()
+}
+
+// ---------------------------------------------------------------------------
+// API Handlers
+// ---------------------------------------------------------------------------
+
+fn snapshot_store(state: &crate::AppState) -> Result<&Arc<SnapshotStore>, ApiError> {
+    state
+        .snapshots
+        .as_ref()
+        .ok_or_else(|| ApiError::internal("Snapshot subsystem not initialized"))
+}
+
+/// GET /v1/rooms/:room_id/snapshots
+pub async fn list_snapshots(
+    State(state): State<Arc<crate::AppState>>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SnapshotInfo>>, ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+    Ok(Json(snapshot_store(&state)?.list(&room_id)))
+}
+
+/// GET /v1/rooms/:room_id/snapshots/:snapshot_id
+pub async fn get_snapshot(
+    State(state): State<Arc<crate::AppState>>,
+    Path((room_id, snapshot_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    crate::auth::require_api_key(&headers, &state.api_keys).await?;
+
+    let info = snapshot_store(&state)?
+        .get(&room_id, &snapshot_id)
+        .ok_or_else(|| ApiError::not_found(format!("Snapshot '{snapshot_id}' not found.")))?;
+    let data = tokio::fs::read(&info.file_path).await.map_err(|e| {
+        warn!("Failed to read snapshot {}: {e}", info.file_path.display());
+        ApiError::not_found(format!("Snapshot '{snapshot_id}' is no longer available."))
+    })?;
+
+    Ok(([(header::CONTENT_TYPE, info.content_type())], data).into_response())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(room_id: &str, n: usize) -> SnapshotInfo {
+        SnapshotInfo {
+            snapshot_id: format!("snap-{n}"),
+            room_id: room_id.to_string(),
+            peer_id: "peer".into(),
+            track: "camera",
+            format: "ivf",
+            codec: "video/vp8".into(),
+            size_bytes: 0,
+            captured_at: Utc::now(),
+            url: String::new(),
+            file_path: PathBuf::from(format!("snap-{n}.ivf")),
+        }
+    }
+
+    #[tokio::test]
+    async fn store_keeps_the_newest_snapshots() {
+        let store = SnapshotStore::new(SnapshotConfig {
+            retain_per_room: 2,
+            ..Default::default()
+        });
+        assert!(store.insert(info("r1", 1)).is_empty());
+        assert!(store.insert(info("r1", 2)).is_empty());
+        assert_eq!(store.insert(info("r1", 3)), vec![PathBuf::from("snap-1.ivf")]);
+        store.insert(info("r2", 4));
+
+        let ids: Vec<_> = store.list("r1").into_iter().map(|s| s.snapshot_id).collect();
+        assert_eq!(ids, ["snap-3", "snap-2"]);
+        assert!(store.get("r1", "snap-1").is_none());
+        assert!(store.get("r2", "snap-3").is_none());
+
+        store.forget_room("r1").await;
+        assert!(store.list("r1").is_empty());
+    }
+
+    #[tokio::test]
+    async fn forget_room_deletes_its_directory() {
+        let base_dir =
+            std::env::temp_dir().join(format!("liverelay-snapshots-{}", uuid::Uuid::new_v4()));
+        let store = SnapshotStore::new(SnapshotConfig {
+            base_dir: base_dir.clone(),
+            ..Default::default()
+        });
+        for room_id in ["r1", "r2"] {
+            tokio::fs::create_dir_all(base_dir.join(room_id)).await.unwrap();
+            tokio::fs::write(base_dir.join(room_id).join("snap.ivf"), b"x")
+                .await
+                .unwrap();
+        }
+
+        store.forget_room("r1").await;
+        assert!(!base_dir.join("r1").exists());
+        assert!(base_dir.join("r2").join("snap.ivf").exists());
+        // A room that never captured anything is fine too.
+        store.forget_room("r3").await;
+
+        let _ = tokio::fs::remove_dir_all(&base_dir).await;
+    }
+
+    #[test]
+    fn vp8_key_frame_header() {
+        // Frame tag (key frame, shown), start code, 640x480.
+        let key = [0x50, 0x2A, 0x00, 0x9D, 0x01, 0x2A, 0x80, 0x02, 0xE0, 0x01];
+        assert!(vp8_is_keyframe(&key));
+        assert_eq!(vp8_dimensions(&key), (640, 480));
+        assert!(!vp8_is_keyframe(&[0x31, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn vp9_key_frame_header() {
+        // Profile 0 key frame: marker, profile, flags, sync code, BT.601
+        // full range, then 1280x720 as width-1 / height-1.
+        let key = [0x82, 0x49, 0x83, 0x42, 0x20, 0x4F, 0xF0, 0x2C, 0xF0];
+        assert_eq!(vp9_key_header(&key), Some((1280, 720)));
+        // Same header with frame_type = 1 (inter frame).
+        assert!(!vp9_is_keyframe(&[0x86, 0x49, 0x83, 0x42]));
+    }
+
+    #[test]
+    fn ivf_wraps_one_frame() {
+        let file = ivf(b"VP80", (640, 480), b"frame");
+        assert_eq!(&file[..4], b"DKIF");
+        assert_eq!(&file[8..12], b"VP80");
+        assert_eq!(u16::from_le_bytes([file[12], file[13]]), 640);
+        assert_eq!(u32::from_le_bytes(file[32..36].try_into().unwrap()), 5);
+        assert_eq!(&file[44..], b"frame");
+    }
+
+    #[test]
+    fn h264_keyframe_needs_an_idr_slice() {
+        assert!(h264_is_keyframe(&[0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x65, 0x88]));
+        assert!(!h264_is_keyframe(&[0, 0, 0, 1, 0x41, 0x9A]));
+    }
+}
@@ -190,7 +190,7 @@ mod tests {
             room_id: None,
             types: Some("participant.joined,participant.left".to_string()),
         };
-        let evt1 = LiveRelayEvent::participant_joined("r", "p", "publish");
+        let evt1 = LiveRelayEvent::participant_joined("r", "p", "publish", None);
         let evt2 = LiveRelayEvent::room_created("r", "broadcast");
         assert!(query.matches(&evt1));
         assert!(!query.matches(&evt2));
@@ -202,9 +202,9 @@ mod tests {
             room_id: Some("room-X".to_string()),
             types: Some("stream.started".to_string()),
         };
-        let good = LiveRelayEvent::stream_started("room-X", "p1", "video");
-        let wrong_room = LiveRelayEvent::stream_started("room-Y", "p1", "video");
-        let wrong_type = LiveRelayEvent::participant_joined("room-X", "p1", "publish");
+        let good = LiveRelayEvent::stream_started("room-X", "p1", "video", None);
+        let wrong_room = LiveRelayEvent::stream_started("room-Y", "p1", "video", None);
+        let wrong_type = LiveRelayEvent::participant_joined("room-X", "p1", "publish", None);
 
         assert!(query.matches(&good));
         assert!(!query.matches(&wrong_room));
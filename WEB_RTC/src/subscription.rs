@@ -0,0 +1,403 @@
+// src/subscription.rs
+//
+// Subscriber-side track control: pause / resume individual forwarded tracks
+// and advertise a preferred maximum resolution.
+//
+// ─ Flow ─────────────────────────────────────────────────────────────────────
+//
+//   Every PeerConnection that receives media (broadcast viewer, call peer,
+//   conference main PC, conference/subscribe PC) registers a `Subscription`
+//   on its room and gets the id back in the SDP answer (`subscription_id`).
+//
+//   POST /sfu/subscriptions/:id   (any token for the same room)
+//     {"pause": ["video"], "resume": ["screen"], "publishers": ["<peer>"],
+//      "max_resolution": {"width": 320, "height": 180}}
+//     → flips the per-track gates the fan-out tasks check before each write.
+//       Resuming video or screen requests a keyframe from the publisher.
+//
+//   GET /sfu/demand   (publish / call / conference token)
+//     → how many subscribers still receive the caller's video, audio and
+//       screen, and the largest resolution any of them asked for.
+//
+// The SFU neither transcodes nor selects simulcast layers, so
+// `max_resolution` is advisory: it only takes effect through the publisher's
+// encoder, driven by `/sfu/demand`.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+use crate::auth::TokenClaims;
+use crate::error::ApiError;
+use crate::room::Room;
+
+// ─── Model ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Screen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+/// One publisher track forwarded to a subscriber.
+struct ForwardedTrack {
+    publisher: String,
+    kind: TrackKind,
+    paused: Arc<AtomicBool>,
+}
+
+/// The tracks a single subscribing PeerConnection receives.
+pub struct Subscription {
+    pub id: String,
+    tracks: Vec<ForwardedTrack>,
+    max_resolution: RwLock<Option<Resolution>>,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Subscription {
+    pub fn new() -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            tracks: Vec::new(),
+            max_resolution: RwLock::new(None),
+        }
+    }
+
+    /// Register a forwarded track and return the flag its fan-out task
+    /// checks before every write.
+    pub fn track(&mut self, publisher: &str, kind: TrackKind) -> Arc<AtomicBool> {
+        let paused = Arc::new(AtomicBool::new(false));
+        self.tracks.push(ForwardedTrack {
+            publisher: publisher.to_string(),
+            kind,
+            paused: paused.clone(),
+        });
+        paused
+    }
+
+    /// Apply an update.  Returns the publishers whose video or screen was
+    /// resumed and therefore need a keyframe.
+    pub fn apply(&self, update: &SubscriptionUpdate) -> Vec<String> {
+        let mut resumed = Vec::new();
+        for track in &self.tracks {
+            if update
+                .publishers
+                .as_ref()
+                .is_some_and(|p| !p.contains(&track.publisher))
+            {
+                continue;
+            }
+            if update.pause.contains(&track.kind) {
+                track.paused.store(true, Ordering::Relaxed);
+            } else if update.resume.contains(&track.kind)
+                && track.paused.swap(false, Ordering::Relaxed)
+                && track.kind != TrackKind::Audio
+                && !resumed.contains(&track.publisher)
+            {
+                resumed.push(track.publisher.clone());
+            }
+        }
+        if let Some(max) = update.max_resolution {
+            *self.max_resolution.write().unwrap() = max;
+        }
+        resumed
+    }
+
+    pub fn info(&self) -> SubscriptionInfo {
+        SubscriptionInfo {
+            id: self.id.clone(),
+            tracks: self
+                .tracks
+                .iter()
+                .map(|t| TrackState {
+                    publisher: t.publisher.clone(),
+                    kind: t.kind,
+                    paused: t.paused.load(Ordering::Relaxed),
+                })
+                .collect(),
+            max_resolution: *self.max_resolution.read().unwrap(),
+        }
+    }
+}
+
+/// What the subscribers of one publisher currently want.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Demand {
+    pub video_subscribers: usize,
+    pub audio_subscribers: usize,
+    pub screen_subscribers: usize,
+    /// Largest resolution requested by a subscriber receiving video; `None`
+    /// when any of them set no limit (or nobody receives video).
+    pub max_resolution: Option<Resolution>,
+}
+
+/// Aggregate the subscriptions in `room` for the camera publisher
+/// `peer_id` (its `<peer_id>-screen` share counts towards `screen`).
+pub fn demand(room: &Room, peer_id: &str) -> Demand {
+    let screen_peer = format!("{peer_id}-screen");
+    let subs = room.subscriptions.read().unwrap();
+
+    let mut demand = Demand::default();
+    let mut unbounded = false;
+    for sub in subs.values() {
+        let receiving = |kind: TrackKind| {
+            sub.tracks.iter().any(|t| {
+                t.kind == kind
+                    && !t.paused.load(Ordering::Relaxed)
+                    && (t.publisher == peer_id
+                        || (kind == TrackKind::Screen && t.publisher == screen_peer))
+            })
+        };
+        if receiving(TrackKind::Video) {
+            demand.video_subscribers += 1;
+            match *sub.max_resolution.read().unwrap() {
+                Some(res) if !unbounded => {
+                    if demand.max_resolution.is_none_or(|m| res.pixels() > m.pixels()) {
+                        demand.max_resolution = Some(res);
+                    }
+                }
+                _ => unbounded = true,
+            }
+        }
+        if receiving(TrackKind::Audio) {
+            demand.audio_subscribers += 1;
+        }
+        if receiving(TrackKind::Screen) {
+            demand.screen_subscribers += 1;
+        }
+    }
+    if unbounded {
+        demand.max_resolution = None;
+    }
+    demand
+}
+
+// ─── HTTP handlers ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscriptionUpdate {
+    #[serde(default)]
+    pub pause: Vec<TrackKind>,
+    #[serde(default)]
+    pub resume: Vec<TrackKind>,
+    /// Restrict the update to these publishers (default: all of them).
+    #[serde(default)]
+    pub publishers: Option<Vec<String>>,
+    /// Preferred maximum resolution; `null` clears it, absent keeps it.
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub max_resolution: Option<Option<Resolution>>,
+}
+
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrackState {
+    pub publisher: String,
+    pub kind: TrackKind,
+    pub paused: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionInfo {
+    pub id: String,
+    pub tracks: Vec<TrackState>,
+    pub max_resolution: Option<Resolution>,
+}
+
+fn verify_claims(state: &crate::AppState, headers: &HeaderMap) -> Result<TokenClaims, ApiError> {
+    crate::auth::require_room_token(headers, &state.jwt_secret, &state.revocations)
+}
+
+fn lookup_room(state: &crate::AppState, room_id: &str) -> Result<Arc<Room>, ApiError> {
+    let rooms = state.rooms.read().unwrap();
+    rooms
+        .get(room_id)
+        .cloned()
+        .ok_or_else(|| ApiError::room_not_found(room_id))
+}
+
+/// `POST /sfu/subscriptions/:subscription_id`
+pub async fn update_subscription(
+    State(state): State<Arc<crate::AppState>>,
+    Path(subscription_id): Path<String>,
+    headers: HeaderMap,
+    Json(update): Json<SubscriptionUpdate>,
+) -> Result<Json<SubscriptionInfo>, ApiError> {
+    let claims = verify_claims(&state, &headers)?;
+    let room = lookup_room(&state, &claims.room_id)?;
+
+    if let Some(Some(res)) = update.max_resolution {
+        if res.width == 0 || res.height == 0 {
+            return Err(ApiError::bad_request(
+                "max_resolution width and height must be positive.",
+            ));
+        }
+    }
+
+    let subscription = room
+        .subscriptions
+        .read()
+        .unwrap()
+        .get(&subscription_id)
+        .cloned()
+        .ok_or_else(|| {
+            ApiError::not_found(format!("Subscription '{subscription_id}' not found."))
+        })?;
+
+    let resumed = subscription.apply(&update);
+    for peer_id in &resumed {
+        let publisher = room.publishers.read().unwrap().get(peer_id).cloned();
+        if let Some(publisher) = publisher {
+            crate::sfu::request_keyframe(&publisher).await;
+        }
+    }
+
+    let info = subscription.info();
+    let paused = info.tracks.iter().filter(|t| t.paused).count();
+    info!(
+        "Subscription '{subscription_id}' in room '{}' updated — {paused}/{} track(s) paused",
+        room.room_id,
+        info.tracks.len(),
+    );
+    Ok(Json(info))
+}
+
+/// `GET /sfu/demand`
+pub async fn publisher_demand(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Demand>, ApiError> {
+    let claims = verify_claims(&state, &headers)?;
+    if !matches!(claims.role.as_str(), "publish" | "call" | "conference" | "host") {
+        return Err(ApiError::role_insufficient(&claims.role));
+    }
+    let room = lookup_room(&state, &claims.room_id)?;
+    Ok(Json(demand(&room, &claims.sub)))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::RoomType;
+
+    fn update(json: serde_json::Value) -> SubscriptionUpdate {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn pause_and_resume_by_kind_and_publisher() {
+        let mut sub = Subscription::new();
+        let a_video = sub.track("a", TrackKind::Video);
+        let a_audio = sub.track("a", TrackKind::Audio);
+        let b_video = sub.track("b", TrackKind::Video);
+
+        let resumed = sub.apply(&update(serde_json::json!({ "pause": ["video"] })));
+        assert!(resumed.is_empty());
+        assert!(a_video.load(Ordering::Relaxed) && b_video.load(Ordering::Relaxed));
+        assert!(!a_audio.load(Ordering::Relaxed));
+
+        let resumed = sub.apply(&update(serde_json::json!({
+            "resume": ["video", "audio"],
+            "publishers": ["b"],
+        })));
+        assert_eq!(resumed, vec!["b".to_string()]);
+        assert!(a_video.load(Ordering::Relaxed));
+        assert!(!b_video.load(Ordering::Relaxed));
+
+        // Only tracks that were actually paused need a keyframe.
+        let resumed = sub.apply(&update(serde_json::json!({ "resume": ["video"] })));
+        assert_eq!(resumed, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn max_resolution_absent_keeps_null_clears() {
+        let sub = Subscription::new();
+        sub.apply(&update(serde_json::json!({
+            "max_resolution": { "width": 320, "height": 180 },
+        })));
+        sub.apply(&update(serde_json::json!({ "pause": [] })));
+        assert_eq!(
+            sub.info().max_resolution,
+            Some(Resolution { width: 320, height: 180 })
+        );
+        sub.apply(&update(serde_json::json!({ "max_resolution": null })));
+        assert_eq!(sub.info().max_resolution, None);
+    }
+
+    #[test]
+    fn demand_aggregates_unpaused_subscribers() {
+        let room = Room::new("r".into(), RoomType::Conference);
+        let insert = |sub: Subscription| {
+            room.subscriptions
+                .write()
+                .unwrap()
+                .insert(sub.id.clone(), Arc::new(sub));
+        };
+
+        let mut small = Subscription::new();
+        small.track("a", TrackKind::Video);
+        small.track("a", TrackKind::Audio);
+        *small.max_resolution.write().unwrap() = Some(Resolution { width: 320, height: 180 });
+        insert(small);
+
+        let mut large = Subscription::new();
+        large.track("a", TrackKind::Video);
+        large.track("a-screen", TrackKind::Screen);
+        *large.max_resolution.write().unwrap() = Some(Resolution { width: 640, height: 360 });
+        insert(large);
+
+        let mut paused = Subscription::new();
+        paused.track("a", TrackKind::Video).store(true, Ordering::Relaxed);
+        insert(paused);
+
+        let d = demand(&room, "a");
+        assert_eq!(d.video_subscribers, 2);
+        assert_eq!(d.audio_subscribers, 1);
+        assert_eq!(d.screen_subscribers, 1);
+        assert_eq!(d.max_resolution, Some(Resolution { width: 640, height: 360 }));
+
+        // One subscriber without a limit lifts the cap.
+        let mut unbounded = Subscription::new();
+        unbounded.track("a", TrackKind::Video);
+        insert(unbounded);
+        assert_eq!(demand(&room, "a").max_resolution, None);
+        assert_eq!(demand(&room, "b"), Demand::default());
+    }
+}
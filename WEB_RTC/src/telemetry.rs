@@ -0,0 +1,185 @@
+// src/telemetry.rs
+//
+// Prometheus metrics for the media plane.
+//
+// ─ Exposed on GET /metrics ──────────────────────────────────────────────────
+//
+//   Gauges (refreshed on every scrape):
+//     sfu_rooms_active{room_type}
+//     sfu_publishers_active{room_type}
+//     sfu_subscribers_active{room_type}
+//     sfu_turn_allocations                  (embedded TURN only)
+//     sfu_recordings_active
+//     sfu_recording_disk_bytes
+//
+//   Counters (updated on the hot path through pre-resolved handles):
+//     sfu_forwarded_packets_total{kind}
+//     sfu_forwarded_bytes_total{kind}
+//     sfu_fanout_lagged_total{kind}         (broadcast receiver lagged)
+//     sfu_fanout_dropped_packets_total{kind}
+//     sfu_ice_failures_total
+//     sfu_dtls_failures_total
+//     sfu_webhook_deliveries_total{outcome}
+//     http_requests_total{method,path,status}  (route pattern or "unmatched")
+//
+//   Histograms:
+//     sfu_signalling_duration_seconds{endpoint,status}   (/sfu/* only)
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, State};
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::Arc;
+use std::time::Instant;
+use webrtc::dtls_transport::dtls_transport_state::RTCDtlsTransportState;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::room::RoomType;
+
+/// Buckets for signalling latency.  An SDP exchange includes server-side ICE
+/// gathering, which is capped at 10 s.
+const SIGNALLING_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0];
+
+/// Install the global Prometheus recorder.
+pub fn init_metrics() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("sfu_signalling_duration_seconds".to_string()),
+            SIGNALLING_BUCKETS,
+        )
+        .expect("signalling buckets are not empty")
+        .install_recorder()
+        .expect("failed to install Prometheus recorder")
+}
+
+/// Map a fan-out task label ("video", "call-audio", "conf-video-ab12cd34",
+/// ...) to the `kind` label value.
+pub fn fanout_kind(label: &str) -> &'static str {
+    if label.contains("screen") {
+        "screen"
+    } else if label.contains("audio") {
+        "audio"
+    } else {
+        "video"
+    }
+}
+
+/// Count ICE and DTLS failures on a freshly created PeerConnection.
+pub fn instrument_peer_connection(pc: &Arc<RTCPeerConnection>) {
+    pc.on_ice_connection_state_change(Box::new(|state| {
+        if state == RTCIceConnectionState::Failed {
+            counter!("sfu_ice_failures_total").increment(1);
+        }
+        Box::pin(async {})
+    }));
+    pc.dtls_transport().on_state_change(Box::new(|state| {
+        if state == RTCDtlsTransportState::Failed {
+            counter!("sfu_dtls_failures_total").increment(1);
+        }
+        Box::pin(async {})
+    }));
+}
+
+/// Requests that matched no route share this `path` label, so scans of
+/// random URLs cannot create unbounded series.
+const UNMATCHED_PATH: &str = "unmatched";
+
+/// Record request counts, plus a latency histogram for signalling endpoints.
+/// Requests are labelled with their route pattern, not the raw URI.
+pub async fn metrics_middleware(
+    matched_path: Option<MatchedPath>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let path = matched_path
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_PATH.to_string());
+
+    let response = next.run(req).await;
+
+    let status = response.status().as_u16().to_string();
+    if path.starts_with("/sfu/") {
+        histogram!(
+            "sfu_signalling_duration_seconds",
+            "endpoint" => path.clone(),
+            "status" => status.clone(),
+        )
+        .record(start.elapsed().as_secs_f64());
+    }
+    counter!(
+        "http_requests_total",
+        "method" => method,
+        "path" => path,
+        "status" => status,
+    )
+    .increment(1);
+
+    response
+}
+
+/// Refresh the gauges that are derived from current state.
+async fn refresh_gauges(state: &crate::AppState) {
+    for room_type in [RoomType::Broadcast, RoomType::Call, RoomType::Conference] {
+        let (rooms, publishers, subscribers) = {
+            let map = state.rooms.read().unwrap();
+            map.values()
+                .filter(|r| r.room_type == room_type)
+                .fold((0usize, 0usize, 0u64), |(n, p, s), r| {
+                    (n + 1, p + r.publisher_count(), s + r.subscriber_count())
+                })
+        };
+        let label = room_type.as_str();
+        gauge!("sfu_rooms_active", "room_type" => label).set(rooms as f64);
+        gauge!("sfu_publishers_active", "room_type" => label).set(publishers as f64);
+        gauge!("sfu_subscribers_active", "room_type" => label).set(subscribers as f64);
+    }
+
+    if let Some(turn) = &state.turn_server {
+        if let Ok(allocations) = turn.get_allocations_info(None).await {
+            gauge!("sfu_turn_allocations").set(allocations.len() as f64);
+        }
+    }
+
+    if let Some(mgr) = &state.recording {
+        let recordings = mgr.list_recordings(None);
+        let active = recordings.iter().filter(|r| r.is_active).count();
+        let mut disk_bytes = 0u64;
+        for r in &recordings {
+            if let Ok(meta) = tokio::fs::metadata(&r.file_path).await {
+                disk_bytes += meta.len();
+            }
+        }
+        gauge!("sfu_recordings_active").set(active as f64);
+        gauge!("sfu_recording_disk_bytes").set(disk_bytes as f64);
+    }
+}
+
+/// `GET /metrics` -- Prometheus text exposition.
+pub async fn metrics_handler(State(state): State<Arc<crate::AppState>>) -> String {
+    refresh_gauges(&state).await;
+    state.metrics_handle.render()
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fanout_kind_from_label() {
+        assert_eq!(fanout_kind("video"), "video");
+        assert_eq!(fanout_kind("call-audio"), "audio");
+        assert_eq!(fanout_kind("screen"), "screen");
+        assert_eq!(fanout_kind("conf-video-ab12cd34"), "video");
+        assert_eq!(fanout_kind("conf-sub-audio"), "audio");
+    }
+}
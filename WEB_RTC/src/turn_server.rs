@@ -8,14 +8,18 @@
 //! 2. **External TURN** (coturn etc.) -- the SFU simply advertises the external
 //!    TURN URLs / credentials to clients via the ICE config API endpoint.
 //!
-//! The choice is driven by `Config::turn_embedded`.
+//! The choice is driven by `Config::turn_embedded`.  In both modes clients
+//! receive short-lived credentials derived from `Config::turn_secret` (see
+//! [`TurnCredentials`]).
 
-use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
 use tokio::net::UdpSocket;
-use tracing::info;
+use tracing::{debug, info};
 use turn::auth::*;
 use turn::relay::relay_static::*;
 use util::vnet::net::Net;
@@ -25,42 +29,81 @@ use turn::Error;
 use crate::config::Config;
 
 // ---------------------------------------------------------------------------
-// Credential provider — long-term credentials (static username/password)
+// Credential provider — TURN REST API (time-limited, shared secret)
 // ---------------------------------------------------------------------------
 
-/// Simple long-term credential handler.
+/// Ephemeral TURN credentials handed to a single client.
 ///
-/// For production with many users you would generate per-session TURN
-/// credentials (HMAC-based ephemeral passwords).  This implementation
-/// uses a single shared username/password pair which is sufficient for a
-/// single-tenant SFU.
-struct StaticAuthHandler {
-    credentials: HashMap<String, Vec<u8>>,
+/// Follows the "TURN REST API" convention (draft-uberti-behave-turn-rest)
+/// that coturn implements with `use-auth-secret`:
+///
+/// ```text
+/// username   = "<unix expiry>:<peer_id>"
+/// credential = base64(HMAC-SHA1(turn_secret, username))
+/// ```
+///
+/// Any TURN server that knows the shared secret can verify them without
+/// talking to the SFU, so the same credentials work against the embedded
+/// relay and against an external coturn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnCredentials {
+    pub username: String,
+    pub credential: String,
 }
 
-impl StaticAuthHandler {
-    fn new(username: &str, password: &str, realm: &str) -> Self {
-        let mut creds = HashMap::new();
-        // TURN long-term credentials: key = MD5(username:realm:password)
-        let key = generate_auth_key(username, realm, password);
-        creds.insert(username.to_string(), key);
+impl TurnCredentials {
+    /// Mint credentials for `peer_id`, valid for `ttl_secs` from now.
+    pub fn mint(secret: &str, peer_id: &str, ttl_secs: u64) -> Self {
+        let expiry = chrono::Utc::now().timestamp().max(0) as u64 + ttl_secs;
+        let username = format!("{expiry}:{peer_id}");
+        let credential = rest_password(secret, &username);
         Self {
-            credentials: creds,
+            username,
+            credential,
         }
     }
 }
 
-impl AuthHandler for StaticAuthHandler {
+/// Derive the TURN password for a REST API username.
+fn rest_password(secret: &str, username: &str) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    BASE64_STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Extract the expiry timestamp from a REST API username.
+fn username_expiry(username: &str) -> Option<u64> {
+    let (expiry, _peer_id) = username.split_once(':')?;
+    expiry.parse().ok()
+}
+
+/// Validates TURN REST API credentials against the shared secret.
+///
+/// Nothing is stored per user: the password is re-derived from the
+/// username, which is only accepted while its embedded expiry lies in the
+/// future.
+struct RestAuthHandler {
+    secret: String,
+}
+
+impl AuthHandler for RestAuthHandler {
     fn auth_handle(
         &self,
         username: &str,
-        _realm: &str,
-        _src_addr: SocketAddr,
+        realm: &str,
+        src_addr: SocketAddr,
     ) -> Result<Vec<u8>, Error> {
-        self.credentials
-            .get(username)
-            .cloned()
-            .ok_or_else(|| Error::Other("unknown user".into()))
+        let expiry = username_expiry(username)
+            .ok_or_else(|| Error::Other(format!("malformed TURN username '{username}'")))?;
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        if expiry < now {
+            debug!("Rejecting expired TURN credentials '{username}' from {src_addr}");
+            return Err(Error::Other(format!("expired TURN username '{username}'")));
+        }
+
+        let password = rest_password(&self.secret, username);
+        Ok(generate_auth_key(username, realm, &password))
     }
 }
 
@@ -215,15 +258,13 @@ pub async fn start_embedded_turn(config: &Config) -> Result<Arc<Server>, Box<dyn
     let conn = Arc::new(UdpSocket::bind(&listen_addr).await?);
 
     info!(
-        "Embedded TURN server binding to UDP {}  realm='{}'  user='{}'",
-        listen_addr, config.turn_realm, config.turn_username
+        "Embedded TURN server binding to UDP {}  realm='{}'",
+        listen_addr, config.turn_realm
     );
 
-    let auth_handler = Arc::new(StaticAuthHandler::new(
-        &config.turn_username,
-        &config.turn_password,
-        &config.turn_realm,
-    ));
+    let auth_handler = Arc::new(RestAuthHandler {
+        secret: config.turn_secret.clone(),
+    });
 
     let server = Server::new(ServerConfig {
         conn_configs: vec![ConnConfig {
@@ -265,26 +306,80 @@ pub async fn get_ice_servers(
     axum::extract::State(state): axum::extract::State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
 ) -> Result<axum::Json<serde_json::Value>, crate::error::ApiError> {
-    // Require at least a valid JWT (any role) -- we do not want to expose
-    // TURN credentials to unauthenticated callers.
-    let token_str = headers
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.strip_prefix("Bearer "))
-        .ok_or_else(crate::error::ApiError::auth_header_missing)?;
-
-    crate::auth::verify_token(&state.jwt_secret, token_str).map_err(|e| {
-        match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                crate::error::ApiError::token_expired()
-            }
-            _ => crate::error::ApiError::token_invalid(),
-        }
-    })?;
+    // Require at least a valid JWT (any role) -- TURN credentials are minted
+    // for the token's peer_id and never handed to unauthenticated callers.
+    let claims =
+        crate::auth::require_room_token(&headers, &state.jwt_secret, &state.revocations)?;
 
-    let ice_servers = state.config.ice_servers_for_client();
+    let ice_servers = state.config.ice_servers_for_client(&claims.sub);
 
     Ok(axum::Json(serde_json::json!({
         "ice_servers": ice_servers
     })))
 }
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:50000".parse().unwrap()
+    }
+
+    #[test]
+    fn password_matches_coturn_scheme() {
+        // base64(HMAC-SHA1("north", "1700000000:peer-1")), as computed by
+        // coturn for `use-auth-secret` / `static-auth-secret=north`.
+        assert_eq!(
+            rest_password("north", "1700000000:peer-1"),
+            "izFBtBN0tC8mfzbn9GsbjcqtfUw="
+        );
+    }
+
+    #[test]
+    fn minted_credentials_are_accepted() {
+        let creds = TurnCredentials::mint("north", "peer-1", 600);
+        assert!(creds.username.ends_with(":peer-1"));
+
+        let handler = RestAuthHandler {
+            secret: "north".into(),
+        };
+        let key = handler
+            .auth_handle(&creds.username, "example.com", addr())
+            .unwrap();
+        assert_eq!(
+            key,
+            generate_auth_key(&creds.username, "example.com", &creds.credential)
+        );
+    }
+
+    #[test]
+    fn expired_or_malformed_usernames_are_rejected() {
+        let handler = RestAuthHandler {
+            secret: "north".into(),
+        };
+        assert!(handler
+            .auth_handle("1700000000:peer-1", "example.com", addr())
+            .is_err());
+        assert!(handler.auth_handle("peer-1", "example.com", addr()).is_err());
+    }
+
+    #[test]
+    fn other_secret_derives_other_key() {
+        let creds = TurnCredentials::mint("north", "peer-1", 600);
+        let handler = RestAuthHandler {
+            secret: "south".into(),
+        };
+        let key = handler
+            .auth_handle(&creds.username, "example.com", addr())
+            .unwrap();
+        assert_ne!(
+            key,
+            generate_auth_key(&creds.username, "example.com", &creds.credential)
+        );
+    }
+}
@@ -0,0 +1,440 @@
+// src/usage.rs
+//
+// Per-API-key usage metering and quotas.
+//
+// ─ Metering ─────────────────────────────────────────────────────────────────
+//
+//   Every room remembers the API key that created it.  A background task
+//   samples all rooms once per interval and adds, per owning key and per
+//   UTC hour:
+//
+//     participant_minutes[role]  : participants present × time metered
+//     recording_minutes          : active recordings × time metered
+//     egress_bytes               : RTP bytes written by the fan-out tasks
+//
+//   The time metered is what elapsed since the room was last sampled (or
+//   created), so a room created just before a tick is billed seconds, not a
+//   whole interval.  A room removed between two ticks is flushed on its way
+//   out (`flush_room`), covering the time since it was last sampled.
+//
+//   Buckets older than `RETENTION_SECS` are pruned.
+//
+// ─ Quotas ───────────────────────────────────────────────────────────────────
+//
+//   Each key may carry `KeyQuotas`:
+//
+//     max_concurrent_rooms          : checked on POST /v1/rooms
+//     max_concurrent_participants   : checked on every /sfu/* join
+//     monthly_participant_minutes   : checked on both, current UTC month
+//
+//   A hit quota answers `429 quota_exceeded`.
+//
+// ────────────────────────────────────────────────────────────────────────────
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use chrono::{Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::error::ApiError;
+use crate::room::{Room, RoomType};
+
+/// How long hourly buckets are kept (62 days covers any calendar month).
+const RETENTION_SECS: u64 = 62 * 24 * 3600;
+
+// ─── Quotas ─────────────────────────────────────────────────────────────────
+
+/// Limits attached to an API key.  `None` means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyQuotas {
+    #[serde(default)]
+    pub max_concurrent_rooms: Option<usize>,
+    #[serde(default)]
+    pub max_concurrent_participants: Option<u64>,
+    #[serde(default)]
+    pub monthly_participant_minutes: Option<u64>,
+}
+
+// ─── Usage buckets ──────────────────────────────────────────────────────────
+
+/// Usage of one API key during one UTC hour.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageBucket {
+    /// Unix timestamp of the start of the hour.
+    pub hour_start: u64,
+    /// Participant-minutes keyed by role ("publish", "subscribe", ...).
+    pub participant_minutes: HashMap<String, f64>,
+    pub recording_minutes: f64,
+    pub egress_bytes: u64,
+}
+
+impl UsageBucket {
+    fn total_participant_minutes(&self) -> f64 {
+        self.participant_minutes.values().sum()
+    }
+}
+
+/// Sample of one room taken by the meter.
+#[derive(Debug, Clone)]
+pub struct RoomSample {
+    pub owner_key: String,
+    pub participants: Vec<(&'static str, u64)>,
+    pub recording: bool,
+    pub egress_bytes: u64,
+    /// Time since the room was last sampled, which the sample covers.
+    pub duration: Duration,
+}
+
+/// Thread-safe store of hourly usage buckets, keyed by API key.
+#[derive(Clone, Default)]
+pub struct UsageStore {
+    inner: Arc<RwLock<HashMap<String, BTreeMap<u64, UsageBucket>>>>,
+}
+
+impl UsageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold a set of room samples, each covering its own `duration`, into
+    /// the bucket for the hour containing `now` (unix seconds).
+    pub async fn record(&self, samples: &[RoomSample], now: u64) {
+        let hour_start = now - now % 3600;
+
+        let mut map = self.inner.write().await;
+        for sample in samples {
+            let minutes = sample.duration.as_secs_f64() / 60.0;
+            let bucket = map
+                .entry(sample.owner_key.clone())
+                .or_default()
+                .entry(hour_start)
+                .or_insert_with(|| UsageBucket {
+                    hour_start,
+                    ..Default::default()
+                });
+
+            for (role, count) in &sample.participants {
+                if *count > 0 {
+                    *bucket
+                        .participant_minutes
+                        .entry(role.to_string())
+                        .or_default() += *count as f64 * minutes;
+                }
+            }
+            if sample.recording {
+                bucket.recording_minutes += minutes;
+            }
+            bucket.egress_bytes += sample.egress_bytes;
+        }
+
+        let cutoff = now.saturating_sub(RETENTION_SECS);
+        for buckets in map.values_mut() {
+            buckets.retain(|hour, _| *hour >= cutoff);
+        }
+    }
+
+    /// Hourly buckets for a key starting at or after `since`.
+    pub async fn buckets(&self, key: &str, since: u64) -> Vec<UsageBucket> {
+        let map = self.inner.read().await;
+        map.get(key)
+            .map(|b| b.range(since - since % 3600..).map(|(_, v)| v.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Participant-minutes used by a key since `since`.
+    pub async fn participant_minutes_since(&self, key: &str, since: u64) -> f64 {
+        self.buckets(key, since)
+            .await
+            .iter()
+            .map(UsageBucket::total_participant_minutes)
+            .sum()
+    }
+}
+
+/// Unix timestamp of the start of the current UTC month.
+fn month_start() -> u64 {
+    let now = Utc::now();
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .map(|t| t.timestamp() as u64)
+        .unwrap_or(0)
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+// ─── Quota checks ───────────────────────────────────────────────────────────
+
+fn quotas_for(state: &crate::AppState, key: &str) -> KeyQuotas {
+    let keys = state.api_keys.read().unwrap();
+    keys.get(key).map(|k| k.quotas.clone()).unwrap_or_default()
+}
+
+async fn check_monthly_minutes(
+    state: &crate::AppState,
+    key: &str,
+    quotas: &KeyQuotas,
+) -> Result<(), ApiError> {
+    if let Some(limit) = quotas.monthly_participant_minutes {
+        let used = state.usage.participant_minutes_since(key, month_start()).await;
+        if used >= limit as f64 {
+            return Err(ApiError::quota_exceeded("monthly_participant_minutes"));
+        }
+    }
+    Ok(())
+}
+
+/// Called before creating a room on behalf of `key`.
+pub async fn check_room_quota(state: &crate::AppState, key: &str) -> Result<(), ApiError> {
+    let quotas = quotas_for(state, key);
+
+    if let Some(limit) = quotas.max_concurrent_rooms {
+        let owned = {
+            let rooms = state.rooms.read().unwrap();
+            rooms.values().filter(|r| r.owner_key == key).count()
+        };
+        if owned >= limit {
+            return Err(ApiError::quota_exceeded("max_concurrent_rooms"));
+        }
+    }
+
+    check_monthly_minutes(state, key, &quotas).await
+}
+
+/// Called before a peer joins `room` through any `/sfu/*` endpoint.
+pub async fn check_participant_quota(
+    state: &crate::AppState,
+    room: &Room,
+) -> Result<(), ApiError> {
+    let key = room.owner_key.as_str();
+    let quotas = quotas_for(state, key);
+
+    if let Some(limit) = quotas.max_concurrent_participants {
+        let present: u64 = {
+            let rooms = state.rooms.read().unwrap();
+            rooms
+                .values()
+                .filter(|r| r.owner_key == key)
+                .map(|r| r.participant_count())
+                .sum()
+        };
+        if present >= limit {
+            return Err(ApiError::quota_exceeded("max_concurrent_participants"));
+        }
+    }
+
+    check_monthly_minutes(state, key, &quotas).await
+}
+
+// ─── Background meter ───────────────────────────────────────────────────────
+
+/// Participants present in a room, split by the role they joined with.
+fn participants_by_role(room: &Room) -> Vec<(&'static str, u64)> {
+    let publishers = room.publisher_count() as u64;
+    match room.room_type {
+        RoomType::Broadcast => vec![
+            ("publish", publishers),
+            ("subscribe", room.subscriber_count()),
+        ],
+        RoomType::Call => vec![("call", publishers)],
+        RoomType::Conference => vec![("conference", publishers)],
+    }
+}
+
+/// Ids of the rooms with an active recording.
+fn recording_rooms(state: &crate::AppState) -> Vec<String> {
+    state
+        .recording
+        .as_ref()
+        .map(|mgr| {
+            mgr.list_recordings(None)
+                .into_iter()
+                .filter(|r| r.is_active)
+                .map(|r| r.room_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Take a sample of `room` covering the time since it was last metered,
+/// and mark it as metered up to now.
+fn sample_room(room: &Room, recording_rooms: &[String]) -> RoomSample {
+    let now = std::time::Instant::now();
+    let last = std::mem::replace(&mut *room.metered_at.lock().unwrap(), now);
+    RoomSample {
+        owner_key: room.owner_key.clone(),
+        participants: participants_by_role(room),
+        recording: recording_rooms.contains(&room.room_id),
+        egress_bytes: room.egress_bytes.swap(0, Ordering::Relaxed),
+        duration: now.duration_since(last),
+    }
+}
+
+/// Record a final sample for a room that has just been removed from
+/// `state.rooms`, covering the time since the meter last saw it.  Without
+/// it a room deleted between two ticks (or before the first one) would
+/// lose its last participant-minutes and egress bytes.
+pub async fn flush_room(state: &crate::AppState, room: &Room) {
+    let sample = sample_room(room, &recording_rooms(state));
+    debug!(
+        room_id = %room.room_id,
+        elapsed_ms = sample.duration.as_millis() as u64,
+        "usage flushed"
+    );
+    state.usage.record(&[sample], now_secs()).await;
+}
+
+/// Spawn the task that samples every room each `interval` and folds the
+/// figures into `state.usage`.
+pub fn spawn_usage_meter(
+    state: Arc<crate::AppState>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!(interval_ms = interval.as_millis() as u64, "usage meter started");
+
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; skip it.
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let recording_rooms = recording_rooms(&state);
+
+            let samples: Vec<RoomSample> = {
+                let rooms = state.rooms.read().unwrap();
+                rooms
+                    .values()
+                    .map(|room| sample_room(room, &recording_rooms))
+                    .collect()
+            };
+
+            debug!(rooms = samples.len(), "usage sampled");
+            state.usage.record(&samples, now_secs()).await;
+        }
+    })
+}
+
+// ─── GET /v1/usage ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Unix timestamp; defaults to 24 hours ago.
+    pub since: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct UsageResponse {
+    pub key_name: String,
+    pub quotas: KeyQuotas,
+    pub month_participant_minutes: f64,
+    pub hourly: Vec<UsageBucket>,
+}
+
+/// `GET /v1/usage` -- hourly usage of the calling API key.
+pub async fn get_usage(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageResponse>, ApiError> {
+    let api_key = crate::auth::require_api_key(&headers, &state.api_keys).await?;
+
+    let since = query.since.unwrap_or_else(|| now_secs().saturating_sub(24 * 3600));
+    let hourly = state.usage.buckets(&api_key.key, since).await;
+    let month_participant_minutes = state
+        .usage
+        .participant_minutes_since(&api_key.key, month_start())
+        .await;
+
+    Ok(Json(UsageResponse {
+        key_name: api_key.name,
+        quotas: api_key.quotas,
+        month_participant_minutes,
+        hourly,
+    }))
+}
+
+// ─── Tests ──────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(key: &str, subs: u64, bytes: u64) -> RoomSample {
+        RoomSample {
+            owner_key: key.into(),
+            participants: vec![("publish", 1), ("subscribe", subs)],
+            recording: true,
+            egress_bytes: bytes,
+            duration: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_aggregates_per_hour() {
+        let store = UsageStore::new();
+        let hour = 1_718_000_000 - 1_718_000_000 % 3600;
+
+        store.record(&[sample("k1", 3, 1000)], hour + 10).await;
+        store.record(&[sample("k1", 1, 500)], hour + 70).await;
+
+        let buckets = store.buckets("k1", hour).await;
+        assert_eq!(buckets.len(), 1);
+        let b = &buckets[0];
+        assert_eq!(b.hour_start, hour);
+        assert_eq!(b.participant_minutes["publish"], 2.0);
+        assert_eq!(b.participant_minutes["subscribe"], 4.0);
+        assert_eq!(b.recording_minutes, 2.0);
+        assert_eq!(b.egress_bytes, 1500);
+
+        assert_eq!(store.participant_minutes_since("k1", hour).await, 6.0);
+        assert!(store.buckets("k2", hour).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn samples_are_billed_for_their_own_duration() {
+        let store = UsageStore::new();
+        let hour = 1_718_000_000 - 1_718_000_000 % 3600;
+        let fresh = RoomSample {
+            duration: Duration::from_secs(6),
+            ..sample("k1", 0, 0)
+        };
+
+        store.record(&[sample("k1", 0, 0), fresh], hour).await;
+
+        let b = &store.buckets("k1", hour).await[0];
+        assert_eq!(b.participant_minutes["publish"], 1.1);
+        assert_eq!(b.recording_minutes, 1.1);
+    }
+
+    #[tokio::test]
+    async fn record_prunes_old_buckets() {
+        let store = UsageStore::new();
+        let now = 10 * RETENTION_SECS;
+
+        store.record(&[sample("k1", 1, 0)], now - RETENTION_SECS - 7200).await;
+        store.record(&[sample("k1", 1, 0)], now).await;
+
+        assert_eq!(store.buckets("k1", 0).await.len(), 1);
+    }
+
+    #[test]
+    fn quotas_default_unlimited() {
+        let q: KeyQuotas = serde_json::from_str("{}").unwrap();
+        assert!(q.max_concurrent_rooms.is_none());
+        assert!(q.max_concurrent_participants.is_none());
+        assert!(q.monthly_participant_minutes.is_none());
+    }
+}
@@ -36,6 +36,7 @@ use axum::{
     Json,
 };
 use hmac::{Hmac, Mac};
+use metrics::counter;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
@@ -161,7 +162,7 @@ pub fn verify_signature(secret: &str, timestamp: u64, body: &[u8], signature: &s
 // ─── Delivery with retry ────────────────────────────────────────────────────
 
 /// Retry configuration.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RetryPolicy {
     /// Maximum number of attempts (including the first).
     pub max_attempts: u32,
@@ -250,6 +251,7 @@ async fn deliver(
                 last_status = Some(status);
 
                 if (200..300).contains(&(status as usize)) {
+                    counter!("sfu_webhook_deliveries_total", "outcome" => "success").increment(1);
                     info!(
                         webhook_id = %webhook.id,
                         event_id = %event.id,
@@ -289,6 +291,7 @@ async fn deliver(
         }
     }
 
+    counter!("sfu_webhook_deliveries_total", "outcome" => "failure").increment(1);
     error!(
         webhook_id = %webhook.id,
         event_id = %event.id,
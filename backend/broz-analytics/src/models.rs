@@ -1,13 +1,14 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::{analytics_events, daily_stats};
 
 // --- Analytics Events ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize)]
+#[derive(Debug, Queryable, Identifiable, Serialize, ToSchema)]
 #[diesel(table_name = analytics_events)]
 pub struct AnalyticsEvent {
     pub id: Uuid,
@@ -27,7 +28,7 @@ pub struct NewAnalyticsEvent {
 
 // --- Daily Stats ---
 
-#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[derive(Debug, Queryable, Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = daily_stats)]
 pub struct DailyStat {
     pub date: NaiveDate,
@@ -0,0 +1,18 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-analytics"),
+    paths(
+        routes::health::health_check,
+        routes::stats::get_overview,
+        routes::stats::get_daily_stats,
+        routes::stats::get_events,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
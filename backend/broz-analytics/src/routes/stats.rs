@@ -4,6 +4,7 @@ use axum::Json;
 use chrono::{NaiveDate, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use broz_shared::errors::{AppError, AppResult};
 use broz_shared::middleware::AdminUser;
@@ -15,7 +16,7 @@ use crate::schema::{analytics_events, daily_stats};
 
 // --- Overview ---
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct StatsOverview {
     pub dau: i64,
     pub wau: i64,
@@ -27,6 +28,12 @@ pub struct StatsOverview {
 /// GET /stats/overview
 /// Returns today's aggregated metrics from the daily_stats table.
 /// Requires AdminUser.
+#[utoipa::path(
+    get,
+    path = "/stats/overview",
+    tag = "analytics",
+    responses((status = 200, body = broz_shared::types::api::ApiResponse<StatsOverview>)),
+)]
 pub async fn get_overview(
     _admin: AdminUser,
     State(state): State<Arc<AppState>>,
@@ -60,7 +67,7 @@ pub async fn get_overview(
 
 // --- Daily Stats by Date Range ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct DateRangeQuery {
     /// Start date in YYYY-MM-DD format
     pub from: String,
@@ -71,6 +78,13 @@ pub struct DateRangeQuery {
 /// GET /stats/daily?from=2025-01-01&to=2025-01-31
 /// Returns all daily_stats rows within the given date range.
 /// Requires AdminUser.
+#[utoipa::path(
+    get,
+    path = "/stats/daily",
+    tag = "analytics",
+    params(DateRangeQuery),
+    responses((status = 200, body = broz_shared::types::api::ApiResponse<Vec<DailyStat>>)),
+)]
 pub async fn get_daily_stats(
     _admin: AdminUser,
     State(state): State<Arc<AppState>>,
@@ -102,6 +116,13 @@ pub async fn get_daily_stats(
 /// GET /stats/events?page=1&per_page=20
 /// Returns a paginated list of recent analytics events.
 /// Requires AdminUser.
+#[utoipa::path(
+    get,
+    path = "/stats/events",
+    tag = "analytics",
+    params(PaginationParams),
+    responses((status = 200, body = broz_shared::types::api::ApiResponse<Paginated<AnalyticsEvent>>)),
+)]
 pub async fn get_events(
     _admin: AdminUser,
     State(state): State<Arc<AppState>>,
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 use futures_lite::StreamExt;
 use lapin::options::BasicAckOptions;
+use tracing::Instrument;
 
+use broz_shared::clients::rabbitmq::delivery_span;
 use broz_shared::types::event::{routing_keys, payloads, Event};
 
 use crate::AppState;
@@ -19,42 +21,47 @@ pub async fn listen_sanction_issued(state: Arc<AppState>) -> anyhow::Result<()>
     while let Some(delivery) = consumer.next().await {
         match delivery {
             Ok(delivery) => {
-                match serde_json::from_slice::<Event<payloads::SanctionIssued>>(&delivery.data) {
-                    Ok(event) => {
-                        let data = &event.data;
-                        tracing::info!(
-                            user_id = %data.user_id,
-                            sanction_type = %data.sanction_type,
-                            "received sanction.issued event"
-                        );
+                let span = delivery_span(&delivery);
+                async {
+                    match serde_json::from_slice::<Event<payloads::SanctionIssued>>(&delivery.data) {
+                        Ok(event) => {
+                            let data = &event.data;
+                            tracing::info!(
+                                user_id = %data.user_id,
+                                sanction_type = %data.sanction_type,
+                                "received sanction.issued event"
+                            );
 
-                        let mut conn = match state.db.get() {
-                            Ok(c) => c,
-                            Err(e) => {
-                                tracing::error!(error = %e, "failed to get db connection");
-                                let _ = delivery.ack(BasicAckOptions::default()).await;
-                                continue;
-                            }
-                        };
+                            let mut conn = match state.db.get() {
+                                Ok(c) => c,
+                                Err(e) => {
+                                    tracing::error!(error = %e, "failed to get db connection");
+                                    let _ = delivery.ack(BasicAckOptions::default()).await;
+                                    return;
+                                }
+                            };
 
-                        use diesel::prelude::*;
-                        use crate::schema::credentials;
+                            use diesel::prelude::*;
+                            use crate::schema::credentials;
 
-                        let is_permanent = data.sanction_type == "ban_permanent";
-                        let _ = diesel::update(
-                            credentials::table.filter(credentials::id.eq(data.user_id))
-                        )
-                        .set((
-                            credentials::is_banned.eq(true),
-                            credentials::ban_until.eq(if is_permanent { None } else { data.expires_at }),
-                        ))
-                        .execute(&mut conn);
-                    }
-                    Err(e) => {
-                        tracing::error!(error = %e, "failed to deserialize sanction.issued event");
+                            let is_permanent = data.sanction_type == "ban_permanent";
+                            let _ = diesel::update(
+                                credentials::table.filter(credentials::id.eq(data.user_id))
+                            )
+                            .set((
+                                credentials::is_banned.eq(true),
+                                credentials::ban_until.eq(if is_permanent { None } else { data.expires_at }),
+                            ))
+                            .execute(&mut conn);
+                        }
+                        Err(e) => {
+                            tracing::error!(error = %e, "failed to deserialize sanction.issued event");
+                        }
                     }
+                    let _ = delivery.ack(BasicAckOptions::default()).await;
                 }
-                let _ = delivery.ack(BasicAckOptions::default()).await;
+                .instrument(span)
+                .await;
             }
             Err(e) => {
                 tracing::error!(error = %e, "consumer error");
@@ -3,16 +3,18 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::pg::PgConnection;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 mod services;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::email::EmailClient;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
 use broz_shared::clients::redis::RedisClient;
@@ -53,6 +55,7 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         .route("/signup", post(routes::register::register))
         .route("/login", post(routes::login::login))
         .route("/verify-email", post(routes::verify_email::verify_email))
@@ -64,7 +67,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/me", get(routes::me::me))
         .route("/oauth/google", post(routes::oauth::google_oauth))
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
@@ -0,0 +1,25 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-auth"),
+    paths(
+        routes::health::health_check,
+        routes::register::register,
+        routes::login::login,
+        routes::verify_email::verify_email,
+        routes::resend_code::resend_code,
+        routes::refresh::refresh_token,
+        routes::logout::logout,
+        routes::forgot_password::forgot_password,
+        routes::reset_password::reset_password,
+        routes::me::me,
+        routes::oauth::google_oauth,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
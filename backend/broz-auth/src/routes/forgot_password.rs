@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::ApiResponse;
@@ -12,11 +13,18 @@ use crate::schema::{credentials, password_resets};
 use crate::services::auth_service;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ForgotPasswordRequest {
     pub email: String,
 }
 
+/// Email a password reset code.
+#[utoipa::path(
+    post,
+    path = "/forgot-password",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<String>)),
+)]
 pub async fn forgot_password(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ForgotPasswordRequest>,
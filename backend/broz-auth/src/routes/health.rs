@@ -1,6 +1,13 @@
 use axum::Json;
 use broz_shared::types::api::HealthResponse;
 
+/// Service health.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "auth",
+    responses((status = 200, body = HealthResponse)),
+)]
 pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse::healthy("broz-auth", env!("CARGO_PKG_VERSION")))
 }
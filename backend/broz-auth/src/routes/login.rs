@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::{TokenPair, UserRole};
@@ -13,13 +14,20 @@ use crate::schema::{credentials, refresh_tokens};
 use crate::services::{auth_service, token_service};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
     pub device_fingerprint: Option<String>,
 }
 
+/// Log in with email and password.
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<TokenPair>)),
+)]
 pub async fn login(
     State(state): State<Arc<AppState>>,
     Json(req): Json<LoginRequest>,
@@ -3,21 +3,31 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult};
+use broz_shared::middleware::OptionalAuthUser;
 use broz_shared::types::ApiResponse;
 
 use crate::schema::refresh_tokens;
 use crate::services::token_service;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LogoutRequest {
     pub refresh_token: String,
 }
 
+/// Revoke the refresh token and, if authenticated, the access token.
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<String>)),
+)]
 pub async fn logout(
     State(state): State<Arc<AppState>>,
+    OptionalAuthUser(user): OptionalAuthUser,
     Json(req): Json<LogoutRequest>,
 ) -> AppResult<Json<ApiResponse<&'static str>>> {
     let token_hash = token_service::hash_token(&req.refresh_token);
@@ -31,5 +41,12 @@ pub async fn logout(
     .set(refresh_tokens::revoked_at.eq(Some(chrono::Utc::now())))
     .execute(&mut conn)?;
 
+    // Revoke the access token this request was made with
+    if let Some(user) = user {
+        if let Err(e) = state.redis.revoke_token(user.token_id, state.config.jwt_access_ttl as u64).await {
+            tracing::error!(error = %e, user_id = %user.id, "failed to revoke access token");
+        }
+    }
+
     Ok(Json(ApiResponse::ok("logged out")))
 }
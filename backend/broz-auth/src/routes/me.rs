@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Serialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::AuthUser;
@@ -11,7 +12,7 @@ use broz_shared::types::ApiResponse;
 use crate::schema::credentials;
 use crate::AppState;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct MeResponse {
     pub id: uuid::Uuid,
     pub email: String,
@@ -19,6 +20,13 @@ pub struct MeResponse {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// The caller's account.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<MeResponse>)),
+)]
 pub async fn me(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
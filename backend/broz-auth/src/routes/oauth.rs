@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::{TokenPair, UserRole};
@@ -13,7 +14,7 @@ use crate::schema::{credentials, oauth_accounts, refresh_tokens};
 use crate::services::token_service;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GoogleOAuthRequest {
     pub code: String,
 }
@@ -36,13 +37,20 @@ struct GoogleUserInfo {
     picture: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct OAuthResponse {
     #[serde(flatten)]
     pub tokens: TokenPair,
     pub is_new_user: bool,
 }
 
+/// Sign in or sign up with a Google authorization code.
+#[utoipa::path(
+    post,
+    path = "/oauth/google",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<OAuthResponse>)),
+)]
 pub async fn google_oauth(
     State(state): State<Arc<AppState>>,
     Json(req): Json<GoogleOAuthRequest>,
@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::{TokenPair, UserRole};
@@ -13,11 +14,18 @@ use crate::schema::{credentials, refresh_tokens};
 use crate::services::token_service;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
+/// Exchange a refresh token for a new token pair.
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<TokenPair>)),
+)]
 pub async fn refresh_token(
     State(state): State<Arc<AppState>>,
     Json(req): Json<RefreshRequest>,
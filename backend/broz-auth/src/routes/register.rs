@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 use validator::Validate;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -14,13 +15,20 @@ use crate::schema::{credentials, email_verifications};
 use crate::services::{auth_service, token_service};
 use crate::AppState;
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequest {
     #[validate(email(message = "invalid email format"))]
     pub email: String,
     pub password: String,
 }
 
+/// Create an account with email and password; sends a verification code.
+#[utoipa::path(
+    post,
+    path = "/signup",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<TokenPair>)),
+)]
 pub async fn register(
     State(state): State<Arc<AppState>>,
     Json(req): Json<RegisterRequest>,
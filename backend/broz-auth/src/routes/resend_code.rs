@@ -12,6 +12,13 @@ use crate::schema::{credentials, email_verifications};
 use crate::services::auth_service;
 use crate::AppState;
 
+/// Send a new email verification code to the caller.
+#[utoipa::path(
+    post,
+    path = "/resend-code",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<String>)),
+)]
 pub async fn resend_code(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
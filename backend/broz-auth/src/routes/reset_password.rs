@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::ApiResponse;
@@ -11,13 +12,20 @@ use crate::schema::{credentials, password_resets};
 use crate::services::auth_service;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ResetPasswordRequest {
     pub email: String,
     pub code: String,
     pub new_password: String,
 }
 
+/// Set a new password with a reset code; revokes all sessions.
+#[utoipa::path(
+    post,
+    path = "/reset-password",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<String>)),
+)]
 pub async fn reset_password(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ResetPasswordRequest>,
@@ -67,6 +75,11 @@ pub async fn reset_password(
     .set(refresh_tokens::revoked_at.eq(Some(chrono::Utc::now())))
     .execute(&mut conn)?;
 
+    // ...and every access token issued so far
+    if let Err(e) = state.redis.revoke_user_tokens(credential.id).await {
+        tracing::error!(error = %e, user_id = %credential.id, "failed to revoke access tokens");
+    }
+
     tracing::info!(user_id = %credential.id, "password reset");
 
     Ok(Json(ApiResponse::ok("password reset successful")))
@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::AuthUser;
@@ -11,11 +12,18 @@ use broz_shared::types::ApiResponse;
 use crate::schema::{credentials, email_verifications};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct VerifyEmailRequest {
     pub code: String,
 }
 
+/// Verify the caller's email with the code sent at signup.
+#[utoipa::path(
+    post,
+    path = "/verify-email",
+    tag = "auth",
+    responses((status = 200, body = ApiResponse<String>)),
+)]
 pub async fn verify_email(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -0,0 +1,508 @@
+use axum::body::Body;
+use axum::http::header::{
+    CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, SET_COOKIE,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::clients::rabbitmq::{delivery_span, RabbitMQClient};
+use broz_shared::types::event::{payloads, routing_keys, Event};
+use broz_shared::ApiErrorResponse;
+use futures_util::StreamExt;
+use lapin::options::BasicAckOptions;
+use redis::aio::ConnectionManager;
+use redis::Script;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tracing::Instrument;
+
+use crate::route_table::{CacheSettings, CacheVary, Route};
+use crate::routes::auth::AuthInfo;
+use crate::AppState;
+
+/// Whether a response came from the cache (`HIT`) or the upstream (`MISS`).
+const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+/// Queue shared by all gateway instances: the cache lives in Redis, so each
+/// event needs handling once.
+const INVALIDATION_QUEUE: &str = "broz-gateway.cache.invalidation";
+
+/// Reconnect delays of the invalidation consumer, doubling from the first
+/// to the second.
+const RECONNECT_BACKOFF: (Duration, Duration) = (Duration::from_secs(1), Duration::from_secs(60));
+
+/// Delete every response listed under the tags, and the tags.
+///
+/// KEYS  tag sets
+///
+/// Returns the number of responses deleted.
+static INVALIDATE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local deleted = 0
+for _, tag in ipairs(KEYS) do
+    for _, key in ipairs(redis.call('SMEMBERS', tag)) do
+        deleted = deleted + redis.call('DEL', key)
+    end
+    redis.call('DEL', tag)
+end
+return deleted
+"#,
+    )
+});
+
+fn response_key(request: &str) -> String {
+    format!("gwcache:resp:{request}")
+}
+
+fn tag_key(tag: &str) -> String {
+    format!("gwcache:tag:{tag}")
+}
+
+/// Whether the invalidation consumer is running.  Until it is, and
+/// whenever it is down, the response cache is bypassed: entries it should
+/// drop could otherwise be served stale.
+#[derive(Default)]
+pub struct InvalidationStatus(AtomicBool);
+
+impl InvalidationStatus {
+    pub fn is_running(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn set_running(&self, running: bool) -> bool {
+        self.0.swap(running, Ordering::AcqRel)
+    }
+}
+
+/// A `GET` on a cached route: where its response is cached and which
+/// tags invalidate it.
+pub struct CacheRequest<'a> {
+    settings: &'a CacheSettings,
+    key: String,
+    tags: Vec<String>,
+    /// The client's `If-None-Match`, answered here rather than upstream.
+    if_none_match: Option<HeaderValue>,
+    /// `Cache-Control: no-cache` from the client: skip the lookup but
+    /// still store the fresh response.
+    revalidate: bool,
+}
+
+impl<'a> CacheRequest<'a> {
+    /// The cache entry for a request, if its route caches and it is a
+    /// `GET`.  Conditional headers are taken off the request so the
+    /// upstream always sends a full body to cache.
+    pub fn new(
+        route: &'a Route,
+        method: &Method,
+        path: &str,
+        query: Option<&str>,
+        identity: Option<&AuthInfo>,
+        headers: &mut HeaderMap,
+    ) -> Option<Self> {
+        let settings = route.entry.cache.as_ref()?;
+        if method != Method::GET {
+            return None;
+        }
+
+        let mut request = path.to_string();
+        if let Some(q) = query {
+            request.push('?');
+            request.push_str(q);
+        }
+        let user = identity.map(|info| info.user_id);
+        if settings.vary == CacheVary::User {
+            request.push_str(&format!("#user={}", user?));
+        }
+
+        let revalidate = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.split(',').any(|d| d.trim().eq_ignore_ascii_case("no-cache")));
+        headers.remove(IF_MODIFIED_SINCE);
+
+        Some(Self {
+            settings,
+            key: response_key(&request),
+            tags: route.cache_tags(path, user),
+            if_none_match: headers.remove(IF_NONE_MATCH),
+            revalidate,
+        })
+    }
+
+    /// The cached response, `304` if the client's `If-None-Match` matches
+    /// it.  `None` on a miss or when Redis fails.
+    pub async fn lookup(&self, state: &AppState) -> Option<Response> {
+        if self.revalidate {
+            return None;
+        }
+        let mut conn = state.redis.clone();
+        let mut hgetall = redis::cmd("HGETALL");
+        hgetall.arg(&self.key);
+        let query = hgetall.query_async::<_, HashMap<String, Vec<u8>>>(&mut conn);
+        let mut fields = match tokio::time::timeout(timeout(state), query).await {
+            Ok(Ok(fields)) => fields,
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "response cache lookup failed");
+                return None;
+            }
+            Err(_) => {
+                tracing::warn!(timeout_ms = state.config.response_cache_timeout_ms, "response cache lookup timed out");
+                return None;
+            }
+        };
+        let body = fields.remove("body")?;
+
+        let mut headers = HeaderMap::new();
+        for (field, name) in [("content_type", CONTENT_TYPE), ("etag", ETAG), ("cache_control", CACHE_CONTROL)] {
+            if let Some(value) = fields.get(field).and_then(|v| HeaderValue::from_bytes(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers.insert(X_CACHE, HeaderValue::from_static("HIT"));
+        Some(self.respond(headers, body.into()))
+    }
+
+    /// Cache a fresh upstream response and relay it, or relay it as is if
+    /// it may not be cached: anything but a `200`, `Cache-Control:
+    /// no-store` / `no-cache` (or `private` on a shared entry), cookies,
+    /// encoded bodies, and bodies without a `Content-Length` or over
+    /// `response_cache_max_body_kb`.  `max-age` / `s-maxage` shorten the
+    /// route's TTL.  Responses without an `ETag` get one.
+    pub async fn store(self, state: &AppState, resp: Response) -> Response {
+        let (mut parts, body) = resp.into_parts();
+        let max_body = state.config.response_cache_max_body_kb * 1024;
+        let content_length = parts
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let ttl = self.ttl(&parts.headers);
+        let cacheable = parts.status == StatusCode::OK
+            && !parts.headers.contains_key(SET_COOKIE)
+            && !parts.headers.contains_key(CONTENT_ENCODING)
+            && content_length.is_some_and(|len| len <= max_body);
+        let Some(ttl) = ttl.filter(|_| cacheable) else {
+            return self.pass_through(Response::from_parts(parts, body));
+        };
+
+        let body = match axum::body::to_bytes(body, max_body as usize).await {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!(error = %e, "upstream response body failed");
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(ApiErrorResponse::new("E0007", "upstream response body failed")),
+                )
+                    .into_response();
+            }
+        };
+        if !parts.headers.contains_key(ETAG) {
+            let digest = hex::encode(Sha256::digest(&body));
+            if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", &digest[..32])) {
+                parts.headers.insert(ETAG, etag);
+            }
+        }
+
+        let mut fields: Vec<(&str, &[u8])> = vec![("body", &body[..])];
+        for (field, name) in [("content_type", CONTENT_TYPE), ("etag", ETAG), ("cache_control", CACHE_CONTROL)] {
+            if let Some(value) = parts.headers.get(&name) {
+                fields.push((field, value.as_bytes()));
+            }
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("DEL").arg(&self.key).ignore()
+            .cmd("HSET").arg(&self.key).arg(&fields).ignore()
+            .cmd("EXPIRE").arg(&self.key).arg(ttl).ignore();
+        for tag in &self.tags {
+            // A tag outlives the longest-lived response listed under it
+            let tag = tag_key(tag);
+            pipe.cmd("SADD").arg(&tag).arg(&self.key).ignore()
+                .cmd("EXPIRE").arg(&tag).arg(ttl).arg("NX").ignore()
+                .cmd("EXPIRE").arg(&tag).arg(ttl).arg("GT").ignore();
+        }
+        let mut conn = state.redis.clone();
+        match tokio::time::timeout(timeout(state), pipe.query_async::<_, ()>(&mut conn)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!(error = %e, "response cache write failed"),
+            Err(_) => tracing::warn!(timeout_ms = state.config.response_cache_timeout_ms, "response cache write timed out"),
+        }
+
+        parts.headers.insert(X_CACHE, HeaderValue::from_static("MISS"));
+        self.respond(parts.headers, body.into())
+    }
+
+    /// The route's TTL, shortened by the upstream's `max-age` or
+    /// `s-maxage`; `None` if the upstream forbids caching.
+    fn ttl(&self, headers: &HeaderMap) -> Option<u64> {
+        let mut ttl = self.settings.ttl_secs;
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|d| d.trim().to_ascii_lowercase());
+        for directive in directives {
+            match directive.split_once('=') {
+                Some(("max-age" | "s-maxage", secs)) => {
+                    ttl = ttl.min(secs.trim_matches('"').parse().unwrap_or(0));
+                }
+                _ if directive == "no-store" || directive == "no-cache" => return None,
+                _ if directive == "private" && self.settings.vary == CacheVary::None => return None,
+                _ => {}
+            }
+        }
+        (ttl > 0).then_some(ttl)
+    }
+
+    /// An uncached response, `304` if the client's `If-None-Match`
+    /// matches the upstream's `ETag`.
+    fn pass_through(&self, resp: Response) -> Response {
+        if resp.status() != StatusCode::OK || !self.not_modified(resp.headers()) {
+            return resp;
+        }
+        let (parts, _) = resp.into_parts();
+        self.respond(parts.headers, Body::empty())
+    }
+
+    /// `200` with the body, or `304` with only the validators if the
+    /// client's `If-None-Match` matches.
+    fn respond(&self, mut headers: HeaderMap, body: Body) -> Response {
+        if !self.not_modified(&headers) {
+            return (StatusCode::OK, headers, body).into_response();
+        }
+        headers.remove(CONTENT_TYPE);
+        headers.remove(CONTENT_LENGTH);
+        (StatusCode::NOT_MODIFIED, headers).into_response()
+    }
+
+    /// Whether the client's `If-None-Match` lists the response's `ETag`,
+    /// compared weakly.
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        let (Some(wanted), Some(etag)) = (&self.if_none_match, headers.get(ETAG)) else {
+            return false;
+        };
+        let (Ok(wanted), Ok(etag)) = (wanted.to_str(), etag.to_str()) else {
+            return false;
+        };
+        let etag = etag.trim_start_matches("W/");
+        wanted
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+    }
+}
+
+fn timeout(state: &AppState) -> Duration {
+    Duration::from_millis(state.config.response_cache_timeout_ms)
+}
+
+/// Drop the cached responses listed under the tags.
+pub async fn invalidate(redis: &ConnectionManager, tags: &[String]) -> redis::RedisResult<u64> {
+    let mut conn = redis.clone();
+    let mut invocation = INVALIDATE.prepare_invoke();
+    for tag in tags {
+        invocation.key(tag_key(tag));
+    }
+    invocation.invoke_async(&mut conn).await
+}
+
+/// Keep the invalidation consumer running: connect to RabbitMQ and
+/// listen, reconnecting with exponential backoff whenever connecting fails
+/// or the connection drops.  The gateway starts and serves without
+/// RabbitMQ; only the response cache waits for it.
+pub async fn run_invalidation_listener(state: Arc<AppState>) {
+    let (first, max) = RECONNECT_BACKOFF;
+    let mut delay = first;
+    loop {
+        let result = match RabbitMQClient::connect(&state.config.rabbitmq_url).await {
+            Ok(rabbitmq) => listen_invalidations(&state, rabbitmq).await,
+            Err(e) => Err(e.into()),
+        };
+        if state.cache_invalidation.set_running(false) {
+            // Had been running: start the backoff over
+            delay = first;
+        }
+        let retry_in_ms = delay.as_millis() as u64;
+        match result {
+            Ok(()) => tracing::warn!(retry_in_ms, "cache invalidation consumer stopped"),
+            Err(e) => tracing::warn!(error = %e, retry_in_ms, "cache invalidation consumer unavailable"),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(max);
+    }
+}
+
+/// Listen for user events and drop the cached responses they make stale:
+/// profile updates invalidate `profile:{id}` (profile and credential id),
+/// accepted and removed follows `follows:{id}` of both users.  Pending
+/// follow requests are not listed anywhere cached.
+///
+/// Returns when the consumer stream ends or fails.
+async fn listen_invalidations(state: &AppState, rabbitmq: RabbitMQClient) -> anyhow::Result<()> {
+    let mut consumer = rabbitmq
+        .subscribe(
+            INVALIDATION_QUEUE,
+            &[
+                routing_keys::USER_PROFILE_UPDATED,
+                routing_keys::USER_FOLLOW_ACCEPTED,
+                routing_keys::USER_FOLLOW_REMOVED,
+            ],
+        )
+        .await?;
+    state.cache_invalidation.set_running(true);
+    tracing::info!("cache invalidation consumer running");
+
+    while let Some(delivery) = consumer.next().await {
+        // Consumer errors come from the channel or connection; reconnect
+        let delivery = delivery?;
+        let span = delivery_span(&delivery);
+        async {
+            let routing_key = delivery.routing_key.as_str();
+            let tags = if routing_key == routing_keys::USER_PROFILE_UPDATED {
+                parse::<payloads::ProfileUpdated>(&delivery.data).map(|data| {
+                    vec![format!("profile:{}", data.profile_id), format!("profile:{}", data.credential_id)]
+                })
+            } else if routing_key == routing_keys::USER_FOLLOW_ACCEPTED {
+                parse::<payloads::FollowAccepted>(&delivery.data).map(|data| {
+                    vec![format!("follows:{}", data.follower_id), format!("follows:{}", data.following_id)]
+                })
+            } else {
+                parse::<payloads::FollowRemoved>(&delivery.data).map(|data| {
+                    vec![format!("follows:{}", data.follower_id), format!("follows:{}", data.following_id)]
+                })
+            };
+
+            match tags {
+                Ok(tags) => match invalidate(&state.redis, &tags).await {
+                    Ok(deleted) => tracing::debug!(?tags, deleted, "cached responses invalidated"),
+                    Err(e) => tracing::error!(error = %e, ?tags, "failed to invalidate cached responses"),
+                },
+                Err(e) => tracing::error!(error = %e, routing_key, "failed to deserialize event"),
+            }
+            let _ = delivery.ack(BasicAckOptions::default()).await;
+        }
+        .instrument(span)
+        .await;
+    }
+
+    Ok(())
+}
+
+fn parse<T: Serialize + DeserializeOwned>(data: &[u8]) -> serde_json::Result<T> {
+    serde_json::from_slice::<Event<T>>(data).map(|event| event.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(vary: CacheVary) -> CacheSettings {
+        CacheSettings {
+            ttl_secs: 60,
+            vary,
+            tags: Vec::new(),
+        }
+    }
+
+    fn request<'a>(settings: &'a CacheSettings, if_none_match: Option<&str>) -> CacheRequest<'a> {
+        CacheRequest {
+            settings,
+            key: response_key("/api/users/profile/42"),
+            tags: Vec::new(),
+            if_none_match: if_none_match.map(|v| HeaderValue::from_str(v).unwrap()),
+            revalidate: false,
+        }
+    }
+
+    fn with_etag(etag: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_str(etag).unwrap());
+        headers
+    }
+
+    fn with_cache_control(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn cache_is_bypassed_until_the_consumer_runs() {
+        let status = InvalidationStatus::default();
+        assert!(!status.is_running());
+
+        assert!(!status.set_running(true));
+        assert!(status.is_running());
+        assert!(status.set_running(false));
+        assert!(!status.is_running());
+    }
+
+    #[test]
+    fn if_none_match_is_compared_weakly() {
+        let settings = settings(CacheVary::None);
+        let matches = |wanted: &str, etag: &str| request(&settings, Some(wanted)).not_modified(&with_etag(etag));
+
+        assert!(matches(r#""abc""#, r#""abc""#));
+        assert!(matches(r#"W/"abc""#, r#""abc""#));
+        assert!(matches(r#""abc""#, r#"W/"abc""#));
+        assert!(matches(r#"W/"abc""#, r#"W/"abc""#));
+        assert!(matches(r#""x", W/"abc" ,"y""#, r#""abc""#));
+        assert!(matches("*", r#""abc""#));
+
+        assert!(!matches(r#""abd""#, r#""abc""#));
+        assert!(!matches(r#""x", "y""#, r#""abc""#));
+        assert!(!matches("abc", r#""abc""#));
+    }
+
+    #[test]
+    fn missing_validators_never_match() {
+        let settings = settings(CacheVary::None);
+
+        assert!(!request(&settings, None).not_modified(&with_etag(r#""abc""#)));
+        assert!(!request(&settings, Some("*")).not_modified(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn not_modified_responses_keep_only_validators() {
+        let settings = settings(CacheVary::None);
+        let mut headers = with_etag(r#""abc""#);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(2u64));
+
+        let resp = request(&settings, Some(r#"W/"abc""#)).respond(headers.clone(), Body::from("{}"));
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[ETAG], r#""abc""#);
+        assert!(!resp.headers().contains_key(CONTENT_TYPE));
+        assert!(!resp.headers().contains_key(CONTENT_LENGTH));
+
+        let resp = request(&settings, Some(r#""other""#)).respond(headers, Body::from("{}"));
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn upstream_cache_control_shortens_or_forbids_caching() {
+        let shared = settings(CacheVary::None);
+        let per_user = settings(CacheVary::User);
+        let ttl = |settings: &CacheSettings, value: &str| request(settings, None).ttl(&with_cache_control(value));
+
+        assert_eq!(request(&shared, None).ttl(&HeaderMap::new()), Some(60));
+        assert_eq!(ttl(&shared, "public, max-age=30"), Some(30));
+        assert_eq!(ttl(&shared, "s-maxage=\"10\""), Some(10));
+        assert_eq!(ttl(&shared, "max-age=600"), Some(60));
+        assert_eq!(ttl(&shared, "max-age=0"), None);
+        assert_eq!(ttl(&shared, "No-Store"), None);
+        assert_eq!(ttl(&shared, "no-cache"), None);
+        assert_eq!(ttl(&shared, "private, max-age=30"), None);
+        assert_eq!(ttl(&per_user, "private, max-age=30"), Some(30));
+    }
+}
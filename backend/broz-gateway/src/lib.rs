@@ -1,9 +1,20 @@
+pub mod cache;
 pub mod config;
+pub mod openapi;
+pub mod revocation;
+pub mod route_table;
 pub mod routes;
+pub mod upstream;
 
 pub struct AppState {
     pub config: config::AppConfig,
+    pub routes: route_table::RouteTable,
+    pub upstreams: upstream::Upstreams,
     pub http_client: reqwest::Client,
-    pub redis: tokio::sync::Mutex<redis::aio::ConnectionManager>,
+    pub redis: redis::aio::ConnectionManager,
+    pub revocations: revocation::RevocationCache,
+    pub api_specs: openapi::ApiSpecs,
     pub metrics_handle: metrics_exporter_prometheus::PrometheusHandle,
+    pub ws_connections: routes::websocket::ConnectionLimiter,
+    pub cache_invalidation: cache::InvalidationStatus,
 }
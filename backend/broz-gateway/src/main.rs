@@ -1,12 +1,17 @@
-use axum::routing::get;
+use axum::routing::{any, get};
 use axum::Router;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use axum::http::{header, Method};
-use tower_http::cors::{AllowHeaders, AllowMethods, CorsLayer};
-use tower_http::trace::TraceLayer;
+use axum::http::{header, HeaderName, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, CorsLayer, ExposeHeaders};
 
+use broz_gateway::cache;
 use broz_gateway::config::AppConfig;
-use broz_gateway::routes::{health, proxy};
+use broz_gateway::openapi::{self, ApiSpecs};
+use broz_gateway::revocation::RevocationCache;
+use broz_gateway::route_table::RouteTable;
+use broz_gateway::routes::{debug, docs, health, proxy, websocket};
+use broz_gateway::upstream::Upstreams;
 use broz_gateway::AppState;
 
 #[tokio::main]
@@ -18,6 +23,11 @@ async fn main() -> anyhow::Result<()> {
     let config = AppConfig::load()?;
     let port = config.port;
 
+    // Load and validate the route table
+    let routes = RouteTable::load(&config)?;
+    tracing::info!(routes = routes.routes().len(), "route table loaded");
+    let upstreams = Upstreams::new(&routes, &config);
+
     // Connect to Redis
     let redis_client = redis::Client::open(config.redis_url.as_str())?;
     let redis_conn = redis_client.get_connection_manager().await?;
@@ -26,23 +36,46 @@ async fn main() -> anyhow::Result<()> {
     // Initialize Prometheus metrics
     let metrics_handle = broz_shared::middleware::init_metrics();
 
-    // Build HTTP client for upstream proxying
+    // Build HTTP client for upstream proxying.  No overall timeout: bodies
+    // are streamed, so large uploads and downloads may take longer.
     let http_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
+        .connect_timeout(std::time::Duration::from_secs(5))
+        .read_timeout(std::time::Duration::from_secs(30))
         .build()?;
 
     // Build shared state
     let state = Arc::new(AppState {
         config,
+        routes,
+        upstreams,
         http_client,
-        redis: tokio::sync::Mutex::new(redis_conn),
+        redis: redis_conn,
+        revocations: RevocationCache::default(),
+        api_specs: ApiSpecs::default(),
         metrics_handle,
+        ws_connections: websocket::ConnectionLimiter::default(),
+        cache_invalidation: cache::InvalidationStatus::default(),
     });
 
+    // Keep the merged API documentation up to date
+    tokio::spawn(openapi::refresh_loop(state.clone()));
+
+    // Invalidate cached responses on user events
+    if state.routes.routes().iter().any(|route| route.entry.cache.is_some()) {
+        tokio::spawn(cache::run_invalidation_listener(state.clone()));
+    }
+
     // Build router
     let app = Router::new()
         .route("/health", get(health::health_check))
         .route("/metrics", get(health::metrics))
+        .route("/debug/routes", get(debug::routes))
+        .route("/api/openapi.json", get(docs::openapi))
+        // Socket.IO tunnels (WebSocket upgrade or long-polling)
+        .route("/ws/matching", any(websocket::ws_handler))
+        .route("/ws/matching/*rest", any(websocket::ws_handler))
+        .route("/ws/messaging", any(websocket::ws_handler))
+        .route("/ws/messaging/*rest", any(websocket::ws_handler))
         .fallback(proxy::proxy_handler)
         .layer(
             CorsLayer::new()
@@ -63,16 +96,25 @@ async fn main() -> anyhow::Result<()> {
                     header::CONTENT_TYPE,
                     header::ACCEPT,
                 ]))
+                // Rate limit state and cache validators, readable by the
+                // browser app
+                .expose_headers(ExposeHeaders::list([
+                    header::RETRY_AFTER,
+                    header::ETAG,
+                    HeaderName::from_static("x-ratelimit-limit"),
+                    HeaderName::from_static("x-ratelimit-remaining"),
+                    HeaderName::from_static("x-ratelimit-reset"),
+                ]))
                 .allow_credentials(true),
         )
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
     tracing::info!(addr = %addr, "broz-gateway starting");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
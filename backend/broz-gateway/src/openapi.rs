@@ -0,0 +1,535 @@
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::ApiErrorResponse;
+use jsonschema::{Draft, Validator};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::route_table::{AuthRequirement, Route, RouteTable};
+use crate::routes::proxy::body_too_large;
+use crate::AppState;
+
+/// How long fetching one service's document may take.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Validation errors listed in a 400 response.
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Path item keys that are operations.
+const OPERATION_METHODS: [&str; 5] = ["get", "put", "post", "delete", "patch"];
+
+/// The services' OpenAPI documents, merged for `/api/openapi.json` and
+/// indexed for request validation.  Kept up to date by [`refresh_loop`].
+#[derive(Default)]
+pub struct ApiSpecs {
+    current: RwLock<Arc<Specs>>,
+}
+
+#[derive(Default)]
+struct Specs {
+    /// Each upstream's own document, by upstream name.
+    documents: BTreeMap<String, Value>,
+    /// The merged document, serialized.
+    merged: Option<Bytes>,
+    /// Each upstream's paths, compiled for validation.
+    paths: HashMap<String, Vec<PathItem>>,
+}
+
+impl ApiSpecs {
+    /// The merged document, once a service's document has loaded.
+    pub fn merged(&self) -> Option<Bytes> {
+        self.current.read().unwrap().merged.clone()
+    }
+
+    fn snapshot(&self) -> Arc<Specs> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `{name}`
+    Param(String),
+}
+
+/// The operations on one upstream path template.
+struct PathItem {
+    segments: Vec<Segment>,
+    operations: HashMap<Method, Operation>,
+}
+
+impl PathItem {
+    fn matches(&self, parts: &[&str]) -> bool {
+        self.segments.len() == parts.len()
+            && self.segments.iter().zip(parts).all(|(segment, part)| match segment {
+                Segment::Literal(literal) => literal == part,
+                Segment::Param(_) => !part.is_empty(),
+            })
+    }
+
+    fn literals(&self) -> usize {
+        self.segments.iter().filter(|s| matches!(s, Segment::Literal(_))).count()
+    }
+}
+
+struct Operation {
+    params: Vec<Param>,
+    /// Schema of the `application/json` request body, if it takes one.
+    body: Option<BodySchema>,
+}
+
+struct Param {
+    name: String,
+    /// `path` or `query`.
+    location: &'static str,
+    required: bool,
+    schema: Value,
+    validator: Option<Validator>,
+}
+
+struct BodySchema {
+    required: bool,
+    validator: Validator,
+}
+
+/// Fetch every upstream's `/openapi.json` now and every
+/// `openapi_refresh_secs` after.  A service that cannot be reached keeps
+/// its last document; upstreams without one (object storage) are skipped.
+pub async fn refresh_loop(state: Arc<AppState>) {
+    let period = Duration::from_secs(state.config.openapi_refresh_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        refresh(&state).await;
+    }
+}
+
+async fn refresh(state: &AppState) {
+    let previous = state.api_specs.snapshot();
+    let mut documents = previous.documents.clone();
+
+    let mut upstreams: Vec<&str> = state.routes.routes().iter().map(|r| r.entry.upstream.as_str()).collect();
+    upstreams.sort_unstable();
+    upstreams.dedup();
+    for upstream in upstreams {
+        match fetch(state, upstream).await {
+            Ok(Some(document)) => {
+                documents.insert(upstream.to_string(), document);
+            }
+            Ok(None) => tracing::debug!(upstream, "upstream publishes no OpenAPI document"),
+            Err(e) => tracing::warn!(upstream, error = %e, "failed to fetch OpenAPI document"),
+        }
+    }
+    if documents.is_empty() || documents == previous.documents {
+        return;
+    }
+
+    let merged = serde_json::to_vec(&merge(&state.routes, &documents)).ok().map(Bytes::from);
+    let paths = documents
+        .iter()
+        .map(|(upstream, document)| (upstream.clone(), compile_paths(upstream, document)))
+        .collect();
+    tracing::info!(services = documents.len(), "OpenAPI documents loaded");
+    *state.api_specs.current.write().unwrap() = Arc::new(Specs { documents, merged, paths });
+}
+
+/// An upstream's document, or `None` if it answers 4xx (it has none).
+async fn fetch(state: &AppState, upstream: &str) -> anyhow::Result<Option<Value>> {
+    let Some(instance) = state.upstreams.get(upstream).and_then(|pool| pool.pick(None)) else {
+        anyhow::bail!("no upstream instance available");
+    };
+    let resp = state
+        .http_client
+        .get(format!("{}/openapi.json", instance.url))
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await;
+    let resp = match resp {
+        Ok(resp) => {
+            instance.record_success();
+            resp
+        }
+        Err(e) => {
+            instance.record_failure();
+            return Err(e.into());
+        }
+    };
+    if resp.status().is_client_error() {
+        return Ok(None);
+    }
+    Ok(Some(resp.error_for_status()?.json().await?))
+}
+
+/// One document for the whole API: each service's operations under their
+/// public paths, tagged with the service, operation ids prefixed with it,
+/// and the route's auth requirement as `security` (plus `x-required-role`
+/// on moderator and admin routes).  Operations no route exposes are left
+/// out.  Schema names that services define differently get the service's
+/// name as a prefix (`MessagingUnreadCountResponse`).
+fn merge(routes: &RouteTable, documents: &BTreeMap<String, Value>) -> Value {
+    let mut definitions: HashMap<&str, &Value> = HashMap::new();
+    let mut clashing: Vec<&str> = Vec::new();
+    for document in documents.values() {
+        for (name, schema) in schemas(document) {
+            match definitions.get(name.as_str()) {
+                Some(seen) if *seen != schema => clashing.push(name.as_str()),
+                Some(_) => {}
+                None => {
+                    definitions.insert(name, schema);
+                }
+            }
+        }
+    }
+
+    let mut paths = Map::new();
+    let mut components = Map::new();
+    let mut security_schemes = Map::new();
+    let mut tags = Vec::new();
+    for (upstream, document) in documents {
+        let prefix = pascal_case(upstream);
+        let renames: HashMap<String, String> = schemas(document)
+            .filter(|(name, _)| clashing.contains(&name.as_str()))
+            .map(|(name, _)| (name.clone(), format!("{prefix}{name}")))
+            .collect();
+        let mut document = document.clone();
+        rename_refs(&mut document, &renames);
+
+        tags.push(json!({ "name": upstream }));
+        for (name, schema) in schemas(&document) {
+            let name = renames.get(name).unwrap_or(name);
+            components.insert(name.clone(), schema.clone());
+        }
+        if let Some(schemes) = document.pointer("/components/securitySchemes").and_then(Value::as_object) {
+            security_schemes.extend(schemes.clone());
+        }
+
+        let Some(items) = document.get("paths").and_then(Value::as_object) else {
+            continue;
+        };
+        for (upstream_path, item) in items {
+            let Some((public_path, route)) = routes.public_path(upstream, upstream_path) else {
+                continue;
+            };
+            let Some(item) = item.as_object() else {
+                continue;
+            };
+            for (method, operation) in item {
+                if !OPERATION_METHODS.contains(&method.as_str()) {
+                    continue;
+                }
+                let mut operation = operation.clone();
+                if let Some(operation) = operation.as_object_mut() {
+                    if let Some(id) = operation.get("operationId").and_then(Value::as_str) {
+                        let id = format!("{upstream}_{id}");
+                        operation.insert("operationId".into(), id.into());
+                    }
+                    if route.entry.auth != AuthRequirement::Public {
+                        let scheme = broz_shared::openapi::BEARER_SCHEME;
+                        operation.insert("security".into(), json!([{ scheme: [] }]));
+                    }
+                    if matches!(route.entry.auth, AuthRequirement::Moderator | AuthRequirement::Admin) {
+                        operation.insert("x-required-role".into(), json!(route.entry.auth));
+                    }
+                }
+
+                let merged_item = paths
+                    .entry(public_path.clone())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if merged_item.get(method).is_some() {
+                    tracing::warn!(path = %public_path, method, upstream, "operation documented by two services");
+                    continue;
+                }
+                merged_item[method] = operation;
+            }
+        }
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "BROZ API",
+            "description": "All services, under the gateway's public paths.",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "tags": tags,
+        "paths": paths,
+        "components": {
+            "schemas": components,
+            "securitySchemes": security_schemes,
+        },
+    })
+}
+
+fn schemas(document: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    document
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+/// Point `$ref`s to renamed schemas at their new names.
+fn rename_refs(value: &mut Value, renames: &HashMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            let renamed = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix("#/components/schemas/"))
+                .and_then(|name| renames.get(name));
+            if let Some(name) = renamed {
+                let reference = format!("#/components/schemas/{name}");
+                object.insert("$ref".into(), reference.into());
+            }
+            object.values_mut().for_each(|v| rename_refs(v, renames));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| rename_refs(v, renames)),
+        _ => {}
+    }
+}
+
+/// `"notification"` to `"Notification"`, `"http://host:3000"` to `"HttpHost3000"`.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+        .collect()
+}
+
+fn compile_paths(upstream: &str, document: &Value) -> Vec<PathItem> {
+    let components = document.get("components").cloned().unwrap_or_else(|| json!({}));
+    let compile = |schema: &Value| -> Option<Validator> {
+        // `$ref`s point into the document's components
+        let mut root = schema.clone();
+        root.as_object_mut()?.insert("components".into(), components.clone());
+        match jsonschema::options()
+            .with_draft(Draft::Draft202012)
+            .should_validate_formats(true)
+            .build(&root)
+        {
+            Ok(validator) => Some(validator),
+            Err(e) => {
+                tracing::warn!(upstream, error = %e, "unusable schema in OpenAPI document");
+                None
+            }
+        }
+    };
+
+    let Some(items) = document.get("paths").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .map(|(path, item)| {
+            let segments = path
+                .trim_start_matches('/')
+                .split('/')
+                .map(|s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => Segment::Param(name.to_string()),
+                    None => Segment::Literal(s.to_string()),
+                })
+                .collect();
+
+            let operations = OPERATION_METHODS
+                .iter()
+                .filter_map(|method| Some((*method, item.get(*method)?)))
+                .map(|(method, operation)| {
+                    let params = operation
+                        .get("parameters")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|param| {
+                            let location = match param.get("in")?.as_str()? {
+                                "path" => "path",
+                                "query" => "query",
+                                _ => return None,
+                            };
+                            let schema = param.get("schema").cloned().unwrap_or_else(|| json!({}));
+                            Some(Param {
+                                name: param.get("name")?.as_str()?.to_string(),
+                                location,
+                                required: location == "path"
+                                    || param.get("required").and_then(Value::as_bool).unwrap_or(false),
+                                validator: compile(&schema),
+                                schema,
+                            })
+                        })
+                        .collect();
+
+                    let request_body = operation.get("requestBody");
+                    let body = request_body
+                        .and_then(|b| b.pointer("/content/application~1json/schema"))
+                        .and_then(&compile)
+                        .map(|validator| BodySchema {
+                            required: request_body
+                                .and_then(|b| b.get("required"))
+                                .and_then(Value::as_bool)
+                                .unwrap_or(false),
+                            validator,
+                        });
+
+                    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes()).unwrap_or(Method::GET);
+                    (method, Operation { params, body })
+                })
+                .collect();
+
+            PathItem { segments, operations }
+        })
+        .collect()
+}
+
+/// Check a request against its upstream's OpenAPI document before it is
+/// proxied, when `openapi_validation` is on.  The path and method must be
+/// documented (404, 405), and path and query parameters and JSON bodies
+/// must match their schemas (400 listing the mismatches).
+///
+/// JSON bodies are buffered to be checked, within the route's body limit,
+/// so the returned body replaces the request's.  Other bodies (uploads)
+/// and upstreams without a document pass through unchecked.
+#[allow(clippy::result_large_err)]
+pub async fn validate_request(
+    state: &AppState,
+    route: &Route,
+    method: &Method,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Body, Response> {
+    if !state.config.openapi_validation {
+        return Ok(body);
+    }
+    let specs = state.api_specs.snapshot();
+    let Some(items) = specs.paths.get(&route.entry.upstream) else {
+        return Ok(body);
+    };
+
+    let upstream_path = route.upstream_path(path, None);
+    let parts: Vec<&str> = upstream_path.trim_start_matches('/').split('/').collect();
+    let Some(item) = items
+        .iter()
+        .filter(|item| item.matches(&parts))
+        .max_by_key(|item| item.literals())
+    else {
+        return Err(error(StatusCode::NOT_FOUND, "E0003", "no such endpoint"));
+    };
+    let method = if method == Method::HEAD { &Method::GET } else { method };
+    let Some(operation) = item.operations.get(method) else {
+        if !OPERATION_METHODS.contains(&method.as_str().to_ascii_lowercase().as_str()) {
+            return Ok(body);
+        }
+        return Err(error(StatusCode::METHOD_NOT_ALLOWED, "E0002", "method not allowed on this endpoint"));
+    };
+
+    let mut issues = Vec::new();
+
+    // Parameters
+    let query_pairs: Vec<(String, String)> = query
+        .and_then(|q| reqwest::Url::parse(&format!("http://gateway/?{q}")).ok())
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default();
+    for param in &operation.params {
+        let raw = match param.location {
+            "path" => item
+                .segments
+                .iter()
+                .position(|s| *s == Segment::Param(param.name.clone()))
+                .map(|i| parts[i].to_string()),
+            _ => query_pairs.iter().find(|(k, _)| *k == param.name).map(|(_, v)| v.clone()),
+        };
+        let location = format!("{}/{}", param.location, param.name);
+        match raw {
+            None if param.required => issues.push(issue(&location, "is required")),
+            None => {}
+            Some(raw) => {
+                let value = coerce(raw, &param.schema);
+                if let Some(validator) = &param.validator {
+                    issues.extend(validator.iter_errors(&value).map(|e| issue(&location, e)));
+                }
+            }
+        }
+    }
+
+    // JSON body
+    let Some(expected) = &operation.body else {
+        return finish(issues).map(|()| body);
+    };
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        if expected.required && body.size_hint().exact() == Some(0) {
+            issues.push(issue("body", "is required"));
+        }
+        return finish(issues).map(|()| body);
+    }
+    let limit = route.body_limit();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::try_from(limit).unwrap_or(usize::MAX)).await else {
+        return Err(body_too_large(limit));
+    };
+    if bytes.is_empty() {
+        if expected.required {
+            issues.push(issue("body", "is required"));
+        }
+    } else {
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(value) => issues.extend(
+                expected
+                    .validator
+                    .iter_errors(&value)
+                    .map(|e| issue(&format!("body{}", e.instance_path()), e)),
+            ),
+            Err(e) => issues.push(issue("body", format!("is not valid JSON: {e}"))),
+        }
+    }
+    finish(issues).map(|()| Body::from(bytes))
+}
+
+/// A raw parameter as the JSON type its schema expects, or as a string if
+/// it does not parse as one (so the schema reports it).
+fn coerce(raw: String, schema: &Value) -> Value {
+    let expected = match schema.get("type") {
+        Some(Value::String(t)) => Some(t.as_str()),
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).find(|t| *t != "null"),
+        _ => None,
+    };
+    let parsed = match expected {
+        Some("integer") => raw.parse::<i64>().ok().map(Value::from),
+        Some("number") => raw.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number),
+        Some("boolean") => raw.parse::<bool>().ok().map(Value::Bool),
+        _ => None,
+    };
+    parsed.unwrap_or(Value::String(raw))
+}
+
+fn issue(location: &str, message: impl ToString) -> Value {
+    json!({ "location": location, "message": message.to_string() })
+}
+
+#[allow(clippy::result_large_err)]
+fn finish(issues: Vec<Value>) -> Result<(), Response> {
+    if issues.is_empty() {
+        return Ok(());
+    }
+    let issues: Vec<Value> = issues.into_iter().take(MAX_REPORTED_ERRORS).collect();
+    Err((
+        StatusCode::BAD_REQUEST,
+        Json(
+            ApiErrorResponse::new("E0002", "request does not match the API schema")
+                .with_details(json!({ "errors": issues })),
+        ),
+    )
+        .into_response())
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response {
+    (status, Json(ApiErrorResponse::new(code, message))).into_response()
+}
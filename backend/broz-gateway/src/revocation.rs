@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::clients::redis::{revoked_token_key, tokens_revoked_before_key};
+use broz_shared::ApiErrorResponse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::routes::auth::AuthInfo;
+use crate::routes::rate_limit::FailMode;
+use crate::AppState;
+
+/// Cached entries above which expired ones are pruned on insert.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Recent revocation verdicts by token id, so a client's burst of requests
+/// costs one Redis round trip per `revocation_cache_secs`.
+#[derive(Default)]
+pub struct RevocationCache {
+    verdicts: Mutex<HashMap<Uuid, (bool, Instant)>>,
+}
+
+impl RevocationCache {
+    fn get(&self, jti: Uuid, ttl: Duration) -> Option<bool> {
+        let verdicts = self.verdicts.lock().unwrap();
+        verdicts
+            .get(&jti)
+            .filter(|(_, checked_at)| checked_at.elapsed() < ttl)
+            .map(|(revoked, _)| *revoked)
+    }
+
+    fn insert(&self, jti: Uuid, revoked: bool, ttl: Duration) {
+        let mut verdicts = self.verdicts.lock().unwrap();
+        if verdicts.len() >= PRUNE_THRESHOLD {
+            verdicts.retain(|_, (_, checked_at)| checked_at.elapsed() < ttl);
+        }
+        verdicts.insert(jti, (revoked, Instant::now()));
+    }
+}
+
+/// Reject tokens revoked since they were issued: the token itself (logout)
+/// or every token of the user issued before a watermark (password reset,
+/// role change, ban).
+///
+/// Keys: `revoked:jti:{jti}` and `revoked:user:{user_id}`, written by
+/// broz-auth and broz-moderation.  Verdicts are cached locally, so a
+/// revocation takes up to `revocation_cache_secs` to apply everywhere.
+///
+/// Returns 401 (E1005, token invalid) if the token is revoked, or, when
+/// Redis fails and `revocation_fail_mode` is closed, 503.
+#[allow(clippy::result_large_err)]
+pub async fn check_revocation(state: &AppState, auth_info: &AuthInfo) -> Result<(), Response> {
+    let ttl = Duration::from_secs(state.config.revocation_cache_secs);
+    let revoked = match state.revocations.get(auth_info.token_id, ttl) {
+        Some(revoked) => revoked,
+        None => {
+            let keys = [
+                revoked_token_key(auth_info.token_id),
+                tokens_revoked_before_key(auth_info.user_id),
+            ];
+            let mut conn = state.redis.clone();
+            let timeout = Duration::from_millis(state.config.revocation_timeout_ms);
+            let mut mget = redis::cmd("MGET");
+            mget.arg(&keys);
+            let query = mget.query_async::<_, (Option<i64>, Option<i64>)>(&mut conn);
+
+            let (denied, revoked_before) = match tokio::time::timeout(timeout, query).await {
+                Ok(Ok(reply)) => reply,
+                Ok(Err(e)) => {
+                    tracing::error!(error = %e, "revocation check failed");
+                    return fail(state.config.revocation_fail_mode);
+                }
+                Err(_) => {
+                    tracing::warn!(timeout_ms = state.config.revocation_timeout_ms, "revocation check timed out");
+                    return fail(state.config.revocation_fail_mode);
+                }
+            };
+            let revoked = denied.is_some() || revoked_before.is_some_and(|t| auth_info.issued_at < t);
+            state.revocations.insert(auth_info.token_id, revoked, ttl);
+            revoked
+        }
+    };
+
+    if revoked {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiErrorResponse::new("E1005", "token has been revoked")),
+        )
+            .into_response());
+    }
+    Ok(())
+}
+
+#[allow(clippy::result_large_err)]
+fn fail(mode: FailMode) -> Result<(), Response> {
+    match mode {
+        FailMode::Open => Ok(()),
+        FailMode::Closed => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse::new("E0007", "revocation check unavailable")),
+        )
+            .into_response()),
+    }
+}
@@ -0,0 +1,610 @@
+use broz_shared::UserRole;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+
+/// Route table compiled into the binary, used unless `routes_file` is set.
+const DEFAULT_ROUTES: &str = include_str!("../routes.toml");
+
+/// First upstream path segment of service-to-service endpoints, which are
+/// never reachable through the gateway.
+const INTERNAL_SEGMENT: &str = "internal";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouteTableError {
+    #[error("failed to load route table: {0}")]
+    Load(#[from] config::ConfigError),
+    #[error("invalid route table: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+/// Who may call a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthRequirement {
+    /// No token needed.
+    Public,
+    /// Any valid token.
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl AuthRequirement {
+    /// Whether a caller with `role` satisfies this requirement.
+    pub fn allows(self, role: UserRole) -> bool {
+        match self {
+            AuthRequirement::Public | AuthRequirement::User => true,
+            AuthRequirement::Moderator => matches!(role, UserRole::Moderator | UserRole::Admin),
+            AuthRequirement::Admin => role == UserRole::Admin,
+        }
+    }
+}
+
+/// How much a request on a route counts against rate limits.  Requests
+/// are charged to the user, or to the client IP on public routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitClass {
+    /// Not rate limited.
+    None,
+    #[default]
+    Standard,
+    /// Media uploads.
+    Upload,
+    /// Credential checks and emails (login, password reset, ...).
+    Auth,
+}
+
+impl RateLimitClass {
+    /// Units charged per request.
+    pub fn cost(self) -> u64 {
+        match self {
+            RateLimitClass::None => 0,
+            RateLimitClass::Standard => 1,
+            RateLimitClass::Auth => 5,
+            RateLimitClass::Upload => 10,
+        }
+    }
+}
+
+/// Whose requests share a cached response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheVary {
+    /// Everyone allowed on the route.
+    #[default]
+    None,
+    /// Only the same user's.
+    User,
+}
+
+/// Response caching for a route's `GET` requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    pub ttl_secs: u64,
+    #[serde(default)]
+    pub vary: CacheVary,
+    /// Tags that events invalidate cached responses by.  `{name}` stands
+    /// for the pattern's `:name` segment, `{user}` for the caller.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Upstream path = `prepend` + request path without `strip`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rewrite {
+    #[serde(default)]
+    pub strip: String,
+    #[serde(default)]
+    pub prepend: String,
+}
+
+/// One entry of the route table, as written in `routes.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteEntry {
+    pub pattern: String,
+    pub upstream: String,
+    #[serde(default)]
+    pub rewrite: Rewrite,
+    #[serde(default)]
+    pub auth: AuthRequirement,
+    #[serde(default = "default_body_limit_mb")]
+    pub body_limit_mb: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub rate_limit: RateLimitClass,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheSettings>,
+}
+
+fn default_body_limit_mb() -> u64 { 10 }
+fn default_timeout_secs() -> u64 { 30 }
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteFile {
+    routes: Vec<RouteEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `:name`, one segment.
+    Param(String),
+    /// Trailing `*`, zero or more segments.
+    Rest,
+}
+
+/// A validated route with its upstream resolved.
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    #[serde(flatten)]
+    pub entry: RouteEntry,
+    /// Base URLs of the upstream's instances.
+    pub instances: Vec<String>,
+    #[serde(skip)]
+    segments: Vec<Segment>,
+}
+
+impl Route {
+    pub fn body_limit(&self) -> u64 {
+        self.entry.body_limit_mb * 1024 * 1024
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.entry.timeout_secs)
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let mut parts = path.trim_start_matches('/').split('/');
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest => return true,
+                Segment::Param(_) => {
+                    if parts.next().is_none_or(str::is_empty) {
+                        return false;
+                    }
+                }
+                Segment::Literal(literal) => {
+                    if parts.next() != Some(literal.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+        parts.next().is_none()
+    }
+
+    /// Upstream path and query for a matching request path, to append to
+    /// an instance's base URL.
+    pub fn upstream_path(&self, path: &str, query: Option<&str>) -> String {
+        let rest = path.strip_prefix(self.entry.rewrite.strip.as_str()).unwrap_or(path);
+        let mut upstream_path = format!("{}{rest}", self.entry.rewrite.prepend);
+        if upstream_path.is_empty() {
+            upstream_path.push('/');
+        }
+        match query {
+            Some(q) => format!("{upstream_path}?{q}"),
+            None => upstream_path,
+        }
+    }
+
+    /// The route's cache tags for a matching request path, with the
+    /// pattern's segments and the caller filled in.
+    pub fn cache_tags(&self, path: &str, user: Option<Uuid>) -> Vec<String> {
+        let Some(cache) = &self.entry.cache else {
+            return Vec::new();
+        };
+        let parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        cache
+            .tags
+            .iter()
+            .map(|template| {
+                let mut tag = template.clone();
+                if let Some(user) = user {
+                    tag = tag.replace("{user}", &user.to_string());
+                }
+                for (segment, part) in self.segments.iter().zip(&parts) {
+                    if let Segment::Param(name) = segment {
+                        tag = tag.replace(&format!("{{{name}}}"), part);
+                    }
+                }
+                tag
+            })
+            .collect()
+    }
+
+    /// The request path this route would rewrite to `upstream_path`, the
+    /// inverse of [`Route::upstream_path`].  `None` if the rewrite cannot
+    /// produce it.
+    pub fn request_path(&self, upstream_path: &str) -> Option<String> {
+        let rest = upstream_path.strip_prefix(self.entry.rewrite.prepend.as_str())?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        Some(format!("{}{rest}", self.entry.rewrite.strip))
+    }
+
+    /// Pattern literals past `rewrite.strip`, i.e. how much of the upstream
+    /// path the pattern pins down.
+    fn specificity(&self) -> usize {
+        let stripped = self.entry.rewrite.strip.split('/').filter(|s| !s.is_empty()).count();
+        self.segments
+            .iter()
+            .skip(stripped)
+            .filter(|s| matches!(s, Segment::Literal(_)))
+            .count()
+    }
+}
+
+/// The gateway's routes, tried in order.
+#[derive(Debug, Clone)]
+pub struct RouteTable {
+    routes: Vec<Route>,
+}
+
+impl RouteTable {
+    /// Load `config.routes_file`, or the built-in table, and validate it.
+    pub fn load(config: &AppConfig) -> Result<Self, RouteTableError> {
+        let builder = config::Config::builder();
+        let builder = match &config.routes_file {
+            Some(path) => builder.add_source(config::File::new(path, config::FileFormat::Toml)),
+            None => builder.add_source(config::File::from_str(DEFAULT_ROUTES, config::FileFormat::Toml)),
+        };
+        let file: RouteFile = builder.build()?.try_deserialize()?;
+        Self::from_entries(file.routes, config)
+    }
+
+    fn from_entries(entries: Vec<RouteEntry>, config: &AppConfig) -> Result<Self, RouteTableError> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        let mut routes = Vec::with_capacity(entries.len());
+
+        for (index, entry) in entries.into_iter().enumerate() {
+            let mut problem = |message: String| {
+                problems.push(format!("route {} ({}): {message}", index + 1, entry.pattern));
+            };
+
+            let segments = match parse_pattern(&entry.pattern) {
+                Ok(segments) => segments,
+                Err(message) => {
+                    problem(message);
+                    continue;
+                }
+            };
+            if !seen.insert(entry.pattern.clone()) {
+                problem("duplicate pattern".into());
+            }
+
+            let literal_prefix: String = segments
+                .iter()
+                .map_while(|s| match s {
+                    Segment::Literal(l) => Some(format!("/{l}")),
+                    _ => None,
+                })
+                .collect();
+            let strip = entry.rewrite.strip.as_str();
+            let strips_segments = strip.is_empty()
+                || literal_prefix
+                    .strip_prefix(strip)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+            if !strips_segments || strip.ends_with('/') {
+                problem(format!(
+                    "rewrite.strip '{}' is not a whole-segment prefix of the pattern",
+                    entry.rewrite.strip
+                ));
+            }
+            if !entry.rewrite.prepend.is_empty() && !entry.rewrite.prepend.starts_with('/') {
+                problem("rewrite.prepend must start with '/'".into());
+            }
+            if entry.rewrite.prepend.split('/').nth(1) == Some(INTERNAL_SEGMENT) {
+                problem("routes cannot target internal endpoints".into());
+            }
+
+            let instances = config.upstream_instances(&entry.upstream);
+            if instances.is_empty() {
+                problem(format!("unknown upstream '{}'", entry.upstream));
+            }
+
+            if entry.body_limit_mb == 0 {
+                problem("body_limit_mb must be at least 1".into());
+            }
+            if entry.timeout_secs == 0 {
+                problem("timeout_secs must be at least 1".into());
+            }
+            if let Some(cache) = &entry.cache {
+                if cache.ttl_secs == 0 {
+                    problem("cache.ttl_secs must be at least 1".into());
+                }
+                let anonymous = entry.auth == AuthRequirement::Public;
+                if anonymous && cache.vary == CacheVary::User {
+                    problem("cache.vary = \"user\" needs an authenticated route".into());
+                }
+                for name in cache.tags.iter().flat_map(|tag| placeholders(tag)) {
+                    let known = if name == "user" {
+                        !anonymous
+                    } else {
+                        segments.contains(&Segment::Param(name.to_string()))
+                    };
+                    if !known {
+                        problem(format!("unknown placeholder '{{{name}}}' in cache.tags"));
+                    }
+                }
+            }
+
+            routes.push(Route { entry, instances, segments });
+        }
+
+        if routes.is_empty() && problems.is_empty() {
+            problems.push("no routes".into());
+        }
+        if problems.is_empty() {
+            Ok(Self { routes })
+        } else {
+            Err(RouteTableError::Invalid(problems))
+        }
+    }
+
+    /// The first route matching `path`.
+    pub fn resolve(&self, path: &str) -> Option<&Route> {
+        self.routes.iter().find(|route| route.matches(path))
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The public path and route under which `upstream` serves
+    /// `upstream_path`, if any.  Where several routes reach it, the one
+    /// whose pattern names most of the upstream path wins (`/api/admin/*`
+    /// over `/api/interactions/*` for `/admin/stats`).
+    pub fn public_path(&self, upstream: &str, upstream_path: &str) -> Option<(String, &Route)> {
+        if is_internal(upstream_path) {
+            return None;
+        }
+        let mut best: Option<(String, &Route)> = None;
+        for route in self.routes.iter().filter(|r| r.entry.upstream == upstream) {
+            let Some(path) = route.request_path(upstream_path) else {
+                continue;
+            };
+            if !self.resolve(&path).is_some_and(|r| std::ptr::eq(r, route)) {
+                continue;
+            }
+            if best.as_ref().is_none_or(|(_, b)| route.specificity() > b.specificity()) {
+                best = Some((path, route));
+            }
+        }
+        best
+    }
+}
+
+/// Whether an upstream path targets a service-internal endpoint, once `.`,
+/// `..` and their percent-encoded forms are resolved the way the outgoing
+/// request URL will be: appended to the instance's base URL, so a leading
+/// `//` stays part of the path.  Unparseable paths count as internal.
+pub fn is_internal(upstream_path: &str) -> bool {
+    match reqwest::Url::parse(&format!("http://upstream{upstream_path}")) {
+        Ok(url) => url
+            .path_segments()
+            .and_then(|mut segments| segments.find(|s| !s.is_empty()))
+            .is_some_and(|first| first.eq_ignore_ascii_case(INTERNAL_SEGMENT)),
+        Err(_) => true,
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Segment>, String> {
+    let Some(rest) = pattern.strip_prefix('/') else {
+        return Err("pattern must start with '/'".into());
+    };
+    let parts: Vec<&str> = rest.split('/').collect();
+    let mut segments = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let segment = match *part {
+            "*" if i == parts.len() - 1 => Segment::Rest,
+            "*" => return Err("'*' is only allowed as the last segment".into()),
+            "" => return Err("empty path segment".into()),
+            p if p.starts_with(':') && p.len() > 1 => Segment::Param(p[1..].to_string()),
+            p if p.contains(['*', ':']) => return Err(format!("invalid segment '{p}'")),
+            p => Segment::Literal(p.to_string()),
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// Names of the `{name}` placeholders in a cache tag.
+fn placeholders(tag: &str) -> impl Iterator<Item = &str> {
+    tag.split('{').skip(1).filter_map(|s| s.split_once('}').map(|(name, _)| name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> AppConfig {
+        config::Config::builder().build().unwrap().try_deserialize().unwrap()
+    }
+
+    fn entries(toml: &str) -> Vec<RouteEntry> {
+        let file: RouteFile = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        file.routes
+    }
+
+    fn table(toml: &str) -> RouteTable {
+        RouteTable::from_entries(entries(toml), &test_config()).unwrap()
+    }
+
+    fn pattern_for<'a>(table: &'a RouteTable, path: &str) -> Option<&'a str> {
+        table.resolve(path).map(|route| route.entry.pattern.as_str())
+    }
+
+    #[test]
+    fn builtin_table_is_valid() {
+        RouteTable::load(&test_config()).unwrap();
+    }
+
+    #[test]
+    fn first_matching_route_wins() {
+        let table = table(
+            r#"
+            [[routes]]
+            pattern = "/api/users/photo"
+            upstream = "user"
+            body_limit_mb = 20
+
+            [[routes]]
+            pattern = "/api/users/:id"
+            upstream = "user"
+
+            [[routes]]
+            pattern = "/api/users/*"
+            upstream = "user"
+
+            [[routes]]
+            pattern = "/api/*"
+            upstream = "auth"
+            "#,
+        );
+
+        assert_eq!(pattern_for(&table, "/api/users/photo"), Some("/api/users/photo"));
+        assert_eq!(pattern_for(&table, "/api/users/42"), Some("/api/users/:id"));
+        assert_eq!(pattern_for(&table, "/api/users/42/likes"), Some("/api/users/*"));
+        assert_eq!(pattern_for(&table, "/api/users"), Some("/api/users/*"));
+        assert_eq!(pattern_for(&table, "/api/login"), Some("/api/*"));
+        assert_eq!(pattern_for(&table, "/health"), None);
+
+        // A catch-all listed first shadows everything after it.
+        let table = self::table(
+            r#"
+            [[routes]]
+            pattern = "/api/*"
+            upstream = "auth"
+
+            [[routes]]
+            pattern = "/api/users/photo"
+            upstream = "user"
+            "#,
+        );
+        assert_eq!(pattern_for(&table, "/api/users/photo"), Some("/api/*"));
+    }
+
+    #[test]
+    fn params_match_exactly_one_segment() {
+        let table = table(
+            r#"
+            [[routes]]
+            pattern = "/api/messages/:id/read"
+            upstream = "messaging"
+            "#,
+        );
+
+        assert!(table.resolve("/api/messages/42/read").is_some());
+        assert!(table.resolve("/api/messages//read").is_none());
+        assert!(table.resolve("/api/messages/42/read/all").is_none());
+        assert!(table.resolve("/api/messages/42").is_none());
+    }
+
+    #[test]
+    fn rewrite_strips_and_prepends() {
+        let table = table(
+            r#"
+            [[routes]]
+            pattern = "/api/users/*"
+            upstream = "user"
+            rewrite = { strip = "/api" }
+
+            [[routes]]
+            pattern = "/ws/messaging/*"
+            upstream = "messaging"
+            rewrite = { strip = "/ws/messaging", prepend = "/socket.io" }
+
+            [[routes]]
+            pattern = "/auth/*"
+            upstream = "auth"
+            rewrite = { strip = "/auth" }
+            "#,
+        );
+
+        let users = table.resolve("/api/users/me").unwrap();
+        assert_eq!(users.upstream_path("/api/users/me", None), "/users/me");
+        assert_eq!(
+            users.upstream_path("/api/users/search", Some("q=a&page=2")),
+            "/users/search?q=a&page=2"
+        );
+        assert_eq!(users.request_path("/users/me").as_deref(), Some("/api/users/me"));
+
+        let ws = table.resolve("/ws/messaging/").unwrap();
+        assert_eq!(ws.upstream_path("/ws/messaging/", Some("EIO=4")), "/socket.io/?EIO=4");
+        assert_eq!(ws.request_path("/socket.io/").as_deref(), Some("/ws/messaging/"));
+        assert_eq!(ws.request_path("/socket.iox"), None);
+
+        // Stripping everything still leaves a path.
+        let auth = table.resolve("/auth").unwrap();
+        assert_eq!(auth.upstream_path("/auth", None), "/");
+    }
+
+    #[test]
+    fn internal_paths_are_detected_after_normalization() {
+        assert!(is_internal("/internal/users/presence"));
+        assert!(is_internal("/Internal/users"));
+        assert!(is_internal("/users/../internal/presence"));
+        assert!(is_internal("/users/%2e%2e/internal/presence"));
+        assert!(is_internal("/users/%2E%2E/%2e/internal"));
+        assert!(is_internal("//internal/users"));
+
+        assert!(!is_internal("/users/internal"));
+        assert!(!is_internal("/internals"));
+        assert!(!is_internal("/users/me?next=/internal"));
+    }
+
+    #[test]
+    fn invalid_entries_are_reported() {
+        let err = RouteTable::from_entries(
+            entries(
+                r#"
+                [[routes]]
+                pattern = "/api/users/*"
+                upstream = "user"
+                rewrite = { strip = "/api/user" }
+
+                [[routes]]
+                pattern = "/api/presence/*"
+                upstream = "user"
+                rewrite = { strip = "/api/presence", prepend = "/internal/presence" }
+
+                [[routes]]
+                pattern = "/api/*/x"
+                upstream = "user"
+
+                [[routes]]
+                pattern = "/api/nowhere"
+                upstream = "billing"
+
+                [[routes]]
+                pattern = "/api/nowhere"
+                upstream = "user"
+                "#,
+            ),
+            &test_config(),
+        )
+        .unwrap_err();
+        let RouteTableError::Invalid(problems) = err else {
+            panic!("expected validation errors, got {err}");
+        };
+        assert_eq!(problems.len(), 5, "{problems:?}");
+    }
+}
@@ -1,7 +1,8 @@
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use broz_shared::{ApiErrorResponse, UserRole};
+use broz_shared::middleware::{sign_identity, IDENTITY_HEADERS, IDENTITY_SIGNATURE_HEADER, IDENTITY_TIMESTAMP_HEADER, TOKEN_ID_HEADER, USER_ID_HEADER, USER_ROLE_HEADER};
+use broz_shared::{ApiErrorResponse, AuthUser, UserRole};
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use uuid::Uuid;
 
@@ -10,6 +11,9 @@ use uuid::Uuid;
 pub struct AuthInfo {
     pub user_id: Uuid,
     pub role: UserRole,
+    pub token_id: Uuid,
+    /// `iat` claim, checked against the user's revocation watermark.
+    pub issued_at: i64,
 }
 
 /// Extract and validate the JWT from request headers.
@@ -19,6 +23,7 @@ pub struct AuthInfo {
 /// - E0004 (Unauthorized): missing or malformed Authorization header
 /// - E0005 (Forbidden): token expired
 /// - E0006 (RateLimited): (unused here, but reserved)
+#[allow(clippy::result_large_err)]
 pub fn extract_auth_user(headers: &HeaderMap, jwt_secret: &str) -> Result<AuthInfo, Response> {
     let auth_header = headers
         .get("Authorization")
@@ -39,8 +44,13 @@ pub fn extract_auth_user(headers: &HeaderMap, jwt_secret: &str) -> Result<AuthIn
             .into_response());
     }
 
-    let token = &auth_header[7..];
+    validate_token(&auth_header[7..], jwt_secret)
+}
 
+/// Validate a raw JWT, wherever it came from (Authorization header or the
+/// `token` query parameter of a WebSocket handshake).
+#[allow(clippy::result_large_err)]
+pub fn validate_token(token: &str, jwt_secret: &str) -> Result<AuthInfo, Response> {
     let mut validation = Validation::new(Algorithm::HS256);
     validation.validate_exp = true;
 
@@ -65,5 +75,37 @@ pub fn extract_auth_user(headers: &HeaderMap, jwt_secret: &str) -> Result<AuthIn
     Ok(AuthInfo {
         user_id: token_data.claims.sub,
         role: token_data.claims.role,
+        token_id: token_data.claims.jti,
+        issued_at: token_data.claims.iat,
     })
 }
+
+/// Replace any client-supplied identity headers with the verified identity,
+/// signed with the gateway secret (or none for unauthenticated requests).
+pub fn set_identity_headers(headers: &mut HeaderMap, auth_info: Option<&AuthInfo>, gateway_secret: &str) {
+    for name in IDENTITY_HEADERS {
+        headers.remove(*name);
+    }
+    let Some(info) = auth_info else {
+        return;
+    };
+
+    let user = AuthUser {
+        id: info.user_id,
+        role: info.role,
+        token_id: info.token_id,
+    };
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign_identity(gateway_secret, &user, timestamp);
+    for (name, value) in [
+        (USER_ID_HEADER, user.id.to_string()),
+        (USER_ROLE_HEADER, user.role.to_string()),
+        (TOKEN_ID_HEADER, user.token_id.to_string()),
+        (IDENTITY_TIMESTAMP_HEADER, timestamp.to_string()),
+        (IDENTITY_SIGNATURE_HEADER, signature),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
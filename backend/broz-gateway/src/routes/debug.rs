@@ -0,0 +1,30 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::{ApiErrorResponse, UserRole};
+use std::sync::Arc;
+
+use crate::revocation::check_revocation;
+use crate::AppState;
+use super::auth::extract_auth_user;
+
+/// Returns the loaded route table, in match order.  Admins only.
+pub async fn routes(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let auth_info = match extract_auth_user(&headers, &state.config.jwt_secret) {
+        Ok(info) => info,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_revocation(&state, &auth_info).await {
+        return resp;
+    }
+    if auth_info.role != UserRole::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ApiErrorResponse::new("E0005", "admin only")),
+        )
+            .into_response();
+    }
+
+    Json(state.routes.routes()).into_response()
+}
@@ -0,0 +1,22 @@
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::ApiErrorResponse;
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// The OpenAPI document of the whole API, merged from the services'
+/// documents under the gateway's public paths.  Public.
+pub async fn openapi(State(state): State<Arc<AppState>>) -> Response {
+    match state.api_specs.merged() {
+        Some(document) => ([(CONTENT_TYPE, "application/json")], document).into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse::new("E0007", "API documentation not loaded yet")),
+        )
+            .into_response(),
+    }
+}
@@ -5,42 +5,56 @@ use axum::Json;
 use broz_shared::{HealthCheck, HealthResponse, HealthStatus};
 use std::sync::Arc;
 
+use crate::upstream::BreakerState;
 use crate::AppState;
 
 /// Health check that probes all downstream services.
+///
+/// Each instance is probed unless its circuit breaker has ejected it.  A
+/// service is healthy when all its instances are, degraded when some are
+/// and unhealthy when none are.
 pub async fn health_check(State(state): State<Arc<AppState>>) -> Response {
-    let services = [
-        ("auth", &state.config.auth_url),
-        ("user", &state.config.user_url),
-        ("matching", &state.config.matching_url),
-        ("messaging", &state.config.messaging_url),
-        ("notification", &state.config.notification_url),
-        ("moderation", &state.config.moderation_url),
-        ("analytics", &state.config.analytics_url),
-    ];
+    let services = ["auth", "user", "matching", "messaging", "notification", "moderation", "analytics"];
 
     let mut checks = Vec::with_capacity(services.len());
 
-    for (name, url) in &services {
-        let health_url = format!("{url}/health");
-        let check = match state.http_client.get(&health_url).timeout(std::time::Duration::from_secs(3)).send().await {
-            Ok(resp) if resp.status().is_success() => HealthCheck {
-                name: name.to_string(),
-                status: HealthStatus::Healthy,
-                message: None,
-            },
-            Ok(resp) => HealthCheck {
-                name: name.to_string(),
-                status: HealthStatus::Degraded,
-                message: Some(format!("status {}", resp.status())),
-            },
-            Err(e) => HealthCheck {
-                name: name.to_string(),
-                status: HealthStatus::Unhealthy,
-                message: Some(format!("{e}")),
-            },
+    for name in services {
+        let Some(pool) = state.upstreams.get(name) else {
+            continue;
         };
-        checks.push(check);
+
+        let mut healthy = 0;
+        let mut problems = Vec::new();
+        for instance in pool.instances() {
+            let breaker = instance.state();
+            if breaker == BreakerState::Open {
+                problems.push(format!("{}: circuit open", instance.url));
+                continue;
+            }
+
+            let health_url = format!("{}/health", instance.url);
+            match state.http_client.get(&health_url).timeout(std::time::Duration::from_secs(3)).send().await {
+                Ok(resp) if resp.status().is_success() && breaker == BreakerState::Closed => healthy += 1,
+                Ok(resp) if resp.status().is_success() => {
+                    problems.push(format!("{}: circuit half-open", instance.url));
+                }
+                Ok(resp) => problems.push(format!("{}: status {}", instance.url, resp.status())),
+                Err(e) => problems.push(format!("{}: {e}", instance.url)),
+            }
+        }
+
+        let status = if problems.is_empty() {
+            HealthStatus::Healthy
+        } else if healthy > 0 {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Unhealthy
+        };
+        checks.push(HealthCheck {
+            name: name.to_string(),
+            status,
+            message: (!problems.is_empty()).then(|| problems.join("; ")),
+        });
     }
 
     let response = HealthResponse::healthy("broz-gateway", env!("CARGO_PKG_VERSION"))
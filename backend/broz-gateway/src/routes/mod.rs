@@ -1,4 +1,7 @@
 pub mod auth;
+pub mod debug;
+pub mod docs;
 pub mod health;
 pub mod proxy;
 pub mod rate_limit;
+pub mod websocket;
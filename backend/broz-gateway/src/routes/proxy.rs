@@ -1,24 +1,26 @@
-use axum::body::Body;
-use axum::extract::{OriginalUri, State};
+use axum::body::{Body, HttpBody};
+use axum::extract::{ConnectInfo, OriginalUri, State};
+use axum::http::header::CONTENT_LENGTH;
 use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use broz_shared::middleware::{PropagateTrace, TRACE_HEADERS};
 use broz_shared::ApiErrorResponse;
+use futures_util::{StreamExt, TryStreamExt};
+use rand::Rng;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 
+use crate::cache::CacheRequest;
+use crate::openapi::validate_request;
+use crate::revocation::check_revocation;
+use crate::route_table::{self, AuthRequirement, Route};
 use crate::AppState;
-use super::auth::extract_auth_user;
-use super::rate_limit::check_rate_limit;
-
-/// Paths that do not require JWT authentication.
-const PUBLIC_PATHS: &[&str] = &[
-    "/api/auth/signup",
-    "/api/auth/login",
-    "/api/auth/refresh",
-    "/api/auth/oauth",
-    "/api/auth/forgot-password",
-    "/api/auth/reset-password",
-];
+use super::auth::{extract_auth_user, set_identity_headers, AuthInfo};
+use super::rate_limit::{check_rate_limit, client_ip, Subject};
 
 /// Headers that must not be forwarded (hop-by-hop).
 const HOP_BY_HOP_HEADERS: &[&str] = &[
@@ -33,166 +35,252 @@ const HOP_BY_HOP_HEADERS: &[&str] = &[
     "host",
 ];
 
-/// Determines whether a given path is public (no JWT required).
-fn is_public(path: &str) -> bool {
-    PUBLIC_PATHS.iter().any(|p| path.starts_with(p))
-}
-
-/// Strip the gateway prefix from the path to produce the upstream path.
-///
-/// Mapping:
-/// - /api/auth/...          -> /...           (strip "/api/auth")
-/// - /api/users/...         -> /...           (strip "/api/users")
-/// - /api/follows/...       -> /follows/...   (strip "/api", keep /follows)
-/// - /api/messages/...      -> /...           (strip "/api/messages")
-/// - /api/notifications/... -> /notifications/... (strip "/api", keep /notifications)
-/// - /api/interactions/...  -> /...           (strip "/api/interactions")
-/// - /api/livecam/...       -> /livecam/...   (strip "/api", keep /livecam)
-/// - /api/admin/...         -> /admin/...     (strip "/api", keep /admin)
-/// - /api/analytics/...     -> /...           (strip "/api/analytics")
-fn strip_prefix(path: &str) -> &str {
-    // Routes that keep their second segment as part of the upstream path
-    if path.starts_with("/api/follows") {
-        return &path[4..]; // "/api/follows/123" -> "/follows/123"
-    }
-    if path.starts_with("/api/admin") {
-        return &path[4..]; // "/api/admin/reports" -> "/admin/reports"
-    }
-    if path.starts_with("/api/livecam") {
-        return &path[4..]; // "/api/livecam/request" -> "/livecam/request"
-    }
-    if path.starts_with("/api/notifications") {
-        return &path[4..]; // "/api/notifications/unread-count" -> "/notifications/unread-count"
-    }
-
-    // All other routes: strip both /api and the service segment
-    let prefixes = &[
-        "/api/auth",
-        "/api/users",
-        "/api/messages",
-        "/api/interactions",
-        "/api/analytics",
-    ];
-
-    for prefix in prefixes {
-        if path.starts_with(prefix) {
-            let rest = &path[prefix.len()..];
-            if rest.is_empty() {
-                return "/";
-            }
-            return rest; // e.g. "/api/auth/login" -> "/login"
-        }
-    }
-
-    // Fallback: forward as-is
-    path
-}
-
 /// The catch-all proxy handler.
 ///
-/// 1. Extract path from OriginalUri
-/// 2. Resolve upstream service (404 if unknown prefix)
-/// 3. For non-public paths: validate JWT and check rate limits
-/// 4. Strip the gateway prefix to build the upstream path
-/// 5. Forward the request (method, headers, body, query string)
-/// 6. Return the upstream response
+/// 1. Resolve the route for the path (404 if none matches)
+/// 2. Unless the route is public: validate the JWT, reject revoked tokens
+///    and check the route's minimum role
+/// 3. Charge the route's rate-limit cost to the user, or to the client IP
+///    on public routes
+/// 4. Rewrite the path for the upstream; service-internal paths
+///    are answered with 404
+/// 5. With `openapi_validation` on, check the request against the
+///    upstream's OpenAPI document
+/// 6. Replace client identity headers with the signed, verified identity
+/// 7. On cached routes, answer `GET`s from the response cache, unless
+///    the invalidation consumer is down
+/// 8. Forward the request (method, headers, streamed body, query string)
+///    to a healthy instance, with the route's body limit and timeout
+/// 9. Stream the upstream response back, with `X-RateLimit-*` headers,
+///    caching it on cached routes
 pub async fn proxy_handler(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     OriginalUri(original_uri): OriginalUri,
     method: Method,
-    headers: HeaderMap,
+    mut headers: HeaderMap,
     body: Body,
 ) -> Response {
     let path = original_uri.path();
     let query = original_uri.query();
 
-    // 1. Resolve upstream
-    let upstream_base = match state.config.resolve_upstream(path) {
-        Some(url) => url,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiErrorResponse::new("E0003", "no upstream service for this path")),
-            )
-                .into_response();
-        }
+    // 1. Resolve route
+    let Some(route) = state.routes.resolve(path) else {
+        return not_found();
     };
 
-    // 2. Auth + rate limit for non-public paths
-    if !is_public(path) {
+    // 2. Auth + role for non-public routes
+    let mut identity: Option<AuthInfo> = None;
+    if route.entry.auth != AuthRequirement::Public {
         let auth_info = match extract_auth_user(&headers, &state.config.jwt_secret) {
             Ok(info) => info,
             Err(resp) => return resp,
         };
+        if let Err(resp) = check_revocation(&state, &auth_info).await {
+            return resp;
+        }
 
-        if let Err(status) = check_rate_limit(
-            &state.redis,
-            auth_info.user_id,
-            auth_info.role,
-            &state.config,
-        )
-        .await
-        {
-            return (
-                status,
-                Json(ApiErrorResponse::new("E0006", "rate limit exceeded")),
-            )
-                .into_response();
+        if !route.entry.auth.allows(auth_info.role) {
+            return insufficient_role();
         }
+        identity = Some(auth_info);
     }
 
-    // 3. Build upstream URL
-    let upstream_path = strip_prefix(path);
-    let upstream_url = match query {
-        Some(q) => format!("{upstream_base}{upstream_path}?{q}"),
-        None => format!("{upstream_base}{upstream_path}"),
+    // 3. Rate limit
+    let subject = match &identity {
+        Some(info) => Subject::User(info.user_id, info.role),
+        None => Subject::Ip(client_ip(&headers, peer, state.config.trust_forwarded_for)),
+    };
+    let rate_limit = match check_rate_limit(&state.redis, subject, route.entry.rate_limit, &state.config).await {
+        Ok(status) => status,
+        Err(resp) => return resp,
     };
 
-    // 4. Read body (max 10 MB)
-    let body_bytes = match axum::body::to_bytes(body, 10 * 1024 * 1024).await {
-        Ok(b) => b,
-        Err(_) => {
-            return (
-                StatusCode::PAYLOAD_TOO_LARGE,
-                Json(ApiErrorResponse::new("E0009", "request body too large (max 10MB)")),
-            )
-                .into_response();
-        }
+    // 4. Build upstream path
+    let upstream_path = route.upstream_path(path, query);
+    if route_table::is_internal(&upstream_path) {
+        return not_found();
+    }
+
+    // 5. Schema validation
+    let body = match validate_request(&state, route, &method, path, query, &headers, body).await {
+        Ok(body) => body,
+        Err(resp) => return resp,
     };
 
-    // 5. Build upstream request
-    let mut upstream_req = state
-        .http_client
-        .request(
-            reqwest::Method::from_bytes(method.as_str().as_bytes()).unwrap_or(reqwest::Method::GET),
-            &upstream_url,
-        )
-        .body(body_bytes.to_vec());
-
-    // Forward headers, skipping hop-by-hop
-    for (name, value) in headers.iter() {
-        let name_lower = name.as_str().to_lowercase();
-        if HOP_BY_HOP_HEADERS.contains(&name_lower.as_str()) {
-            continue;
-        }
-        if let Ok(val) = value.to_str() {
-            upstream_req = upstream_req.header(name.as_str(), val);
+    // 6. Identity headers
+    set_identity_headers(&mut headers, identity.as_ref(), &state.config.gateway_secret);
+
+    // 7. Response cache, bypassed while invalidations could be missed
+    let cache = if state.cache_invalidation.is_running() {
+        CacheRequest::new(route, &method, path, query, identity.as_ref(), &mut headers)
+    } else {
+        None
+    };
+    let cached = match &cache {
+        Some(cache) => cache.lookup(&state).await,
+        None => None,
+    };
+
+    let mut resp = match cached {
+        Some(resp) => resp,
+        None => {
+            let resp = forward(&state, method, &headers, body, route, &upstream_path, None).await;
+            match cache {
+                Some(cache) => cache.store(&state, resp).await,
+                None => resp,
+            }
         }
+    };
+    if let Some(status) = rate_limit {
+        status.apply(resp.headers_mut());
     }
+    resp
+}
+
+/// 404 for paths no route serves.
+pub fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiErrorResponse::new("E0003", "no upstream service for this path")),
+    )
+        .into_response()
+}
+
+/// 403 for callers below a route's minimum role.
+pub fn insufficient_role() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(ApiErrorResponse::new("E0005", "insufficient role for this route")),
+    )
+        .into_response()
+}
 
-    // 6. Send and return upstream response
-    let upstream_resp = match upstream_req.send().await {
-        Ok(r) => r,
-        Err(e) => {
-            tracing::error!(error = %e, upstream = %upstream_url, "upstream request failed");
+/// Forward a request to an instance of the route's upstream and relay the
+/// response.
+///
+/// Instances are tried round-robin, or starting from the one `affinity`
+/// maps to, skipping those whose circuit breaker
+/// is open; 503 if none is available.  Connection errors, timeouts and
+/// 502/503/504 responses count against the instance's breaker.  Idempotent
+/// requests without a body are retried on another instance, up to
+/// `upstream_max_retries` times with jittered exponential backoff; each
+/// attempt gets the route's timeout.
+///
+/// Bodies are streamed in both directions, never buffered.  `Content-Length`
+/// is passed through when the client or upstream sends one; otherwise the
+/// body is chunked.  `Range` requests and `206` responses pass through
+/// unchanged.  Hop-by-hop headers are dropped in both directions.
+pub async fn forward(
+    state: &AppState,
+    method: Method,
+    headers: &HeaderMap,
+    body: Body,
+    route: &Route,
+    upstream_path: &str,
+    affinity: Option<Uuid>,
+) -> Response {
+    let Some(pool) = state.upstreams.get(&route.entry.upstream) else {
+        return not_found();
+    };
+    let body_limit = route.body_limit();
+
+    // Check the declared body size, then enforce the limit while streaming
+    let declared_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared_length.is_some_and(|len| len > body_limit) {
+        return body_too_large(body_limit);
+    }
+
+    let too_large = Arc::new(AtomicBool::new(false));
+    let mut upstream_body = None;
+    if body.size_hint().exact() != Some(0) {
+        let too_large = too_large.clone();
+        let mut received: u64 = 0;
+        let stream = body.into_data_stream().map(move |chunk| {
+            let chunk = chunk.map_err(std::io::Error::other)?;
+            received += chunk.len() as u64;
+            if received > body_limit {
+                too_large.store(true, Ordering::Relaxed);
+                return Err(std::io::Error::other("request body too large"));
+            }
+            Ok(chunk)
+        });
+        upstream_body = Some(reqwest::Body::wrap_stream(stream));
+    }
+
+    // A streamed body cannot be replayed, so only bodiless requests retry
+    let attempts = if upstream_body.is_none() && method.is_idempotent() {
+        1 + state.config.upstream_max_retries
+    } else {
+        1
+    };
+    let method = reqwest::Method::from_bytes(method.as_str().as_bytes()).unwrap_or(reqwest::Method::GET);
+
+    let mut attempt = 0;
+    let (upstream_resp, upstream_url) = loop {
+        attempt += 1;
+        let Some(instance) = pool.pick(affinity) else {
+            tracing::warn!(upstream = %route.entry.upstream, "no upstream instance available");
             return (
-                StatusCode::BAD_GATEWAY,
-                Json(ApiErrorResponse::new("E0007", format!("upstream unavailable: {e}"))),
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiErrorResponse::new("E0007", "upstream unavailable: circuit open")),
             )
                 .into_response();
+        };
+        let upstream_url = format!("{}{upstream_path}", instance.url);
+
+        // Build upstream request, forwarding headers except hop-by-hop, and
+        // the trace context of this gateway request instead of the client's
+        let mut upstream_req = state
+            .http_client
+            .request(method.clone(), &upstream_url)
+            .timeout(route.timeout());
+        if let Some(body) = upstream_body.take() {
+            upstream_req = upstream_req.body(body);
+        }
+        for (name, value) in headers.iter() {
+            let name_lower = name.as_str().to_lowercase();
+            if HOP_BY_HOP_HEADERS.contains(&name_lower.as_str()) || TRACE_HEADERS.contains(&name_lower.as_str()) {
+                continue;
+            }
+            upstream_req = upstream_req.header(name.as_str(), value.as_bytes());
+        }
+        upstream_req = upstream_req.with_trace_context();
+
+        match upstream_req.send().await {
+            Ok(resp) if is_upstream_failure(resp.status()) => {
+                instance.record_failure();
+                if attempt >= attempts {
+                    break (resp, upstream_url);
+                }
+                tracing::warn!(status = %resp.status(), upstream = %upstream_url, attempt, "retrying upstream request");
+            }
+            Ok(resp) => {
+                instance.record_success();
+                break (resp, upstream_url);
+            }
+            Err(_) if too_large.load(Ordering::Relaxed) => return body_too_large(body_limit),
+            Err(e) => {
+                instance.record_failure();
+                if attempt >= attempts {
+                    tracing::error!(error = %e, upstream = %upstream_url, "upstream request failed");
+                    return (
+                        StatusCode::BAD_GATEWAY,
+                        Json(ApiErrorResponse::new("E0007", format!("upstream unavailable: {e}"))),
+                    )
+                        .into_response();
+                }
+                tracing::warn!(error = %e, upstream = %upstream_url, attempt, "retrying upstream request");
+            }
         }
+
+        tokio::time::sleep(retry_backoff(state.config.upstream_retry_backoff_ms, attempt)).await;
     };
 
+    // Stream the upstream response back
     let status = StatusCode::from_u16(upstream_resp.status().as_u16())
         .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
@@ -206,21 +294,36 @@ pub async fn proxy_handler(
             HeaderName::from_bytes(name.as_str().as_bytes()),
             HeaderValue::from_bytes(value.as_bytes()),
         ) {
-            response_headers.insert(hn, hv);
+            response_headers.append(hn, hv);
         }
     }
 
-    let resp_body = match upstream_resp.bytes().await {
-        Ok(b) => b,
-        Err(e) => {
-            tracing::error!(error = %e, "failed to read upstream response body");
-            return (
-                StatusCode::BAD_GATEWAY,
-                Json(ApiErrorResponse::new("E0007", "failed to read upstream response")),
-            )
-                .into_response();
-        }
-    };
+    let resp_body = Body::from_stream(upstream_resp.bytes_stream().inspect_err(move |e| {
+        tracing::error!(error = %e, upstream = %upstream_url, "upstream response body failed");
+    }));
 
     (status, response_headers, resp_body).into_response()
 }
+
+/// Responses that mean the instance, not the request, is at fault.
+fn is_upstream_failure(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502..=504)
+}
+
+/// Full jitter: uniform in `[0, base * 2^(attempt - 1)]`, capped at 1s.
+fn retry_backoff(base_ms: u64, attempt: u32) -> Duration {
+    let ceiling = base_ms.saturating_mul(1 << (attempt - 1).min(10)).min(1000);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+}
+
+/// 413 for bodies over a route's limit.
+pub fn body_too_large(limit: u64) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ApiErrorResponse::new(
+            "E0009",
+            format!("request body too large (max {}MB)", limit / (1024 * 1024)),
+        )),
+    )
+        .into_response()
+}
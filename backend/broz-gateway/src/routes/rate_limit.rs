@@ -1,66 +1,290 @@
-use axum::http::StatusCode;
-use broz_shared::UserRole;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::{ApiErrorResponse, UserRole};
 use chrono::Utc;
-use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use redis::Script;
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::config::AppConfig;
+use crate::route_table::RateLimitClass;
 
-/// Check per-minute and per-hour rate limits for a user.
+/// Sliding-window counter over several windows, checked and charged
+/// atomically.
 ///
-/// Keys:
-/// - Per-minute: `rl:{user_id}:min:{YYYYMMDDHHMM}`
-/// - Per-hour:   `rl:{user_id}:hr:{YYYYMMDDHH}`
+/// Each window keeps one counter per fixed bucket; usage is the current
+/// bucket plus the previous one weighted by how much of it still overlaps
+/// the window.  The request is charged to every window only if all of them
+/// have room.
 ///
-/// `UserRole::User` gets the free-tier limits; `Moderator`/`Admin` get premium limits.
+/// KEYS[i]           counter prefix of window i
+/// ARGV[1], ARGV[2]  now (ms), cost
+/// ARGV[2i+1..2i+2]  length (ms) and limit of window i
 ///
-/// Returns `Err(StatusCode::TOO_MANY_REQUESTS)` if any limit is exceeded.
+/// Returns `{allowed, limit, remaining, reset_ms}` for the tightest window.
+/// `reset_ms` is when the request would fit if denied, or when the current
+/// bucket rolls over if allowed.
+static SLIDING_WINDOW: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local now = tonumber(ARGV[1])
+local cost = tonumber(ARGV[2])
+local allowed = 1
+local out_limit, out_remaining, out_reset = 0, nil, 0
+local charge = {}
+
+for i, prefix in ipairs(KEYS) do
+    local window = tonumber(ARGV[1 + 2 * i])
+    local limit = tonumber(ARGV[2 + 2 * i])
+    local bucket = math.floor(now / window)
+    local elapsed = now - bucket * window
+    local curr_key = prefix .. ':' .. bucket
+    local prev = tonumber(redis.call('GET', prefix .. ':' .. (bucket - 1)) or '0')
+    local curr = tonumber(redis.call('GET', curr_key) or '0')
+    local used = prev * (window - elapsed) / window + curr
+    local remaining = math.max(0, math.floor(limit - used))
+
+    if used + cost > limit then
+        local reset
+        if curr + cost <= limit and prev > 0 then
+            -- fits once enough of the previous bucket has slid out
+            local weight = (limit - curr - cost) / prev
+            reset = window * (1 - weight) - elapsed
+        else
+            -- only fits in a later bucket, with this one as its predecessor
+            local weight = curr > 0 and math.max(0, (limit - cost) / curr) or 1
+            reset = window - elapsed + window * (1 - weight)
+        end
+        if allowed == 1 or reset > out_reset then
+            out_limit, out_remaining, out_reset = limit, remaining, reset
+        end
+        allowed = 0
+    elseif allowed == 1 and (out_remaining == nil or remaining - cost < out_remaining) then
+        out_limit, out_remaining, out_reset = limit, remaining - cost, window - elapsed
+    end
+    charge[#charge + 1] = {curr_key, window}
+end
+
+if allowed == 1 then
+    for _, c in ipairs(charge) do
+        redis.call('INCRBY', c[1], cost)
+        redis.call('PEXPIRE', c[1], c[2] * 2)
+    end
+end
+
+return {allowed, out_limit, out_remaining or 0, math.ceil(out_reset)}
+"#,
+    )
+});
+
+/// What to do with a request when Redis errors or does not answer within
+/// `rate_limit_timeout_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailMode {
+    /// Let the request through unlimited.
+    #[default]
+    Open,
+    /// Reject it with 503.
+    Closed,
+}
+
+/// Who a request is charged to.
+#[derive(Debug, Clone, Copy)]
+pub enum Subject {
+    /// An authenticated user, with the limits of their tier.
+    User(Uuid, UserRole),
+    /// An anonymous client on a public route.
+    Ip(IpAddr),
+}
+
+/// The tightest window after a request, sent as `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the request fits again (denied) or the window rolls
+    /// over (allowed).
+    pub reset_secs: u64,
+}
+
+impl RateLimitStatus {
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in [
+            ("x-ratelimit-limit", self.limit),
+            ("x-ratelimit-remaining", self.remaining),
+            ("x-ratelimit-reset", self.reset_secs),
+        ] {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+    }
+}
+
+/// Charge a request of `class` to `subject`.
+///
+/// Users get per-minute and per-hour windows by tier (`UserRole::User` the
+/// free tier, `Moderator`/`Admin` premium); anonymous clients get the
+/// per-IP windows.  Each request costs its class's weight.
+///
+/// Keys: `rl:user:{user_id}:{window_ms}:{bucket}` and
+/// `rl:ip:{addr}:{window_ms}:{bucket}`.
+///
+/// Returns the status to report on the response, `None` if the request
+/// was not limited, or the 429 (or, failing closed, 503) to send instead.
+#[allow(clippy::result_large_err)]
 pub async fn check_rate_limit(
-    redis: &tokio::sync::Mutex<redis::aio::ConnectionManager>,
-    user_id: Uuid,
-    role: UserRole,
+    redis: &ConnectionManager,
+    subject: Subject,
+    class: RateLimitClass,
     config: &AppConfig,
-) -> Result<(), StatusCode> {
-    let now = Utc::now();
-    let minute_key = format!("rl:{}:min:{}", user_id, now.format("%Y%m%d%H%M"));
-    let hour_key = format!("rl:{}:hr:{}", user_id, now.format("%Y%m%d%H"));
-
-    let (rpm_limit, rph_limit) = match role {
-        UserRole::User => (config.free_rpm, config.free_rph),
-        UserRole::Moderator | UserRole::Admin => (config.premium_rpm, config.premium_rph),
+) -> Result<Option<RateLimitStatus>, Response> {
+    let cost = class.cost();
+    if cost == 0 {
+        return Ok(None);
+    }
+
+    let (prefix, per_minute, per_hour) = match subject {
+        Subject::User(user_id, UserRole::User) => (format!("rl:user:{user_id}"), config.free_rpm, config.free_rph),
+        Subject::User(user_id, UserRole::Moderator | UserRole::Admin) => {
+            (format!("rl:user:{user_id}"), config.premium_rpm, config.premium_rph)
+        }
+        Subject::Ip(addr) => (format!("rl:ip:{addr}"), config.ip_rpm, config.ip_rph),
     };
 
-    let mut conn = redis.lock().await;
-
-    // Per-minute check
-    let minute_count: u64 = conn.incr(&minute_key, 1u64).await.map_err(|e| {
-        tracing::error!(error = %e, "redis incr failed for minute key");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    if minute_count == 1 {
-        let _: () = conn.expire(&minute_key, 60).await.map_err(|e| {
-            tracing::error!(error = %e, "redis expire failed for minute key");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let mut invocation = SLIDING_WINDOW.prepare_invoke();
+    invocation.arg(Utc::now().timestamp_millis()).arg(cost);
+    for (window_ms, limit) in [(60_000u64, per_minute), (3_600_000, per_hour)] {
+        invocation.key(format!("{prefix}:{window_ms}")).arg(window_ms).arg(limit);
     }
-    if minute_count > rpm_limit {
-        return Err(StatusCode::TOO_MANY_REQUESTS);
+
+    // ConnectionManager multiplexes one connection; clones are cheap
+    let mut conn = redis.clone();
+    let timeout = Duration::from_millis(config.rate_limit_timeout_ms);
+    let result = tokio::time::timeout(timeout, invocation.invoke_async::<_, (u8, u64, u64, u64)>(&mut conn)).await;
+
+    let (allowed, limit, remaining, reset_ms) = match result {
+        Ok(Ok(reply)) => reply,
+        Ok(Err(e)) => {
+            tracing::error!(error = %e, "rate limit script failed");
+            return fail(config.rate_limit_fail_mode);
+        }
+        Err(_) => {
+            tracing::warn!(timeout_ms = config.rate_limit_timeout_ms, "rate limit check timed out");
+            return fail(config.rate_limit_fail_mode);
+        }
+    };
+
+    let status = RateLimitStatus {
+        limit,
+        remaining,
+        reset_secs: reset_ms.div_ceil(1000),
+    };
+    if allowed == 1 {
+        return Ok(Some(status));
+    }
+
+    let mut resp = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ApiErrorResponse::new("E0006", "rate limit exceeded")),
+    )
+        .into_response();
+    status.apply(resp.headers_mut());
+    resp.headers_mut()
+        .insert(axum::http::header::RETRY_AFTER, HeaderValue::from(status.reset_secs.max(1)));
+    Err(resp)
+}
+
+#[allow(clippy::result_large_err)]
+fn fail(mode: FailMode) -> Result<Option<RateLimitStatus>, Response> {
+    match mode {
+        FailMode::Open => Ok(None),
+        FailMode::Closed => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse::new("E0007", "rate limiter unavailable")),
+        )
+            .into_response()),
+    }
+}
+
+/// The client's address: the peer, or with `trust_forwarded_for` the
+/// address our reverse proxy appended to `X-Forwarded-For`.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_forwarded_for: bool) -> IpAddr {
+    if trust_forwarded_for {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|v| v.trim().parse().ok());
+        if let Some(addr) = forwarded {
+            return addr;
+        }
+    }
+    peer.ip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "10.0.0.7:51234".parse().unwrap()
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
     }
 
-    // Per-hour check
-    let hour_count: u64 = conn.incr(&hour_key, 1u64).await.map_err(|e| {
-        tracing::error!(error = %e, "redis incr failed for hour key");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    if hour_count == 1 {
-        let _: () = conn.expire(&hour_key, 3600).await.map_err(|e| {
-            tracing::error!(error = %e, "redis expire failed for hour key");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    #[test]
+    fn peer_address_is_used_by_default() {
+        let headers = forwarded("203.0.113.9");
+        assert_eq!(client_ip(&headers, peer(), false), peer().ip());
+        assert_eq!(client_ip(&HeaderMap::new(), peer(), true), peer().ip());
     }
-    if hour_count > rph_limit {
-        return Err(StatusCode::TOO_MANY_REQUESTS);
+
+    #[test]
+    fn trusted_forwarded_for_uses_the_last_hop() {
+        // earlier entries are whatever the client sent; only the one our
+        // proxy appended can be trusted
+        let headers = forwarded("198.51.100.1, 192.0.2.44 ,203.0.113.9");
+        assert_eq!(client_ip(&headers, peer(), true), "203.0.113.9".parse::<IpAddr>().unwrap());
+
+        let headers = forwarded("2001:db8::1");
+        assert_eq!(client_ip(&headers, peer(), true), "2001:db8::1".parse::<IpAddr>().unwrap());
     }
 
-    Ok(())
+    #[test]
+    fn unparseable_forwarded_for_falls_back_to_the_peer() {
+        for value in ["", "unknown", "203.0.113.9, not-an-ip", "203.0.113.9:443"] {
+            assert_eq!(client_ip(&forwarded(value), peer(), true), peer().ip(), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn status_is_reported_as_headers() {
+        let mut headers = HeaderMap::new();
+        RateLimitStatus {
+            limit: 60,
+            remaining: 12,
+            reset_secs: 7,
+        }
+        .apply(&mut headers);
+
+        assert_eq!(headers["x-ratelimit-limit"], "60");
+        assert_eq!(headers["x-ratelimit-remaining"], "12");
+        assert_eq!(headers["x-ratelimit-reset"], "7");
+    }
+
+    #[test]
+    fn fail_mode_decides_unavailable_limiter() {
+        assert!(matches!(fail(FailMode::Open), Ok(None)));
+        let resp = fail(FailMode::Closed).err().unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
 }
@@ -0,0 +1,307 @@
+use axum::body::Body;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{OriginalUri, State};
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use broz_shared::middleware::{inject_context, IDENTITY_HEADERS};
+use broz_shared::ApiErrorResponse;
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::{self, protocol};
+use uuid::Uuid;
+
+use crate::revocation::check_revocation;
+use crate::route_table;
+use crate::AppState;
+use super::auth::{set_identity_headers, validate_token};
+use super::proxy::{forward, insufficient_role, not_found};
+
+/// Close code sent to both ends when a tunnel idles out (1001 Going Away).
+const CLOSE_GOING_AWAY: u16 = 1001;
+
+type UpstreamSocket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Open WebSocket tunnels per user on this gateway instance.
+#[derive(Default)]
+pub struct ConnectionLimiter {
+    open: Arc<Mutex<HashMap<Uuid, usize>>>,
+}
+
+impl ConnectionLimiter {
+    /// Reserve a tunnel for `user_id`, or `None` if they already have `max` open.
+    pub fn try_acquire(&self, user_id: Uuid, max: usize) -> Option<ConnectionSlot> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(user_id).or_default();
+        if *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(ConnectionSlot {
+            open: self.open.clone(),
+            user_id,
+        })
+    }
+}
+
+/// A reserved tunnel; the slot is released when this is dropped.
+pub struct ConnectionSlot {
+    open: Arc<Mutex<HashMap<Uuid, usize>>>,
+    user_id: Uuid,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.user_id) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.user_id);
+            }
+        }
+    }
+}
+
+/// Socket.IO entry point for `/ws/matching` and `/ws/messaging`.
+///
+/// Clients connect with `path: "/ws/matching"` (or `/ws/messaging`) and
+/// their access token in the `token` query parameter or Authorization header.
+///
+/// 1. Validate the JWT and reject revoked tokens
+/// 2. Check the route's minimum role, then rewrite the path as the route
+///    table says (`/ws/<service>/...` to `/socket.io/...`), adding the token
+///    to the query string, which is how the services authenticate, and
+///    attach the signed identity headers
+/// 3. Long-polling requests are forwarded like any other request
+/// 4. WebSocket upgrades take one of the user's connection slots, connect to
+///    an upstream instance, then relay frames both ways until either side closes or
+///    no frame has passed for `ws_idle_timeout_secs`
+///
+/// A user's polling requests and upgrades all go to the same upstream
+/// instance while it is healthy, since Socket.IO sessions live on one
+/// instance.  Tunnels are not rate limited per request; the connection
+/// limit applies instead.
+pub async fn ws_handler(
+    State(state): State<Arc<AppState>>,
+    OriginalUri(original_uri): OriginalUri,
+    method: Method,
+    mut headers: HeaderMap,
+    upgrade: Option<WebSocketUpgrade>,
+    body: Body,
+) -> Response {
+    let path = original_uri.path();
+    let query = original_uri.query();
+
+    // 1. Resolve route
+    let Some(route) = state.routes.resolve(path) else {
+        return not_found();
+    };
+
+    // 2. Auth at handshake
+    let token = match query_param(query, "token").or_else(|| bearer_token(&headers)) {
+        Some(token) => token.to_string(),
+        None => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiErrorResponse::new("E0004", "missing token")),
+            )
+                .into_response();
+        }
+    };
+    let auth_info = match validate_token(&token, &state.config.jwt_secret) {
+        Ok(info) => info,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_revocation(&state, &auth_info).await {
+        return resp;
+    }
+    if !route.entry.auth.allows(auth_info.role) {
+        return insufficient_role();
+    }
+
+    // 3. Build upstream path
+    let upstream_query = match query {
+        Some(q) if query_param(Some(q), "token").is_some() => q.to_string(),
+        Some(q) => format!("{q}&token={token}"),
+        None => format!("token={token}"),
+    };
+    let upstream_path = route.upstream_path(path, Some(&upstream_query));
+    if route_table::is_internal(&upstream_path) {
+        return not_found();
+    }
+    set_identity_headers(&mut headers, Some(&auth_info), &state.config.gateway_secret);
+
+    // 4. Long-polling transport
+    let Some(upgrade) = upgrade else {
+        return forward(&state, method, &headers, body, route, &upstream_path, Some(auth_info.user_id)).await;
+    };
+
+    // 5. WebSocket transport
+    let Some(slot) = state
+        .ws_connections
+        .try_acquire(auth_info.user_id, state.config.ws_max_connections_per_user)
+    else {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ApiErrorResponse::new("E0006", "too many open connections")),
+        )
+            .into_response();
+    };
+
+    let Some(instance) = state
+        .upstreams
+        .get(&route.entry.upstream)
+        .and_then(|pool| pool.pick(Some(auth_info.user_id)))
+    else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse::new("E0007", "upstream unavailable: circuit open")),
+        )
+            .into_response();
+    };
+    let ws_url = format!("{}{upstream_path}", instance.url)
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1);
+    let upstream = match connect_upstream(&ws_url, &headers).await {
+        Ok(socket) => {
+            instance.record_success();
+            socket
+        }
+        Err(e) => {
+            instance.record_failure();
+            tracing::error!(error = %e, upstream = %instance.url, "upstream websocket handshake failed");
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(ApiErrorResponse::new("E0007", format!("upstream unavailable: {e}"))),
+            )
+                .into_response();
+        }
+    };
+
+    let idle_timeout = Duration::from_secs(state.config.ws_idle_timeout_secs);
+    let user_id = auth_info.user_id;
+    let upstream_base = instance.url.clone();
+    upgrade.on_upgrade(move |client| async move {
+        tracing::info!(user_id = %user_id, upstream = %upstream_base, "websocket tunnel opened");
+        let reason = relay(client, upstream, idle_timeout).await;
+        drop(slot);
+        tracing::info!(user_id = %user_id, upstream = %upstream_base, reason, "websocket tunnel closed");
+    })
+}
+
+async fn connect_upstream(
+    ws_url: &str,
+    headers: &HeaderMap,
+) -> Result<UpstreamSocket, tungstenite::Error> {
+    let mut request = ws_url.into_client_request()?;
+    let forwarded = ["authorization", "user-agent", "x-forwarded-for"];
+    for name in forwarded.iter().chain(IDENTITY_HEADERS) {
+        if let Some(value) = headers.get(*name) {
+            request.headers_mut().insert(*name, value.clone());
+        }
+    }
+    inject_context(request.headers_mut());
+    let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+    Ok(socket)
+}
+
+/// Relay frames between the client and the upstream until the tunnel ends,
+/// returning why it ended.
+///
+/// Pings and pongs are answered by each leg's WebSocket library rather than
+/// forwarded, but still count as activity.
+async fn relay(client: WebSocket, upstream: UpstreamSocket, idle_timeout: Duration) -> &'static str {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    loop {
+        tokio::select! {
+            msg = client_rx.next() => match msg {
+                Some(Ok(Message::Close(frame))) => {
+                    let frame = frame.map(|f| protocol::CloseFrame {
+                        code: CloseCode::from(f.code),
+                        reason: f.reason,
+                    });
+                    let _ = upstream_tx.send(tungstenite::Message::Close(frame)).await;
+                    return "client closed";
+                }
+                Some(Ok(msg)) => {
+                    let msg = match msg {
+                        Message::Text(text) => tungstenite::Message::Text(text),
+                        Message::Binary(data) => tungstenite::Message::Binary(data),
+                        _ => continue,
+                    };
+                    if upstream_tx.send(msg).await.is_err() {
+                        let _ = client_tx.close().await;
+                        return "upstream disconnected";
+                    }
+                }
+                Some(Err(_)) | None => {
+                    let _ = upstream_tx.close().await;
+                    return "client disconnected";
+                }
+            },
+            msg = upstream_rx.next() => match msg {
+                Some(Ok(tungstenite::Message::Close(frame))) => {
+                    let frame = frame.map(|f| CloseFrame {
+                        code: u16::from(f.code),
+                        reason: f.reason.into_owned().into(),
+                    });
+                    let _ = client_tx.send(Message::Close(frame)).await;
+                    return "upstream closed";
+                }
+                Some(Ok(msg)) => {
+                    let msg = match msg {
+                        tungstenite::Message::Text(text) => Message::Text(text),
+                        tungstenite::Message::Binary(data) => Message::Binary(data),
+                        _ => continue,
+                    };
+                    if client_tx.send(msg).await.is_err() {
+                        let _ = upstream_tx.close().await;
+                        return "client disconnected";
+                    }
+                }
+                Some(Err(_)) | None => {
+                    let _ = client_tx.close().await;
+                    return "upstream disconnected";
+                }
+            },
+            _ = tokio::time::sleep(idle_timeout) => {
+                let _ = client_tx
+                    .send(Message::Close(Some(CloseFrame {
+                        code: CLOSE_GOING_AWAY,
+                        reason: "idle timeout".into(),
+                    })))
+                    .await;
+                let _ = upstream_tx
+                    .send(tungstenite::Message::Close(Some(protocol::CloseFrame {
+                        code: CloseCode::from(CLOSE_GOING_AWAY),
+                        reason: "idle timeout".into(),
+                    })))
+                    .await;
+                return "idle timeout";
+            }
+        }
+    }
+}
+
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::route_table::RouteTable;
+
+/// Circuit breaker state of one upstream instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Taking traffic.
+    Closed,
+    /// Ejected after repeated failures; no traffic until the cool-down ends.
+    Open,
+    /// Cool-down over; the next request is a probe that closes or reopens it.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started: Option<Instant>,
+}
+
+/// One instance of an upstream service.
+#[derive(Debug)]
+pub struct Instance {
+    pub url: String,
+    breaker: Mutex<Breaker>,
+    failure_threshold: u32,
+    open_for: Duration,
+}
+
+impl Instance {
+    fn new(url: String, failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            url,
+            breaker: Mutex::new(Breaker::default()),
+            failure_threshold,
+            open_for,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        let breaker = self.breaker.lock().unwrap();
+        match breaker.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.open_for => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may go to this instance now.  While half-open,
+    /// only one probe at a time is let through; a probe that never reports
+    /// back (the client went away) is replaced after another cool-down.
+    fn try_acquire(&self) -> bool {
+        let mut breaker = self.breaker.lock().unwrap();
+        let Some(opened_at) = breaker.opened_at else {
+            return true;
+        };
+        if opened_at.elapsed() < self.open_for {
+            return false;
+        }
+        match breaker.probe_started {
+            Some(started) if started.elapsed() < self.open_for => false,
+            _ => {
+                breaker.probe_started = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if breaker.opened_at.is_some() {
+            tracing::info!(upstream = %self.url, "upstream instance recovered");
+        }
+        *breaker = Breaker::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if breaker.probe_started.take().is_some() {
+            breaker.opened_at = Some(Instant::now());
+            tracing::warn!(upstream = %self.url, "upstream probe failed, instance stays ejected");
+            return;
+        }
+        breaker.consecutive_failures += 1;
+        if breaker.opened_at.is_none() && breaker.consecutive_failures >= self.failure_threshold {
+            breaker.opened_at = Some(Instant::now());
+            tracing::warn!(
+                upstream = %self.url,
+                failures = breaker.consecutive_failures,
+                open_secs = self.open_for.as_secs(),
+                "upstream instance ejected"
+            );
+        }
+    }
+}
+
+/// The instances of one upstream, used round-robin.
+#[derive(Debug)]
+pub struct UpstreamPool {
+    instances: Vec<Arc<Instance>>,
+    next: AtomicUsize,
+}
+
+impl UpstreamPool {
+    /// The next instance whose breaker lets a request through, or `None`
+    /// if every instance is ejected.
+    ///
+    /// With an `affinity` key the same instance is chosen for the same key
+    /// while it is available, for upstreams that keep per-client state
+    /// (Socket.IO sessions); otherwise instances take turns.
+    pub fn pick(&self, affinity: Option<Uuid>) -> Option<Arc<Instance>> {
+        let start = match affinity {
+            Some(key) => (key.as_u128() % self.instances.len() as u128) as usize,
+            None => self.next.fetch_add(1, Ordering::Relaxed),
+        };
+        (0..self.instances.len())
+            .map(|i| &self.instances[(start + i) % self.instances.len()])
+            .find(|instance| instance.try_acquire())
+            .cloned()
+    }
+
+    pub fn instances(&self) -> &[Arc<Instance>] {
+        &self.instances
+    }
+}
+
+/// Instance pools of every upstream the route table uses, keyed by the
+/// route's `upstream` (service name or URL list).
+#[derive(Debug)]
+pub struct Upstreams {
+    pools: HashMap<String, UpstreamPool>,
+}
+
+impl Upstreams {
+    pub fn new(routes: &RouteTable, config: &AppConfig) -> Self {
+        let open_for = Duration::from_secs(config.breaker_open_secs);
+        let mut pools = HashMap::new();
+        for route in routes.routes() {
+            pools.entry(route.entry.upstream.clone()).or_insert_with(|| UpstreamPool {
+                instances: route
+                    .instances
+                    .iter()
+                    .map(|url| Arc::new(Instance::new(url.clone(), config.breaker_failure_threshold, open_for)))
+                    .collect(),
+                next: AtomicUsize::new(0),
+            });
+        }
+        Self { pools }
+    }
+
+    pub fn get(&self, upstream: &str) -> Option<&UpstreamPool> {
+        self.pools.get(upstream)
+    }
+}
@@ -6,17 +6,19 @@ use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
 use socketioxide::SocketIo;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod matching;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 mod socket;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
 use broz_shared::clients::redis::RedisClient;
 
@@ -62,6 +64,7 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         // Health
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         // LiveCam REST endpoints
         .route("/livecam/request", post(routes::livecam::create_livecam_request))
         .route(
@@ -74,7 +77,7 @@ async fn main() -> anyhow::Result<()> {
         )
         .layer(sio_layer)
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::{match_sessions, livecam_requests};
@@ -28,7 +29,7 @@ pub struct NewMatchSession {
 
 // --- LiveCamRequest ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = livecam_requests)]
 pub struct LiveCamRequest {
     pub id: Uuid,
@@ -0,0 +1,19 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.  The Socket.IO
+/// endpoint is not described.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-matching"),
+    paths(
+        routes::health::health_check,
+        routes::livecam::create_livecam_request,
+        routes::livecam::respond_livecam_request,
+        routes::livecam::get_pending_requests,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
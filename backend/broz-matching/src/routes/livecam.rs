@@ -5,6 +5,7 @@ use axum::Json;
 use chrono::{Duration, Utc};
 use diesel::prelude::*;
 use serde::Deserialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -20,12 +21,12 @@ use crate::AppState;
 // Request / response types
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateLiveCamRequestPayload {
     pub target_id: Uuid,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RespondLiveCamPayload {
     pub accepted: bool,
 }
@@ -34,6 +35,13 @@ pub struct RespondLiveCamPayload {
 // POST /livecam/request
 // ---------------------------------------------------------------------------
 
+/// Ask a user for a LiveCam session.
+#[utoipa::path(
+    post,
+    path = "/livecam/request",
+    tag = "matching",
+    responses((status = 200, body = ApiResponse<LiveCamRequest>)),
+)]
 pub async fn create_livecam_request(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -88,6 +96,13 @@ pub async fn create_livecam_request(
 // PUT /livecam/:id/respond
 // ---------------------------------------------------------------------------
 
+/// Accept or decline a LiveCam request addressed to the caller.
+#[utoipa::path(
+    put,
+    path = "/livecam/{id}/respond",
+    tag = "matching",
+    responses((status = 200, body = ApiResponse<LiveCamRequest>)),
+)]
 pub async fn respond_livecam_request(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -165,6 +180,13 @@ pub async fn respond_livecam_request(
 // GET /livecam/pending
 // ---------------------------------------------------------------------------
 
+/// LiveCam requests awaiting the caller's answer.
+#[utoipa::path(
+    get,
+    path = "/livecam/pending",
+    tag = "matching",
+    responses((status = 200, body = ApiResponse<Vec<LiveCamRequest>>)),
+)]
 pub async fn get_pending_requests(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
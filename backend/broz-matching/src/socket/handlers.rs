@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 use socketioxide::extract::{Data, SocketRef, State};
 use uuid::Uuid;
 
+use broz_shared::middleware::PropagateTrace;
+
 use crate::events::publisher;
 use crate::matching::{algorithm, history, queue};
 use crate::models::NewMatchSession;
@@ -382,6 +384,7 @@ async fn on_send_like(
             "liked_id": partner_id,
             "match_session_id": match_id,
         }))
+        .with_trace_context()
         .send()
         .await;
 
@@ -442,6 +445,7 @@ async fn on_send_follow_request(
     let res = client
         .post(&follow_url)
         .header("Authorization", format!("Bearer {}", get_user_token(&socket)))
+        .with_trace_context()
         .send()
         .await;
 
@@ -510,6 +514,7 @@ async fn on_respond_follow_request(
     let res = client
         .put(&follow_url)
         .header("Authorization", format!("Bearer {}", get_user_token(&socket)))
+        .with_trace_context()
         .send()
         .await;
 
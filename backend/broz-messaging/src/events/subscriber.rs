@@ -2,8 +2,10 @@ use std::sync::Arc;
 use diesel::prelude::*;
 use futures_lite::StreamExt;
 use lapin::options::BasicAckOptions;
+use tracing::Instrument;
 use uuid::Uuid;
 
+use broz_shared::clients::rabbitmq::delivery_span;
 use broz_shared::types::event::{routing_keys, payloads, Event};
 
 use crate::AppState;
@@ -23,33 +25,38 @@ pub async fn listen_follow_accepted(state: Arc<AppState>) -> anyhow::Result<()>
     while let Some(delivery) = consumer.next().await {
         match delivery {
             Ok(delivery) => {
-                match serde_json::from_slice::<Event<payloads::FollowAccepted>>(&delivery.data) {
-                    Ok(event) => {
-                        let data = &event.data;
-                        tracing::info!(
-                            follower_id = %data.follower_id,
-                            following_id = %data.following_id,
-                            "received follow.accepted event"
-                        );
-
-                        if let Err(e) = create_dm_if_not_exists(
-                            &state.db,
-                            data.follower_id,
-                            data.following_id,
-                        ) {
-                            tracing::error!(
-                                error = %e,
+                let span = delivery_span(&delivery);
+                async {
+                    match serde_json::from_slice::<Event<payloads::FollowAccepted>>(&delivery.data) {
+                        Ok(event) => {
+                            let data = &event.data;
+                            tracing::info!(
                                 follower_id = %data.follower_id,
                                 following_id = %data.following_id,
-                                "failed to auto-create DM conversation"
+                                "received follow.accepted event"
                             );
+
+                            if let Err(e) = create_dm_if_not_exists(
+                                &state.db,
+                                data.follower_id,
+                                data.following_id,
+                            ) {
+                                tracing::error!(
+                                    error = %e,
+                                    follower_id = %data.follower_id,
+                                    following_id = %data.following_id,
+                                    "failed to auto-create DM conversation"
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!(error = %e, "failed to deserialize follow.accepted event");
                         }
                     }
-                    Err(e) => {
-                        tracing::error!(error = %e, "failed to deserialize follow.accepted event");
-                    }
+                    let _ = delivery.ack(BasicAckOptions::default()).await;
                 }
-                let _ = delivery.ack(BasicAckOptions::default()).await;
+                .instrument(span)
+                .await;
             }
             Err(e) => {
                 tracing::error!(error = %e, "consumer error");
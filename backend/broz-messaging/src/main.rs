@@ -7,16 +7,18 @@ use dashmap::DashMap;
 use uuid::Uuid;
 use crate::socket::handlers::CallSession;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 mod socket;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
 use broz_shared::clients::redis::RedisClient;
 use broz_shared::clients::minio::MinioClient;
@@ -84,6 +86,7 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         // Health
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         // Conversations
         .route("/conversations", get(routes::conversations::list_conversations))
         .route("/conversations/group", post(routes::conversations::create_group))
@@ -105,7 +108,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/unread-count", get(routes::messages::get_unread_count))
         .layer(sio_layer)
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
@@ -1,13 +1,14 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::{conversations, conversation_members, messages};
 
 // --- Conversation ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = conversations)]
 pub struct Conversation {
     pub id: Uuid,
@@ -28,7 +29,7 @@ pub struct NewConversation {
 
 // --- ConversationMember ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = conversation_members)]
 pub struct ConversationMember {
     pub id: Uuid,
@@ -47,7 +48,7 @@ pub struct NewConversationMember {
 
 // --- Message ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = messages)]
 pub struct Message {
     pub id: Uuid,
@@ -0,0 +1,29 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.  The Socket.IO
+/// endpoint is not described.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-messaging"),
+    paths(
+        routes::health::health_check,
+        routes::conversations::list_conversations,
+        routes::conversations::create_group,
+        routes::conversations::get_conversation,
+        routes::conversations::add_member,
+        routes::conversations::update_group_photo,
+        routes::conversations::rename_group,
+        routes::messages::list_messages,
+        routes::messages::send_message,
+        routes::messages::delete_message,
+        routes::messages::mark_as_read,
+        routes::messages::send_message_simple,
+        routes::messages::send_media,
+        routes::messages::get_unread_count,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
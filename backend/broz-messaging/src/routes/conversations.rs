@@ -5,11 +5,13 @@ use diesel::prelude::*;
 use diesel::dsl::count_star;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::api::ApiResponse;
 use broz_shared::types::auth::AuthUser;
+use broz_shared::middleware::PropagateTrace;
 
 use crate::AppState;
 use crate::models::{
@@ -19,7 +21,7 @@ use crate::schema::{conversation_members, conversations, messages};
 
 // --- Response DTOs ---
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationPreview {
     pub id: Uuid,
     pub is_group: bool,
@@ -37,14 +39,14 @@ pub struct ConversationPreview {
     pub unread_count: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationDetail {
     #[serde(flatten)]
     pub conversation: Conversation,
     pub members: Vec<EnrichedMember>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct EnrichedMember {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -57,13 +59,13 @@ pub struct EnrichedMember {
 
 // --- Request DTOs ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateGroupRequest {
     pub name: String,
     pub member_ids: Vec<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AddMemberRequest {
     pub user_id: Uuid,
 }
@@ -71,6 +73,12 @@ pub struct AddMemberRequest {
 // --- Handlers ---
 
 /// GET /conversations - list user's conversations with last message preview and unread count
+#[utoipa::path(
+    get,
+    path = "/conversations",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<Vec<ConversationPreview>>)),
+)]
 pub async fn list_conversations(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -174,6 +182,7 @@ pub async fn list_conversations(
         if let Ok(resp) = client
             .post(&url)
             .json(&serde_json::json!({ "credential_ids": partner_ids }))
+            .with_trace_context()
             .send()
             .await
         {
@@ -216,6 +225,12 @@ pub async fn list_conversations(
 }
 
 /// POST /conversations/group - create a group conversation
+#[utoipa::path(
+    post,
+    path = "/conversations/group",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<ConversationDetail>)),
+)]
 pub async fn create_group(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -275,6 +290,12 @@ pub async fn create_group(
 }
 
 /// GET /conversations/:id - get conversation details with members (enriched with profile data)
+#[utoipa::path(
+    get,
+    path = "/conversations/{id}",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<ConversationDetail>)),
+)]
 pub async fn get_conversation(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -331,6 +352,7 @@ async fn enrich_members(state: &AppState, raw_members: &[ConversationMember]) ->
     let profiles: Vec<serde_json::Value> = match client
         .post(&url)
         .json(&serde_json::json!({ "credential_ids": credential_ids }))
+        .with_trace_context()
         .send()
         .await
     {
@@ -382,6 +404,12 @@ async fn enrich_members(state: &AppState, raw_members: &[ConversationMember]) ->
 }
 
 /// POST /conversations/:id/members - add a member to a group conversation
+#[utoipa::path(
+    post,
+    path = "/conversations/{id}/members",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<ConversationMember>)),
+)]
 pub async fn add_member(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -454,17 +482,17 @@ pub async fn add_member(
 
 // --- Group management DTOs ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RenameGroupRequest {
     pub name: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct GroupPhotoResponse {
     pub group_photo: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct GroupNameResponse {
     pub name: String,
 }
@@ -506,6 +534,13 @@ fn verify_group_membership(
 }
 
 /// POST /conversations/group/:id/photo - upload group photo
+#[utoipa::path(
+    post,
+    path = "/conversations/group/{id}/photo",
+    tag = "messaging",
+    request_body(content_type = "multipart/form-data", description = "The image in a `file` field"),
+    responses((status = 200, body = ApiResponse<GroupPhotoResponse>)),
+)]
 pub async fn update_group_photo(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -591,6 +626,12 @@ pub async fn update_group_photo(
 }
 
 /// PUT /conversations/group/:id/name - rename a group
+#[utoipa::path(
+    put,
+    path = "/conversations/group/{id}/name",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<GroupNameResponse>)),
+)]
 pub async fn rename_group(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -5,6 +5,7 @@ use diesel::prelude::*;
 use diesel::dsl::count_star;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -48,7 +49,7 @@ fn build_socket_payload(
 
 // --- Request DTOs ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     pub content: Option<String>,
     pub media_url: Option<String>,
@@ -57,7 +58,7 @@ pub struct SendMessageRequest {
 
 // --- Response DTOs ---
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UnreadCountResponse {
     pub total_unread: i64,
 }
@@ -91,6 +92,13 @@ fn verify_membership(
 // --- Handlers ---
 
 /// GET /conversations/:id/messages - get paginated messages for a conversation
+#[utoipa::path(
+    get,
+    path = "/conversations/{id}/messages",
+    tag = "messaging",
+    params(PaginationParams),
+    responses((status = 200, body = ApiResponse<Paginated<Message>>)),
+)]
 pub async fn list_messages(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -123,6 +131,12 @@ pub async fn list_messages(
 }
 
 /// POST /conversations/:id/messages - send a message in a conversation
+#[utoipa::path(
+    post,
+    path = "/conversations/{id}/messages",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<Message>)),
+)]
 pub async fn send_message(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -211,6 +225,12 @@ pub async fn send_message(
 }
 
 /// DELETE /messages/:id - soft delete a message (only the sender can delete)
+#[utoipa::path(
+    delete,
+    path = "/messages/{id}",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<Message>)),
+)]
 pub async fn delete_message(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -244,6 +264,12 @@ pub async fn delete_message(
 }
 
 /// POST /conversations/:id/read - mark conversation as read (update last_read_at)
+#[utoipa::path(
+    post,
+    path = "/conversations/{id}/read",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<serde_json::Value>)),
+)]
 pub async fn mark_as_read(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -274,6 +300,12 @@ pub async fn mark_as_read(
 }
 
 /// GET /unread-count - get total unread messages count across all conversations
+#[utoipa::path(
+    get,
+    path = "/unread-count",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<UnreadCountResponse>)),
+)]
 pub async fn get_unread_count(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -307,7 +339,7 @@ pub async fn get_unread_count(
 
 // --- POST /send ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SimpleSendRequest {
     pub conversation_id: Option<Uuid>,
     pub partner_id: Option<Uuid>,
@@ -318,13 +350,19 @@ pub struct SimpleSendRequest {
     pub participants: Option<Vec<Uuid>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SimpleSendResponse {
     pub conversation_id: Uuid,
     pub message: Message,
 }
 
 /// POST /send - send a message, auto-creating conversation if needed
+#[utoipa::path(
+    post,
+    path = "/send",
+    tag = "messaging",
+    responses((status = 200, body = ApiResponse<SimpleSendResponse>)),
+)]
 pub async fn send_message_simple(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -547,6 +585,16 @@ fn create_group_conversation(
 }
 
 /// POST /send-media - upload a media file and send as a message
+#[utoipa::path(
+    post,
+    path = "/send-media",
+    tag = "messaging",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "The media in a `file` field, and the conversation as in `/send`: `conversation_id`, or `partner_id`, or `is_group` with `participants` (a JSON array of user ids)",
+    ),
+    responses((status = 200, body = ApiResponse<SimpleSendResponse>)),
+)]
 pub async fn send_media(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use socketioxide::extract::{Data, SocketRef};
 use uuid::Uuid;
 
+use broz_shared::middleware::PropagateTrace;
+
 use crate::AppState;
 
 #[derive(Debug, Clone)]
@@ -212,6 +214,7 @@ async fn on_call_invite(socket: SocketRef, payload: serde_json::Value, state: &A
         .post(format!("{api_url}/v1/rooms"))
         .header("Authorization", format!("Bearer {api_key}"))
         .json(&serde_json::json!({ "room_type": "call" }))
+        .with_trace_context()
         .send()
         .await;
 
@@ -393,6 +396,7 @@ async fn delete_sfu_room(state: &Arc<AppState>, room_id: &str) {
     if let Err(e) = state.http_client
         .delete(format!("{api_url}/v1/rooms/{room_id}"))
         .header("Authorization", format!("Bearer {api_key}"))
+        .with_trace_context()
         .send()
         .await
     {
@@ -413,6 +417,7 @@ async fn update_presence(state: &Arc<AppState>, user_id: Uuid, is_online: bool)
             "user_id": user_id,
             "is_online": is_online,
         }))
+        .with_trace_context()
         .send()
         .await
     {
@@ -424,7 +429,7 @@ async fn update_presence(state: &Arc<AppState>, user_id: Uuid, is_online: bool)
 async fn notify_followers_presence(state: &Arc<AppState>, user_id: Uuid, is_online: bool) {
     // Get follower credential_ids from broz-user
     let url = format!("{}/internal/follower-ids/{}", state.config.user_service_url, user_id);
-    let res = match state.http_client.get(&url).send().await {
+    let res = match state.http_client.get(&url).with_trace_context().send().await {
         Ok(r) => r,
         Err(e) => {
             tracing::warn!(error = %e, "failed to fetch follower ids");
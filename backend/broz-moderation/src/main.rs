@@ -4,16 +4,19 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::pg::PgConnection;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
+use broz_shared::clients::redis::RedisClient;
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
@@ -21,6 +24,7 @@ pub struct AppState {
     pub db: DbPool,
     pub config: AppConfig,
     pub rabbitmq: RabbitMQClient,
+    pub redis: RedisClient,
 }
 
 #[tokio::main]
@@ -34,8 +38,9 @@ async fn main() -> anyhow::Result<()> {
     let db = Pool::builder().max_size(10).build(manager)?;
 
     let rabbitmq = RabbitMQClient::connect(&config.rabbitmq_url).await?;
+    let redis = RedisClient::connect(&config.redis_url).await?;
 
-    let state = Arc::new(AppState { db, config, rabbitmq });
+    let state = Arc::new(AppState { db, config, rabbitmq, redis });
 
     let admin_routes = Router::new()
         .route("/reports", get(routes::admin_routes::list_reports))
@@ -50,10 +55,11 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         .route("/report", post(routes::user_routes::create_report))
         .nest("/admin", admin_routes)
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
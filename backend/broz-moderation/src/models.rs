@@ -1,13 +1,14 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::{reports, sanctions, admin_actions};
 
 // --- Report ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = reports)]
 pub struct Report {
     pub id: Uuid,
@@ -38,7 +39,7 @@ pub struct NewReport {
 
 // --- Sanction ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = sanctions)]
 pub struct Sanction {
     pub id: Uuid,
@@ -65,7 +66,7 @@ pub struct NewSanction {
 
 // --- AdminAction ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = admin_actions)]
 pub struct AdminAction {
     pub id: Uuid,
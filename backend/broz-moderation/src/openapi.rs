@@ -0,0 +1,25 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-moderation"),
+    paths(
+        routes::health::health_check,
+        routes::user_routes::create_report,
+        routes::admin_routes::list_reports,
+        routes::admin_routes::get_report,
+        routes::admin_routes::review_report,
+        routes::admin_routes::get_user_sanctions,
+        routes::admin_routes::issue_sanction,
+        routes::admin_routes::lift_sanction,
+        routes::admin_routes::list_active_sanctions,
+        routes::admin_routes::get_stats,
+        routes::admin_routes::get_audit_log,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
@@ -4,6 +4,7 @@ use chrono::{Duration, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -18,7 +19,7 @@ use crate::AppState;
 
 // --- Request / Response types ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct ReportFilterParams {
     #[serde(default = "default_page")]
     pub page: u64,
@@ -39,21 +40,21 @@ impl ReportFilterParams {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReviewReportRequest {
     pub status: String, // "actioned" or "dismissed"
     pub sanction_type: Option<String>,
     pub reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct IssueSanctionRequest {
     pub sanction_type: String,
     pub reason: String,
     pub expires_at: Option<chrono::DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DashboardStats {
     pub pending_reports: i64,
     pub active_sanctions: i64,
@@ -62,6 +63,14 @@ pub struct DashboardStats {
 
 // --- List reports (paginated, optional status filter) ---
 
+/// Reports, newest first, optionally filtered by status.
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    tag = "moderation",
+    params(ReportFilterParams),
+    responses((status = 200, body = ApiResponse<Paginated<Report>>)),
+)]
 pub async fn list_reports(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -112,6 +121,13 @@ pub async fn list_reports(
 
 // --- Get report details ---
 
+/// A report.
+#[utoipa::path(
+    get,
+    path = "/admin/reports/{id}",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Report>)),
+)]
 pub async fn get_report(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -132,6 +148,13 @@ pub async fn get_report(
 
 // --- Review report ---
 
+/// Action or dismiss a report, optionally sanctioning the reported user.
+#[utoipa::path(
+    put,
+    path = "/admin/reports/{id}/review",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Report>)),
+)]
 pub async fn review_report(
     State(state): State<Arc<AppState>>,
     admin: AdminUser,
@@ -203,6 +226,7 @@ pub async fn review_report(
                 sanction.expires_at,
             )
             .await;
+            revoke_tokens_if_banned(&state, &sanction).await;
         }
     }
 
@@ -230,6 +254,13 @@ pub async fn review_report(
 
 // --- Get user sanction history ---
 
+/// A user's sanction history.
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Vec<Sanction>>)),
+)]
 pub async fn get_user_sanctions(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -249,6 +280,13 @@ pub async fn get_user_sanctions(
 
 // --- Issue sanction directly ---
 
+/// Sanction a user directly.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/sanction",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Sanction>)),
+)]
 pub async fn issue_sanction(
     State(state): State<Arc<AppState>>,
     admin: AdminUser,
@@ -297,6 +335,7 @@ pub async fn issue_sanction(
         sanction.expires_at,
     )
     .await;
+    revoke_tokens_if_banned(&state, &sanction).await;
 
     // Log admin action
     let action_detail = serde_json::json!({
@@ -323,6 +362,13 @@ pub async fn issue_sanction(
 
 // --- Lift sanction ---
 
+/// Lift a sanction.
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/sanction/{sid}",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Sanction>)),
+)]
 pub async fn lift_sanction(
     State(state): State<Arc<AppState>>,
     admin: AdminUser,
@@ -378,6 +424,14 @@ pub async fn lift_sanction(
 
 // --- List active sanctions (paginated) ---
 
+/// Sanctions in effect.
+#[utoipa::path(
+    get,
+    path = "/admin/sanctions",
+    tag = "moderation",
+    params(PaginationParams),
+    responses((status = 200, body = ApiResponse<Paginated<Sanction>>)),
+)]
 pub async fn list_active_sanctions(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -409,6 +463,13 @@ pub async fn list_active_sanctions(
 
 // --- Dashboard stats ---
 
+/// Moderation dashboard counts.
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<DashboardStats>)),
+)]
 pub async fn get_stats(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -446,6 +507,14 @@ pub async fn get_stats(
 
 // --- Audit log (paginated admin actions) ---
 
+/// Admin actions, newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/audit-log",
+    tag = "moderation",
+    params(PaginationParams),
+    responses((status = 200, body = ApiResponse<Paginated<AdminAction>>)),
+)]
 pub async fn get_audit_log(
     State(state): State<Arc<AppState>>,
     _admin: AdminUser,
@@ -473,6 +542,19 @@ pub async fn get_audit_log(
     Ok(Json(ApiResponse::ok(paginated)))
 }
 
+// --- Helper: revoke a banned user's access tokens ---
+
+/// Bans take effect immediately: the user's existing access tokens are
+/// revoked at the gateway rather than left valid until they expire.
+async fn revoke_tokens_if_banned(state: &AppState, sanction: &Sanction) {
+    if !sanction.sanction_type.starts_with("ban") {
+        return;
+    }
+    if let Err(e) = state.redis.revoke_user_tokens(sanction.user_id).await {
+        tracing::error!(error = %e, user_id = %sanction.user_id, "failed to revoke access tokens");
+    }
+}
+
 // --- Helper: compute expires_at based on sanction type ---
 
 fn compute_expires_at(sanction_type: &str) -> Option<chrono::DateTime<Utc>> {
@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -14,7 +15,7 @@ use crate::models::{NewReport, Report};
 use crate::schema::reports;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateReportRequest {
     pub reported_id: Uuid,
     pub report_type: String,
@@ -24,6 +25,13 @@ pub struct CreateReportRequest {
     pub message_id: Option<Uuid>,
 }
 
+/// Report a user.
+#[utoipa::path(
+    post,
+    path = "/report",
+    tag = "moderation",
+    responses((status = 200, body = ApiResponse<Report>)),
+)]
 pub async fn create_report(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
@@ -4,16 +4,18 @@ use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 mod services;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
@@ -75,12 +77,13 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         .route("/notifications", get(routes::notifications::list_notifications))
         .route("/notifications/unread-count", get(routes::notifications::unread_count))
         .route("/notifications/mark-all-read", post(routes::notifications::mark_all_read))
         .route("/notifications/:id/read", post(routes::notifications::mark_read))
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
@@ -1,11 +1,12 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::notifications;
 
-#[derive(Debug, Queryable, Identifiable, Serialize)]
+#[derive(Debug, Queryable, Identifiable, Serialize, ToSchema)]
 #[diesel(table_name = notifications)]
 pub struct Notification {
     pub id: Uuid,
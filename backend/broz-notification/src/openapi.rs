@@ -0,0 +1,19 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-notification"),
+    paths(
+        routes::health::health_check,
+        routes::notifications::list_notifications,
+        routes::notifications::unread_count,
+        routes::notifications::mark_all_read,
+        routes::notifications::mark_read,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
@@ -15,6 +15,13 @@ use crate::AppState;
 
 /// GET /notifications
 /// List notifications for the authenticated user with pagination.
+#[utoipa::path(
+    get,
+    path = "/notifications",
+    tag = "notification",
+    params(PaginationParams),
+    responses((status = 200, body = ApiResponse<Paginated<Notification>>)),
+)]
 pub async fn list_notifications(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -36,6 +43,12 @@ pub async fn list_notifications(
 
 /// GET /notifications/unread-count
 /// Get the count of unread notifications for the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/notifications/unread-count",
+    tag = "notification",
+    responses((status = 200, body = ApiResponse<UnreadCountResponse>)),
+)]
 pub async fn unread_count(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -45,13 +58,19 @@ pub async fn unread_count(
     Ok(Json(ApiResponse::ok(UnreadCountResponse { count })))
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct UnreadCountResponse {
     pub count: i64,
 }
 
 /// POST /notifications/mark-all-read
 /// Mark all unread notifications as read for the authenticated user.
+#[utoipa::path(
+    post,
+    path = "/notifications/mark-all-read",
+    tag = "notification",
+    responses((status = 200, body = ApiResponse<MarkAllReadResponse>)),
+)]
 pub async fn mark_all_read(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -61,13 +80,19 @@ pub async fn mark_all_read(
     Ok(Json(ApiResponse::ok(MarkAllReadResponse { updated })))
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct MarkAllReadResponse {
     pub updated: usize,
 }
 
 /// POST /notifications/:id/read
 /// Mark a single notification as read.
+#[utoipa::path(
+    post,
+    path = "/notifications/{id}/read",
+    tag = "notification",
+    responses((status = 200, body = ApiResponse<Notification>)),
+)]
 pub async fn mark_read(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
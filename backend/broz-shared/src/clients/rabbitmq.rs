@@ -1,9 +1,13 @@
 use lapin::{
-    options::*, types::FieldTable, BasicProperties, Channel, Connection, ConnectionProperties,
-    Consumer,
+    message::Delivery,
+    options::*,
+    types::{AMQPValue, FieldTable},
+    BasicProperties, Channel, Connection, ConnectionProperties, Consumer,
 };
 use serde::Serialize;
+use std::collections::HashMap;
 
+use crate::middleware::{context_carrier, set_parent_from_carrier};
 use crate::types::Event;
 
 const EXCHANGE_NAME: &str = "broz.events";
@@ -35,7 +39,9 @@ impl RabbitMQClient {
         Ok(Self { channel })
     }
 
-    /// Publish an event with a routing key
+    /// Publish an event with a routing key.  The current trace context is
+    /// sent in the message headers; consumers pick it up with
+    /// [`delivery_span`].
     pub async fn publish<T: Serialize>(
         &self,
         routing_key: &str,
@@ -58,7 +64,8 @@ impl RabbitMQClient {
                 &payload,
                 BasicProperties::default()
                     .with_content_type("application/json".into())
-                    .with_delivery_mode(2), // persistent
+                    .with_delivery_mode(2) // persistent
+                    .with_headers(trace_headers()),
             )
             .await?
             .await?;
@@ -126,3 +133,34 @@ impl RabbitMQClient {
         &self.channel
     }
 }
+
+fn trace_headers() -> FieldTable {
+    let mut headers = FieldTable::default();
+    for (key, value) in context_carrier() {
+        headers.insert(key.into(), AMQPValue::LongString(value.into()));
+    }
+    headers
+}
+
+/// Span for handling a delivery, continuing the publisher's trace.
+///
+/// Run the handler inside it with `tracing::Instrument`.
+pub fn delivery_span(delivery: &Delivery) -> tracing::Span {
+    let span = tracing::info_span!(
+        "consume",
+        routing_key = %delivery.routing_key,
+        trace_id = tracing::field::Empty,
+    );
+    let carrier: HashMap<String, String> = delivery
+        .properties
+        .headers()
+        .iter()
+        .flat_map(|headers| headers.inner())
+        .filter_map(|(key, value)| match value {
+            AMQPValue::LongString(value) => Some((key.to_string(), value.to_string())),
+            _ => None,
+        })
+        .collect();
+    set_parent_from_carrier(&span, &carrier);
+    span
+}
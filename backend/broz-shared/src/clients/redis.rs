@@ -1,5 +1,21 @@
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// How long a per-user revocation watermark is kept.  Must outlive the
+/// longest access-token lifetime, or revoked tokens become valid again.
+pub const TOKEN_WATERMARK_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// Denylist entry for a single access token, by `jti`.
+pub fn revoked_token_key(jti: Uuid) -> String {
+    format!("revoked:jti:{jti}")
+}
+
+/// Unix time (seconds) before which the user's access tokens are invalid:
+/// a token is revoked if its `iat` is earlier.
+pub fn tokens_revoked_before_key(user_id: Uuid) -> String {
+    format!("revoked:user:{user_id}")
+}
 
 #[derive(Clone)]
 pub struct RedisClient {
@@ -112,6 +128,18 @@ impl RedisClient {
         pipe.query_async(&mut conn).await
     }
 
+    /// Revoke one access token until it would have expired anyway.
+    pub async fn revoke_token(&self, jti: Uuid, ttl_secs: u64) -> Result<(), redis::RedisError> {
+        self.set(&revoked_token_key(jti), "1", ttl_secs.max(1)).await
+    }
+
+    /// Revoke every access token issued to the user before now.
+    pub async fn revoke_user_tokens(&self, user_id: Uuid) -> Result<(), redis::RedisError> {
+        let now = chrono::Utc::now().timestamp();
+        self.set(&tokens_revoked_before_key(user_id), &now.to_string(), TOKEN_WATERMARK_TTL_SECS)
+            .await
+    }
+
     pub fn connection(&self) -> ConnectionManager {
         self.conn.clone()
     }
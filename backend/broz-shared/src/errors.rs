@@ -2,6 +2,8 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
 
 use crate::types::ApiErrorResponse;
 
@@ -82,6 +84,71 @@ pub enum ErrorCode {
 }
 
 impl ErrorCode {
+    /// Every code, in catalogue order.
+    pub const ALL: &'static [ErrorCode] = &[
+        // Shared (E0xxx)
+        Self::InternalError,
+        Self::ValidationError,
+        Self::NotFound,
+        Self::Unauthorized,
+        Self::Forbidden,
+        Self::RateLimited,
+        Self::ServiceUnavailable,
+        Self::BadRequest,
+        Self::PayloadTooLarge,
+
+        // Auth (E1xxx)
+        Self::InvalidCredentials,
+        Self::EmailAlreadyExists,
+        Self::EmailNotVerified,
+        Self::TokenExpired,
+        Self::TokenInvalid,
+        Self::RefreshTokenRevoked,
+        Self::OAuthError,
+        Self::PasswordTooWeak,
+        Self::VerificationCodeExpired,
+        Self::VerificationCodeInvalid,
+        Self::ResetCodeExpired,
+        Self::ResetCodeInvalid,
+        Self::EmailRateLimited,
+        Self::UserBanned,
+
+        // User (E2xxx)
+        Self::ProfileNotFound,
+        Self::DisplayNameTaken,
+        Self::InvalidDisplayName,
+        Self::PhotoUploadFailed,
+        Self::FollowAlreadyExists,
+        Self::FollowNotFound,
+        Self::CannotFollowSelf,
+        Self::OnboardingIncomplete,
+
+        // Matching (E3xxx)
+        Self::AlreadyInQueue,
+        Self::NotInQueue,
+        Self::NotInMatch,
+        Self::MatchNotFound,
+        Self::LiveCamRequestNotFound,
+        Self::LiveCamRequestExpired,
+        Self::AlreadyInMatch,
+
+        // Messaging (E4xxx)
+        Self::ConversationNotFound,
+        Self::NotConversationMember,
+        Self::MessageNotFound,
+        Self::GroupNameRequired,
+
+        // Notification (E5xxx)
+        Self::NotificationNotFound,
+
+        // Moderation (E6xxx)
+        Self::ReportNotFound,
+        Self::SanctionNotFound,
+        Self::ReportAlreadyReviewed,
+        Self::CannotReportSelf,
+        Self::DuplicateReport,
+    ];
+
     pub fn code(&self) -> &'static str {
         match self {
             // Shared
@@ -173,6 +240,25 @@ impl ErrorCode {
     }
 }
 
+/// Documented as the string codes clients see, with each code's name and
+/// HTTP status in the description.
+impl utoipa::PartialSchema for ErrorCode {
+    fn schema() -> RefOr<Schema> {
+        let catalogue = Self::ALL
+            .iter()
+            .map(|code| format!("- `{}` {:?} ({})", code.code(), code, code.status_code().as_u16()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .enum_values(Some(Self::ALL.iter().map(|code| code.code())))
+            .description(Some(format!("BROZ error code.\n\n{catalogue}")))
+            .into()
+    }
+}
+
+impl utoipa::ToSchema for ErrorCode {}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("{message}")]
@@ -2,6 +2,7 @@ pub mod types;
 pub mod errors;
 pub mod middleware;
 pub mod clients;
+pub mod openapi;
 
 pub use types::*;
 pub use errors::{AppError, ErrorCode, AppResult};
@@ -5,6 +5,7 @@ use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
 
 use crate::errors::{AppError, ErrorCode};
 use crate::types::auth::{AuthUser, Claims, UserRole};
+use super::identity::verify_identity;
 
 #[axum::async_trait]
 impl<S> FromRequestParts<S> for AuthUser
@@ -14,6 +15,13 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Behind the gateway: trust its signed identity headers
+        if let Ok(gateway_secret) = std::env::var("GATEWAY_SECRET") {
+            if let Some(user) = verify_identity(&parts.headers, &gateway_secret)? {
+                return Ok(user);
+            }
+        }
+
         let token = extract_bearer_token(&parts.headers)?;
         let claims = validate_jwt(&token)?;
 
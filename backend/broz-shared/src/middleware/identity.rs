@@ -0,0 +1,172 @@
+use axum::http::HeaderMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::types::auth::{AuthUser, UserRole};
+
+/// Identity headers set by the gateway after it has verified the JWT.
+pub const USER_ID_HEADER: &str = "x-user-id";
+pub const USER_ROLE_HEADER: &str = "x-user-role";
+pub const TOKEN_ID_HEADER: &str = "x-user-token-id";
+pub const IDENTITY_TIMESTAMP_HEADER: &str = "x-gateway-timestamp";
+pub const IDENTITY_SIGNATURE_HEADER: &str = "x-gateway-signature";
+
+/// All identity headers.  The gateway drops these from client requests.
+pub const IDENTITY_HEADERS: &[&str] = &[
+    USER_ID_HEADER,
+    USER_ROLE_HEADER,
+    TOKEN_ID_HEADER,
+    IDENTITY_TIMESTAMP_HEADER,
+    IDENTITY_SIGNATURE_HEADER,
+];
+
+/// How old a signed identity may be before services reject it.
+const MAX_IDENTITY_AGE_SECS: i64 = 60;
+
+/// HMAC-SHA256 (hex) over the identity fields, keyed with the gateway secret.
+pub fn sign_identity(secret: &str, user: &AuthUser, timestamp: i64) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(identity_payload(user.id, user.role, user.token_id, timestamp).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// The gateway-signed identity of a request, if it carries one.
+///
+/// Returns `Ok(None)` when there is no signature header, and an error when
+/// the headers are malformed, the signature does not match or it is older
+/// than a minute.
+pub fn verify_identity(headers: &HeaderMap, secret: &str) -> Result<Option<AuthUser>, AppError> {
+    let Some(signature) = header(headers, IDENTITY_SIGNATURE_HEADER) else {
+        return Ok(None);
+    };
+    let invalid = || AppError::new(ErrorCode::TokenInvalid, "invalid gateway identity");
+
+    let id: Uuid = header(headers, USER_ID_HEADER).and_then(|v| v.parse().ok()).ok_or_else(invalid)?;
+    let role: UserRole = header(headers, USER_ROLE_HEADER).and_then(|v| v.parse().ok()).ok_or_else(invalid)?;
+    let token_id: Uuid = header(headers, TOKEN_ID_HEADER).and_then(|v| v.parse().ok()).ok_or_else(invalid)?;
+    let timestamp: i64 = header(headers, IDENTITY_TIMESTAMP_HEADER)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(invalid)?;
+
+    if (Utc::now().timestamp() - timestamp).abs() > MAX_IDENTITY_AGE_SECS {
+        return Err(AppError::new(ErrorCode::TokenExpired, "gateway identity has expired"));
+    }
+
+    let signature = hex::decode(signature).map_err(|_| invalid())?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(identity_payload(id, role, token_id, timestamp).as_bytes());
+    mac.verify_slice(&signature).map_err(|_| invalid())?;
+
+    Ok(Some(AuthUser { id, role, token_id }))
+}
+
+fn identity_payload(id: Uuid, role: UserRole, token_id: Uuid, timestamp: i64) -> String {
+    format!("{id}:{role}:{token_id}:{timestamp}")
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "gateway-secret";
+
+    fn user() -> AuthUser {
+        AuthUser {
+            id: Uuid::new_v4(),
+            role: UserRole::Moderator,
+            token_id: Uuid::new_v4(),
+        }
+    }
+
+    fn signed_headers(user: &AuthUser, secret: &str, timestamp: i64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_ID_HEADER, user.id.to_string().parse().unwrap());
+        headers.insert(USER_ROLE_HEADER, user.role.to_string().parse().unwrap());
+        headers.insert(TOKEN_ID_HEADER, user.token_id.to_string().parse().unwrap());
+        headers.insert(IDENTITY_TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(
+            IDENTITY_SIGNATURE_HEADER,
+            sign_identity(secret, user, timestamp).parse().unwrap(),
+        );
+        headers
+    }
+
+    fn error_code(result: Result<Option<AuthUser>, AppError>) -> ErrorCode {
+        match result {
+            Err(AppError::Known { code, .. }) => code,
+            other => panic!("expected a known error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn signed_identity_round_trips() {
+        let user = user();
+        let headers = signed_headers(&user, SECRET, Utc::now().timestamp());
+
+        let verified = verify_identity(&headers, SECRET).unwrap().unwrap();
+        assert_eq!(verified.id, user.id);
+        assert_eq!(verified.role, user.role);
+        assert_eq!(verified.token_id, user.token_id);
+    }
+
+    #[test]
+    fn unsigned_requests_have_no_identity() {
+        let user = user();
+        let mut headers = signed_headers(&user, SECRET, Utc::now().timestamp());
+        headers.remove(IDENTITY_SIGNATURE_HEADER);
+
+        assert!(verify_identity(&headers, SECRET).unwrap().is_none());
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        let headers = signed_headers(&user(), "another-secret", Utc::now().timestamp());
+
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenInvalid);
+    }
+
+    #[test]
+    fn tampered_fields_are_rejected() {
+        let mut headers = signed_headers(&user(), SECRET, Utc::now().timestamp());
+        headers.insert(USER_ROLE_HEADER, "admin".parse().unwrap());
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenInvalid);
+
+        let mut headers = signed_headers(&user(), SECRET, Utc::now().timestamp());
+        headers.insert(USER_ID_HEADER, Uuid::new_v4().to_string().parse().unwrap());
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenInvalid);
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let mut headers = signed_headers(&user(), SECRET, Utc::now().timestamp());
+        headers.insert(IDENTITY_SIGNATURE_HEADER, "not-hex".parse().unwrap());
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenInvalid);
+
+        let mut headers = signed_headers(&user(), SECRET, Utc::now().timestamp());
+        headers.remove(TOKEN_ID_HEADER);
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenInvalid);
+    }
+
+    #[test]
+    fn stale_identities_expire() {
+        let now = Utc::now().timestamp();
+
+        let headers = signed_headers(&user(), SECRET, now - MAX_IDENTITY_AGE_SECS + 5);
+        assert!(verify_identity(&headers, SECRET).unwrap().is_some());
+
+        let headers = signed_headers(&user(), SECRET, now - MAX_IDENTITY_AGE_SECS - 5);
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenExpired);
+
+        let headers = signed_headers(&user(), SECRET, now + MAX_IDENTITY_AGE_SECS + 5);
+        assert_eq!(error_code(verify_identity(&headers, SECRET)), ErrorCode::TokenExpired);
+    }
+}
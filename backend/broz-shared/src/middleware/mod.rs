@@ -1,7 +1,11 @@
 mod auth_extractor;
+mod identity;
+mod trace_context;
 mod tracing_layer;
 mod metrics_layer;
 
 pub use auth_extractor::*;
+pub use identity::*;
+pub use trace_context::*;
 pub use tracing_layer::*;
 pub use metrics_layer::*;
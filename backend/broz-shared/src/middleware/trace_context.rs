@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+use tower_http::classify::{ServerErrorsAsFailures, SharedClassifier};
+use tower_http::trace::{MakeSpan, TraceLayer};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+/// W3C trace context headers.
+pub const TRACE_HEADERS: &[&str] = &["traceparent", "tracestate"];
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// The trace context sent by the caller, if any.
+pub fn extract_context(headers: &HeaderMap) -> Context {
+    opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// Set `traceparent`/`tracestate` for the current span, replacing any
+/// already present.
+pub fn inject_context(headers: &mut HeaderMap) {
+    for name in TRACE_HEADERS {
+        headers.remove(*name);
+    }
+    let context = Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// The current span's trace context as key/value pairs, for carriers
+/// other than HTTP headers (AMQP message headers).
+pub fn context_carrier() -> HashMap<String, String> {
+    let mut carrier = HashMap::new();
+    let context = Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+    carrier
+}
+
+/// Make `span` a child of the trace context in `carrier`, and record its
+/// trace id in the span's `trace_id` field for log correlation.
+pub fn set_parent_from_carrier(span: &Span, carrier: &HashMap<String, String>) {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(carrier));
+    set_parent(span, parent);
+}
+
+fn set_parent(span: &Span, parent: Context) {
+    span.set_parent(parent);
+    let trace_id = span.context().span().span_context().trace_id();
+    span.record("trace_id", tracing::field::display(trace_id));
+}
+
+/// The current trace id, as used for `Event::correlation_id`.
+pub fn current_trace_id() -> Option<Uuid> {
+    let context = Span::current().context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| Uuid::from_bytes(span_context.trace_id().to_bytes()))
+}
+
+/// Forward the current trace context on outgoing service-to-service calls.
+pub trait PropagateTrace {
+    fn with_trace_context(self) -> Self;
+}
+
+impl PropagateTrace for reqwest::RequestBuilder {
+    fn with_trace_context(self) -> Self {
+        context_carrier()
+            .into_iter()
+            .fold(self, |request, (name, value)| request.header(name, value))
+    }
+}
+
+/// Request spans that continue the caller's trace (`traceparent`), or
+/// start a new one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceContextMakeSpan;
+
+impl<B> MakeSpan<B> for TraceContextMakeSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let span = tracing::info_span!(
+            "request",
+            method = %request.method(),
+            uri = %request.uri(),
+            version = ?request.version(),
+            trace_id = tracing::field::Empty,
+        );
+        set_parent(&span, extract_context(request.headers()));
+        span
+    }
+}
+
+/// `TraceLayer` for HTTP servers, with W3C trace context propagation.
+pub fn http_trace_layer() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, TraceContextMakeSpan> {
+    TraceLayer::new_for_http().make_span_with(TraceContextMakeSpan)
+}
@@ -1,9 +1,40 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+/// Set up logging and tracing.
+///
+/// Spans always carry W3C trace context so it can be propagated between
+/// services.  They are also exported over OTLP/HTTP when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set (e.g. `http://localhost:4318`);
+/// the other standard `OTEL_EXPORTER_OTLP_*` variables apply too.
 pub fn init_tracing(service_name: &str) {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(format!("info,{service_name}=debug,tower_http=debug")));
 
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut provider = TracerProvider::builder()
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name.to_string())]));
+    let otlp_endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty());
+    let mut export_error = None;
+    if otlp_endpoint.is_some() {
+        match opentelemetry_otlp::SpanExporter::builder().with_http().build() {
+            Ok(exporter) => {
+                provider = provider.with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio);
+            }
+            Err(e) => export_error = Some(e),
+        }
+    }
+    let provider = provider.build();
+    let otel_layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(service_name.to_string()));
+    opentelemetry::global::set_tracer_provider(provider);
+
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(true)
         .with_thread_ids(true)
@@ -22,14 +53,19 @@ pub fn init_tracing(service_name: &str) {
 
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(otel_layer)
             .with(json_layer)
             .init();
     } else {
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(otel_layer)
             .with(fmt_layer)
             .init();
     }
 
-    tracing::info!(service = service_name, "tracing initialized");
+    tracing::info!(service = service_name, otlp_endpoint = ?otlp_endpoint, "tracing initialized");
+    if let Some(e) = export_error {
+        tracing::error!(error = %e, "failed to set up OTLP export, spans are not exported");
+    }
 }
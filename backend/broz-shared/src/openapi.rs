@@ -0,0 +1,74 @@
+use axum::routing::{get, MethodRouter};
+use axum::Json;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, OpenApi, Ref, ResponseBuilder};
+use utoipa::{Modify, PartialSchema, ToSchema};
+
+use crate::types::ApiErrorResponse;
+
+/// Security scheme name for the bearer access token.
+pub const BEARER_SCHEME: &str = "bearer";
+
+/// Adds what every service's document shares: the bearer security scheme,
+/// the error envelope with the `ErrorCode` catalogue, and that envelope as
+/// the `default` response of every operation.
+///
+/// ```ignore
+/// #[derive(OpenApi)]
+/// #[openapi(paths(routes::login::login), modifiers(&CommonComponents))]
+/// struct ApiDoc;
+/// ```
+pub struct CommonComponents;
+
+impl Modify for CommonComponents {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            BEARER_SCHEME,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+
+        let mut schemas = vec![(ApiErrorResponse::name().into_owned(), ApiErrorResponse::schema())];
+        ApiErrorResponse::schemas(&mut schemas);
+        components.schemas.extend(schemas);
+
+        let error = ResponseBuilder::new()
+            .description("Error, with one of the `ErrorCode` codes")
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name(ApiErrorResponse::name())))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.patch,
+            ];
+            for operation in operations.into_iter().flatten() {
+                operation
+                    .responses
+                    .responses
+                    .entry("default".into())
+                    .or_insert_with(|| error.clone().into());
+            }
+        }
+    }
+}
+
+/// `GET /openapi.json`, serving the service's document.
+pub fn serve<S>(doc: OpenApi) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    get(move || async move { Json(doc) })
+}
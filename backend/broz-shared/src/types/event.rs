@@ -4,6 +4,9 @@ use uuid::Uuid;
 
 /// RabbitMQ Event envelope wrapping all domain events.
 ///
+/// `correlation_id` defaults to the trace id of the request that caused
+/// the event.
+///
 /// Routing key format: `broz.{domain}.{entity}.{action}`
 /// Example: `broz.auth.user.registered`
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,7 +27,7 @@ impl<T: Serialize> Event<T> {
             source: source.into(),
             event_type: event_type.into(),
             timestamp: Utc::now(),
-            correlation_id: None,
+            correlation_id: crate::middleware::current_trace_id(),
             user_id: None,
             data,
         }
@@ -1,10 +1,16 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PaginationParams {
+    /// 1-based page number.
     #[serde(default = "default_page")]
+    #[param(default = 1)]
     pub page: u64,
+    /// Items per page; values above 100 are capped.
     #[serde(default = "default_per_page")]
+    #[param(default = 20)]
     pub per_page: u64,
 }
 
@@ -27,7 +33,7 @@ impl Default for PaginationParams {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Paginated<T: Serialize> {
     pub items: Vec<T>,
     pub total: u64,
@@ -39,7 +45,7 @@ pub struct Paginated<T: Serialize> {
 impl<T: Serialize> Paginated<T> {
     pub fn new(items: Vec<T>, total: u64, params: &PaginationParams) -> Self {
         let per_page = params.limit();
-        let total_pages = if total == 0 { 0 } else { (total + per_page - 1) / per_page };
+        let total_pages = if total == 0 { 0 } else { total.div_ceil(per_page) };
         Self {
             items,
             total,
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 use futures_lite::StreamExt;
 use lapin::options::BasicAckOptions;
+use tracing::Instrument;
 
+use broz_shared::clients::rabbitmq::delivery_span;
 use broz_shared::types::event::{routing_keys, payloads, Event};
 
 use crate::AppState;
@@ -20,40 +22,45 @@ pub async fn listen_user_registered(state: Arc<AppState>) -> anyhow::Result<()>
     while let Some(delivery) = consumer.next().await {
         match delivery {
             Ok(delivery) => {
-                match serde_json::from_slice::<Event<payloads::UserRegistered>>(&delivery.data) {
-                    Ok(event) => {
-                        let data = &event.data;
-                        tracing::info!(
-                            credential_id = %data.credential_id,
-                            email = %data.email,
-                            "received user.registered event"
-                        );
+                let span = delivery_span(&delivery);
+                async {
+                    match serde_json::from_slice::<Event<payloads::UserRegistered>>(&delivery.data) {
+                        Ok(event) => {
+                            let data = &event.data;
+                            tracing::info!(
+                                credential_id = %data.credential_id,
+                                email = %data.email,
+                                "received user.registered event"
+                            );
 
-                        match profile_service::create_default_profile(
-                            &state.db,
-                            data.credential_id,
-                            &data.email,
-                        ) {
-                            Ok(profile) => {
-                                tracing::info!(
-                                    profile_id = %profile.id,
-                                    "profile created for new user"
-                                );
-                            }
-                            Err(e) => {
-                                tracing::error!(
-                                    error = %e,
-                                    credential_id = %data.credential_id,
-                                    "failed to create default profile"
-                                );
+                            match profile_service::create_default_profile(
+                                &state.db,
+                                data.credential_id,
+                                &data.email,
+                            ) {
+                                Ok(profile) => {
+                                    tracing::info!(
+                                        profile_id = %profile.id,
+                                        "profile created for new user"
+                                    );
+                                }
+                                Err(e) => {
+                                    tracing::error!(
+                                        error = %e,
+                                        credential_id = %data.credential_id,
+                                        "failed to create default profile"
+                                    );
+                                }
                             }
                         }
+                        Err(e) => {
+                            tracing::error!(error = %e, "failed to deserialize user.registered event");
+                        }
                     }
-                    Err(e) => {
-                        tracing::error!(error = %e, "failed to deserialize user.registered event");
-                    }
+                    let _ = delivery.ack(BasicAckOptions::default()).await;
                 }
-                let _ = delivery.ack(BasicAckOptions::default()).await;
+                .instrument(span)
+                .await;
             }
             Err(e) => {
                 tracing::error!(error = %e, "consumer error");
@@ -3,16 +3,18 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::pg::PgConnection;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 mod config;
 mod events;
 mod models;
+mod openapi;
 mod routes;
 mod schema;
 mod services;
 
 use config::AppConfig;
+use openapi::ApiDoc;
+use utoipa::OpenApi;
 use broz_shared::clients::rabbitmq::RabbitMQClient;
 use broz_shared::clients::redis::RedisClient;
 use broz_shared::clients::minio::MinioClient;
@@ -60,6 +62,7 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(routes::health::health_check))
+        .route("/openapi.json", broz_shared::openapi::serve(ApiDoc::openapi()))
         .route("/me", get(routes::profile::get_profile).patch(routes::profile::update_profile))
         .route("/onboarding", post(routes::profile::complete_onboarding))
         .route("/check-pseudo", get(routes::profile::check_display_name))
@@ -78,7 +81,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/internal/follower-ids/:id", get(routes::internal::get_follower_ids))
         .route("/internal/profiles/batch", post(routes::internal::batch_profiles))
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .layer(broz_shared::middleware::http_trace_layer())
         .with_state(state);
 
     let addr = format!("0.0.0.0:{port}");
@@ -1,13 +1,14 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::schema::{profiles, follows, likes};
 
 // --- Profile ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize, Clone)]
+#[derive(Debug, Queryable, Identifiable, Serialize, Clone, ToSchema)]
 #[diesel(table_name = profiles)]
 pub struct Profile {
     pub id: Uuid,
@@ -34,7 +35,7 @@ pub struct NewProfile {
     pub credential_id: Uuid,
 }
 
-#[derive(Debug, AsChangeset, Deserialize, Default)]
+#[derive(Debug, AsChangeset, Deserialize, Default, ToSchema)]
 #[diesel(table_name = profiles)]
 pub struct UpdateProfile {
     pub display_name: Option<String>,
@@ -48,7 +49,7 @@ pub struct UpdateProfile {
 
 // --- Follow ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize)]
+#[derive(Debug, Queryable, Identifiable, Serialize, ToSchema)]
 #[diesel(table_name = follows)]
 pub struct Follow {
     pub id: Uuid,
@@ -67,7 +68,7 @@ pub struct NewFollow {
 
 // --- Like ---
 
-#[derive(Debug, Queryable, Identifiable, Serialize)]
+#[derive(Debug, Queryable, Identifiable, Serialize, ToSchema)]
 #[diesel(table_name = likes)]
 pub struct Like {
     pub id: Uuid,
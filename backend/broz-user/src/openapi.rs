@@ -0,0 +1,30 @@
+use broz_shared::openapi::CommonComponents;
+use utoipa::OpenApi;
+
+use crate::routes;
+
+/// The service's API document, served at `/openapi.json`.  Service-internal
+/// routes are left out.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "broz-user"),
+    paths(
+        routes::health::health_check,
+        routes::profile::get_profile,
+        routes::profile::update_profile,
+        routes::profile::complete_onboarding,
+        routes::profile::check_display_name,
+        routes::profile::get_public_profile,
+        routes::search::search_users,
+        routes::follows::send_follow_request,
+        routes::follows::remove_follow,
+        routes::follows::respond_follow,
+        routes::follows::list_followers,
+        routes::follows::list_following,
+        routes::likes::send_like,
+        routes::likes::check_like,
+        routes::photo::upload_photo,
+    ),
+    modifiers(&CommonComponents),
+)]
+pub struct ApiDoc;
@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -16,6 +17,13 @@ use crate::AppState;
 
 // --- POST /follows/:id ---
 
+/// Ask to follow a user.
+#[utoipa::path(
+    post,
+    path = "/follows/{id}",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Follow>)),
+)]
 pub async fn send_follow_request(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -95,11 +103,18 @@ pub async fn send_follow_request(
 
 // --- PUT /follows/:id/respond ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RespondFollowRequest {
     pub accepted: bool,
 }
 
+/// Accept or decline a follow request.
+#[utoipa::path(
+    put,
+    path = "/follows/{id}/respond",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Follow>)),
+)]
 pub async fn respond_follow(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -151,6 +166,13 @@ pub async fn respond_follow(
 
 // --- DELETE /follows/:id ---
 
+/// Unfollow, or cancel a follow request.
+#[utoipa::path(
+    delete,
+    path = "/follows/{id}",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<FollowRemovedResponse>)),
+)]
 pub async fn remove_follow(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -178,18 +200,36 @@ pub async fn remove_follow(
     diesel::delete(follows::table.filter(follows::id.eq(follow_id)))
         .execute(&mut conn)?;
 
-    publisher::publish_follow_removed(&state.rabbitmq, follow.follower_id, follow.following_id).await;
+    // Resolve credential_ids from profile_ids for the event
+    let follower_cred: Uuid = profiles::table
+        .find(follow.follower_id)
+        .select(profiles::credential_id)
+        .first(&mut conn)
+        .unwrap_or(follow.follower_id);
+    let following_cred: Uuid = profiles::table
+        .find(follow.following_id)
+        .select(profiles::credential_id)
+        .first(&mut conn)
+        .unwrap_or(follow.following_id);
+    publisher::publish_follow_removed(&state.rabbitmq, follower_cred, following_cred).await;
 
     Ok(Json(ApiResponse::ok(FollowRemovedResponse { removed: true })))
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct FollowRemovedResponse {
     pub removed: bool,
 }
 
 // --- GET /followers ---
 
+/// The caller's followers.
+#[utoipa::path(
+    get,
+    path = "/followers",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Vec<Profile>>)),
+)]
 pub async fn list_followers(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -230,6 +270,13 @@ pub async fn list_followers(
 
 // --- GET /following ---
 
+/// Users the caller follows.
+#[utoipa::path(
+    get,
+    path = "/following",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Vec<Profile>>)),
+)]
 pub async fn list_following(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::events::publisher;
 use crate::schema::{follows, profiles};
 use crate::models::Profile;
 use crate::AppState;
@@ -37,18 +38,23 @@ pub async fn update_presence(
     let result = if req.is_online {
         diesel::update(profiles::table.filter(profiles::credential_id.eq(req.user_id)))
             .set(profiles::is_online.eq(true))
-            .execute(&mut conn)
+            .returning(profiles::id)
+            .get_result::<Uuid>(&mut conn)
     } else {
         diesel::update(profiles::table.filter(profiles::credential_id.eq(req.user_id)))
             .set((
                 profiles::is_online.eq(false),
                 profiles::last_seen_at.eq(Some(Utc::now())),
             ))
-            .execute(&mut conn)
+            .returning(profiles::id)
+            .get_result::<Uuid>(&mut conn)
     };
 
-    match result {
-        Ok(_) => {
+    match result.optional() {
+        Ok(profile_id) => {
+            if let Some(profile_id) = profile_id {
+                publisher::publish_profile_updated(&state.rabbitmq, profile_id, req.user_id).await;
+            }
             tracing::debug!(user_id = %req.user_id, is_online = req.is_online, "presence updated");
             Json(PresenceResponse { ok: true })
         }
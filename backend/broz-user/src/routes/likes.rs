@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
@@ -14,12 +15,19 @@ use crate::models::{Like, NewLike, Profile};
 use crate::schema::{likes, profiles};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendLikeRequest {
     pub liked_id: Uuid,
     pub match_session_id: Option<Uuid>,
 }
 
+/// Like a user.
+#[utoipa::path(
+    post,
+    path = "/likes",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Like>)),
+)]
 pub async fn send_like(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -68,6 +76,7 @@ pub async fn send_like(
     diesel::update(profiles::table.filter(profiles::id.eq(liked.id)))
         .set(profiles::total_likes.eq(profiles::total_likes + 1))
         .execute(&mut conn)?;
+    publisher::publish_profile_updated(&state.rabbitmq, liked.id, liked.credential_id).await;
 
     let display_name = liker.display_name.as_deref().unwrap_or("unknown");
     publisher::publish_like_sent(
@@ -82,12 +91,18 @@ pub async fn send_like(
     Ok(Json(ApiResponse::ok(like)))
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LikeCheckResponse {
     pub already_liked: bool,
 }
 
 /// GET /likes/check/:target_id - check if current user already liked target
+#[utoipa::path(
+    get,
+    path = "/likes/check/{target_id}",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<LikeCheckResponse>)),
+)]
 pub async fn check_like(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -3,21 +3,31 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Serialize;
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::AuthUser;
 use broz_shared::types::ApiResponse;
 
+use crate::events::publisher;
 use crate::models::Profile;
 use crate::schema::profiles;
 use crate::AppState;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PhotoUploadResponse {
     pub photo_url: String,
 }
 
+/// Upload the caller's profile photo.
+#[utoipa::path(
+    post,
+    path = "/photo",
+    tag = "user",
+    request_body(content_type = "multipart/form-data", description = "JPEG, PNG, WebP or GIF image as the first field"),
+    responses((status = 200, body = ApiResponse<PhotoUploadResponse>)),
+)]
 pub async fn upload_photo(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -80,6 +90,8 @@ pub async fn upload_photo(
         ))
         .execute(&mut conn)?;
 
+    publisher::publish_profile_updated(&state.rabbitmq, profile.id, profile.credential_id).await;
+
     tracing::info!(
         profile_id = %profile.id,
         photo_url = %photo_url,
@@ -4,6 +4,7 @@ use chrono::NaiveDate;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use broz_shared::errors::{AppError, AppResult, ErrorCode};
 use broz_shared::types::auth::AuthUser;
@@ -16,6 +17,13 @@ use crate::AppState;
 
 // --- GET /me ---
 
+/// The caller's profile.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Profile>)),
+)]
 pub async fn get_profile(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -32,6 +40,13 @@ pub async fn get_profile(
 
 // --- PATCH /me ---
 
+/// Update the caller's profile.
+#[utoipa::path(
+    patch,
+    path = "/me",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Profile>)),
+)]
 pub async fn update_profile(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -58,7 +73,7 @@ pub async fn update_profile(
 
 // --- POST /onboarding ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct OnboardingRequest {
     pub display_name: String,
     pub birth_date: String,
@@ -67,6 +82,13 @@ pub struct OnboardingRequest {
     pub country: String,
 }
 
+/// Complete the caller's onboarding.
+#[utoipa::path(
+    post,
+    path = "/onboarding",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Profile>)),
+)]
 pub async fn complete_onboarding(
     user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -124,6 +146,7 @@ pub async fn complete_onboarding(
         ))
         .get_result::<Profile>(&mut conn)?;
 
+    publisher::publish_profile_updated(&state.rabbitmq, updated.id, updated.credential_id).await;
     publisher::publish_onboarding_completed(&state.rabbitmq, user.id, &req.display_name).await;
 
     tracing::info!(
@@ -137,6 +160,13 @@ pub async fn complete_onboarding(
 
 // --- GET /profile/:id --- (public profile by credential_id or profile_id)
 
+/// A user's public profile.
+#[utoipa::path(
+    get,
+    path = "/profile/{id}",
+    tag = "user",
+    responses((status = 200, body = ApiResponse<Profile>)),
+)]
 pub async fn get_public_profile(
     _user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -160,16 +190,24 @@ pub async fn get_public_profile(
 
 // --- GET /check-pseudo ---
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct CheckNameQuery {
     pub name: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CheckNameResponse {
     pub available: bool,
 }
 
+/// Whether a display name is available.
+#[utoipa::path(
+    get,
+    path = "/check-pseudo",
+    tag = "user",
+    params(CheckNameQuery),
+    responses((status = 200, body = ApiResponse<CheckNameResponse>)),
+)]
 pub async fn check_display_name(
     State(state): State<Arc<AppState>>,
     Query(query): Query<CheckNameQuery>,
@@ -3,6 +3,7 @@ use axum::Json;
 use diesel::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::IntoParams;
 
 use broz_shared::errors::{AppError, AppResult};
 use broz_shared::types::auth::AuthUser;
@@ -12,7 +13,7 @@ use crate::models::Profile;
 use crate::schema::profiles;
 use crate::AppState;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 pub struct SearchParams {
     q: String,
     #[serde(default = "default_limit")]
@@ -24,6 +25,13 @@ fn default_limit() -> i64 {
 }
 
 /// GET /search?q=<query>&limit=20
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "user",
+    params(SearchParams),
+    responses((status = 200, body = ApiResponse<Vec<Profile>>)),
+)]
 pub async fn search_users(
     user: AuthUser,
     State(state): State<Arc<AppState>>,